@@ -0,0 +1,179 @@
+//! Đo thay đổi InMemoryDB index bytecode theo code hash (`contracts` map) so với InMemoryDB cũ,
+//! trên đúng loop song song của `run_chain_revm_snapshot_parallel`. Chạy offline trên snapshot tổng hợp:
+//!
+//!     cargo bench --bench code_hash_index
+//!
+//! Snapshot chỉ lưu `code_hash` trong account (như file snapshot / `PersistentDB`), nên mỗi call
+//! revm phải gọi `code_by_hash`. "before" giữ nguyên cách InMemoryDB cũ tìm bytecode: quét tuyến tính
+//! các account + `hash_slow()`.
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use alloy::primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy::sol_types::SolValue;
+use futures::stream::{FuturesUnordered, StreamExt};
+use revm::db::{CacheDB, DatabaseRef};
+use revm::primitives::{AccountInfo, BlockEnv, Bytecode};
+
+use univ3_revm_arbitrage::core::db::revm_call_db;
+use univ3_revm_arbitrage::core::db_empty::InMemoryDB;
+use univ3_revm_arbitrage::core::executor::{
+    call_snapshot_parallel, ExecutorConfig, QuoteKind, QuoteRequest, SimulationExecutor,
+};
+use univ3_revm_arbitrage::core::logger::{measure_end, measure_start};
+
+const CONTRACTS: usize = 500;
+const HOLDERS_PER_CONTRACT: usize = 100;
+const CALLS: usize = 1000;
+const CODE_LOOKUPS: usize = 200;
+
+/// InMemoryDB trước khi đổi sang `contracts` map (giữ lại để đo).
+/// Bytecode nằm trong `AccountInfo` của từng account; account trả về qua `basic` chỉ có `code_hash`
+/// như khi state đến từ snapshot, nên bytecode chỉ tìm được bằng cách quét.
+#[derive(Debug, Clone, Default)]
+struct LegacyInMemoryDB {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, U256), U256>,
+}
+
+impl DatabaseRef for LegacyInMemoryDB {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.accounts.get(&address).map(|info| AccountInfo { code: None, ..info.clone() }))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        for info in self.accounts.values() {
+            if let Some(code) = &info.code {
+                if code.hash_slow() == code_hash {
+                    return Ok(code.clone());
+                }
+            }
+        }
+        Ok(Bytecode::new())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(*self.storage.get(&(address, index)).unwrap_or(&U256::ZERO))
+    }
+
+    fn block_hash_ref(&self, _number: u64) -> Result<B256, Self::Error> {
+        Ok(B256::ZERO)
+    }
+}
+
+fn token(index: usize) -> Address {
+    Address::left_padding_from(&(0x1000 + index as u64).to_be_bytes())
+}
+
+fn holder(index: usize) -> Address {
+    Address::left_padding_from(&(0x100000 + index as u64).to_be_bytes())
+}
+
+/// `CONTRACTS` bản ERC20 giả (khác nhau ở byte cuối nên khác code hash), mỗi bản có `HOLDERS_PER_CONTRACT` balance
+fn build_snapshots() -> (LegacyInMemoryDB, InMemoryDB) {
+    let erc20 = Bytes::from_str(include_str!("../src/bytecode/generic_erc20.hex")).unwrap();
+    let mut legacy = LegacyInMemoryDB::default();
+    let mut indexed = InMemoryDB::default();
+    for index in 0..CONTRACTS {
+        let mut code = erc20.to_vec();
+        code.extend_from_slice(&(index as u32).to_be_bytes());
+        let code = Bytecode::new_raw(code.into());
+        let info = AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        legacy.accounts.insert(token(index), info.clone());
+        // Account chỉ giữ `code_hash`, bytecode sang `contracts`
+        indexed.insert_account_info(token(index), info);
+
+        for holder_index in 0..HOLDERS_PER_CONTRACT {
+            let slot: U256 = keccak256((holder(holder_index), U256::ZERO).abi_encode()).into();
+            let balance = U256::from(index * HOLDERS_PER_CONTRACT + holder_index);
+            legacy.storage.insert((token(index), slot), balance);
+            indexed.storage.insert((token(index), slot), balance);
+        }
+    }
+    assert!(indexed.accounts.values().all(|info| info.code.is_none()));
+    (legacy, indexed)
+}
+
+/// `balanceOf(holder)` trên cùng một token
+fn calldatas() -> Vec<Bytes> {
+    (0..CALLS)
+        .map(|index| {
+            let mut calldata = vec![0x70, 0xa0, 0x82, 0x31];
+            calldata.extend_from_slice(&holder(index % HOLDERS_PER_CONTRACT).abi_encode());
+            calldata.into()
+        })
+        .collect()
+}
+
+/// Cùng cấu trúc `call_snapshot_parallel` (một tokio task + một overlay riêng cho mỗi call)
+/// cho DB không phải `InMemoryDB`
+async fn call_parallel<DB>(snapshot: Arc<DB>, from: Address, to: Address, calldatas: Vec<Bytes>) -> Vec<Bytes>
+where
+    DB: DatabaseRef + Send + Sync + 'static,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut futs = FuturesUnordered::new();
+    for (index, calldata) in calldatas.into_iter().enumerate() {
+        let snapshot = snapshot.clone();
+        futs.push(tokio::spawn(async move {
+            let response = revm_call_db(from, to, calldata, &BlockEnv::default(), &mut CacheDB::new(snapshot)).unwrap();
+            (index, response)
+        }));
+    }
+    let mut responses = vec![Bytes::new(); futs.len()];
+    while let Some(result) = futs.next().await {
+        let (index, response) = result.unwrap();
+        responses[index] = response;
+    }
+    responses
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() {
+    let from = Address::repeat_byte(0x01);
+    let (legacy, indexed) = build_snapshots();
+    println!("Snapshot: {} contracts (code_hash only), {} storage slots, {} calls", CONTRACTS, indexed.storage.len(), CALLS);
+    let (legacy, indexed) = (Arc::new(legacy), Arc::new(indexed));
+
+    // 1️⃣ code_by_hash: quét tuyến tính vs lookup theo hash
+    let hashes: Vec<B256> = (0..CODE_LOOKUPS).map(|index| indexed.accounts[&token(index % CONTRACTS)].code_hash).collect();
+    let start = measure_start("code_by_hash before (linear scan)");
+    for hash in &hashes {
+        legacy.code_by_hash_ref(*hash).unwrap();
+    }
+    measure_end(start);
+    let start = measure_start("code_by_hash after (contracts map)");
+    for hash in &hashes {
+        indexed.code_by_hash_ref(*hash).unwrap();
+    }
+    measure_end(start);
+
+    // 2️⃣ Loop song song của `run_chain_revm_snapshot_parallel`
+    let target = token(CONTRACTS / 2);
+    let start = measure_start("parallel loop before (legacy InMemoryDB)");
+    let before = call_parallel(legacy, from, target, calldatas()).await;
+    measure_end(start);
+    let start = measure_start("call_snapshot_parallel after (indexed InMemoryDB + LayeredDB)");
+    let after = call_snapshot_parallel(indexed.clone(), from, target, calldatas(), &BlockEnv::default()).await.unwrap();
+    measure_end(start);
+    assert_eq!(before, after);
+
+    // 3️⃣ Cùng batch trên `SimulationExecutor` như bước cuối của runner
+    let executor = SimulationExecutor::new(indexed, ExecutorConfig::default());
+    let requests = calldatas()
+        .into_iter()
+        .map(|calldata| QuoteRequest { from, to: target, calldata, kind: QuoteKind::Call })
+        .collect();
+    let start = measure_start("executor batch after (indexed InMemoryDB)");
+    let results = tokio::task::block_in_place(|| executor.quote_batch(requests));
+    measure_end(start);
+    if let Some(stats) = executor.last_stats() {
+        println!("Executor: {}", stats);
+    }
+    assert!(results.into_iter().map(|result| result.output.unwrap()).eq(after.iter().cloned()));
+    println!("✅ {} responses identical", after.len());
+}
@@ -9,7 +9,7 @@ echo "" > "$ALL_LOG"  # Clear file cũ nếu có
 
 # Cấu hình chain và method
 CHAINS=("eth" "avax")
-METHODS=("call" "revm" "anvil" "revm_cached" "revm_quoter" "validate")
+METHODS=("call" "revm" "anvil" "revm_cached" "revm_quoter" "validate" "native" "cycles")
 
 for CHAIN in "${CHAINS[@]}"; do
     echo "=== CHAIN = $CHAIN ===" | tee -a "$ALL_LOG"
@@ -9,7 +9,8 @@ use alloy::{
     transports::http::reqwest::Url,
 };
 
-use univ3_revm_arbitrage::{chain::avax::*, types::ONE_ETHER};
+use univ3_revm_arbitrage::chain::registry::{Chain, ChainRegistry};
+use univ3_revm_arbitrage::types::ONE_ETHER;
 use univ3_revm_arbitrage::source::*;
 
 
@@ -17,15 +18,15 @@ use univ3_revm_arbitrage::source::*;
 async fn main() -> Result<()> {
     env_logger::init();
 
-    let rpc = "https://api.avax.network/ext/bc/C/rpc";
-    let provider = ProviderBuilder::new().on_http(Url::parse(rpc)?);
+    let config = ChainRegistry::default().load("avax")?;
+    let provider = ProviderBuilder::new().on_http(Url::parse(&config.rpc_url)?);
     let provider = Arc::new(provider);
 
     let base_fee = provider.get_gas_price().await?;
 
     let volume = ONE_ETHER.div(U256::from(10));
-    let calldata = quote_calldata(WAVAX_ADDR, USDC_ADDR, volume, 3000);
-    let tx = build_tx_avalanche(V3_QUOTER_ADDR, ME, calldata, base_fee, Some(CHAIN_ID));
+    let calldata = quote_calldata(config.native_token()?.address, config.stable_token()?.address, volume, config.defaults.default_fee);
+    let tx = build_tx_avalanche(config.default_quoter()?, config.me, calldata, base_fee, Some(config.chain_id));
 
     let start = measure_start("avax_call");
     println!("\ntx: {:?}", tx);
@@ -11,7 +11,7 @@ use alloy::eips::BlockId;
 use alloy::primitives::{address, Address};
 
 use univ3_revm_arbitrage::{source::*, types::ONE_ETHER};
-use univ3_revm_arbitrage::chain::ronin::*;
+use univ3_revm_arbitrage::chain::registry::ChainRegistry;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -59,8 +59,8 @@ async fn main() -> Result<()> {
 
     // let tx = build_tx(quoterAddress, ME, calldata, base_fee);
     let chain_id = 2021;
-    let wallet = address!("ebe94ab981899efbc8bb6cb2b045fc7490726ff3");
-    let tx = build_tx_ronin(quoter_address, ME, calldata, base_fee, Some(chain_id));
+    let me = ChainRegistry::default().load("ronin")?.me;
+    let tx = build_tx_ronin(quoter_address, me, calldata, base_fee, Some(chain_id));
     let start = measure_start("eth_call_one");
     println!("tx: {:?}", tx);
     let call = provider.call(&tx).block(BlockId::latest());
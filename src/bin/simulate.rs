@@ -1,6 +1,9 @@
+use std::path::PathBuf;
+use alloy::{eips::BlockId, primitives::Address};
 use clap::Parser;
-use univ3_revm_arbitrage::chain::actors::get_chain_actors;
-use univ3_revm_arbitrage::types::{load_chain_config, ChainConfig};
+use univ3_revm_arbitrage::chain::actors::ChainActors;
+use univ3_revm_arbitrage::chain::registry::ChainRegistry;
+use univ3_revm_arbitrage::types::ChainConfig;
 use univ3_revm_arbitrage::core::{
     chain_call::run_chain_call,
     chain_anvil::run_chain_anvil,
@@ -10,40 +13,179 @@ use univ3_revm_arbitrage::core::{
     chain_revm_quoter::run_chain_revm_quoter,
     chain_arbitrage::run_chain_arbitrage,
     chain_validate::run_chain_validate,
+    chain_native::run_chain_native,
+    chain_cycles::run_chain_cycles,
+    chain_snapshot::{record_chain_snapshot, run_chain_snapshot},
+    chain_access::{record_chain_access, run_chain_prewarmed},
+    chain_watch::{run_chain_watch, WatchConfig},
+    chain_backrun::{run_chain_backrun, BackrunConfig},
+    chain_execute::run_chain_execute,
+    chain_discover::run_chain_discover,
+    pool_discovery::DiscoverConfig,
+    submit::{SignerSource, Submitter},
+    db::cache_dir,
+    db_persistent::{prune_cache, PruneConfig},
 };
 
 #[derive(Parser, Debug)]
 #[command(author = "Kyhoolee", version = "1.0", about = "Simulate EVM quote/arbitrage")]
 struct Args {
-    /// Tên chain (eth, avax, ronin)
+    /// Tên chain: file `<chain>.toml` trong `--config-dir` (eth, avax, ronin, ...)
     #[arg(long, default_value = "eth")]
     chain: String,
 
-    /// Logic cần chạy (call, revm, anvil, arbitrage, validate)
+    /// Thư mục chain config
+    #[arg(long, default_value = "src/config")]
+    config_dir: PathBuf,
+
+    /// Logic cần chạy (call, revm, anvil, arbitrage, validate, native, cycles, watch, backrun, execute, discover)
     #[arg(long, default_value = "call")]
     method: String,
+
+    /// Chạy offline từ file snapshot, không gọi RPC (revm_cached, revm_quoter, arbitrage, native, cycles)
+    #[arg(long, conflicts_with = "save_snapshot")]
+    snapshot: Option<PathBuf>,
+
+    /// Chạy online rồi lưu state đã warm-up ra file (`.json` hoặc binary)
+    #[arg(long)]
+    save_snapshot: Option<PathBuf>,
+
+    /// Record access set của các quote (revm_cached, arbitrage) ra manifest JSON + in EIP-2930 access list
+    #[arg(long, conflicts_with_all = ["snapshot", "save_snapshot"])]
+    record_access: Option<PathBuf>,
+
+    /// Nạp trước state từ access manifest trước khi simulate (revm_cached, revm_quoter, arbitrage, native, cycles)
+    #[arg(long, conflicts_with_all = ["snapshot", "save_snapshot", "record_access"])]
+    prewarm: Option<PathBuf>,
+
+    /// Block để fork mọi provider: số (`21000000`, `0x1406f40`), block hash hoặc tag
+    /// (`latest`, `safe`, `finalized`...). Resolve một lần lúc bắt đầu để kết quả tái lập được
+    #[arg(long, default_value = "latest")]
+    block: BlockId,
+
+    /// Scan chạy lại mỗi block mới ở `--method watch` (revm_cached, revm_quoter, arbitrage, native, cycles)
+    #[arg(long, default_value = "arbitrage")]
+    scan: String,
+
+    /// Dừng `--method watch` sau số block này (mặc định chạy mãi)
+    #[arg(long)]
+    watch_blocks: Option<usize>,
+
+    /// `--method watch`: đối chiếu pool UniV3 với on-chain mỗi số block này, pool lệch bị recover
+    #[arg(long, default_value_t = 10)]
+    consistency_interval: u64,
+
+    /// Dừng `--method backrun` sau số pending tx có swap trên pool đang track (mặc định chạy mãi)
+    #[arg(long)]
+    max_victims: Option<usize>,
+
+    /// `--method execute`: ký transaction `execute` cho cycle lãi nhất (key từ ARB_PRIVATE_KEY hoặc --keystore)
+    #[arg(long)]
+    submit: bool,
+
+    /// Chỉ in raw transaction đã ký, không gửi; gửi thật bằng `--dry-run false`
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    dry_run: bool,
+
+    /// Keystore JSON của signer, password trong ARB_KEYSTORE_PASSWORD
+    #[arg(long)]
+    keystore: Option<PathBuf>,
+
+    /// `--method discover`: UniswapV3Factory thay cho `factory` trong chain config
+    #[arg(long)]
+    factory: Option<Address>,
+
+    /// `--method discover`: quét thêm `PoolCreated` từ block này tới block ghim
+    #[arg(long)]
+    from_block: Option<u64>,
+
+    /// `--method discover`: bỏ pool có `liquidity()` nhỏ hơn ngưỡng này
+    #[arg(long, default_value_t = 1)]
+    min_liquidity: u128,
+
+    /// `--method discover`: file registry thay cho `pool_registry` trong chain config
+    #[arg(long)]
+    registry: Option<PathBuf>,
+
+    /// Trước khi chạy, xoá entry trong `.evm_cache` cũ hơn số giờ này
+    #[arg(long)]
+    prune_cache_hours: Option<u64>,
+
+    /// Trước khi chạy, xoá entry cũ nhất trong `.evm_cache` tới khi còn dưới số MB này
+    #[arg(long)]
+    prune_cache_mb: Option<u64>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    // Load config từ file src/config/<chain>.toml
-    let config_path = format!("src/config/{}.toml", args.chain);
-    let config: ChainConfig = load_chain_config(&config_path)?;
-    let actors = get_chain_actors(&args.chain);
+    // Load config từ file <config_dir>/<chain>.toml
+    let config: ChainConfig = ChainRegistry::new(&args.config_dir).load(&args.chain)?;
+    let actors = ChainActors::from(&config);
 
+    if args.prune_cache_hours.is_some() || args.prune_cache_mb.is_some() {
+        let prune = PruneConfig {
+            max_age: args.prune_cache_hours.map(|hours| std::time::Duration::from_secs(hours * 3600)),
+            max_bytes: args.prune_cache_mb.map(|mb| mb * 1024 * 1024),
+        };
+        println!("🧹 Pruned {}: {}", cache_dir(), prune_cache(&cache_dir(), &prune)?);
+    }
+
+    if let Some(path) = &args.snapshot {
+        return run_chain_snapshot(&config, &actors, &args.method, path);
+    }
+    if let Some(path) = &args.save_snapshot {
+        return record_chain_snapshot(&config, &actors, &args.method, path, args.block).await;
+    }
+    if let Some(path) = &args.record_access {
+        return record_chain_access(&config, &actors, &args.method, path, args.block).await;
+    }
+    if let Some(path) = &args.prewarm {
+        return run_chain_prewarmed(&config, &actors, &args.method, path, args.block).await;
+    }
 
     // Dispatch logic dựa theo --method
     match args.method.as_str() {
-        "call" => run_chain_call(&config, &actors).await?,
-        "anvil" => run_chain_anvil(&config, &actors).await?,
-        "revm" => run_chain_revm(&config, &actors).await?,
-        "revm_cached" => run_chain_revm_cached(&config, &actors).await?,
-        "revm_cached_parallel" => run_chain_revm_snapshot_parallel(&config, &actors).await?, // ADD LINE
-        "revm_quoter" => run_chain_revm_quoter(&config, &actors).await?,
-        "arbitrage" => run_chain_arbitrage(&config, &actors).await?,
-        "validate" => run_chain_validate(&config, &actors).await?,
+        "call" => run_chain_call(&config, &actors, args.block).await?,
+        "anvil" => run_chain_anvil(&config, &actors, args.block).await?,
+        "revm" => run_chain_revm(&config, &actors, args.block).await?,
+        "revm_cached" => run_chain_revm_cached(&config, &actors, args.block).await?,
+        "revm_cached_parallel" => run_chain_revm_snapshot_parallel(&config, &actors, args.block).await?, // ADD LINE
+        "revm_quoter" => run_chain_revm_quoter(&config, &actors, args.block).await?,
+        "arbitrage" => run_chain_arbitrage(&config, &actors, args.block).await?,
+        "validate" => run_chain_validate(&config, &actors, args.block).await?,
+        "native" => run_chain_native(&config, &actors, args.block).await?,
+        "cycles" => run_chain_cycles(&config, &actors, args.block).await?,
+        "execute" => {
+            let submitter = match args.submit {
+                true => Some(Submitter::new(SignerSource::from_args(args.keystore.clone()).load()?, &config, args.dry_run)),
+                false => None,
+            };
+            run_chain_execute(&config, &actors, args.block, submitter.as_ref()).await?;
+        }
+        "watch" => {
+            let watch = WatchConfig {
+                max_blocks: args.watch_blocks,
+                consistency_interval: args.consistency_interval,
+                ..WatchConfig::for_chain(&config)
+            };
+            run_chain_watch(&config, &actors, &args.scan, args.block, &watch).await?;
+        }
+        "backrun" => {
+            let backrun = BackrunConfig { max_victims: args.max_victims, ..Default::default() };
+            run_chain_backrun(&config, &actors, args.block, &backrun).await?;
+        }
+        "discover" => {
+            let discover = DiscoverConfig {
+                factory: args.factory,
+                from_block: args.from_block,
+                min_liquidity: args.min_liquidity,
+                registry: args.registry.clone(),
+                ..Default::default()
+            };
+            run_chain_discover(&config, args.block, &discover).await?;
+        }
 
         _ => eprintln!("Unknown method: {}", args.method),
     }
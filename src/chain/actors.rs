@@ -1,42 +1,22 @@
+use crate::types::ChainConfig;
+
+/// Vai trò token của chain (symbol trong `[[tokens]]`) + fee tier, lấy từ `[defaults]` của chain TOML
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChainActors {
-    pub native_token_key: &'static str,
-    pub stable_token_key: &'static str,
-    pub quoter_key: &'static str,
-    pub custom_quoter_key: Option<&'static str>,
-    pub pool_500_key: Option<&'static str>,
-    pub pool_3000_key: Option<&'static str>,
+    pub native_token_key: String,
+    pub stable_token_key: String,
     pub default_fee: u32,
+    /// Fee tier của pool còn lại trong arbitrage 2 pool (cùng cặp native / stable với `default_fee`)
+    pub low_fee: u32,
 }
 
-pub fn get_chain_actors(chain_name: &str) -> ChainActors {
-    match chain_name {
-        "eth" => ChainActors {
-            native_token_key: "WETH",
-            stable_token_key: "USDC",
-            quoter_key: "QUOTER",
-            custom_quoter_key: Some("CUSTOM_QUOTER"),
-            pool_500_key: Some("POOL_500"),
-            pool_3000_key: Some("POOL_3000"),
-            default_fee: 3000,
-        },
-        "avax" => ChainActors {
-            native_token_key: "WAVAX",
-            stable_token_key: "USDC",
-            quoter_key: "QUOTER",
-            custom_quoter_key: Some("CUSTOM_QUOTER"), // nếu chưa có thì để None
-            pool_500_key: Some("POOL_500"),
-            pool_3000_key: Some("POOL_3000"),
-            default_fee: 3000, // tùy DEX
-        },
-        "ronin" => ChainActors {
-            native_token_key: "WRON",
-            stable_token_key: "USDC",
-            quoter_key: "QUOTER",
-            custom_quoter_key: Some("CUSTOM_QUOTER"),
-            pool_500_key: Some("POOL_500"),
-            pool_3000_key: Some("POOL_3000"),
-            default_fee: 3000,
-        },
-        _ => panic!("Unknown chain"),
+impl From<&ChainConfig> for ChainActors {
+    fn from(config: &ChainConfig) -> Self {
+        ChainActors {
+            native_token_key: config.defaults.native_token.clone(),
+            stable_token_key: config.defaults.stable_token.clone(),
+            default_fee: config.defaults.default_fee,
+            low_fee: config.defaults.low_fee,
+        }
     }
 }
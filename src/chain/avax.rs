@@ -1,8 +0,0 @@
-use alloy::primitives::{address, Address};
-
-pub static WAVAX_ADDR: Address = address!("B31f66AA3C1e785363F0875A1B74E27b85FD66c7");
-pub static USDC_ADDR: Address = address!("B97EF9Ef8734C71904D8002F8b6Bc66Dd9c48a6E");
-pub static V3_QUOTER_ADDR: Address = address!("be0F5544EC67e9B3b2D979aaA43f18Fd87E6257F");
-
-pub static ME: Address = address!("0000000000000000000000000000000000000001");
-pub const CHAIN_ID: u64 = 43114;
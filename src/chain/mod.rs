@@ -1,4 +1,2 @@
-pub mod eth;
-pub mod avax;
-pub mod ronin;
 pub mod actors;
+pub mod registry; // Chain trait + danh sách chain từ src/config/*.toml
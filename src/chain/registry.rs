@@ -0,0 +1,191 @@
+//! Chain được khai báo hoàn toàn bằng TOML trong `src/config/<name>.toml`: thêm chain mới (Arbitrum,
+//! Base, BSC, Polygon...) chỉ cần thêm file config, không cần code Rust.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use alloy::primitives::Address;
+use anyhow::{anyhow, Result};
+
+use crate::source::builder::{FeePolicy, TxType};
+use crate::types::{load_chain_config, ChainConfig, QuoterKind, TokenConfig};
+
+/// Thư mục chứa chain config mặc định
+pub const CONFIG_DIR: &str = "src/config";
+
+/// Hậu tố file registry pool (`--method discover`), không phải chain config
+const POOL_REGISTRY_SUFFIX: &str = ".pools.toml";
+
+/// Mặc định một chain cung cấp cho các runner
+pub trait Chain {
+    fn name(&self) -> &str;
+    fn chain_id(&self) -> u64;
+    /// Wrapped native token (WETH, WAVAX, WRON...): token đầu / cuối của cycle, trả gas
+    fn native_token(&self) -> Result<&TokenConfig>;
+    fn stable_token(&self) -> Result<&TokenConfig>;
+    /// Quoter on-chain dùng cho `eth_call`
+    fn default_quoter(&self) -> Result<Address>;
+    fn fee_policy(&self) -> FeePolicy;
+    fn tx_type(&self) -> TxType;
+    fn block_time(&self) -> Duration;
+}
+
+impl Chain for ChainConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn native_token(&self) -> Result<&TokenConfig> {
+        self.token(&self.defaults.native_token)
+    }
+
+    fn stable_token(&self) -> Result<&TokenConfig> {
+        self.token(&self.defaults.stable_token)
+    }
+
+    fn default_quoter(&self) -> Result<Address> {
+        ChainConfig::quoter(self, QuoterKind::UniswapV3)
+    }
+
+    fn fee_policy(&self) -> FeePolicy {
+        self.defaults.fee_policy
+    }
+
+    fn tx_type(&self) -> TxType {
+        self.defaults.tx_type
+    }
+
+    fn block_time(&self) -> Duration {
+        self.defaults.block_time
+    }
+}
+
+/// Danh sách chain = các file `<name>.toml` trong một thư mục config
+#[derive(Debug, Clone)]
+pub struct ChainRegistry {
+    dir: PathBuf,
+}
+
+impl Default for ChainRegistry {
+    fn default() -> Self {
+        ChainRegistry::new(CONFIG_DIR)
+    }
+}
+
+impl ChainRegistry {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ChainRegistry { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Tên các chain có config, sắp theo alphabet
+    pub fn available(&self) -> Result<Vec<String>> {
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| anyhow!("Cannot read chain config dir {}: {}", self.dir.display(), e))?;
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|file| !file.ends_with(POOL_REGISTRY_SUFFIX))
+            .filter_map(|file| file.strip_suffix(".toml").map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn config_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.dir.join(format!("{}.toml", name));
+        if name.is_empty() || name.ends_with(".pools") || !path.is_file() {
+            return Err(anyhow!(
+                "Unknown chain '{}': no {}. Available configs under {}: {}",
+                name,
+                path.display(),
+                self.dir.display(),
+                self.available()?.join(", ")
+            ));
+        }
+        Ok(path)
+    }
+
+    pub fn load(&self, name: &str) -> Result<ChainConfig> {
+        let path = self.config_path(name)?;
+        load_chain_config(&path.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_chains() {
+        let registry = ChainRegistry::default();
+        assert_eq!(registry.available().unwrap(), vec!["avax", "eth", "ronin"]);
+        let ronin = registry.load("ronin").unwrap();
+        assert_eq!((ronin.name(), ronin.chain_id()), ("ronin", 2021));
+        assert_eq!(ronin.native_token().unwrap().symbol, "WRON");
+        assert_eq!((ronin.fee_policy(), ronin.tx_type()), (FeePolicy::Ronin, TxType::Legacy));
+        assert_eq!(ronin.block_time(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_unknown_chain_lists_available_configs() {
+        let err = ChainRegistry::default().load("arbitrum").unwrap_err().to_string();
+        assert!(err.contains("Unknown chain 'arbitrum'"), "{err}");
+        assert!(err.contains("avax, eth, ronin"), "{err}");
+    }
+
+    #[test]
+    fn test_new_chain_needs_only_toml() {
+        let dir = std::env::temp_dir().join(format!("uv3-chain-registry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("arbitrum.toml"), r#"
+chain_id = 42161
+rpc_url = "https://arb1.arbitrum.io/rpc"
+gas_multiplier = 1.2
+me = "0x0000000000000000000000000000000000000001"
+
+[defaults]
+native_token = "WETH"
+stable_token = "USDC"
+fee_policy = { custom = { max_fee_bps = 20000, priority_fee_bps = 0 } }
+block_time_ms = 250
+
+[[tokens]]
+symbol = "WETH"
+address = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"
+decimals = 18
+
+[[tokens]]
+symbol = "USDC"
+address = "0xaf88d065e77c8cC2239327C5EDb3A432268e5831"
+decimals = 6
+
+[[quoters]]
+kind = "uniswap_v3"
+address = "0x61fFE014bA17989E743c5F6cB21bF9697530B21e"
+"#).unwrap();
+        // Registry pool nằm cạnh config không phải là chain
+        std::fs::write(dir.join("arbitrum.pools.toml"), "chain_id = 42161\nfactory = \"0x0\"\nblock = 1\n").unwrap();
+
+        let registry = ChainRegistry::new(&dir);
+        assert_eq!(registry.available().unwrap(), vec!["arbitrum"]);
+        assert!(registry.load("arbitrum.pools").is_err());
+        let arbitrum = registry.load("arbitrum").unwrap();
+        assert_eq!(arbitrum.name(), "arbitrum");
+        assert_eq!(arbitrum.fee_policy(), FeePolicy::Custom { max_fee_bps: 20000, priority_fee_bps: 0 });
+        // Thiếu tx_type: theo fee policy, thiếu fee tier: 3000 / 500
+        assert_eq!(arbitrum.tx_type(), TxType::Eip1559);
+        assert_eq!((arbitrum.defaults.default_fee, arbitrum.defaults.low_fee), (3000, 500));
+        assert_eq!(arbitrum.block_time(), Duration::from_millis(250));
+        assert_eq!(arbitrum.default_quoter().unwrap(), alloy::primitives::address!("61fFE014bA17989E743c5F6cB21bF9697530B21e"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
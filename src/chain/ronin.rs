@@ -1,8 +0,0 @@
-use alloy::primitives::{address, Address};
-
-pub static WETH_ADDR: Address = address!("c99a6A985eD2Cac1ef41640596C5A5f9F4E19Ef5");
-pub static WRON_ADDR: Address = address!("e514d9DEB7966c8BE0ca922de8a064264eA6bcd4");
-pub static V3_QUOTER_ADDR: Address = address!("84ab2f9fdc4bf66312b0819d879437b8749efdf2");
-
-pub static ME: Address = address!("0000000000000000000000000000000000000001");
-pub const CHAIN_ID: u64 = 2021;
@@ -0,0 +1,95 @@
+//SPDX-License-Identifier: MIT
+pragma solidity 0.8.24;
+
+// Runtime bytecode: src/bytecode/arb_executor.hex (nạp thẳng vào state, không chạy constructor).
+// Build: solc 0.8.24 --bin-runtime --optimize --optimize-runs 200 --evm-version cancun --metadata-hash none
+// (src/contracts/build.sh). Bản hex hiện tại vẫn là bản assemble tay theo đúng ABI + logic dưới đây,
+// chưa sinh lại bằng solc; `test_bytecode_matches_solc` (ignored, cần solc) kiểm tra hai bản khớp.
+
+interface IERC20 {
+    function balanceOf(address account) external view returns (uint256);
+    function transfer(address to, uint256 amount) external returns (bool);
+}
+
+interface IUniV3Pool {
+    function swap(
+        address recipient,
+        bool zeroForOne,
+        int256 amountSpecified,
+        uint160 sqrtPriceLimitX96,
+        bytes calldata data
+    ) external returns (int256 amount0, int256 amount1);
+}
+
+/// Flash-swap một cycle qua các pool UniV3: mỗi hop nhận token ra trước, swap hop kế tiếp
+/// ngay trong callback, rồi mới trả token vào cho pool. Không cần vốn ban đầu.
+///
+/// `path` gồm các hop 41 byte nối nhau: pool (20) | tokenIn (20) | zeroForOne (1).
+/// Token vào của hop đầu cũng là token ra của hop cuối.
+contract ArbExecutor {
+    uint160 internal constant MIN_SQRT_RATIO_LIMIT = 4295128749;
+    uint160 internal constant MAX_SQRT_RATIO_LIMIT = 1461446703485210103287273052203988822378723970341;
+    uint256 internal constant HOP_SIZE = 41;
+
+    /// Pool duy nhất được phép gọi callback tiếp theo
+    address internal expectedPool;
+    /// Account duy nhất được gọi `execute` (slot 1, `deploy_executor` ghi thẳng khi nạp runtime bytecode)
+    address public owner;
+
+    constructor() {
+        owner = msg.sender;
+    }
+
+    modifier onlyOwner() {
+        require(msg.sender == owner);
+        _;
+    }
+
+    /// Chạy cycle với `amountIn` token đầu vào, revert nếu lãi < `minProfit`.
+    /// Lãi (token đầu vào) chuyển về cho caller.
+    function execute(bytes calldata path, uint256 amountIn, uint256 minProfit) external onlyOwner returns (uint256 profit) {
+        require(path.length >= HOP_SIZE);
+        IERC20 start = IERC20(address(bytes20(path[20:40])));
+        uint256 balanceBefore = start.balanceOf(address(this));
+
+        _swap(path, amountIn);
+        expectedPool = address(0);
+
+        uint256 balanceAfter = start.balanceOf(address(this));
+        require(balanceAfter >= balanceBefore + minProfit);
+        profit = balanceAfter - balanceBefore;
+        if (profit > 0) {
+            _transfer(start, msg.sender, profit);
+        }
+    }
+
+    /// `data` là phần path bắt đầu từ hop của pool đang gọi
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata data) external {
+        require(msg.sender == expectedPool);
+        (int256 owed, int256 other) = amount0Delta > 0 ? (amount0Delta, amount1Delta) : (amount1Delta, amount0Delta);
+
+        if (data.length > HOP_SIZE) {
+            _swap(data[HOP_SIZE:], uint256(-other));
+        }
+        _transfer(IERC20(address(bytes20(data[20:40]))), msg.sender, uint256(owed));
+    }
+
+    function _swap(bytes calldata path, uint256 amountIn) internal {
+        address pool = address(bytes20(path[0:20]));
+        bool zeroForOne = path[40] != 0;
+        expectedPool = pool;
+        IUniV3Pool(pool).swap(
+            address(this),
+            zeroForOne,
+            int256(amountIn),
+            zeroForOne ? MIN_SQRT_RATIO_LIMIT : MAX_SQRT_RATIO_LIMIT,
+            path
+        );
+    }
+
+    /// Chấp nhận token không trả bool (USDT)
+    function _transfer(IERC20 token, address to, uint256 amount) internal {
+        (bool success, bytes memory result) = address(token).call(abi.encodeCall(IERC20.transfer, (to, amount)));
+        require(success && (result.length == 0 || abi.decode(result, (bool))));
+    }
+}
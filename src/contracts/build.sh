@@ -0,0 +1,36 @@
+#!/usr/bin/env sh
+# Sinh runtime bytecode (hex, không xuống dòng) từ các contract trong src/contracts.
+#
+#     src/contracts/build.sh            # ghi đè src/bytecode/arb_executor.hex, src/fixtures/mock_univ3_pool.hex
+#     src/contracts/build.sh <out_dir>  # ghi vào <out_dir> để so sánh (test `test_bytecode_matches_solc`)
+#
+# Compiler + flags cố định để output tái lập được; đổi ở đây thì đổi cả header của file .sol.
+set -eu
+
+SOLC_VERSION="0.8.24"
+SOLC_FLAGS="--optimize --optimize-runs 200 --evm-version cancun --metadata-hash none"
+
+cd "$(dirname "$0")/../.."
+solc --version | grep -q "Version: $SOLC_VERSION" || {
+    echo "solc $SOLC_VERSION required, found: $(solc --version | tail -n 1)" >&2
+    exit 1
+}
+
+out_dir="${1:-}"
+build_dir="$(mktemp -d)"
+trap 'rm -rf "$build_dir"' EXIT
+
+# build <file .sol> <tên contract> <file hex trong repo>
+build() {
+    # shellcheck disable=SC2086
+    solc $SOLC_FLAGS --bin-runtime --overwrite -o "$build_dir" "$1"
+    target="$3"
+    if [ -n "$out_dir" ]; then
+        target="$out_dir/$(basename "$3")"
+    fi
+    printf '0x%s' "$(cat "$build_dir/$2.bin-runtime")" > "$target"
+    echo "$1:$2 -> $target"
+}
+
+build src/contracts/arb_executor.sol ArbExecutor src/bytecode/arb_executor.hex
+build src/contracts/mock_univ3_pool.sol MockUniV3Pool src/fixtures/mock_univ3_pool.hex
@@ -0,0 +1,40 @@
+//SPDX-License-Identifier: MIT
+pragma solidity 0.8.24;
+
+// Pool giả cho test offline của ArbExecutor, runtime bytecode: src/fixtures/mock_univ3_pool.hex
+// Build: solc 0.8.24 --bin-runtime --optimize --optimize-runs 200 --evm-version cancun --metadata-hash none
+// (src/contracts/build.sh). Bản hex hiện tại vẫn assemble tay theo logic dưới đây, chưa sinh lại bằng solc.
+
+interface IERC20 {
+    function balanceOf(address account) external view returns (uint256);
+    function transfer(address to, uint256 amount) external returns (bool);
+}
+
+interface IUniswapV3SwapCallback {
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata data) external;
+}
+
+/// Swap exact input với tỉ giá cố định `rateBps / 10000`, bỏ qua price limit.
+/// Giống UniswapV3Pool: chuyển token ra trước, gọi callback, rồi kiểm tra đã nhận đủ token vào.
+contract MockUniV3Pool {
+    address public token0;   // slot 0
+    address public token1;   // slot 1
+    uint256 public rateBps;  // slot 2
+
+    function swap(address recipient, bool zeroForOne, int256 amountSpecified, uint160, bytes calldata data)
+        external
+        returns (int256 amount0, int256 amount1)
+    {
+        uint256 amountIn = uint256(amountSpecified);
+        uint256 amountOut = amountIn * rateBps / 10000;
+        (address tokenIn, address tokenOut) = zeroForOne ? (token0, token1) : (token1, token0);
+
+        IERC20(tokenOut).transfer(recipient, amountOut);
+        uint256 balanceBefore = IERC20(tokenIn).balanceOf(address(this));
+        (amount0, amount1) = zeroForOne
+            ? (int256(amountIn), -int256(amountOut))
+            : (-int256(amountOut), int256(amountIn));
+        IUniswapV3SwapCallback(msg.sender).uniswapV3SwapCallback(amount0, amount1, data);
+        require(IERC20(tokenIn).balanceOf(address(this)) >= balanceBefore + amountIn);
+    }
+}
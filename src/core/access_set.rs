@@ -0,0 +1,203 @@
+use alloy::eips::eip2930::{AccessList, AccessListItem};
+use alloy::primitives::{Address, B256, U256};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Tăng khi đổi layout manifest
+pub const ACCESS_MANIFEST_VERSION: u32 = 1;
+
+/// Những gì một call đã đọc của một account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountAccess {
+    /// Code hash lúc đọc `basic` (None nếu account không tồn tại)
+    pub code_hash: Option<B256>,
+    /// Slot → giá trị lúc đọc lần đầu
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Access set của một hoặc nhiều call: account, code hash, storage slot kèm giá trị, block hash
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessSet {
+    pub accounts: BTreeMap<Address, AccountAccess>,
+    pub code_hashes: Vec<B256>,
+    pub block_hashes: BTreeMap<u64, B256>,
+}
+
+impl AccessSet {
+    pub fn record_account(&mut self, address: Address, code_hash: Option<B256>) {
+        let account = self.accounts.entry(address).or_default();
+        if account.code_hash.is_none() {
+            account.code_hash = code_hash;
+        }
+    }
+
+    pub fn record_code(&mut self, code_hash: B256) {
+        if !self.code_hashes.contains(&code_hash) {
+            self.code_hashes.push(code_hash);
+        }
+    }
+
+    /// Giữ giá trị đọc đầu tiên (state trước call) nếu slot bị đọc lại
+    pub fn record_storage(&mut self, address: Address, slot: U256, value: U256) {
+        self.accounts.entry(address).or_default().storage.entry(slot).or_insert(value);
+    }
+
+    pub fn record_block_hash(&mut self, number: u64, hash: B256) {
+        self.block_hashes.insert(number, hash);
+    }
+
+    /// Gộp access set của call khác, bỏ trùng
+    pub fn merge(&mut self, other: &AccessSet) {
+        for (address, access) in &other.accounts {
+            self.record_account(*address, access.code_hash);
+            for (slot, value) in &access.storage {
+                self.record_storage(*address, *slot, *value);
+            }
+        }
+        for code_hash in &other.code_hashes {
+            self.record_code(*code_hash);
+        }
+        for (number, hash) in &other.block_hashes {
+            self.record_block_hash(*number, *hash);
+        }
+    }
+
+    pub fn storage_slots(&self) -> Vec<(Address, U256)> {
+        self.accounts
+            .iter()
+            .flat_map(|(address, access)| access.storage.keys().map(move |slot| (*address, *slot)))
+            .collect()
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.accounts.values().map(|access| access.storage.len()).sum()
+    }
+
+    /// EIP-2930 access list cho transaction thật: bỏ `exclude` (sender, contract giả...) và precompile
+    pub fn to_access_list(&self, exclude: &[Address]) -> AccessList {
+        let items = self
+            .accounts
+            .iter()
+            .filter(|(address, _)| !exclude.contains(address) && !is_precompile(address))
+            .map(|(address, access)| AccessListItem {
+                address: *address,
+                storage_keys: access.storage.keys().map(|slot| B256::from(*slot)).collect(),
+            })
+            .collect();
+        AccessList(items)
+    }
+}
+
+/// Precompile nằm ở các địa chỉ nhỏ (0x01..0x0a, 0x100...), luôn warm nên không cần trong access list
+fn is_precompile(address: &Address) -> bool {
+    address.0[..18].iter().all(|byte| *byte == 0)
+}
+
+/// Manifest JSON: access set đã gộp của một lần chạy, dùng để prewarm lần chạy sau
+/// và dựng access list cho transaction thật
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessManifest {
+    pub version: u32,
+    pub chain_id: u64,
+    pub method: String,
+    /// Block lúc record (None = latest)
+    pub block: Option<u64>,
+    /// Số call đã gộp vào `access`
+    pub calls: usize,
+    pub access: AccessSet,
+}
+
+impl AccessManifest {
+    pub fn new(chain_id: u64, method: &str, block: Option<u64>) -> Self {
+        AccessManifest {
+            version: ACCESS_MANIFEST_VERSION,
+            chain_id,
+            method: method.to_string(),
+            block,
+            calls: 0,
+            access: AccessSet::default(),
+        }
+    }
+
+    /// Gộp access set của một call
+    pub fn record_call(&mut self, access: &AccessSet) {
+        self.calls += 1;
+        self.access.merge(access);
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let manifest: AccessManifest = serde_json::from_str(json)?;
+        if manifest.version != ACCESS_MANIFEST_VERSION {
+            bail!("Unsupported access manifest version {} (expected {})", manifest.version, ACCESS_MANIFEST_VERSION);
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?).with_context(|| format!("writing access manifest {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("reading access manifest {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("parsing access manifest {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_dedups_and_keeps_first_value() {
+        let (pool, token) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let mut first = AccessSet::default();
+        first.record_account(pool, Some(B256::repeat_byte(1)));
+        first.record_storage(pool, U256::ZERO, U256::from(10));
+        first.record_code(B256::repeat_byte(1));
+
+        let mut second = AccessSet::default();
+        second.record_storage(pool, U256::ZERO, U256::from(99));
+        second.record_storage(pool, U256::from(4), U256::from(7));
+        second.record_storage(token, U256::from(1), U256::from(1));
+        second.record_code(B256::repeat_byte(1));
+        second.record_block_hash(100, B256::repeat_byte(2));
+
+        let mut manifest = AccessManifest::new(1, "arbitrage", Some(100));
+        manifest.record_call(&first);
+        manifest.record_call(&second);
+        let access = &manifest.access;
+        assert_eq!(manifest.calls, 2);
+        assert_eq!(access.accounts[&pool].storage[&U256::ZERO], U256::from(10));
+        assert_eq!(access.accounts[&pool].code_hash, Some(B256::repeat_byte(1)));
+        assert_eq!(access.slot_count(), 3);
+        assert_eq!(access.code_hashes.len(), 1);
+        assert_eq!(access.storage_slots()[0], (pool, U256::ZERO));
+
+        let loaded = AccessManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, manifest);
+        let mut future = manifest.clone();
+        future.version = ACCESS_MANIFEST_VERSION + 1;
+        assert!(AccessManifest::from_json(&future.to_json().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_access_list_skips_sender_and_precompiles() {
+        let (sender, pool) = (Address::repeat_byte(0x01), Address::repeat_byte(0xaa));
+        let precompile = Address::with_last_byte(0x02);
+        let mut access = AccessSet::default();
+        access.record_account(sender, None);
+        access.record_account(precompile, None);
+        access.record_storage(pool, U256::from(4), U256::from(7));
+
+        let list = access.to_access_list(&[sender]);
+        assert_eq!(list.0.len(), 1);
+        assert_eq!(list.0[0].address, pool);
+        assert_eq!(list.0[0].storage_keys, vec![B256::from(U256::from(4))]);
+    }
+}
@@ -0,0 +1,323 @@
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::primitives::{address, Address, Bytes, U256};
+use anyhow::Result;
+use revm::{
+    db::{CacheDB, DatabaseCommit, DatabaseRef},
+    primitives::{BlockEnv, Bytecode, ExecutionResult, Output},
+};
+
+use crate::core::bundle::{transact_bundle, Bundle, BundleResult};
+use crate::core::db::init_account_with_bytecode;
+use crate::core::path_search::Cycle;
+use crate::source::abi::{decode_execute_response, encode_executor_path, execute_calldata};
+
+/// Address mặc định để nạp executor khi config không có `arb_executor`
+pub const DEFAULT_EXECUTOR: Address = address!("00000000000000000000000000000000000a4b17");
+
+/// Slot của `owner` trong `ArbExecutor` (slot 0 là `expectedPool`)
+pub const EXECUTOR_OWNER_SLOT: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+/// Nạp runtime bytecode của `ArbExecutor` vào `executor` với `owner` là account duy nhất được gọi `execute`.
+/// Constructor không chạy nên `owner` được ghi thẳng vào storage.
+pub fn deploy_executor<ExtDB>(executor: Address, owner: Address, cache_db: &mut CacheDB<ExtDB>) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let bytecode = include_str!("../bytecode/arb_executor.hex");
+    init_account_with_bytecode(executor, Bytecode::new_raw(Bytes::from_str(bytecode)?), cache_db)?;
+    cache_db.insert_account_storage(executor, EXECUTOR_OWNER_SLOT, U256::from_be_slice(owner.as_slice()))?;
+    Ok(())
+}
+
+/// Path cho `execute`: mỗi hop là pool, token vào và chiều swap
+pub fn executor_path(cycle: &Cycle) -> Bytes {
+    let hops: Vec<_> = cycle.hops.iter().map(|hop| (hop.pool, hop.token_in, hop.token_in < hop.token_out)).collect();
+    encode_executor_path(&hops)
+}
+
+/// Executor được gọi từ đâu, trên block env nào
+#[derive(Debug, Clone)]
+pub struct ExecutionEnv {
+    pub executor: Address,
+    pub me: Address,
+    pub block: BlockEnv,
+    pub chain_id: u64,
+}
+
+/// Kết quả swap thật của một cycle qua executor
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    pub cycle: Cycle,
+    pub amount_in: U256,
+    /// Lãi executor trả về (token đầu của cycle), None nếu revert
+    pub profit: Option<U256>,
+    pub bundle: BundleResult,
+}
+
+impl fmt::Display for ExecutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.profit {
+            Some(profit) => write!(f, "✅ {} | in={} profit={} gas={}", self.cycle, self.amount_in, profit, self.bundle.gas_used())?,
+            None => {
+                let reason = self.bundle.outcomes.last().map(|outcome| describe_failure(&outcome.result)).unwrap_or_default();
+                write!(f, "❌ {} | in={} reverted: {}", self.cycle, self.amount_in, reason)?
+            }
+        }
+        for delta in &self.bundle.balance_deltas {
+            let token = delta.token.map_or("native".to_string(), |token| format!("{:?}", token));
+            write!(f, "\n    ME {}: {} → {} ({})", token, delta.before, delta.after, delta.delta())?;
+        }
+        Ok(())
+    }
+}
+
+fn describe_failure(result: &ExecutionResult) -> String {
+    match result {
+        ExecutionResult::Revert { output, .. } if output.is_empty() => "empty revert".to_string(),
+        ExecutionResult::Revert { output, .. } => format!("{}", output),
+        ExecutionResult::Halt { reason, .. } => format!("{:?}", reason),
+        ExecutionResult::Success { .. } => String::new(),
+    }
+}
+
+/// Gọi `execute` của executor từ `me` trên `db`: swap thật qua pool với token transfer thật.
+/// Balance của `me` đọc bằng `balanceOf` của chính token (storage ERC20 thật, không mock).
+/// Chỉ commit xuống `db` khi không revert.
+pub fn simulate_execution<DB>(
+    db: &mut DB,
+    env: &ExecutionEnv,
+    cycle: &Cycle,
+    amount_in: U256,
+    min_profit: U256,
+) -> Result<ExecutionReport>
+where
+    DB: DatabaseRef + DatabaseCommit,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let calldata = execute_calldata(executor_path(cycle), amount_in, min_profit);
+    let bundle = Bundle::new().call(&format!("execute {}", cycle), env.me, env.executor, calldata, U256::ZERO);
+
+    let tokens: Vec<Address> = cycle.hops.iter().map(|hop| hop.token_in).collect();
+    let bundle = transact_bundle(db, &bundle, &env.block, env.chain_id, env.me, &tokens)?;
+
+    let profit = match bundle.outcomes.last().map(|outcome| &outcome.result) {
+        Some(ExecutionResult::Success { output: Output::Call(output), .. }) if bundle.is_success() => {
+            Some(decode_execute_response(output.clone())?)
+        }
+        _ => None,
+    };
+    Ok(ExecutionReport { cycle: cycle.clone(), amount_in, profit, bundle })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::{insert_mapping_storage_slot, revm_call_db};
+    use crate::core::path_search::PoolEdge;
+    use crate::source::abi::{balance_of_calldata, decode_balance_of_response, decode_owner_response, owner_calldata, uniswapV3SwapCallbackCall};
+    use crate::types::ONE_ETHER;
+    use alloy::primitives::{aliases::U24, I256, U160};
+    use alloy::sol;
+    use alloy::sol_types::SolCall;
+    use revm::db::EmptyDB;
+
+    sol! {
+        struct ExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint24 fee;
+            address recipient;
+            uint256 amountIn;
+            uint256 amountOutMinimum;
+            uint160 sqrtPriceLimitX96;
+        }
+        function exactInputSingle(ExactInputSingleParams calldata params) external payable returns (uint256 amountOut);
+    }
+
+    const ME: Address = Address::repeat_byte(0x01);
+    const TOKENS: [Address; 3] = [Address::repeat_byte(0xa1), Address::repeat_byte(0xb2), Address::repeat_byte(0xc3)];
+
+    fn balance_of(cache_db: &mut CacheDB<EmptyDB>, token: Address, holder: Address) -> U256 {
+        let response = revm_call_db(Address::ZERO, token, balance_of_calldata(holder), &BlockEnv::default(), cache_db).unwrap();
+        decode_balance_of_response(response).unwrap()
+    }
+
+    /// Token ERC20 giả + mỗi cặp token liền nhau một pool giả với tỉ giá `rates_bps[i]` theo chiều của cycle.
+    /// Pool giữ sẵn 1000 ether mỗi token.
+    fn setup(rates_bps: &[u64]) -> (CacheDB<EmptyDB>, Cycle, ExecutionEnv) {
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        let erc20 = Bytecode::new_raw(Bytes::from_str(include_str!("../bytecode/generic_erc20.hex")).unwrap());
+        for token in TOKENS {
+            init_account_with_bytecode(token, erc20.clone(), &mut cache_db).unwrap();
+        }
+        let pool_code = Bytecode::new_raw(Bytes::from_str(include_str!("../fixtures/mock_univ3_pool.hex")).unwrap());
+
+        let mut hops = Vec::new();
+        for (index, rate) in rates_bps.iter().enumerate() {
+            let pool = Address::repeat_byte(0x10 + index as u8);
+            let (token_in, token_out) = (TOKENS[index], TOKENS[(index + 1) % rates_bps.len()]);
+            let (token0, token1) = if token_in < token_out { (token_in, token_out) } else { (token_out, token_in) };
+            init_account_with_bytecode(pool, pool_code.clone(), &mut cache_db).unwrap();
+            cache_db.insert_account_storage(pool, U256::ZERO, U256::from_be_slice(token0.as_slice())).unwrap();
+            cache_db.insert_account_storage(pool, U256::from(1), U256::from_be_slice(token1.as_slice())).unwrap();
+            cache_db.insert_account_storage(pool, U256::from(2), U256::from(*rate)).unwrap();
+            for token in [token0, token1] {
+                insert_mapping_storage_slot(token, U256::ZERO, pool, ONE_ETHER * U256::from(1000), &mut cache_db).unwrap();
+            }
+            hops.push(PoolEdge {
+                pool, token_in, token_out,
+                symbol_in: format!("T{}", index), symbol_out: format!("T{}", (index + 1) % rates_bps.len()), fee: 500,
+            });
+        }
+
+        let env = ExecutionEnv { executor: DEFAULT_EXECUTOR, me: ME, block: BlockEnv::default(), chain_id: 1 };
+        deploy_executor(env.executor, env.me, &mut cache_db).unwrap();
+        (cache_db, Cycle { hops }, env)
+    }
+
+    #[test]
+    fn test_executor_path_packs_hops() {
+        let (_, cycle, _) = setup(&[10_000, 10_000]);
+        let path = executor_path(&cycle);
+        assert_eq!(path.len(), 2 * 41);
+        assert_eq!(&path[0..20], cycle.hops[0].pool.as_slice());
+        assert_eq!(&path[20..40], TOKENS[0].as_slice());
+        assert_eq!((path[40], path[81]), (1, 0));
+    }
+
+    #[test]
+    fn test_flash_swap_cycle_pays_profit_to_me() {
+        // T0 → T1 (x2) → T2 (x0.8) → T0 (x0.75): ra 1.2 lần
+        let (mut cache_db, cycle, env) = setup(&[20_000, 8_000, 7_500]);
+        let amount_in = ONE_ETHER;
+
+        let report = simulate_execution(&mut cache_db, &env, &cycle, amount_in, U256::ZERO).unwrap();
+        assert!(report.bundle.is_success(), "{}", report);
+        let profit = ONE_ETHER / U256::from(5);
+        assert_eq!(report.profit, Some(profit));
+        assert_eq!(report.bundle.balance_deltas[1].token, Some(TOKENS[0]));
+        assert_eq!(report.bundle.balance_deltas[1].after, profit);
+        assert!(report.bundle.balance_deltas[2..].iter().all(|delta| delta.after == delta.before));
+
+        // Đã commit: ME giữ lãi, executor không còn token nào
+        assert_eq!(balance_of(&mut cache_db, TOKENS[0], ME), profit);
+        for token in TOKENS {
+            assert_eq!(balance_of(&mut cache_db, token, env.executor), U256::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_unprofitable_cycle_reverts_without_state_change() {
+        let (mut cache_db, cycle, env) = setup(&[10_000, 9_000]);
+        let report = simulate_execution(&mut cache_db, &env, &cycle, ONE_ETHER, U256::ZERO).unwrap();
+        assert_eq!((report.bundle.reverted, report.profit), (Some(0), None));
+        assert_eq!(balance_of(&mut cache_db, TOKENS[1], cycle.hops[0].pool), ONE_ETHER * U256::from(1000));
+
+        // Có lãi nhưng dưới `minProfit`
+        let (mut cache_db, cycle, env) = setup(&[10_000, 11_000]);
+        let report = simulate_execution(&mut cache_db, &env, &cycle, ONE_ETHER, ONE_ETHER).unwrap();
+        assert_eq!(report.bundle.reverted, Some(0));
+        let report = simulate_execution(&mut cache_db, &env, &cycle, ONE_ETHER, U256::ZERO).unwrap();
+        assert_eq!(report.profit, Some(ONE_ETHER / U256::from(10)));
+    }
+
+    #[test]
+    fn test_callback_rejects_unexpected_caller() {
+        let (mut cache_db, cycle, env) = setup(&[20_000, 10_000]);
+        let calldata = uniswapV3SwapCallbackCall {
+            amount0Delta: I256::try_from(1).unwrap(),
+            amount1Delta: I256::ZERO,
+            data: executor_path(&cycle),
+        };
+        let bundle = Bundle::new().call("callback", ME, env.executor, calldata.abi_encode().into(), U256::ZERO);
+        let result = transact_bundle(&mut cache_db, &bundle, &env.block, 1, ME, &[]).unwrap();
+        assert_eq!(result.reverted, Some(0));
+    }
+
+    #[test]
+    fn test_execute_only_owner() {
+        let (mut cache_db, cycle, env) = setup(&[20_000, 10_000]);
+        let response = revm_call_db(ME, env.executor, owner_calldata(), &env.block, &mut cache_db).unwrap();
+        assert_eq!(decode_owner_response(response).unwrap(), ME);
+
+        // Cycle có lãi nhưng caller không phải owner
+        let stranger = ExecutionEnv { me: Address::repeat_byte(0x02), ..env.clone() };
+        let report = simulate_execution(&mut cache_db, &stranger, &cycle, ONE_ETHER, U256::ZERO).unwrap();
+        assert_eq!((report.bundle.reverted, report.profit), (Some(0), None));
+        let report = simulate_execution(&mut cache_db, &env, &cycle, ONE_ETHER, U256::ZERO).unwrap();
+        assert_eq!(report.profit, Some(ONE_ETHER));
+    }
+
+    /// File hex trong repo phải đúng là output của `src/contracts/build.sh` (solc + flags ghi trong .sol)
+    #[test]
+    #[ignore = "requires solc 0.8.24"]
+    fn test_bytecode_matches_solc() {
+        let out_dir = std::env::temp_dir().join(format!("uv3-solc-{}", std::process::id()));
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let status = std::process::Command::new("sh").arg("src/contracts/build.sh").arg(&out_dir).status().unwrap();
+        assert!(status.success());
+        for committed in ["src/bytecode/arb_executor.hex", "src/fixtures/mock_univ3_pool.hex"] {
+            let name = std::path::Path::new(committed).file_name().unwrap();
+            let built = std::fs::read_to_string(out_dir.join(name)).unwrap();
+            assert_eq!(std::fs::read_to_string(committed).unwrap(), built, "{committed} is not solc output, run src/contracts/build.sh");
+        }
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    /// Executor trên WETH / USDC và pool UniV3 thật: trader (WETH thật qua `BalanceSlots`) bán lớn vào
+    /// pool 500 qua SwapRouter02, rồi cycle WETH -(3000)-> USDC -(500)-> WETH mua lại WETH rẻ
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires RPC_URL"]
+    async fn test_backrun_on_real_tokens_and_pools() {
+        use crate::core::backrun::next_block_env;
+        use crate::core::balance_slot::BalanceSlots;
+        use crate::core::db::init_cache_db;
+        use crate::core::provider::MultiProvider;
+        use crate::source::abi::approve_calldata;
+        use crate::types::load_chain_config;
+        use alloy::eips::BlockId;
+
+        const SWAP_ROUTER_02: Address = address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45");
+        const TRADER: Address = Address::repeat_byte(0x7a);
+
+        let config = load_chain_config("src/config/eth.toml").unwrap();
+        let multi_provider = MultiProvider::new(&[std::env::var("RPC_URL").expect("Please set RPC_URL env")])
+            .with_chain_id(config.chain_id);
+        let pinned = multi_provider.pin_block(BlockId::latest()).await.unwrap();
+        let mut cache_db = init_cache_db(&multi_provider);
+        let (weth, usdc) = (config.token("WETH").unwrap().address, config.token("USDC").unwrap().address);
+        let (pool_500, pool_3000) = (config.pool(weth, usdc, 500).unwrap().address, config.pool(weth, usdc, 3000).unwrap().address);
+
+        let env = ExecutionEnv { executor: DEFAULT_EXECUTOR, me: ME, block: next_block_env(&pinned, 0), chain_id: config.chain_id };
+        deploy_executor(env.executor, env.me, &mut cache_db).unwrap();
+
+        let amount = ONE_ETHER * U256::from(5_000);
+        let mut balance_slots = BalanceSlots::for_provider(&multi_provider).with_declared(&config.tokens);
+        balance_slots.set_real_token_balance(weth, TRADER, amount, &mut cache_db).await.unwrap();
+        let params = ExactInputSingleParams {
+            tokenIn: weth, tokenOut: usdc, fee: U24::from(500), recipient: TRADER,
+            amountIn: amount, amountOutMinimum: U256::ZERO, sqrtPriceLimitX96: U160::ZERO,
+        };
+        let victim = Bundle::new()
+            .call("approve", TRADER, weth, approve_calldata(SWAP_ROUTER_02, amount), U256::ZERO)
+            .call("swap", TRADER, SWAP_ROUTER_02, exactInputSingleCall { params }.abi_encode().into(), U256::ZERO);
+        let victim = transact_bundle(&mut cache_db, &victim, &env.block, config.chain_id, TRADER, &[weth, usdc]).unwrap();
+        assert!(victim.is_success(), "{:?}", victim.outcomes);
+
+        let cycle = Cycle {
+            hops: vec![
+                PoolEdge { pool: pool_3000, token_in: weth, token_out: usdc, symbol_in: "WETH".into(), symbol_out: "USDC".into(), fee: 3000 },
+                PoolEdge { pool: pool_500, token_in: usdc, token_out: weth, symbol_in: "USDC".into(), symbol_out: "WETH".into(), fee: 500 },
+            ],
+        };
+        let report = simulate_execution(&mut cache_db, &env, &cycle, ONE_ETHER * U256::from(10), U256::ZERO).unwrap();
+        assert!(report.bundle.is_success(), "{}", report);
+        let profit = report.profit.unwrap();
+        assert!(profit > U256::ZERO);
+        let weth_delta = report.bundle.balance_deltas.iter().find(|delta| delta.token == Some(weth)).unwrap();
+        assert_eq!(weth_delta.after - weth_delta.before, profit);
+    }
+}
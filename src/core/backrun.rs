@@ -0,0 +1,280 @@
+use std::fmt;
+
+use alloy::{
+    primitives::{Address, I256, B256, U256},
+    rpc::types::Transaction,
+    sol_types::SolEvent,
+};
+use anyhow::{anyhow, Result};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{BlockEnv, ExecutionResult, TxEnv, TxKind},
+    Evm,
+};
+
+use crate::core::path_search::CycleResult;
+use crate::core::provider::PinnedBlock;
+use crate::source::abi::IUniswapV3PoolEvents::Swap;
+use crate::types::ChainConfig;
+
+/// Env của block kế tiếp, nơi pending tx sẽ được đưa vào
+pub fn next_block_env(pinned: &PinnedBlock, base_fee: u128) -> BlockEnv {
+    BlockEnv {
+        number: U256::from(pinned.number + 1),
+        timestamp: U256::from(pinned.timestamp + 1),
+        basefee: U256::from(base_fee),
+        ..Default::default()
+    }
+}
+
+/// `TxEnv` của transaction lấy từ RPC (legacy, EIP-2930, EIP-1559)
+pub fn tx_env(tx: &Transaction) -> TxEnv {
+    TxEnv {
+        caller: tx.from,
+        gas_limit: tx.gas,
+        // EIP-1559: REVM coi `gas_price` là max fee khi có `gas_priority_fee`
+        gas_price: U256::from(tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default()),
+        transact_to: tx.to.map(TxKind::Call).unwrap_or(TxKind::Create),
+        value: tx.value,
+        data: tx.input.clone(),
+        nonce: Some(tx.nonce),
+        chain_id: tx.chain_id,
+        access_list: tx.access_list.clone().map(|list| list.0).unwrap_or_default(),
+        gas_priority_fee: tx.max_priority_fee_per_gas.map(U256::from),
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        max_fee_per_blob_gas: tx.max_fee_per_blob_gas.map(U256::from),
+        authorization_list: None,
+    }
+}
+
+/// Lọc rẻ trước khi simulate: tx gọi thẳng pool, hoặc calldata có chứa address của pool / token
+/// đang track (router encode token trong params / path)
+pub fn may_touch_pools(tx: &Transaction, config: &ChainConfig) -> bool {
+    if tx.input.is_empty() {
+        return false;
+    }
+    config.pools.iter().any(|pool| {
+        tx.to == Some(pool.address)
+            || [pool.address, pool.token0, pool.token1]
+                .iter()
+                .any(|address| tx.input.windows(20).any(|window| window == address.as_slice()))
+    })
+}
+
+/// Một `Swap` của victim trên pool đang track
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VictimSwap {
+    pub pool: Address,
+    pub amount0: I256,
+    pub amount1: I256,
+}
+
+/// Pending tx sau khi áp lên state
+#[derive(Debug, Clone)]
+pub struct VictimTx {
+    pub hash: B256,
+    pub from: Address,
+    pub gas_used: u64,
+    pub swaps: Vec<VictimSwap>,
+}
+
+/// Chạy pending `tx` bằng `transact_commit` lên `cache_db` (state sau tx nằm lại trong cache),
+/// trả về các swap trên pool thuộc `tracked`. Lỗi nếu tx revert / halt.
+pub fn apply_pending_tx<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    tx: &Transaction,
+    block: &BlockEnv,
+    chain_id: u64,
+    tracked: &[Address],
+) -> Result<VictimTx>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut evm = Evm::builder()
+        .with_db(cache_db)
+        .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+        .modify_block_env(|env| *env = block.clone())
+        .modify_tx_env(|env| *env = tx_env(tx))
+        .build();
+    let result = evm.transact_commit().map_err(|e| anyhow!("Pending tx {} invalid: {:?}", tx.hash, e))?;
+
+    let (logs, gas_used) = match result {
+        ExecutionResult::Success { logs, gas_used, .. } => (logs, gas_used),
+        result => return Err(anyhow!("Pending tx {} failed: {:?}", tx.hash, result)),
+    };
+    let swaps = logs
+        .iter()
+        .filter(|log| tracked.contains(&log.address) && log.topics().first() == Some(&Swap::SIGNATURE_HASH))
+        .map(|log| {
+            let event = Swap::decode_log_data(&log.data, true)?;
+            Ok(VictimSwap { pool: log.address, amount0: event.amount0, amount1: event.amount1 })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(VictimTx { hash: tx.hash, from: tx.from, gas_used, swaps })
+}
+
+/// Các cycle backrun cho một pending tx, xếp theo lợi nhuận gộp (chưa trừ gas) trên state sau tx
+#[derive(Debug, Clone)]
+pub struct BackrunReport {
+    pub victim: VictimTx,
+    /// Cycle có lãi sau victim, kèm lợi nhuận của cùng cycle khi không có victim
+    pub candidates: Vec<(CycleResult, Option<I256>)>,
+}
+
+impl BackrunReport {
+    /// Giữ cycle có lãi từ kết quả đã xếp hạng, gắn lợi nhuận baseline (state trước victim)
+    pub fn new(victim: VictimTx, ranked: Vec<CycleResult>, baseline: &[CycleResult]) -> Self {
+        let candidates = ranked
+            .into_iter()
+            .filter(|result| result.gross_profit.is_positive())
+            .map(|result| {
+                let before = baseline.iter().find(|base| base.cycle == result.cycle).map(|base| base.gross_profit);
+                (result, before)
+            })
+            .collect();
+        BackrunReport { victim, candidates }
+    }
+}
+
+impl fmt::Display for BackrunReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "🎯 Victim {} from {:?} (gas {}):", self.victim.hash, self.victim.from, self.victim.gas_used)?;
+        for swap in &self.victim.swaps {
+            write!(f, "\n    swap on {:?}: amount0={} amount1={}", swap.pool, swap.amount0, swap.amount1)?;
+        }
+        if self.candidates.is_empty() {
+            return write!(f, "\n    no profitable backrun");
+        }
+        for (rank, (result, before)) in self.candidates.iter().enumerate() {
+            let before = before.map_or("n/a".to_string(), |gross| gross.to_string());
+            write!(
+                f,
+                "\n{:>2}. {} | in={} out={} gross={} (before victim: {})",
+                rank + 1, result.cycle, result.best_amount_in, result.amount_out, result.gross_profit, before
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::init_account_with_bytecode;
+    use crate::source::builder::{FeePolicy, TxType};
+    use crate::types::{ChainDefaults, PoolConfig, Protocol};
+    use std::time::Duration;
+    use alloy::primitives::{hex, Bytes};
+    use revm::db::{DatabaseRef, EmptyDB};
+    use revm::primitives::Bytecode;
+
+    const POOL: Address = Address::repeat_byte(0xaa);
+    const TOKEN0: Address = Address::repeat_byte(0xbb);
+    const TOKEN1: Address = Address::repeat_byte(0xcc);
+    const CALLER: Address = Address::repeat_byte(0x11);
+
+    /// `amount0 = 5`, các field khác 0: MSTORE(0, 5) rồi LOG3(0, 0xa0, Swap, 0, 0)
+    fn swap_emitter() -> Bytecode {
+        let mut code = hex!("6005600052 6000 6000 7f").to_vec();
+        code.extend_from_slice(Swap::SIGNATURE_HASH.as_slice());
+        code.extend_from_slice(&hex!("60a0 6000 a3 00"));
+        Bytecode::new_raw(code.into())
+    }
+
+    fn call(to: Address, input: Bytes) -> Transaction {
+        Transaction { hash: B256::repeat_byte(0x01), from: CALLER, to: Some(to), gas: 100_000, input, ..Default::default() }
+    }
+
+    fn config() -> ChainConfig {
+        ChainConfig {
+            name: "test".to_string(),
+            chain_id: 1,
+            rpc_url: String::new(),
+            rpc_urls: vec![],
+            gas_multiplier: 1.0,
+            me: Address::ZERO,
+            arb_executor: None,
+            factory: None,
+            pool_registry: None,
+            defaults: ChainDefaults {
+                native_token: "WETH".to_string(),
+                stable_token: "USDC".to_string(),
+                default_fee: 3000,
+                low_fee: 500,
+                fee_policy: FeePolicy::Eth,
+                tx_type: TxType::Eip1559,
+                block_time: Duration::from_secs(12),
+            },
+            tokens: Vec::new(),
+            quoters: Vec::new(),
+            pools: vec![PoolConfig {
+                protocol: Protocol::UniV3, address: POOL, token0: TOKEN0, token1: TOKEN1,
+                symbol0: "T0".to_string(), symbol1: "T1".to_string(), fee: 500, tick_spacing: Some(10),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_tx_env_from_eip1559_and_create() {
+        let mut tx = call(POOL, Bytes::from_static(&[1, 2]));
+        tx.nonce = 7;
+        tx.chain_id = Some(1);
+        tx.gas_price = Some(30);
+        tx.max_fee_per_gas = Some(50);
+        tx.max_priority_fee_per_gas = Some(2);
+        let env = tx_env(&tx);
+        assert_eq!((env.caller, env.transact_to, env.gas_limit), (CALLER, TxKind::Call(POOL), 100_000));
+        assert_eq!((env.gas_price, env.gas_priority_fee), (U256::from(50), Some(U256::from(2))));
+        assert_eq!((env.nonce, env.chain_id, env.data.len()), (Some(7), Some(1), 2));
+
+        tx.to = None;
+        tx.max_fee_per_gas = None;
+        tx.max_priority_fee_per_gas = None;
+        let env = tx_env(&tx);
+        assert_eq!((env.transact_to, env.gas_price, env.gas_priority_fee), (TxKind::Create, U256::from(30), None));
+    }
+
+    #[test]
+    fn test_apply_pending_tx_commits_and_decodes_tracked_swaps() {
+        let other = Address::repeat_byte(0xdd);
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        init_account_with_bytecode(POOL, swap_emitter(), &mut cache_db).unwrap();
+        init_account_with_bytecode(other, swap_emitter(), &mut cache_db).unwrap();
+        let block = BlockEnv::default();
+
+        let victim = apply_pending_tx(&mut cache_db, &call(POOL, Bytes::new()), &block, 1, &[POOL]).unwrap();
+        assert_eq!(victim.hash, B256::repeat_byte(0x01));
+        assert_eq!(victim.swaps, vec![VictimSwap { pool: POOL, amount0: I256::try_from(5).unwrap(), amount1: I256::ZERO }]);
+        // State sau tx nằm lại trong cache
+        assert_eq!(cache_db.basic_ref(CALLER).unwrap().unwrap().nonce, 1);
+
+        let mut tx = call(other, Bytes::new());
+        tx.nonce = 1;
+        let victim = apply_pending_tx(&mut cache_db, &tx, &block, 1, &[POOL]).unwrap();
+        assert!(victim.swaps.is_empty());
+    }
+
+    #[test]
+    fn test_apply_pending_tx_fails_on_revert() {
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        init_account_with_bytecode(POOL, Bytecode::new_raw(hex!("60006000fd").into()), &mut cache_db).unwrap();
+        let err = apply_pending_tx(&mut cache_db, &call(POOL, Bytes::new()), &BlockEnv::default(), 1, &[POOL]).unwrap_err();
+        assert!(err.to_string().contains("failed"));
+    }
+
+    #[test]
+    fn test_may_touch_pools_matches_target_or_calldata() {
+        let config = config();
+        let router = Address::repeat_byte(0xee);
+        assert!(may_touch_pools(&call(POOL, Bytes::from_static(&[0x12, 0x34, 0x56, 0x78])), &config));
+
+        // Router call có token trong params (bị lệch khỏi word boundary như trong path)
+        let mut input = vec![0x04, 0xe4, 0x5a, 0xaf, 0x00, 0x01];
+        input.extend_from_slice(TOKEN1.as_slice());
+        assert!(may_touch_pools(&call(router, input.into()), &config));
+
+        assert!(!may_touch_pools(&call(router, Bytes::from_static(&[0xd0, 0xe3, 0x0d, 0xb0])), &config));
+        assert!(!may_touch_pools(&call(POOL, Bytes::new()), &config));
+    }
+}
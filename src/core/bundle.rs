@@ -0,0 +1,280 @@
+//! API bundle của crate: `transact_bundle` chạy nguyên tử một chuỗi tx trên DB bất kỳ có commit.
+//! `SimulationStrategyTrait::transact_bundle` của `Revm` (`src/simulation`) gọi lại hàm này trên `StateDB`;
+//! module đó chưa nằm trong module tree của crate vì phụ thuộc `e_primitives` không có ở đây.
+
+use std::fmt;
+
+use alloy::primitives::{Address, Bytes, Log, I256, U256};
+use anyhow::{anyhow, Result};
+use revm::{
+    db::{CacheDB, DatabaseCommit, DatabaseRef},
+    primitives::{BlockEnv, EvmState, ExecutionResult, TxEnv, TxKind},
+    Evm,
+};
+
+use crate::core::db::revm_call_db;
+use crate::source::abi::{approve_calldata, balance_of_calldata, decode_balance_of_response};
+
+/// Một transaction trong bundle, `label` chỉ dùng để in log / báo lỗi
+#[derive(Debug, Clone)]
+pub struct BundleTx {
+    pub label: String,
+    pub tx: TxEnv,
+}
+
+/// Chuỗi transaction có thứ tự (victim, arb, approve...) chạy nguyên tử trên cùng một state fork
+#[derive(Debug, Clone, Default)]
+pub struct Bundle {
+    pub txs: Vec<BundleTx>,
+}
+
+impl Bundle {
+    pub fn new() -> Self {
+        Bundle::default()
+    }
+
+    /// Thêm transaction bất kỳ (vd. victim từ `backrun::tx_env`)
+    pub fn push(mut self, label: &str, tx: TxEnv) -> Self {
+        self.txs.push(BundleTx { label: label.to_string(), tx });
+        self
+    }
+
+    /// Thêm call của `from` tới `to`; nonce để REVM tự lấy từ state
+    pub fn call(self, label: &str, from: Address, to: Address, data: Bytes, value: U256) -> Self {
+        let tx = TxEnv {
+            caller: from,
+            gas_limit: BUNDLE_TX_GAS_LIMIT,
+            transact_to: TxKind::Call(to),
+            value,
+            data,
+            nonce: None,
+            ..Default::default()
+        };
+        self.push(label, tx)
+    }
+
+    /// Thêm `approve(spender, amount)` của `owner` trên `token`
+    pub fn approve(self, owner: Address, token: Address, spender: Address, amount: U256) -> Self {
+        self.call(&format!("approve {:?}", token), owner, token, approve_calldata(spender, amount), U256::ZERO)
+    }
+}
+
+/// Gas limit mặc định cho transaction tự dựng trong bundle
+pub const BUNDLE_TX_GAS_LIMIT: u64 = 1_000_000;
+
+/// Kết quả của một transaction trong bundle
+#[derive(Debug, Clone)]
+pub struct TxOutcome {
+    pub label: String,
+    pub result: ExecutionResult,
+}
+
+impl TxOutcome {
+    pub fn gas_used(&self) -> u64 {
+        self.result.gas_used()
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        self.result.logs()
+    }
+}
+
+/// Balance của `ME` trước / sau bundle, `token = None` là native token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceDelta {
+    pub token: Option<Address>,
+    pub before: U256,
+    pub after: U256,
+}
+
+impl BalanceDelta {
+    pub fn delta(&self) -> I256 {
+        I256::from_raw(self.after) - I256::from_raw(self.before)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleResult {
+    /// Kết quả từng transaction theo thứ tự, dừng ở transaction revert / halt đầu tiên
+    pub outcomes: Vec<TxOutcome>,
+    /// Vị trí transaction làm bundle thất bại, khi đó state không bị thay đổi
+    pub reverted: Option<usize>,
+    /// Chỉ có khi bundle thành công
+    pub balance_deltas: Vec<BalanceDelta>,
+}
+
+impl BundleResult {
+    pub fn is_success(&self) -> bool {
+        self.reverted.is_none()
+    }
+
+    pub fn gas_used(&self) -> u64 {
+        self.outcomes.iter().map(TxOutcome::gas_used).sum()
+    }
+}
+
+impl fmt::Display for BundleResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.reverted {
+            None => write!(f, "📦 Bundle of {} txs succeeded, gas {}", self.outcomes.len(), self.gas_used())?,
+            Some(index) => write!(f, "📦 Bundle reverted at tx {} ({}), rolled back", index, self.outcomes[index].label)?,
+        }
+        for (index, outcome) in self.outcomes.iter().enumerate() {
+            let status = if outcome.result.is_success() { "✅" } else { "❌" };
+            write!(f, "\n{:>2}. {} {} | gas {} | {} logs", index, status, outcome.label, outcome.gas_used(), outcome.logs().len())?;
+        }
+        for delta in &self.balance_deltas {
+            let token = delta.token.map_or("native".to_string(), |token| format!("{:?}", token));
+            write!(f, "\n    Δ {}: {} → {} ({})", token, delta.before, delta.after, delta.delta())?;
+        }
+        Ok(())
+    }
+}
+
+/// Chạy `bundle` theo thứ tự trên một overlay của `db`. Chỉ khi mọi transaction thành công
+/// thì state thay đổi mới được commit xuống `db`; gặp revert / halt thì `db` giữ nguyên.
+/// Transaction không hợp lệ (nonce, balance, gas...) là lỗi, không phải revert.
+pub fn transact_bundle<DB>(
+    db: &mut DB,
+    bundle: &Bundle,
+    block: &BlockEnv,
+    chain_id: u64,
+    me: Address,
+    tokens: &[Address],
+) -> Result<BundleResult>
+where
+    DB: DatabaseRef + DatabaseCommit,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut overlay = CacheDB::new(&*db);
+    let before = read_balances(&mut overlay, block, me, tokens)?;
+
+    let mut outcomes = Vec::with_capacity(bundle.txs.len());
+    let mut changes: Vec<EvmState> = Vec::with_capacity(bundle.txs.len());
+    for (index, bundle_tx) in bundle.txs.iter().enumerate() {
+        let mut evm = Evm::builder()
+            .with_db(&mut overlay)
+            .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+            .modify_block_env(|env| *env = block.clone())
+            .modify_tx_env(|env| *env = bundle_tx.tx.clone())
+            .build();
+        let result_and_state = evm
+            .transact()
+            .map_err(|e| anyhow!("Bundle tx {} ({}) invalid: {:?}", index, bundle_tx.label, e))?;
+        drop(evm);
+
+        let success = result_and_state.result.is_success();
+        outcomes.push(TxOutcome { label: bundle_tx.label.clone(), result: result_and_state.result });
+        if !success {
+            return Ok(BundleResult { outcomes, reverted: Some(index), balance_deltas: Vec::new() });
+        }
+        overlay.commit(result_and_state.state.clone());
+        changes.push(result_and_state.state);
+    }
+
+    let after = read_balances(&mut overlay, block, me, tokens)?;
+    let balance_deltas = before
+        .into_iter()
+        .zip(after)
+        .map(|((token, before), (_, after))| BalanceDelta { token, before, after })
+        .collect();
+
+    // Bundle thành công: commit lần lượt thay đổi của từng transaction xuống state gốc
+    drop(overlay);
+    for state in changes {
+        db.commit(state);
+    }
+    Ok(BundleResult { outcomes, reverted: None, balance_deltas })
+}
+
+/// Balance native + ERC20 của `owner` (gọi `balanceOf` từ address 0, không commit)
+fn read_balances<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+    owner: Address,
+    tokens: &[Address],
+) -> Result<Vec<(Option<Address>, U256)>>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let native = cache_db.basic_ref(owner)?.map(|info| info.balance).unwrap_or_default();
+    let mut balances = vec![(None, native)];
+    for token in tokens {
+        let response = revm_call_db(Address::ZERO, *token, balance_of_calldata(owner), block, cache_db)?;
+        balances.push((Some(*token), decode_balance_of_response(response)?));
+    }
+    Ok(balances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::{init_account_with_bytecode, insert_mapping_storage_slot};
+    use crate::types::ONE_ETHER;
+    use alloy::primitives::hex;
+    use revm::db::EmptyDB;
+    use revm::primitives::{AccountInfo, Bytecode};
+    use std::str::FromStr;
+
+    const ME: Address = Address::repeat_byte(0x01);
+    const RECIPIENT: Address = Address::repeat_byte(0x02);
+    const TOKEN: Address = Address::repeat_byte(0xbb);
+    const REVERTER: Address = Address::repeat_byte(0xcc);
+
+    fn setup() -> CacheDB<EmptyDB> {
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        cache_db.insert_account_info(ME, AccountInfo { balance: ONE_ETHER * U256::from(10), ..Default::default() });
+        let erc20 = Bytecode::new_raw(Bytes::from_str(include_str!("../bytecode/generic_erc20.hex")).unwrap());
+        init_account_with_bytecode(TOKEN, erc20, &mut cache_db).unwrap();
+        insert_mapping_storage_slot(TOKEN, U256::ZERO, ME, U256::from(42), &mut cache_db).unwrap();
+        init_account_with_bytecode(REVERTER, Bytecode::new_raw(hex!("60006000fd").into()), &mut cache_db).unwrap();
+        cache_db
+    }
+
+    fn transfer() -> Bundle {
+        Bundle::new().call("transfer", ME, RECIPIENT, Bytes::new(), ONE_ETHER)
+    }
+
+    #[test]
+    fn test_bundle_commits_all_txs_and_reports_deltas() {
+        let mut cache_db = setup();
+        let bundle = transfer().approve(ME, TOKEN, RECIPIENT, U256::MAX);
+
+        let result = transact_bundle(&mut cache_db, &bundle, &BlockEnv::default(), 1, ME, &[TOKEN]).unwrap();
+        assert!(result.is_success());
+        assert_eq!(result.outcomes.len(), 2);
+        assert!(result.outcomes.iter().all(|outcome| outcome.result.is_success() && outcome.gas_used() > 0));
+        assert_eq!(result.balance_deltas[0].delta(), -I256::from_raw(ONE_ETHER));
+        assert_eq!(result.balance_deltas[1], BalanceDelta { token: Some(TOKEN), before: U256::from(42), after: U256::from(42) });
+
+        let me = cache_db.basic_ref(ME).unwrap().unwrap();
+        assert_eq!((me.nonce, me.balance), (2, ONE_ETHER * U256::from(9)));
+        assert_eq!(cache_db.basic_ref(RECIPIENT).unwrap().unwrap().balance, ONE_ETHER);
+    }
+
+    #[test]
+    fn test_bundle_rolls_back_on_revert() {
+        let mut cache_db = setup();
+        let bundle = transfer().call("reverter", ME, REVERTER, Bytes::new(), U256::ZERO);
+
+        let result = transact_bundle(&mut cache_db, &bundle, &BlockEnv::default(), 1, ME, &[]).unwrap();
+        assert_eq!(result.reverted, Some(1));
+        assert!(result.outcomes[0].result.is_success());
+        assert!(result.balance_deltas.is_empty());
+
+        // Transfer ở tx 0 không được commit
+        let me = cache_db.basic_ref(ME).unwrap().unwrap();
+        assert_eq!((me.nonce, me.balance), (0, ONE_ETHER * U256::from(10)));
+        assert!(cache_db.basic_ref(RECIPIENT).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_bundle_tx_is_an_error() {
+        let mut cache_db = setup();
+        let mut tx = transfer().txs.remove(0).tx;
+        tx.nonce = Some(5);
+        let err = transact_bundle(&mut cache_db, &Bundle::new().push("bad nonce", tx), &BlockEnv::default(), 1, ME, &[]).unwrap_err();
+        assert!(err.to_string().contains("bad nonce"));
+    }
+}
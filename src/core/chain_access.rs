@@ -0,0 +1,247 @@
+use std::ops::Div;
+use std::path::Path;
+use anyhow::{anyhow, bail, Result};
+use alloy::{eips::BlockId, primitives::{Address, U256}};
+use revm::{db::{CacheDB, DatabaseRef}, primitives::BlockEnv};
+
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
+use crate::chain::actors::ChainActors;
+use crate::core::access_set::AccessManifest;
+use crate::core::chain_snapshot::{prepare_on_db, simulate_on_db, ensure_snapshot_method};
+use crate::core::db::{init_cache_db, revm_call_with_access, revm_revert_with_access};
+use crate::core::logger::{measure_start, measure_end};
+use crate::core::opportunity::fetch_base_fee;
+use crate::core::prefetch::{prefetch_state, PrefetchConfig, PrefetchReport};
+use crate::core::provider::MultiProvider;
+use crate::core::db_persistent::{PersistentDB, PersistentState};
+use crate::core::db_rpc::RpcDB;
+use crate::source::{abi::*, builder::volumes};
+
+/// Các method record được access set (quote chạy qua `LoggingDB`)
+pub const ACCESS_METHODS: &[&str] = &["revm_cached", "arbitrage"];
+
+/// Số volume quote khi record, đủ để đi qua các tick gần giá hiện tại
+const RECORD_VOLUMES: usize = 10;
+
+/// Quote của `method` qua `LoggingDB` với vài volume, gộp access set từng call vào `manifest`.
+/// Trả về các address không thuộc transaction thật (sender, quoter giả) để loại khỏi access list.
+pub fn record_access_on_db<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    cache_db: &mut CacheDB<ExtDB>,
+    manifest: &mut AccessManifest,
+) -> Result<Vec<Address>>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let volumes = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), RECORD_VOLUMES);
+
+    match method {
+        "revm_cached" => {
+            let quoter = config.quoter(QuoterKind::UniswapV3)?;
+            for volume in volumes {
+                let calldata = quote_calldata(token_in, token_out, volume, actors.default_fee);
+                let (_, access) = revm_call_with_access(from, quoter, calldata, &BlockEnv::default(), cache_db)?;
+                manifest.record_call(&access);
+            }
+            Ok(vec![from])
+        }
+        "arbitrage" => {
+            let pool1 = config.pool(token_in, token_out, actors.low_fee)?.address;
+            let pool2 = config.pool(token_in, token_out, actors.default_fee)?.address;
+            let quoter = config.quoter(QuoterKind::Custom)?;
+            for volume in volumes {
+                let calldata1 = get_amount_out_calldata(pool1, token_in, token_out, volume);
+                let (resp1, _, access) = revm_revert_with_access(from, quoter, calldata1, &BlockEnv::default(), cache_db)?;
+                manifest.record_call(&access);
+                let token_out_amount = decode_get_amount_out_response(resp1)?;
+
+                let calldata2 = get_amount_out_calldata(pool2, token_out, token_in, U256::from(token_out_amount));
+                let (_, _, access) = revm_revert_with_access(from, quoter, calldata2, &BlockEnv::default(), cache_db)?;
+                manifest.record_call(&access);
+            }
+            Ok(vec![from, quoter])
+        }
+        _ => bail!("Method '{}' cannot record an access set (supported: {})", method, ACCESS_METHODS.join(", ")),
+    }
+}
+
+/// Chạy quote của `method` online (ghim block), ghi access set đã gộp ra manifest JSON `path`
+/// và in EIP-2930 access list tương ứng cho transaction thật
+pub async fn record_chain_access(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    path: &Path,
+    block: BlockId,
+) -> Result<()> {
+    if !ACCESS_METHODS.contains(&method) {
+        bail!("Method '{}' cannot record an access set (supported: {})", method, ACCESS_METHODS.join(", "));
+    }
+
+    // 1️⃣ Ghim block để access set nhất quán
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("Recording access set of '{}' at block {}", method, pinned);
+    let rpc_db = RpcDB::new(&multi_provider, pinned.id())
+        .ok_or_else(|| anyhow!("RpcDB requires a multi-thread tokio runtime"))?;
+    let mut cache_db = CacheDB::new(PersistentDB::for_provider(rpc_db, &multi_provider));
+
+    // 2️⃣ Chuẩn bị state rồi quote qua LoggingDB
+    prepare_on_db(config, actors, method, &mut cache_db, &multi_provider).await?;
+    let mut manifest = AccessManifest::new(config.chain_id, method, Some(pinned.number));
+    let exclude = record_access_on_db(config, actors, method, &mut cache_db, &mut manifest)?;
+
+    // 3️⃣ Ghi manifest + in access list
+    manifest.save(path)?;
+    let access_list = manifest.access.to_access_list(&exclude);
+    println!(
+        "💾 Access manifest saved to {}: {} calls, {} accounts, {} slots, {} code hashes",
+        path.display(), manifest.calls, manifest.access.accounts.len(), manifest.access.slot_count(), manifest.access.code_hashes.len()
+    );
+    println!("EIP-2930 access list ({} addresses):", access_list.0.len());
+    for item in &access_list.0 {
+        println!("  {:?}: {} storage keys", item.address, item.storage_keys.len());
+    }
+    Ok(())
+}
+
+/// Nạp trước mọi account + slot trong manifest vào `cache_db` tại `block` (một lượt batch)
+pub async fn prewarm_cache_db<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    multi_provider: &MultiProvider,
+    block: BlockId,
+    manifest: &AccessManifest,
+) -> Result<PrefetchReport>
+where
+    ExtDB: DatabaseRef + PersistentState,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let accounts: Vec<Address> = manifest.access.accounts.keys().copied().collect();
+    let slots = manifest.access.storage_slots();
+    prefetch_state(cache_db, multi_provider, block, &accounts, &slots, &PrefetchConfig::default()).await
+}
+
+/// Chạy `method` online như thường nhưng prewarm state từ manifest trước lần simulate đầu
+pub async fn run_chain_prewarmed(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    path: &Path,
+    block: BlockId,
+) -> Result<()> {
+    ensure_snapshot_method(method)?;
+    let manifest = AccessManifest::load(path)?;
+    if manifest.chain_id != config.chain_id {
+        bail!("Access manifest {} is for chain {}, config is chain {}", path.display(), manifest.chain_id, config.chain_id);
+    }
+
+    // 1️⃣ Setup provider + cache db, chuẩn bị state như `run_chain_<method>`
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+    let mut cache_db = init_cache_db(&multi_provider);
+    prepare_on_db(config, actors, method, &mut cache_db, &multi_provider).await?;
+
+    // 2️⃣ Prewarm: slot đã mock trong bước chuẩn bị được giữ nguyên
+    let start = measure_start("access_prewarm");
+    let report = prewarm_cache_db(&mut cache_db, &multi_provider, pinned.id(), &manifest).await?;
+    measure_end(start);
+    println!("🔥 Prewarmed from {} ({} calls recorded): {}", path.display(), manifest.calls, report);
+
+    // 3️⃣ Simulate
+    let base_fee = fetch_base_fee(&multi_provider).await?;
+    simulate_on_db(config, actors, method, &mut cache_db, &BlockEnv::default(), base_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::access_set::AccessSet;
+    use crate::core::db::init_account_with_bytecode;
+    use crate::core::mock_rpc::{default_account_response, MockRpc};
+    use alloy::primitives::{keccak256, Bytes};
+    use alloy::sol_types::SolValue;
+    use revm::db::{Database, EmptyDB};
+    use revm::primitives::Bytecode;
+    use serde_json::{json, Value};
+    use std::str::FromStr;
+
+    fn balance_slot(holder: Address) -> U256 {
+        keccak256((holder, U256::ZERO).abi_encode()).into()
+    }
+
+    #[test]
+    fn test_logging_db_records_access_set() {
+        let (token, holder, caller) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22), Address::repeat_byte(0x33));
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        let erc20 = Bytecode::new_raw(Bytes::from_str(include_str!("../bytecode/generic_erc20.hex")).unwrap());
+        init_account_with_bytecode(token, erc20.clone(), &mut cache_db).unwrap();
+        cache_db.insert_account_storage(token, balance_slot(holder), U256::from(42)).unwrap();
+
+        let mut manifest = AccessManifest::new(1, "test", None);
+        for _ in 0..2 {
+            let mut calldata = vec![0x70, 0xa0, 0x82, 0x31];
+            calldata.extend_from_slice(&holder.abi_encode());
+            let (output, access) = revm_call_with_access(caller, token, calldata.into(), &BlockEnv::default(), &mut cache_db).unwrap();
+            assert_eq!(U256::abi_decode(&output, true).unwrap(), U256::from(42));
+            manifest.record_call(&access);
+        }
+
+        let access = &manifest.access;
+        assert_eq!(manifest.calls, 2);
+        assert_eq!(access.accounts[&token].code_hash, Some(erc20.hash_slow()));
+        assert_eq!(access.accounts[&token].storage[&balance_slot(holder)], U256::from(42));
+        assert!(access.accounts.contains_key(&caller));
+        // Gọi hai lần nhưng access set không trùng
+        assert_eq!(access.slot_count(), 1);
+
+        // Còn lại token; coinbase mặc định (address 0) bị bỏ như precompile
+        let list = access.to_access_list(&[caller]);
+        assert_eq!(list.0.len(), 1);
+        assert_eq!((list.0[0].address, list.0[0].storage_keys.len()), (token, 1));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prewarm_from_manifest_skips_cached_state() {
+        let mock = MockRpc::spawn(|method, _: &Value| match method {
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", 5))),
+            _ => default_account_response(method).ok_or(format!("unexpected method {method}")),
+        })
+        .await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let mut cache_db = init_cache_db(&multi_provider);
+
+        let (pool, mocked_token, sender) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb), Address::repeat_byte(0xcc));
+        let mut access = AccessSet::default();
+        access.record_account(sender, None);
+        for slot in 0..5 {
+            access.record_storage(pool, U256::from(slot), U256::ZERO);
+        }
+        access.record_storage(mocked_token, U256::from(1), U256::ZERO);
+        let mut manifest = AccessManifest::new(1, "arbitrage", Some(10));
+        manifest.record_call(&access);
+
+        // Token đã mock ở bước chuẩn bị: không fetch lại
+        cache_db.insert_account_info(mocked_token, Default::default());
+        cache_db.insert_account_storage(mocked_token, U256::from(1), U256::from(77)).unwrap();
+
+        let report = prewarm_cache_db(&mut cache_db, &multi_provider, BlockId::latest(), &manifest).await.unwrap();
+        assert_eq!((report.slots_fetched, report.already_cached, report.accounts_fetched), (5, 1, 2));
+        assert_eq!((report.calls, report.round_trips), (2 * 3 + 5, 1));
+        assert_eq!(mock.http_requests(), 1);
+
+        let calls = mock.calls();
+        assert_eq!(cache_db.storage(pool, U256::from(3)).unwrap(), U256::from(5));
+        assert_eq!(cache_db.storage(mocked_token, U256::from(1)).unwrap(), U256::from(77));
+        assert!(cache_db.basic(sender).unwrap().is_some());
+        assert_eq!(mock.calls(), calls);
+    }
+}
@@ -1,22 +1,24 @@
 use std::sync::Arc;
 use std::ops::Div;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use alloy::providers::Provider;
 use alloy::{
+    eips::BlockId,
+    rpc::types::BlockTransactionsKind,
     node_bindings::Anvil,
     primitives::U256,
-    providers::{ProviderBuilder, RootProvider},
+    providers::ProviderBuilder,
     transports::http::reqwest::Url,
 };
 
-use crate::types::{ChainConfig, ONE_ETHER};
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
 use crate::source::{builder::volumes, abi::*, builder::build_tx};
 use crate::core::logger::{measure_start, measure_end};
 
 use crate::chain::actors::ChainActors; // cần thêm import
 
 /// Chạy mô phỏng quote thông qua Anvil forked mainnet (multi-chain)
-pub async fn run_chain_anvil(config: &ChainConfig, actors: &ChainActors) -> Result<()> {
+pub async fn run_chain_anvil(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
     // 1️⃣ Parse RPC URL và khởi tạo provider thật
     let rpc_url = config.rpc_url.parse::<Url>()?;
     let provider = ProviderBuilder::new().on_http(rpc_url.clone());
@@ -24,7 +26,13 @@ pub async fn run_chain_anvil(config: &ChainConfig, actors: &ChainActors) -> Resu
 
     // 2️⃣ Lấy base_fee và block height để tạo fork
     let base_fee = provider.get_gas_price().await?;
-    let fork_block = provider.get_block_number().await?;
+    let fork_block = provider
+        .get_block(block, BlockTransactionsKind::Hashes)
+        .await?
+        .ok_or_else(|| anyhow!("Block {} not found", block))?
+        .header
+        .number;
+    println!("📌 Forking at block #{}", fork_block);
 
     // 3️⃣ Tạo instance Anvil fork từ block thực
     let anvil = Anvil::new()
@@ -39,10 +47,10 @@ pub async fn run_chain_anvil(config: &ChainConfig, actors: &ChainActors) -> Resu
     let anvil_provider = Arc::new(anvil_provider);
 
     // 5️⃣ Lấy thông tin token / quoter / from từ config + actors
-    let from = config.addr("ME")?;
-    let token_in = config.addr(actors.native_token_key)?;
-    let token_out = config.addr(actors.stable_token_key)?;
-    let quoter = config.addr(actors.quoter_key)?;
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let quoter = config.quoter(QuoterKind::UniswapV3)?;
 
     // 6️⃣ Chuẩn bị volumes để benchmark
     let volumes = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), 100);
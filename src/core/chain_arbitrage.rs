@@ -1,42 +1,63 @@
-use std::sync::Arc;
 use std::ops::Div;
 use std::str::FromStr;
 use anyhow::Result;
 use alloy::{
+    eips::BlockId,
     primitives::{Bytes, U256},
-    providers::{Provider, ProviderBuilder},
 };
-use revm::primitives::Bytecode;
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{BlockEnv, Bytecode},
+};
 
-use crate::types::{ChainConfig, ONE_ETHER};
-use crate::source::{abi::*, builder::volumes};
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
+use crate::source::abi::*;
 use crate::core::db::*;
 use crate::core::logger::{measure_start, measure_end};
 use crate::chain::actors::ChainActors;
 use crate::core::provider::MultiProvider;
+use crate::core::optimizer::{optimize, OptimizerConfig};
+use crate::core::opportunity::{fetch_base_fee, OpportunityEvaluator};
+use crate::core::arb_executor::{deploy_executor, simulate_execution, ExecutionEnv, DEFAULT_EXECUTOR};
+use crate::core::path_search::{Cycle, PoolEdge};
 
 
 /// Mô phỏng back-and-forth arbitrage Native -> Stable -> Native
 /// Dùng custom UniV3Quoter để quote offchain qua REVM
-pub async fn run_chain_arbitrage(config: &ChainConfig, actors: &ChainActors) -> Result<()> {
+pub async fn run_chain_arbitrage(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
     // let provider = ProviderBuilder::new()
     //     .on_http(config.rpc_url.parse()?);
     // let provider = Arc::new(provider);
 
     // let mut cache_db = init_cache_db(provider.clone());
-    let multi_provider = MultiProvider::new(&config.rpc_urls);
+    let multi_provider = MultiProvider::for_chain(config);
     println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
 
     // let base_fee = provider.get_gas_price().await?;
 
     let mut cache_db = init_cache_db(&multi_provider);
 
-    let from = config.addr("ME")?;
-    let token_in = config.addr(actors.native_token_key)?;
-    let token_out = config.addr(actors.stable_token_key)?;
-    let pool1 = config.addr(actors.pool_500_key.expect("pool_500_key required"))?;
-    let pool2 = config.addr(actors.pool_3000_key.expect("pool_3000_key required"))?;
-    let quoter = config.addr(actors.custom_quoter_key.expect("custom_quoter_key required"))?;
+    prepare_chain_arbitrage(config, actors, &mut cache_db, &multi_provider).await?;
+
+    let base_fee = fetch_base_fee(&multi_provider).await?;
+    simulate_chain_arbitrage(config, actors, &mut cache_db, &BlockEnv::default(), base_fee)
+}
+
+/// Nạp bytecode pool thật (qua RPC) + ERC20 / quoter giả và balance giả vào `cache_db`
+pub async fn prepare_chain_arbitrage(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut AlloyCacheDB,
+    multi_provider: &MultiProvider,
+) -> Result<()> {
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool1 = config.pool(token_in, token_out, actors.low_fee)?.address;
+    let pool2 = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
 
     // Load bytecode
     let mocked_erc20 = include_str!("../bytecode/generic_erc20.hex");
@@ -46,50 +67,101 @@ pub async fn run_chain_arbitrage(config: &ChainConfig, actors: &ChainActors) ->
     let mocked_quoter = Bytecode::new_raw(Bytes::from_str(mocked_quoter)?);
 
     // Init accounts
-    init_account(from, &mut cache_db, &multi_provider).await?;
-    init_account(pool1, &mut cache_db, &multi_provider).await?;
-    init_account(pool2, &mut cache_db, &multi_provider).await?;
+    init_account(from, cache_db, multi_provider).await?;
+    init_account(pool1, cache_db, multi_provider).await?;
+    init_account(pool2, cache_db, multi_provider).await?;
 
-    init_account_with_bytecode(token_in, mocked_erc20.clone(), &mut cache_db)?;
-    init_account_with_bytecode(token_out, mocked_erc20.clone(), &mut cache_db)?;
-    init_account_with_bytecode(quoter, mocked_quoter, &mut cache_db)?;
+    init_account_with_bytecode(token_in, mocked_erc20.clone(), cache_db)?;
+    init_account_with_bytecode(token_out, mocked_erc20.clone(), cache_db)?;
+    init_account_with_bytecode(quoter, mocked_quoter, cache_db)?;
 
     // Insert fake balances
     let mocked_balance = U256::MAX / U256::from(2);
     for &pool in &[pool1, pool2] {
-        insert_mapping_storage_slot(token_in, U256::ZERO, pool, mocked_balance, &mut cache_db)?;
-        insert_mapping_storage_slot(token_out, U256::ZERO, pool, mocked_balance, &mut cache_db)?;
+        insert_mapping_storage_slot(token_in, U256::ZERO, pool, mocked_balance, cache_db)?;
+        insert_mapping_storage_slot(token_out, U256::ZERO, pool, mocked_balance, cache_db)?;
     }
 
-    // Arbitrage loop
-    let vols = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), 100);
-    measure_start("chain_arbitrage"); // Optional: đo log
+    Ok(())
+}
 
-    for vol in vols {
+/// Tìm volume tối ưu và đánh giá lợi nhuận ròng với `base_fee` cho trước,
+/// chạy được offline trên state đã chuẩn bị (vd. load từ snapshot)
+pub fn simulate_chain_arbitrage<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+    base_fee: u128,
+) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool1 = config.pool(token_in, token_out, actors.low_fee)?.address;
+    let pool2 = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
+
+    // Tìm volume tối ưu thay vì sweep tuyến tính
+    let start = measure_start("chain_arbitrage");
+    let mut quote_round_trip = |vol: U256| -> Result<U256> {
         let calldata1 = get_amount_out_calldata(pool1, token_in, token_out, vol);
-        let resp1 = revm_revert(from, quoter, calldata1, &mut cache_db)?;
+        let resp1 = revm_revert(from, quoter, calldata1, block, cache_db)?;
         let token_out_amount = decode_get_amount_out_response(resp1)?;
 
         let calldata2 = get_amount_out_calldata(pool2, token_out, token_in, U256::from(token_out_amount));
-        let resp2 = revm_revert(from, quoter, calldata2, &mut cache_db)?;
+        let resp2 = revm_revert(from, quoter, calldata2, block, cache_db)?;
         let token_in_back = decode_get_amount_out_response(resp2)?;
-        let token_in_back = U256::from(token_in_back);
-
-        println!(
-            "{} {} → {} {} → {} {}",
-            vol, actors.native_token_key,
-            token_out_amount, actors.stable_token_key,
-            token_in_back, actors.native_token_key
-        );
-
-        if token_in_back > vol {
-            let profit = token_in_back - vol;
-            println!("✅ Arbitrage profit: {} {}", profit, actors.native_token_key);
-        } else {
-            println!("❌ No profit");
-        }
-    }
-
-    measure_end(measure_start("chain_arbitrage")); // Optional đo log
+        Ok(U256::from(token_in_back))
+    };
+    let optimizer_config = OptimizerConfig {
+        lower: U256::ZERO,
+        upper: ONE_ETHER.div(U256::from(10)),
+        tolerance: ONE_ETHER.div(U256::from(1_000_000)),
+        max_iterations: 64,
+    };
+    let best = optimize(&mut quote_round_trip, &optimizer_config)?;
+
+    println!(
+        "{} {} → {} → {} {} ({:?}, {} iterations, {} quotes)",
+        best.amount_in, actors.native_token_key,
+        actors.stable_token_key,
+        best.amount_out, actors.native_token_key,
+        best.method, best.iterations, best.quotes
+    );
+
+    // Gas thật của cả arb tx: chạy cycle qua ArbExecutor tại volume tối ưu (swap + transfer token thật)
+    // trên overlay để `cache_db` giữ nguyên
+    let cycle = Cycle {
+        hops: vec![
+            PoolEdge {
+                pool: pool1, token_in, token_out,
+                symbol_in: actors.native_token_key.clone(), symbol_out: actors.stable_token_key.clone(), fee: actors.low_fee,
+            },
+            PoolEdge {
+                pool: pool2, token_in: token_out, token_out: token_in,
+                symbol_in: actors.stable_token_key.clone(), symbol_out: actors.native_token_key.clone(), fee: actors.default_fee,
+            },
+        ],
+    };
+    let env = ExecutionEnv {
+        executor: config.arb_executor.unwrap_or(DEFAULT_EXECUTOR),
+        me: from,
+        block: call_block_env(block),
+        chain_id: config.chain_id,
+    };
+    let mut overlay = CacheDB::new(&*cache_db);
+    deploy_executor(env.executor, env.me, &mut overlay)?;
+    let report = simulate_execution(&mut overlay, &env, &cycle, best.amount_in, U256::ZERO)?;
+    println!("{}", report);
+
+    let evaluator = OpportunityEvaluator::new(config, base_fee);
+    let opportunity = evaluator.evaluate_execution(best.amount_out, &report);
+    println!("{} (net in {})", opportunity, actors.native_token_key);
+
+    measure_end(start);
     Ok(())
 }
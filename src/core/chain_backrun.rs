@@ -0,0 +1,215 @@
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, B256},
+    providers::Provider,
+};
+use revm::db::CacheDB;
+
+use crate::types::ChainConfig;
+use crate::chain::actors::ChainActors;
+use crate::core::backrun::{apply_pending_tx, may_touch_pools, next_block_env, BackrunReport};
+use crate::core::chain_cycles::{mock_cycle_state, search_cycles, search_cycles_overlay};
+use crate::core::chain_watch::StateFollower;
+use crate::core::db::{init_account, init_cache_db};
+use crate::core::opportunity::fetch_base_fee;
+use crate::core::provider::{MultiProvider, PinnedBlock};
+use crate::core::state_diff::ProtectedState;
+
+#[derive(Debug, Clone)]
+pub struct BackrunConfig {
+    /// Chu kỳ poll pending tx filter + head mới
+    pub poll_interval: Duration,
+    /// Dừng sau số victim (tx có swap trên pool đang track) này, None = chạy mãi
+    pub max_victims: Option<usize>,
+    /// Nhảy xa hơn số block này thì refresh cả cache thay vì trace từng block
+    pub max_trace_gap: u64,
+}
+
+impl Default for BackrunConfig {
+    fn default() -> Self {
+        BackrunConfig { poll_interval: Duration::from_millis(200), max_victims: None, max_trace_gap: 8 }
+    }
+}
+
+/// Theo dõi mempool: mỗi pending tx chạm tới pool trong `[[pools]]` được áp lên state block ghim
+/// bằng `transact_commit`, rồi tìm cycle arbitrage trên post-state và in danh sách backrun theo lợi nhuận
+pub async fn run_chain_backrun(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    block: BlockId,
+    backrun: &BackrunConfig,
+) -> Result<Vec<BackrunReport>> {
+    let tracked: Vec<Address> = config.pools.iter().map(|pool| pool.address).collect();
+
+    // 1️⃣ Setup provider + cache db với state thật (chỉ nạp pool), token mock nằm ở lớp overlay
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let mut pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Backrunning from block {}", pinned);
+    let mut base = init_cache_db(&multi_provider);
+    for pool in &config.pools {
+        init_account(pool.address, &mut base, &multi_provider).await?;
+    }
+    let mut follower = StateFollower::new(ProtectedState::default(), backrun.max_trace_gap);
+
+    // 2️⃣ Baseline trên block ghim (đồng thời warm-up storage pool), quote trong env của block kế tiếp như victim
+    let mut baseline = search_cycles_overlay(config, actors, &mut base, &next_block_env(&pinned, 0))?;
+    let mut base_fee = fetch_base_fee(&multi_provider).await?;
+    println!("{} cycles at baseline, {} profitable", baseline.len(), baseline.iter().filter(|r| r.gross_profit.is_positive()).count());
+
+    // 3️⃣ Pending tx filter: filter id gắn với một node nên giữ cố định provider
+    let (provider, url) = multi_provider.next();
+    let filter = provider
+        .new_pending_transactions_filter(false)
+        .await
+        .map_err(|e| anyhow!("Pending transaction filter not supported by {}: {}", url, e))?;
+    println!("👀 Watching pending transactions via {}", url);
+
+    // 4️⃣ Poll: head mới → đưa base lên block mới, pending tx → simulate backrun
+    let mut reports: Vec<BackrunReport> = Vec::new();
+    let mut interval = tokio::time::interval(backrun.poll_interval);
+    while backrun.max_victims.map_or(true, |max| reports.len() < max) {
+        interval.tick().await;
+
+        match multi_provider.request(|provider| async move { provider.get_block_number().await }).await {
+            Ok(head) if head > pinned.number => {
+                let (block, invalidation) = follower.advance(&mut base, &multi_provider, &pinned, head).await?;
+                println!("🧱 New head #{}: {}", block.number, invalidation);
+                pinned = block;
+                base_fee = fetch_base_fee(&multi_provider).await?;
+                baseline = search_cycles_overlay(config, actors, &mut base, &next_block_env(&pinned, 0))?;
+            }
+            Ok(_) => {}
+            Err(e) => println!("⚠️ Head poll failed: {}", e),
+        }
+
+        let hashes = match provider.get_filter_changes::<B256>(filter).await {
+            Ok(hashes) => hashes,
+            Err(e) => {
+                println!("⚠️ Pending filter poll failed: {}", e);
+                continue;
+            }
+        };
+        for hash in hashes {
+            if backrun.max_victims.is_some_and(|max| reports.len() >= max) {
+                break;
+            }
+            // Chỉ filter cần giữ provider cố định, tx theo hash đọc qua endpoint nào cũng được
+            let tx = multi_provider.request(|provider| async move { provider.get_transaction_by_hash(hash).await }).await;
+            let Some(tx) = tx.ok().flatten() else {
+                continue;
+            };
+            if !may_touch_pools(&tx, config) {
+                continue;
+            }
+
+            // Victim chạy trên state thật, sau đó mới mock token + quoter để tìm cycle
+            let start = Instant::now();
+            let mut overlay = CacheDB::new(&base);
+            let env = next_block_env(&pinned, base_fee);
+            let victim = match apply_pending_tx(&mut overlay, &tx, &env, config.chain_id, &tracked) {
+                Ok(victim) if !victim.swaps.is_empty() => victim,
+                Ok(_) => continue,
+                Err(e) => {
+                    println!("⚠️ Skip pending tx {}: {}", hash, e);
+                    continue;
+                }
+            };
+            mock_cycle_state(config, &mut overlay)?;
+            let ranked = search_cycles(config, actors, &mut overlay, &env)?;
+
+            let report = BackrunReport::new(victim, ranked, &baseline);
+            println!("{}", report);
+            println!("⏱️ Victim → backrun candidates in {:.2?}", start.elapsed());
+            reports.push(report);
+        }
+    }
+
+    provider.client().request::<_, bool>("eth_uninstallFilter", (filter,)).await.ok();
+    print_summary(&reports, &pinned);
+    Ok(reports)
+}
+
+fn print_summary(reports: &[BackrunReport], pinned: &PinnedBlock) {
+    let profitable = reports.iter().filter(|report| !report.candidates.is_empty()).count();
+    println!("📊 {} victims up to block #{}, {} with a profitable backrun", reports.len(), pinned.number, profitable);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, aliases::U24, U160, U256};
+    use alloy::rpc::types::TransactionRequest;
+    use alloy::sol;
+    use alloy::sol_types::SolCall;
+
+    sol! {
+        function deposit() external payable;
+
+        struct ExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint24 fee;
+            address recipient;
+            uint256 amountIn;
+            uint256 amountOutMinimum;
+            uint160 sqrtPriceLimitX96;
+        }
+        function exactInputSingle(ExactInputSingleParams calldata params) external payable returns (uint256 amountOut);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires anvil + RPC_URL"]
+    async fn test_backrun_pending_swap_on_anvil() {
+        use alloy::node_bindings::Anvil;
+        use alloy::providers::ProviderBuilder;
+        use crate::chain::actors::ChainActors;
+        use crate::source::abi::approve_calldata;
+        use crate::types::load_chain_config;
+
+        const SWAP_ROUTER_02: Address = address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45");
+
+        let anvil = Anvil::new()
+            .fork(std::env::var("RPC_URL").expect("Please set RPC_URL env"))
+            .arg("--no-mining")
+            .spawn();
+        let provider = ProviderBuilder::new().on_http(anvil.endpoint().parse().unwrap());
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        config.rpc_urls = vec![anvil.endpoint()];
+        let (weth, usdc) = (config.token("WETH").unwrap().address, config.token("USDC").unwrap().address);
+        let pool_500 = config.pool(weth, usdc, 500).unwrap().address;
+        let trader = anvil.addresses()[0];
+
+        // Chuẩn bị WETH + approve rồi mine, trước khi bắt đầu theo dõi mempool
+        let amount = U256::from(1_000) * crate::types::ONE_ETHER;
+        let deposit = TransactionRequest::default().from(trader).to(weth).value(amount).input(depositCall {}.abi_encode().into());
+        let approve = TransactionRequest::default().from(trader).to(weth).input(approve_calldata(SWAP_ROUTER_02, amount).into());
+        for tx in [deposit, approve] {
+            let _ = provider.send_transaction(tx).await.unwrap();
+        }
+        provider.client().request::<_, String>("evm_mine", ()).await.unwrap();
+
+        let runner = tokio::spawn(async move {
+            let backrun = BackrunConfig { max_victims: Some(1), ..Default::default() };
+            run_chain_backrun(&config, &ChainActors::from(&config), BlockId::latest(), &backrun).await
+        });
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        // Swap lớn WETH → USDC trên pool 500, nằm lại trong mempool
+        let params = ExactInputSingleParams {
+            tokenIn: weth, tokenOut: usdc, fee: U24::from(500), recipient: trader,
+            amountIn: amount, amountOutMinimum: U256::ZERO, sqrtPriceLimitX96: U160::ZERO,
+        };
+        let swap = exactInputSingleCall { params }.abi_encode();
+        let swap = TransactionRequest::default().from(trader).to(SWAP_ROUTER_02).input(swap.into());
+        let hash = *provider.send_transaction(swap).await.unwrap().tx_hash();
+
+        let reports = tokio::time::timeout(Duration::from_secs(120), runner).await.unwrap().unwrap().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].victim.hash, hash);
+        assert_eq!(reports[0].victim.swaps[0].pool, pool_500);
+        assert!(!reports[0].candidates.is_empty());
+    }
+}
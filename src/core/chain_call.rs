@@ -1,34 +1,39 @@
-use std::sync::Arc;
 use std::ops::Div;
 use anyhow::Result;
 use alloy::{
-    eips::BlockId,
+    eips::{BlockId, BlockNumberOrTag},
     primitives::U256,
-    providers::{Provider, ProviderBuilder},
+    providers::Provider,
 };
-use crate::{source::build_tx_avalanche, types::{ChainConfig, ONE_ETHER}};
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
 use crate::source::{builder::volumes, abi::quote_calldata, builder::build_tx};
 use crate::core::logger::{measure_start, measure_end};
 use crate::chain::actors::ChainActors;
-use crate::core::provider::MultiProvider;
+use crate::core::provider::{MultiProvider, HEDGE_FANOUT, READ_QUORUM};
 
 
 /// Mô phỏng quote swap bằng eth_call (multi-chain)
-pub async fn run_chain_call(config: &ChainConfig, actors: &ChainActors) -> Result<()> {
+pub async fn run_chain_call(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
     // let provider = ProviderBuilder::new()
     //     .on_http(config.rpc_url.parse()?);
     // let provider = Arc::new(provider);
-    let multi_provider = MultiProvider::new(&config.rpc_urls);
+    let multi_provider = MultiProvider::for_chain(config);
     println!("MultiProvider with {} providers", multi_provider.len());
 
     // let base_fee = provider.get_gas_price().await?;
-    let (provider, _url) = multi_provider.next();
-    let base_fee = provider.get_gas_price().await?;
-    let block_number = provider.get_block_number().await?;
-    let from = config.addr("ME")?;
-    let token_in = config.addr(actors.native_token_key)?;
-    let token_out = config.addr(actors.stable_token_key)?;
-    let quoter = config.addr(actors.quoter_key)?;
+    // Gas price lấy endpoint trả lời nhanh nhất. `latest` ghim vào head mà ít nhất `READ_QUORUM` endpoint
+    // đã tới, để không ghim mọi eth_call vào block mà chỉ một node nhanh có
+    let base_fee = multi_provider.hedged(HEDGE_FANOUT, |provider| async move { provider.get_gas_price().await }).await?;
+    let block = match block {
+        BlockId::Number(BlockNumberOrTag::Latest) => BlockId::number(multi_provider.lagged_head(READ_QUORUM).await?),
+        block => block,
+    };
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let quoter = config.quoter(QuoterKind::UniswapV3)?;
 
     // print address 
     // println!("From address: {}", from);
@@ -49,7 +54,12 @@ pub async fn run_chain_call(config: &ChainConfig, actors: &ChainActors) -> Resul
         // println!("Calling provider with transaction...");
 
         // let response = provider.call(&tx).await?;
-        let response = provider.call(&tx).block(block_number.into()).await?;
+        let response = multi_provider
+            .hedged(HEDGE_FANOUT, |provider| {
+                let tx = tx.clone();
+                async move { provider.call(&tx).block(pinned.id()).await }
+            })
+            .await?;
 
         let amount_out = crate::source::abi::decode_quote_response(response)?;
 
@@ -0,0 +1,247 @@
+use std::ops::Div;
+use std::str::FromStr;
+use anyhow::{bail, Result};
+use alloy::{
+    eips::BlockId,
+    primitives::{Bytes, U256},
+    providers::Provider,
+};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{BlockEnv, Bytecode},
+};
+
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
+use crate::source::{abi::*, builder::build_tx};
+use crate::core::db::*;
+use crate::core::logger::{measure_start, measure_end};
+use crate::core::optimizer::{optimize, OptimizerConfig, RevmCycleQuoter};
+use crate::core::path_search::{rank_cycles, CycleResult, PoolGraph};
+use crate::chain::actors::ChainActors;
+use crate::core::provider::MultiProvider;
+use crate::core::opportunity::fetch_base_fee;
+
+/// Số hop tối thiểu / tối đa của cycle
+const MIN_HOPS: usize = 2;
+const MAX_HOPS: usize = 4;
+
+/// Tìm mọi cycle 2-4 hop từ native token qua các pool trong `[[pools]]`,
+/// quote từng hop bằng custom UniV3Quoter qua REVM, tìm volume tối ưu và xếp hạng theo lợi nhuận gộp (chưa trừ gas)
+pub async fn run_chain_cycles(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
+    if config.pools.is_empty() {
+        bail!("No [[pools]] declared in chain config, cannot build cycle graph");
+    }
+
+    // 1️⃣ Setup provider và cache db
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+    let mut cache_db = init_cache_db(&multi_provider);
+
+    prepare_chain_cycles(config, actors, &mut cache_db, &multi_provider).await?;
+    let ranked = simulate_chain_cycles(config, actors, &mut cache_db, &BlockEnv::default())?;
+
+    // 4️⃣ Đối chiếu cycle đứng đầu bằng quoteExactInput (multi-hop path) qua eth_call
+    if let Some(top) = ranked.first() {
+        let from = config.me;
+        let uniswap_quoter = config.quoter(QuoterKind::UniswapV3)?;
+        let base_fee = fetch_base_fee(&multi_provider).await?;
+        let calldata = quote_exact_input_calldata(&top.cycle.tokens(), &top.cycle.fees(), top.best_amount_in);
+        let tx = build_tx(uniswap_quoter, from, calldata, base_fee);
+        let response = multi_provider
+            .request(|provider| {
+                let tx = tx.clone();
+                async move { provider.call(&tx).block(pinned.id()).await }
+            })
+            .await;
+        match response {
+            Ok(response) => println!(
+                "🔎 quoteExactInput: {} -> {} (revm: {})",
+                top.best_amount_in, decode_quote_exact_input_response(response)?, top.amount_out
+            ),
+            Err(e) => println!("⚠️ quoteExactInput failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Nạp bytecode mọi pool trong `[[pools]]` (qua RPC) + ERC20 / custom quoter giả vào `cache_db`
+pub async fn prepare_chain_cycles(
+    config: &ChainConfig,
+    _actors: &ChainActors,
+    cache_db: &mut AlloyCacheDB,
+    multi_provider: &MultiProvider,
+) -> Result<()> {
+    for pool in &config.pools {
+        init_account(pool.address, cache_db, multi_provider).await?;
+    }
+    mock_cycle_state(config, cache_db)
+}
+
+/// Thay bytecode mọi token trong `[[pools]]` bằng ERC20 giả (balance giả cho pool) và nạp custom quoter.
+/// Không gọi RPC nên dùng được trên lớp overlay (vd. state sau pending tx)
+pub fn mock_cycle_state<ExtDB>(config: &ChainConfig, cache_db: &mut CacheDB<ExtDB>) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let quoter = config.quoter(QuoterKind::Custom)?;
+
+    let mocked_erc20 = include_str!("../bytecode/generic_erc20.hex");
+    let mocked_erc20 = Bytecode::new_raw(Bytes::from_str(mocked_erc20)?);
+    let mocked_quoter = include_str!("../bytecode/uni_v3_quoter.hex");
+    let mocked_quoter = Bytecode::new_raw(Bytes::from_str(mocked_quoter)?);
+    init_account_with_bytecode(quoter, mocked_quoter, cache_db)?;
+
+    let mocked_balance = U256::MAX / U256::from(2);
+    for pool in &config.pools {
+        for token in [pool.token0, pool.token1] {
+            init_account_with_bytecode(token, mocked_erc20.clone(), cache_db)?;
+        }
+    }
+    // Balance set sau khi mọi token đã được thay bytecode
+    for pool in &config.pools {
+        for token in [pool.token0, pool.token1] {
+            insert_mapping_storage_slot(token, U256::ZERO, pool.address, mocked_balance, cache_db)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Tìm cycle trên lớp overlay của `base` (token mock + quoter nằm ở overlay, `base` giữ state thật).
+/// Storage pool đọc được trong lúc tìm được chép xuống `base` để lần sau không phải fetch lại.
+pub fn search_cycles_overlay<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    base: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+) -> Result<Vec<CycleResult>>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let (ranked, warmed) = {
+        let mut overlay = CacheDB::new(&*base);
+        mock_cycle_state(config, &mut overlay)?;
+        let ranked = search_cycles(config, actors, &mut overlay, block)?;
+        let warmed: Vec<_> = config
+            .pools
+            .iter()
+            .filter_map(|pool| overlay.accounts.get(&pool.address).map(|account| (pool.address, account.storage.clone())))
+            .collect();
+        (ranked, warmed)
+    };
+    for (pool, storage) in warmed {
+        if let Some(account) = base.accounts.get_mut(&pool) {
+            for (slot, value) in storage {
+                account.storage.entry(slot).or_insert(value);
+            }
+        }
+    }
+    Ok(ranked)
+}
+
+/// Liệt kê cycle, tìm volume tối ưu và xếp hạng trên `cache_db` đã chuẩn bị (online hoặc từ snapshot)
+pub fn simulate_chain_cycles<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+) -> Result<Vec<CycleResult>>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let start = measure_start("chain_cycles");
+    let ranked = search_cycles(config, actors, cache_db, block)?;
+    measure_end(start);
+
+    // In bảng xếp hạng
+    println!("{} pools → {} cycles ({}-{} hops) from {}", config.pools.len(), ranked.len(), MIN_HOPS, MAX_HOPS, actors.native_token_key);
+    println!("\n🏆 Ranked cycles (best volume, gross profit in {}):", actors.native_token_key);
+    for (rank, result) in ranked.iter().enumerate() {
+        let mark = if result.gross_profit.is_positive() { "✅" } else { "❌" };
+        println!(
+            "{:>2}. {} {} | in={} out={} gross={}",
+            rank + 1, mark, result.cycle, result.best_amount_in, result.amount_out, result.gross_profit
+        );
+    }
+
+    Ok(ranked)
+}
+
+/// Phần tính toán của `simulate_chain_cycles`: mọi cycle 2-4 hop từ native token, volume tối ưu
+/// của từng cycle, xếp theo lợi nhuận gộp giảm dần (chỉ in cycle bị lỗi)
+pub fn search_cycles<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+) -> Result<Vec<CycleResult>>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    // Đồ thị chỉ gồm pool swap được bằng quoter UniV3
+    let pools = config.univ3_pools();
+    if pools.is_empty() {
+        bail!("No univ3 [[pools]] declared in chain config, cannot build cycle graph");
+    }
+
+    let from = config.me;
+    let native = config.token(&actors.native_token_key)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
+
+    // 1️⃣ Dựng đồ thị và liệt kê cycle
+    let graph = PoolGraph::from_pools(&pools);
+    let cycles = graph.cycles(native, MIN_HOPS, MAX_HOPS);
+
+    // 2️⃣ Tìm volume tối ưu cho từng cycle
+    let optimizer_config = OptimizerConfig {
+        lower: U256::ZERO,
+        upper: ONE_ETHER.div(U256::from(10)),
+        tolerance: ONE_ETHER.div(U256::from(1_000_000)),
+        max_iterations: 64,
+    };
+    let mut results = Vec::new();
+    for cycle in cycles {
+        let mut cycle_quoter = RevmCycleQuoter { cache_db, block, from, quoter, cycle: &cycle };
+        match optimize(&mut cycle_quoter, &optimizer_config) {
+            Ok(best) => results.push(CycleResult::new(cycle, best.amount_in, best.amount_out)),
+            Err(e) => println!("⚠️ {} failed: {}", cycle, e),
+        }
+    }
+
+    Ok(rank_cycles(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::actors::ChainActors;
+    use crate::types::load_chain_config;
+
+    #[test]
+    fn test_eth_config_has_cycles() {
+        let config = load_chain_config("src/config/eth.toml").unwrap();
+        let native = config.token("WETH").unwrap().address;
+        let cycles = PoolGraph::from_pools(&config.univ3_pools()).cycles(native, MIN_HOPS, MAX_HOPS);
+        assert!(cycles.iter().any(|cycle| cycle.len() == 2));
+        assert!(cycles.iter().any(|cycle| cycle.len() == 3));
+        for cycle in &cycles {
+            assert_eq!(cycle.tokens().first(), Some(&native));
+            assert_eq!(cycle.tokens().last(), Some(&native));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires RPC_URL"]
+    async fn test_run_chain_cycles() {
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        config.rpc_urls = vec![std::env::var("RPC_URL").expect("Please set RPC_URL env")];
+        run_chain_cycles(&config, &ChainActors::from(&config), BlockId::latest()).await.unwrap();
+    }
+}
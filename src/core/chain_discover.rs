@@ -0,0 +1,71 @@
+use alloy::eips::BlockId;
+use anyhow::Result;
+
+use crate::core::logger::{measure_end, measure_start};
+use crate::core::pool_discovery::{discover_pools, DiscoverConfig, DiscoveredPool};
+use crate::core::provider::MultiProvider;
+use crate::types::ChainConfig;
+
+/// Tìm pool UniV3 của mọi cặp token trong `[[tokens]]` trên factory, lọc theo liquidity và ghi
+/// registry (`pool_registry`) để các lần chạy sau dùng như `[[pools]]` khai báo tay
+pub async fn run_chain_discover(config: &ChainConfig, block: BlockId, discover: &DiscoverConfig) -> Result<Vec<DiscoveredPool>> {
+    // 1️⃣ Ghim block: getPool, PoolCreated và liquidity cùng đọc một state
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+
+    // 2️⃣ getPool / PoolCreated → lọc liquidity → registry
+    let start = measure_start("chain_discover");
+    let pools = discover_pools(config, &multi_provider, &pinned, discover).await?;
+    measure_end(start);
+
+    Ok(pools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::abi::IUniswapV3Factory;
+    use crate::types::{load_chain_config, TokenConfig};
+    use alloy::{
+        node_bindings::Anvil,
+        primitives::{aliases::U24, Address},
+        providers::{Provider, ProviderBuilder},
+        rpc::types::TransactionRequest,
+        sol_types::SolCall,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires anvil + RPC_URL"]
+    async fn test_discover_on_anvil_fork() {
+        let anvil = Anvil::new().fork(std::env::var("RPC_URL").expect("Please set RPC_URL env")).spawn();
+        let provider = ProviderBuilder::new().on_http(anvil.endpoint().parse().unwrap());
+        let fork_block = provider.get_block_number().await.unwrap();
+
+        // Tạo pool mới trên factory thật của bản fork cho hai token chưa có pool
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        let factory = config.factory.unwrap();
+        let (token_x, token_y) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        for (symbol, address) in [("X", token_x), ("Y", token_y)] {
+            config.tokens.push(TokenConfig { symbol: symbol.to_string(), address, decimals: 18, balance_slot: None });
+        }
+        let create = IUniswapV3Factory::createPoolCall { tokenA: token_y, tokenB: token_x, fee: U24::from(3000) };
+        let tx = TransactionRequest::default().from(anvil.addresses()[0]).to(factory).input(create.abi_encode().into());
+        provider.send_transaction(tx).await.unwrap().get_receipt().await.unwrap();
+
+        let registry = std::env::temp_dir().join(format!("uv3-discover-anvil-{}.toml", std::process::id()));
+        let multi_provider = MultiProvider::new(&[anvil.endpoint()]);
+        let pinned = multi_provider.pin_block(BlockId::latest()).await.unwrap();
+        let discover = DiscoverConfig { from_block: Some(fork_block + 1), min_liquidity: 0, registry: Some(registry.clone()), ..Default::default() };
+        let pools = discover_pools(&config, &multi_provider, &pinned, &discover).await.unwrap();
+
+        let created = pools.iter().find(|pool| (pool.token0, pool.token1, pool.fee) == (token_x, token_y, 3000)).unwrap();
+        assert_eq!((created.tick_spacing, created.liquidity), (60, 0));
+        // Mọi pool khai báo tay trong eth.toml đều tìm lại được
+        for declared in &config.pools {
+            assert!(pools.iter().any(|pool| pool.address == declared.address && pool.liquidity > 0), "{:?}", declared.address);
+        }
+        std::fs::remove_file(&registry).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{anyhow, bail, Result};
+use alloy::{eips::BlockId, primitives::U256};
+use revm::db::{CacheDB, DatabaseRef};
+
+use crate::types::ChainConfig;
+use crate::chain::actors::ChainActors;
+use crate::core::arb_executor::{deploy_executor, simulate_execution, ExecutionEnv, ExecutionReport, DEFAULT_EXECUTOR};
+use crate::core::backrun::next_block_env;
+use crate::core::chain_cycles::search_cycles_overlay;
+use crate::core::db::{init_account, init_cache_db, revm_call_db};
+use crate::core::logger::{measure_start, measure_end};
+use crate::core::provider::MultiProvider;
+use crate::core::submit::{ensure_pinned_head, Submitter};
+use crate::source::abi::{decode_owner_response, execute_calldata, owner_calldata};
+use crate::core::arb_executor::executor_path;
+
+/// Số cycle đứng đầu bảng xếp hạng được swap thật qua executor
+const EXECUTE_TOP: usize = 3;
+
+/// Xếp hạng cycle bằng quoter (token mock) như `run_chain_cycles`, rồi swap thật các cycle đứng đầu
+/// qua `ArbExecutor` từ `ME` trên state thật (token, pool không mock), in lãi + balance delta của `ME`.
+/// Có `submitter` thì mô phỏng từ address của signer trên executor đã deploy (`arb_executor`, signer phải là owner)
+/// và ký (gửi nếu không dry-run) cycle lãi nhất; chỉ chạy được khi `block` là head hiện tại.
+pub async fn run_chain_execute(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    block: BlockId,
+    submitter: Option<&Submitter>,
+) -> Result<Vec<ExecutionReport>> {
+    if config.pools.is_empty() {
+        bail!("No [[pools]] declared in chain config, cannot build cycle graph");
+    }
+
+    // 1️⃣ Setup provider + cache db với state thật (chỉ nạp pool)
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+    if submitter.is_some() {
+        ensure_pinned_head(&multi_provider).await?;
+    }
+    let mut base = init_cache_db(&multi_provider);
+    for pool in &config.pools {
+        init_account(pool.address, &mut base, &multi_provider).await?;
+    }
+
+    // 2️⃣ Xếp hạng cycle + volume tối ưu bằng quoter trên lớp overlay, cùng block env với executor
+    let block = next_block_env(&pinned, 0);
+    let ranked = search_cycles_overlay(config, actors, &mut base, &block)?;
+    println!("{} cycles ranked, executing top {}", ranked.len(), EXECUTE_TOP.min(ranked.len()));
+
+    // 3️⃣ Executor: bản đã deploy khi submit (code + owner đọc từ chain), bản trong repo khi chỉ mô phỏng;
+    // basefee 0 để ME không cần ETH trả gas (gas used vẫn đúng)
+    let env = ExecutionEnv {
+        executor: config.arb_executor.unwrap_or(DEFAULT_EXECUTOR),
+        me: match submitter {
+            Some(submitter) => submitter.address(),
+            None => config.me,
+        },
+        block,
+        chain_id: config.chain_id,
+    };
+    match submitter {
+        Some(_) => {
+            let executor = config.arb_executor
+                .ok_or_else(|| anyhow!("Set arb_executor in chain config to the deployed executor before submitting"))?;
+            init_account(executor, &mut base, &multi_provider).await?;
+            if !base.basic_ref(executor)?.and_then(|info| info.code).is_some_and(|code| !code.is_empty()) {
+                bail!("No code at arb_executor {:?} on pinned block {}", executor, pinned);
+            }
+            let response = revm_call_db(env.me, executor, owner_calldata(), &env.block, &mut CacheDB::new(&base))?;
+            let owner = decode_owner_response(response)?;
+            if owner != env.me {
+                bail!("arb_executor {:?} is owned by {:?}, not the signer {:?}", executor, owner, env.me);
+            }
+        }
+        None => deploy_executor(env.executor, env.me, &mut base)?,
+    }
+
+    // 4️⃣ Swap thật từng cycle trên overlay riêng để kết quả độc lập nhau
+    let start = measure_start("chain_execute");
+    let mut reports = Vec::new();
+    for result in ranked.iter().filter(|result| !result.best_amount_in.is_zero()).take(EXECUTE_TOP) {
+        let mut overlay = CacheDB::new(&base);
+        let report = simulate_execution(&mut overlay, &env, &result.cycle, result.best_amount_in, U256::ZERO)?;
+        println!("{} (quoter gross={})", report, result.gross_profit);
+        reports.push(report);
+    }
+    measure_end(start);
+
+    // 5️⃣ Ký + gửi cycle lãi nhất; on-chain revert nếu lãi tụt dưới một nửa lãi mô phỏng
+    if let Some(submitter) = submitter {
+        let best = reports.iter().filter_map(|report| report.profit.map(|profit| (profit, report))).max_by_key(|(profit, _)| *profit);
+        match best {
+            Some((profit, report)) => {
+                let calldata = execute_calldata(executor_path(&report.cycle), report.amount_in, profit / U256::from(2));
+                let gas_limit = (report.bundle.gas_used() as f64 * config.gas_multiplier.max(1.0)).ceil() as u64;
+                let submission = submitter.submit(&multi_provider, env.executor, calldata, U256::ZERO, Some(gas_limit)).await?;
+                println!("{}", submission);
+            }
+            None => println!("No profitable cycle to submit"),
+        }
+    }
+
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::actors::ChainActors;
+    use crate::types::load_chain_config;
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires RPC_URL"]
+    async fn test_run_chain_execute() {
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        config.rpc_urls = vec![std::env::var("RPC_URL").expect("Please set RPC_URL env")];
+        let reports = run_chain_execute(&config, &ChainActors::from(&config), BlockId::latest(), None).await.unwrap();
+        assert!(!reports.is_empty());
+        // Lãi chỉ có khi swap thật không revert
+        for report in &reports {
+            assert_eq!(report.profit.is_some(), report.bundle.is_success());
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::ops::Div;
+use std::str::FromStr;
+use anyhow::Result;
+use alloy::{eips::BlockId, primitives::{Bytes, U256}};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{BlockEnv, Bytecode},
+};
+
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
+use crate::source::{builder::volumes, abi::*};
+use crate::core::db::*;
+use crate::core::logger::{measure_start, measure_end};
+use crate::chain::actors::ChainActors;
+use crate::core::provider::MultiProvider;
+use crate::univ3::PoolState;
+
+/// Quote bằng mô hình UniswapV3 thuần Rust (không chạy EVM), đối chiếu với custom UniV3Quoter qua REVM
+pub async fn run_chain_native(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
+    // 1️⃣ Setup provider và cache db
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+
+    let mut cache_db = init_cache_db(&multi_provider);
+
+    prepare_chain_native(config, actors, &mut cache_db, &multi_provider).await?;
+    simulate_chain_native(config, actors, &mut cache_db, &BlockEnv::default())
+}
+
+/// Nạp bytecode pool thật + ERC20 / custom quoter giả để đối chiếu native với REVM
+pub async fn prepare_chain_native(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut AlloyCacheDB,
+    multi_provider: &MultiProvider,
+) -> Result<()> {
+    // 1️⃣ Đọc address từ config
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
+
+    // 2️⃣ Pool thật + ERC20 / custom quoter giả để đối chiếu với REVM
+    init_account(pool, cache_db, multi_provider).await?;
+    let mocked_erc20 = include_str!("../bytecode/generic_erc20.hex");
+    let mocked_erc20 = Bytecode::new_raw(Bytes::from_str(mocked_erc20)?);
+    init_account_with_bytecode(token_in, mocked_erc20.clone(), cache_db)?;
+    init_account_with_bytecode(token_out, mocked_erc20, cache_db)?;
+
+    let mocked_balance = U256::MAX / U256::from(2);
+    insert_mapping_storage_slot(token_in, U256::ZERO, pool, mocked_balance, cache_db)?;
+    insert_mapping_storage_slot(token_out, U256::ZERO, pool, mocked_balance, cache_db)?;
+
+    let mocked_custom_quoter = include_str!("../bytecode/uni_v3_quoter.hex");
+    let mocked_custom_quoter = Bytecode::new_raw(Bytes::from_str(mocked_custom_quoter)?);
+    init_account_with_bytecode(quoter, mocked_custom_quoter, cache_db)?;
+
+    Ok(())
+}
+
+/// Quote native trên `cache_db` rồi đối chiếu từng mốc volume với custom quoter qua REVM
+pub fn simulate_chain_native<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    // 1️⃣ Đọc address từ config
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
+    let zero_for_one = token_in < token_out;
+
+    let volumes = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), 100);
+
+    // 2️⃣ Load slot0 + liquidity của pool (tick bitmap load lazily khi quote)
+    let start = measure_start("chain_native_load_pool");
+    let mut pool_state = PoolState::load(cache_db, pool, actors.default_fee)?;
+    println!(
+        "Pool {:?}: sqrtPriceX96={} tick={} liquidity={}",
+        pool, pool_state.sqrt_price_x96, pool_state.tick, pool_state.liquidity
+    );
+    measure_end(start);
+
+    // 3️⃣ Quote đầu tiên (có thể phải load tick bitmap qua RPC nếu online)
+    let start = measure_start("chain_native_first");
+    let amount_out = pool_state.quote_exact_input(cache_db, zero_for_one, volumes[0])?;
+    println!("{} {} -> {} {}", volumes[0], actors.native_token_key, actors.stable_token_key, amount_out);
+    measure_end(start);
+
+    // 4️⃣ Loop benchmark, state đã nằm hết trong PoolState
+    let start = measure_start("chain_native_loop");
+    for (index, volume) in volumes.iter().enumerate() {
+        let amount_out = pool_state.quote_exact_input(cache_db, zero_for_one, *volume)?;
+        if index % 20 == 0 {
+            println!("{} {} -> {} {}", volume, actors.native_token_key, actors.stable_token_key, amount_out);
+        }
+    }
+    measure_end(start);
+
+    // 5️⃣ Đối chiếu với REVM + custom quoter trên cùng cache
+    for volume in volumes.into_iter().step_by(20) {
+        let native_amount_out = pool_state.quote_exact_input(cache_db, zero_for_one, volume)?;
+
+        let calldata = get_amount_out_calldata(pool, token_in, token_out, volume);
+        let response = revm_revert(from, quoter, calldata, block, cache_db)?;
+        let revm_amount_out = decode_get_amount_out_response(response)?;
+
+        println!(
+            "{} {} -> {} | NATIVE: {} | REVM: {}",
+            volume, actors.native_token_key, actors.stable_token_key, native_amount_out, revm_amount_out
+        );
+        assert_eq!(native_amount_out, U256::from(revm_amount_out));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use alloy::primitives::Address;
+    use revm::db::EmptyDB;
+    use uniswap_v3_math::{liquidity_math, swap_math, tick_bitmap, tick_math};
+    use uniswap_v3_math_primitives::{I256 as RefI256, U256 as RefU256};
+    use super::{CacheDB, U256};
+    use crate::chain::actors::ChainActors;
+    use crate::core::chain_snapshot::run_chain_snapshot;
+    use crate::types::load_chain_config;
+    use crate::univ3::pool::{mapping_slot, LIQUIDITY_SLOT, SLOT0_SLOT, TICKS_SLOT, TICK_BITMAP_SLOT};
+    use crate::univ3::PoolState;
+
+    /// Ghi từ mainnet bằng
+    /// `cargo run --bin simulate -- --chain eth --method native --block <n> --save-snapshot src/fixtures/snapshots/native.json`
+    const NATIVE_FIXTURE: &str = "src/fixtures/snapshots/native.json";
+
+    #[test]
+    #[ignore = "requires src/fixtures/snapshots/native.json recorded from mainnet (RPC_URL), not committed yet"]
+    fn test_native_matches_revm_quoter() {
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        // Replay không được chạm RPC
+        config.rpc_urls = vec!["http://127.0.0.1:1".to_string()];
+        run_chain_snapshot(&config, &ChainActors::from(&config), "native", Path::new(NATIVE_FIXTURE)).unwrap();
+    }
+
+    const FEE: u32 = 3000;
+    const TICK_SPACING: i32 = 60;
+    const ONE_E18: u128 = 1_000_000_000_000_000_000;
+
+    fn to_ref(value: U256) -> RefU256 {
+        RefU256::from_limbs(value.into_limbs())
+    }
+
+    /// Pool fee 3000 nhiều vị trí LP chồng nhau, trải qua nhiều word của tick bitmap
+    struct Positions {
+        sqrt_price_x96: RefU256,
+        /// `(tickLower, tickUpper, liquidity)`
+        ranges: Vec<(i32, i32, u128)>,
+    }
+
+    impl Positions {
+        fn sample() -> Self {
+            Positions {
+                // Giá nằm giữa hai tick, không trùng tick đã khởi tạo
+                sqrt_price_x96: tick_math::get_sqrt_ratio_at_tick(-37).unwrap() + RefU256::from(123_456_789u64),
+                ranges: vec![
+                    (-30000, 30000, ONE_E18),
+                    (-600, 600, 5 * ONE_E18),
+                    (-1200, 2400, 2 * ONE_E18),
+                    (-16020, -13980, 3 * ONE_E18),
+                    (15420, 17400, 4 * ONE_E18),
+                ],
+            }
+        }
+
+        fn tick(&self) -> i32 {
+            tick_math::get_tick_at_sqrt_ratio(self.sqrt_price_x96).unwrap()
+        }
+
+        fn liquidity(&self) -> u128 {
+            let tick = self.tick();
+            self.ranges.iter().filter(|(lower, upper, _)| *lower <= tick && tick < *upper).map(|(_, _, liquidity)| liquidity).sum()
+        }
+
+        /// `(liquidityGross, liquidityNet)` theo tick
+        fn ticks(&self) -> HashMap<i32, (u128, i128)> {
+            let mut ticks: HashMap<i32, (u128, i128)> = HashMap::new();
+            for (lower, upper, liquidity) in &self.ranges {
+                for (tick, net) in [(*lower, *liquidity as i128), (*upper, -(*liquidity as i128))] {
+                    let entry = ticks.entry(tick).or_default();
+                    entry.0 += liquidity;
+                    entry.1 += net;
+                }
+            }
+            ticks
+        }
+
+        fn bitmap(&self) -> HashMap<i16, RefU256> {
+            let mut bitmap = HashMap::new();
+            for tick in self.ticks().keys() {
+                tick_bitmap::flip_tick(&mut bitmap, *tick, TICK_SPACING).unwrap();
+            }
+            bitmap
+        }
+
+        /// Storage theo layout `UniswapV3Pool` để `PoolState` đọc như pool thật
+        fn store(&self, db: &mut CacheDB<EmptyDB>, pool: Address) {
+            let sqrt_price = U256::from_limbs(self.sqrt_price_x96.into_limbs());
+            let slot0 = sqrt_price | (U256::from((self.tick() as u32) & 0xffffff) << 160);
+            db.insert_account_storage(pool, SLOT0_SLOT, slot0).unwrap();
+            db.insert_account_storage(pool, LIQUIDITY_SLOT, U256::from(self.liquidity())).unwrap();
+            for (word, value) in self.bitmap() {
+                let value = U256::from_limbs(value.into_limbs());
+                db.insert_account_storage(pool, mapping_slot(word as i32, TICK_BITMAP_SLOT), value).unwrap();
+            }
+            for (tick, (gross, net)) in self.ticks() {
+                let info = U256::from(gross) | (U256::from(net as u128) << 128);
+                db.insert_account_storage(pool, mapping_slot(tick, TICKS_SLOT), info).unwrap();
+            }
+        }
+
+        /// Exact input của `UniswapV3Pool.swap` với price limit của `UniV3Quoter`,
+        /// mọi phép tính lấy từ `uniswap_v3_math` (không dùng gì của `crate::univ3`)
+        fn reference_quote(&self, zero_for_one: bool, amount_in: u128) -> U256 {
+            let (min_tick, max_tick) = (tick_math::MIN_TICK, tick_math::MAX_TICK);
+            let limit = if zero_for_one {
+                tick_math::get_sqrt_ratio_at_tick(min_tick).unwrap() + RefU256::from(10)
+            } else {
+                tick_math::get_sqrt_ratio_at_tick(max_tick).unwrap() - RefU256::from(1)
+            };
+            let (bitmap, ticks) = (self.bitmap(), self.ticks());
+            let (mut sqrt_price, mut tick, mut liquidity) = (self.sqrt_price_x96, self.tick(), self.liquidity());
+            let mut remaining = RefI256::from_raw(RefU256::from(amount_in));
+            let mut amount_out = RefU256::ZERO;
+
+            while !remaining.is_zero() && sqrt_price != limit {
+                let start = sqrt_price;
+                let (tick_next, initialized) =
+                    tick_bitmap::next_initialized_tick_within_one_word(&bitmap, tick, TICK_SPACING, zero_for_one).unwrap();
+                let tick_next = tick_next.clamp(min_tick, max_tick);
+                let sqrt_price_next = tick_math::get_sqrt_ratio_at_tick(tick_next).unwrap();
+                let target = if (zero_for_one && sqrt_price_next < limit) || (!zero_for_one && sqrt_price_next > limit) {
+                    limit
+                } else {
+                    sqrt_price_next
+                };
+                let (next, step_in, step_out, fee) =
+                    swap_math::compute_swap_step(sqrt_price, target, liquidity, remaining, FEE).unwrap();
+                sqrt_price = next;
+                remaining -= RefI256::from_raw(step_in + fee);
+                amount_out += step_out;
+
+                if sqrt_price == sqrt_price_next {
+                    if initialized {
+                        let net = ticks[&tick_next].1;
+                        liquidity = liquidity_math::add_delta(liquidity, if zero_for_one { -net } else { net }).unwrap();
+                    }
+                    tick = if zero_for_one { tick_next - 1 } else { tick_next };
+                } else if sqrt_price != start {
+                    tick = tick_math::get_tick_at_sqrt_ratio(sqrt_price).unwrap();
+                }
+            }
+            U256::from_limbs(amount_out.into_limbs())
+        }
+    }
+
+    /// Đối chiếu `PoolState` với port UniswapV3 độc lập (`uniswap_v3_math`), không cần RPC hay snapshot:
+    /// swap nhỏ trong một tick, swap cắt qua nhiều tick / nhiều word, swap vét hết liquidity
+    #[test]
+    fn test_native_matches_uniswap_v3_math() {
+        let positions = Positions::sample();
+        let pool = Address::repeat_byte(0x11);
+        let mut db = CacheDB::new(EmptyDB::default());
+        positions.store(&mut db, pool);
+        assert_eq!(to_ref(PoolState::load(&mut db, pool, FEE).unwrap().sqrt_price_x96), positions.sqrt_price_x96);
+
+        for zero_for_one in [true, false] {
+            for amount_in in [1_000u128, ONE_E18 / 1000, ONE_E18, 20 * ONE_E18, 1_000 * ONE_E18, 1_000_000 * ONE_E18] {
+                let mut pool_state = PoolState::load(&mut db, pool, FEE).unwrap();
+                let native = pool_state.quote_exact_input(&mut db, zero_for_one, U256::from(amount_in)).unwrap();
+                let expected = positions.reference_quote(zero_for_one, amount_in);
+                assert!(native > U256::ZERO);
+                assert_eq!(native, expected, "zero_for_one={} amount_in={}", zero_for_one, amount_in);
+            }
+        }
+
+        // Swap lớn nhất đi qua cả 5 vị trí → đã load nhiều word tick bitmap
+        let mut pool_state = PoolState::load(&mut db, pool, FEE).unwrap();
+        pool_state.quote_exact_input(&mut db, true, U256::from(1_000_000 * ONE_E18)).unwrap();
+        assert!(pool_state.tick_bitmap.len() > 2);
+        assert!(pool_state.ticks.len() >= 4);
+    }
+}
@@ -1,79 +1,109 @@
-use std::sync::Arc;
 use std::ops::Div;
 use std::str::FromStr;
 use anyhow::Result;
 use alloy::{
+    eips::BlockId,
     primitives::{Bytes, U256},
-    providers::{Provider, ProviderBuilder},
 };
-use revm::primitives::Bytecode;
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    primitives::{BlockEnv, Bytecode},
+};
 
-use crate::types::{ChainConfig, ONE_ETHER};
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
 use crate::source::{builder::volumes, abi::*};
 use crate::core::db::*;
 use crate::core::logger::{measure_start, measure_end};
 use crate::chain::actors::ChainActors;
 use crate::core::provider::MultiProvider;
+use crate::core::balance_slot::BalanceSlots;
 
 /// REVM chạy quote bằng custom UniV3Quoter contract (trả kết quả qua revert), multi-chain version
-pub async fn run_chain_revm_quoter(config: &ChainConfig, actors: &ChainActors) -> Result<()> {
+pub async fn run_chain_revm_quoter(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
     // 1️⃣ Setup provider và cache db
     // let provider = ProviderBuilder::new()
     //     .on_http(config.rpc_url.parse()?);
     // let provider = Arc::new(provider);
 
     // let mut cache_db = init_cache_db(provider.clone());
-    let multi_provider = MultiProvider::new(&config.rpc_urls);
+    let multi_provider = MultiProvider::for_chain(config);
     println!("MultiProvider with {} providers", multi_provider.len());
-
-    // let base_fee = provider.get_gas_price().await?;
-    let (provider, url) = multi_provider.next();
-    let base_fee = provider.get_gas_price().await?;
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
 
     let mut cache_db = init_cache_db(&multi_provider);
+    prepare_chain_revm_quoter(config, actors, &mut cache_db, &multi_provider).await?;
+    simulate_chain_revm_quoter(config, actors, &mut cache_db, &BlockEnv::default())
+}
 
+/// Nạp bytecode pool + ERC20 thật (qua RPC), quoter giả và balance giả của pool vào `cache_db`
+pub async fn prepare_chain_revm_quoter(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut AlloyCacheDB,
+    multi_provider: &MultiProvider,
+) -> Result<()> {
     // 2️⃣ Đọc address từ config
-    let from = config.addr("ME")?;
-    let token_in = config.addr(actors.native_token_key)?;
-    let token_out = config.addr(actors.stable_token_key)?;
-    let pool = config.addr(actors.pool_3000_key.expect("Missing pool_3000_key"))?;
-    let quoter = config.addr(actors.custom_quoter_key.expect("Missing custom_quoter_key"))?;
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
 
-    // 3️⃣ Chuẩn bị volume để benchmark
-    let volumes = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), 100);
-
-    // 4️⃣ Load bytecode thật và giả vào REVM
-    init_account(from, &mut cache_db, &multi_provider).await?;
-    init_account(pool, &mut cache_db, &multi_provider).await?;
+    // 3️⃣ Load bytecode thật và giả vào REVM
+    init_account(from, cache_db, multi_provider).await?;
+    init_account(pool, cache_db, multi_provider).await?;
 
-    let mocked_erc20 = include_str!("../bytecode/generic_erc20.hex");
-    let mocked_erc20 = Bytecode::new_raw(Bytes::from_str(mocked_erc20)?);
-    init_account_with_bytecode(token_in, mocked_erc20.clone(), &mut cache_db)?;
-    init_account_with_bytecode(token_out, mocked_erc20.clone(), &mut cache_db)?;
+    init_account(token_in, cache_db, multi_provider).await?;
+    init_account(token_out, cache_db, multi_provider).await?;
 
-    // 5️⃣ Insert fake balances vào REVM storage
+    // 4️⃣ Insert fake balances của pool vào storage token thật (slot tìm qua BalanceSlots)
     let mocked_balance = U256::MAX / U256::from(2);
-    insert_mapping_storage_slot(token_in, U256::ZERO, pool, mocked_balance, &mut cache_db)?;
-    insert_mapping_storage_slot(token_out, U256::ZERO, pool, mocked_balance, &mut cache_db)?;
+    let mut balance_slots = BalanceSlots::for_provider(multi_provider).with_declared(&config.tokens);
+    balance_slots.set_real_token_balance(token_in, pool, mocked_balance, cache_db).await?;
+    balance_slots.set_real_token_balance(token_out, pool, mocked_balance, cache_db).await?;
 
-    // 6️⃣ Load custom quoter bytecode
+    // 5️⃣ Load custom quoter bytecode
     let mocked_custom_quoter = include_str!("../bytecode/uni_v3_quoter.hex");
     let mocked_custom_quoter = Bytecode::new_raw(Bytes::from_str(mocked_custom_quoter)?);
-    init_account_with_bytecode(quoter, mocked_custom_quoter, &mut cache_db)?;
+    init_account_with_bytecode(quoter, mocked_custom_quoter, cache_db)?;
+
+    Ok(())
+}
+
+/// Benchmark quote trên `cache_db` đã chuẩn bị, không cần RPC nếu state đã đủ (vd. load từ snapshot)
+pub fn simulate_chain_revm_quoter<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::Custom)?;
+
+    // 1️⃣ Chuẩn bị volume để benchmark
+    let volumes = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), 100);
 
-    // 7️⃣ Quote đầu tiên
+    // 2️⃣ Quote đầu tiên
     let start = measure_start("chain_revm_quoter_first");
     let calldata = get_amount_out_calldata(pool, token_in, token_out, volumes[0]);
-    let response = revm_revert(from, quoter, calldata, &mut cache_db)?;
+    let response = revm_revert(from, quoter, calldata, block, cache_db)?;
     let amount_out = decode_get_amount_out_response(response)?;
     println!("{} {} -> {} {}", volumes[0], actors.native_token_key, actors.stable_token_key, amount_out);
     measure_end(start);
 
-    // 8️⃣ Loop benchmark các volume còn lại
+    // 3️⃣ Loop benchmark các volume còn lại
     let start = measure_start("chain_revm_quoter_loop");
     for (index, volume) in volumes.into_iter().enumerate() {
         let calldata = get_amount_out_calldata(pool, token_in, token_out, volume);
-        let response = revm_revert(from, quoter, calldata, &mut cache_db)?;
+        let response = revm_revert(from, quoter, calldata, block, cache_db)?;
         let amount_out = decode_get_amount_out_response(response)?;
         if index % 20 == 0 {
             println!("{} {} -> {} {}", volume, actors.native_token_key, actors.stable_token_key, amount_out);
@@ -0,0 +1,285 @@
+use std::path::Path;
+use anyhow::{anyhow, bail, Result};
+use alloy::{
+    eips::BlockId,
+    providers::Provider,
+    rpc::types::BlockTransactionsKind,
+};
+use revm::{db::{CacheDB, DatabaseRef}, primitives::BlockEnv};
+
+use crate::types::ChainConfig;
+use crate::chain::actors::ChainActors;
+use crate::core::db::AlloyCacheDB;
+use crate::core::db_empty::InMemoryDB;
+use crate::core::logger::{measure_start, measure_end};
+use crate::core::provider::MultiProvider;
+use crate::core::db_persistent::PersistentDB;
+use crate::core::db_rpc::RpcDB;
+use crate::core::snapshot::{Snapshot, SnapshotBlock};
+use crate::core::chain_revm_cached::{prepare_chain_revm_cached, simulate_chain_revm_cached};
+use crate::core::chain_revm_quoter::{prepare_chain_revm_quoter, simulate_chain_revm_quoter};
+use crate::core::chain_arbitrage::{prepare_chain_arbitrage, simulate_chain_arbitrage};
+use crate::core::chain_native::{prepare_chain_native, simulate_chain_native};
+use crate::core::chain_cycles::{prepare_chain_cycles, simulate_chain_cycles};
+
+/// Các method chỉ chạy REVM / native trên state đã cache nên replay được từ snapshot
+pub const SNAPSHOT_METHODS: &[&str] = &["revm_cached", "revm_quoter", "arbitrage", "native", "cycles"];
+
+pub(crate) fn ensure_snapshot_method(method: &str) -> Result<()> {
+    if !SNAPSHOT_METHODS.contains(&method) {
+        bail!("Method '{}' needs RPC and cannot run from a snapshot (supported: {})", method, SNAPSHOT_METHODS.join(", "));
+    }
+    Ok(())
+}
+
+/// Block context của block đã ghim (block mới nhất nếu chưa ghim), base fee fallback `eth_gasPrice` nếu chain không có EIP-1559
+async fn fetch_snapshot_block(multi_provider: &MultiProvider) -> Result<SnapshotBlock> {
+    let block_id = multi_provider.block_id();
+    let block = multi_provider
+        .request(|provider| async move { provider.get_block(block_id, BlockTransactionsKind::Hashes).await })
+        .await?
+        .ok_or_else(|| anyhow!("Block {} not found", block_id))?;
+    let base_fee = match block.header.base_fee_per_gas {
+        Some(base_fee) => base_fee as u128,
+        None => multi_provider.request(|provider| async move { provider.get_gas_price().await }).await?,
+    };
+    Ok(SnapshotBlock {
+        number: block.header.number,
+        timestamp: block.header.timestamp,
+        base_fee,
+        gas_limit: block.header.gas_limit,
+        coinbase: block.header.miner,
+        prevrandao: block.header.mix_hash.unwrap_or_default(),
+    })
+}
+
+/// Nạp state cần cho `method` qua RPC (bytecode thật, ERC20 / quoter giả, balance giả)
+pub(crate) async fn prepare_on_db(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    cache_db: &mut AlloyCacheDB,
+    multi_provider: &MultiProvider,
+) -> Result<()> {
+    match method {
+        "revm_cached" => prepare_chain_revm_cached(config, actors, cache_db, multi_provider).await,
+        "revm_quoter" => prepare_chain_revm_quoter(config, actors, cache_db, multi_provider).await,
+        "arbitrage" => prepare_chain_arbitrage(config, actors, cache_db, multi_provider).await,
+        "native" => prepare_chain_native(config, actors, cache_db, multi_provider).await,
+        "cycles" => prepare_chain_cycles(config, actors, cache_db, multi_provider).await,
+        _ => ensure_snapshot_method(method),
+    }
+}
+
+/// Phần chạy trên state của từng method, dùng chung cho online (record) và offline (replay)
+pub(crate) fn simulate_on_db<ExtDB>(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    cache_db: &mut CacheDB<ExtDB>,
+    block: &BlockEnv,
+    base_fee: u128,
+) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    match method {
+        "revm_cached" => simulate_chain_revm_cached(config, actors, cache_db, block),
+        "revm_quoter" => simulate_chain_revm_quoter(config, actors, cache_db, block),
+        "arbitrage" => simulate_chain_arbitrage(config, actors, cache_db, block, base_fee),
+        "native" => simulate_chain_native(config, actors, cache_db, block),
+        "cycles" => simulate_chain_cycles(config, actors, cache_db, block).map(|_| ()),
+        _ => ensure_snapshot_method(method),
+    }
+}
+
+/// Chạy `method` online với mọi lần đọc ghim vào `block`,
+/// rồi ghi toàn bộ state đã warm-up + block env ra `path`
+pub async fn record_chain_snapshot(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    path: &Path,
+    block: BlockId,
+) -> Result<()> {
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    record_snapshot_on(config, actors, method, path, &multi_provider, block).await
+}
+
+/// Như `record_chain_snapshot` nhưng đọc state qua `multi_provider` cho trước
+pub(crate) async fn record_snapshot_on(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    path: &Path,
+    multi_provider: &MultiProvider,
+    block: BlockId,
+) -> Result<()> {
+    ensure_snapshot_method(method)?;
+
+    // 1️⃣ Ghim block để state trong snapshot nhất quán
+    multi_provider.pin_block(block).await?;
+    let block = fetch_snapshot_block(multi_provider).await?;
+    println!("Recording '{}' at block {}", method, block.number);
+    let rpc_db = RpcDB::new(multi_provider, BlockId::number(block.number))
+        .ok_or_else(|| anyhow!("RpcDB requires a multi-thread tokio runtime"))?;
+    let mut cache_db = CacheDB::new(PersistentDB::for_provider(rpc_db, multi_provider));
+
+    // 2️⃣ Chuẩn bị state qua RPC
+    prepare_on_db(config, actors, method, &mut cache_db, multi_provider).await?;
+
+    // 3️⃣ Chạy một lượt (cùng block env sẽ dùng khi replay) để mọi slot cần thiết nằm trong cache
+    simulate_on_db(config, actors, method, &mut cache_db, &block.to_block_env(), block.base_fee)?;
+
+    // 4️⃣ Ghi snapshot
+    let start = measure_start("snapshot_save");
+    let snapshot = Snapshot::from_db(config.chain_id, block, &InMemoryDB::from_cache_db(&cache_db));
+    snapshot.save(path)?;
+    measure_end(start);
+    println!(
+        "💾 Snapshot saved to {}: {} accounts, {} storage slots, block {}",
+        path.display(), snapshot.accounts.len(), snapshot.storage_slots(), snapshot.block.number
+    );
+
+    Ok(())
+}
+
+/// Chạy `method` hoàn toàn offline trên snapshot đã lưu, không mở kết nối RPC nào
+pub fn run_chain_snapshot(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    method: &str,
+    path: &Path,
+) -> Result<()> {
+    ensure_snapshot_method(method)?;
+
+    let start = measure_start("snapshot_load");
+    let snapshot = Snapshot::load(path)?;
+    if snapshot.chain_id != config.chain_id {
+        bail!("Snapshot {} is for chain {}, config is chain {}", path.display(), snapshot.chain_id, config.chain_id);
+    }
+    let mut cache_db = CacheDB::new(snapshot.to_db()?);
+    measure_end(start);
+    println!(
+        "📂 Snapshot {}: {} accounts, {} storage slots, block {} (base fee {})",
+        path.display(), snapshot.accounts.len(), snapshot.storage_slots(), snapshot.block.number, snapshot.block.base_fee
+    );
+
+    // EVM chạy với block env đã chụp (number, timestamp, coinbase, prevrandao, gas limit)
+    simulate_on_db(config, actors, method, &mut cache_db, &snapshot.block.to_block_env(), snapshot.block.base_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::actors::ChainActors;
+    use crate::core::mock_rpc::{default_account_response, mock_block, MockRpc};
+    use crate::types::load_chain_config;
+    use alloy::primitives::{Address, U256};
+    use serde_json::{json, Value};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("uv3-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_rejects_network_methods_and_wrong_chain() {
+        let config = load_chain_config("src/config/eth.toml").unwrap();
+        let actors = ChainActors::from(&config);
+        let path = temp_path("avax.json");
+        Snapshot::from_db(43114, SnapshotBlock::default(), &InMemoryDB::default()).save(&path).unwrap();
+
+        let err = run_chain_snapshot(&config, &actors, "anvil", &path).unwrap_err();
+        assert!(err.to_string().contains("cannot run from a snapshot"));
+        let err = run_chain_snapshot(&config, &actors, "revm_quoter", &path).unwrap_err();
+        assert!(err.to_string().contains("chain 43114"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Snapshot commit sẵn, replay được không cần RPC. Dữ liệu TỔNG HỢP, không phải state mainnet:
+    /// ghi từ `fixture_chain` (pool `MockUniV3Pool` tỉ giá cố định đặt tại địa chỉ pool thật của eth.toml,
+    /// block #21000000 giả với timestamp / base fee của `mock_block`), chỉ dùng để test đường replay
+    const FIXTURE_DIR: &str = "src/fixtures/snapshots";
+    const FIXTURE_METHODS: &[&str] = &["revm_quoter", "arbitrage"];
+    const FIXTURE_BLOCK: u64 = 21_000_000;
+
+    fn fixture_path(method: &str) -> std::path::PathBuf {
+        Path::new(FIXTURE_DIR).join(format!("{method}.json"))
+    }
+
+    /// Chain local (giả) để ghi fixture: các pool WETH / USDC của config chạy `MockUniV3Pool` (tỉ giá cố định),
+    /// ERC20 / quoter / balance do `prepare_on_db` nạp như khi ghi với RPC thật
+    async fn fixture_chain(config: &ChainConfig, actors: &ChainActors) -> MockRpc {
+        let weth = config.token(&actors.native_token_key).unwrap().address;
+        let usdc = config.token(&actors.stable_token_key).unwrap().address;
+        let (token0, token1) = if weth < usdc { (weth, usdc) } else { (usdc, weth) };
+        // Pool fee thấp lệch giá 0.5% so với pool fee mặc định để arbitrage có cơ hội
+        let pools = [
+            (config.pool(weth, usdc, actors.low_fee).unwrap().address, 10_050u64),
+            (config.pool(weth, usdc, actors.default_fee).unwrap().address, 10_000u64),
+        ];
+        let pool_code = include_str!("../fixtures/mock_univ3_pool.hex").trim().to_string();
+        // Token "thật" của chain giả: balance ở slot 0, khác slot khai báo trong eth.toml nên
+        // `BalanceSlots` phải dò lại
+        let token_code = include_str!("../bytecode/generic_erc20.hex").trim().to_string();
+
+        MockRpc::spawn(move |method, params: &Value| {
+            let address = params[0].as_str().and_then(|address| address.parse::<Address>().ok());
+            let pool = pools.iter().find(|(pool, _)| Some(*pool) == address);
+            match (method, pool) {
+                ("eth_getBlockByNumber", _) => Ok(mock_block(FIXTURE_BLOCK, Some(12_000_000_000))),
+                ("eth_getCode", Some(_)) => Ok(json!(pool_code)),
+                ("eth_getCode", None) if address == Some(weth) || address == Some(usdc) => Ok(json!(token_code)),
+                ("eth_getStorageAt", Some((_, rate_bps))) => {
+                    let value = match params[1].as_str().and_then(|slot| U256::from_str_radix(slot.trim_start_matches("0x"), 16).ok()) {
+                        Some(slot) if slot == U256::ZERO => U256::from_be_slice(token0.as_slice()),
+                        Some(slot) if slot == U256::from(1) => U256::from_be_slice(token1.as_slice()),
+                        Some(slot) if slot == U256::from(2) => U256::from(*rate_bps),
+                        _ => U256::ZERO,
+                    };
+                    Ok(json!(format!("0x{:064x}", value)))
+                }
+                ("eth_getStorageAt", None) => Ok(json!(format!("0x{:064x}", 0))),
+                _ => default_account_response(method).ok_or_else(|| format!("unexpected method {method}")),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_replay_committed_snapshots() {
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        // Replay không được chạm RPC
+        config.rpc_urls = vec!["http://127.0.0.1:1".to_string()];
+        let actors = ChainActors::from(&config);
+
+        for method in FIXTURE_METHODS {
+            let path = fixture_path(method);
+            let snapshot = Snapshot::load(&path).unwrap();
+            assert_eq!((snapshot.chain_id, snapshot.block.number), (config.chain_id, FIXTURE_BLOCK));
+            run_chain_snapshot(&config, &actors, method, &path).unwrap();
+        }
+    }
+
+    /// Ghi lại fixture trong `src/fixtures/snapshots` qua đúng đường record (`record_snapshot_on`)
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "regenerates src/fixtures/snapshots"]
+    async fn record_fixture_snapshots() {
+        let config = load_chain_config("src/config/eth.toml").unwrap();
+        let actors = ChainActors::from(&config);
+        let chain = fixture_chain(&config, &actors).await;
+        let cache = temp_path("fixture-cache");
+
+        for method in FIXTURE_METHODS {
+            let multi_provider = MultiProvider::new(std::slice::from_ref(&chain.url))
+                .with_chain_id(config.chain_id)
+                .with_cache_dir(cache.to_str().unwrap());
+            record_snapshot_on(&config, &actors, method, &fixture_path(method), &multi_provider, BlockId::latest())
+                .await
+                .unwrap();
+        }
+        std::fs::remove_dir_all(&cache).ok();
+    }
+}
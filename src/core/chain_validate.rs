@@ -1,24 +1,25 @@
-use std::sync::Arc;
 use std::ops::{Div, Mul};
 use std::str::FromStr;
-use alloy::eips::BlockId;
-use anyhow::Result;
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use anyhow::{anyhow, Result};
 use alloy::{
     primitives::{Bytes, U256},
-    providers::{Provider, ProviderBuilder},
+    providers::Provider,
 };
-use revm::primitives::Bytecode;
+use revm::{db::CacheDB, primitives::{BlockEnv, Bytecode}};
 
-use crate::types::{ChainConfig, ONE_ETHER};
+use crate::types::{ChainConfig, QuoterKind, ONE_ETHER};
 use crate::source::{builder::volumes, abi::*, builder::build_tx};
 use crate::core::db::*;
 use crate::core::logger::{measure_start, measure_end};
 use crate::chain::actors::ChainActors;
-use crate::core::provider::MultiProvider;
+use crate::core::db_persistent::PersistentDB;
+use crate::core::db_rpc::RpcDB;
+use crate::core::provider::{MultiProvider, HEDGE_FANOUT, READ_QUORUM};
 
 /// So sánh kết quả quote từ `eth_call` và `revm`
 /// Dùng custom UniV3Quoter để đảm bảo REVM phản hồi `amountOut` đúng
-pub async fn run_chain_validate(config: &ChainConfig, actors: &ChainActors) -> Result<()> {
+pub async fn run_chain_validate(config: &ChainConfig, actors: &ChainActors, block: BlockId) -> Result<()> {
     // 1️⃣ Setup RPC và provider
     // let provider = ProviderBuilder::new()
     //     .on_http(config.rpc_url.parse()?);
@@ -26,25 +27,31 @@ pub async fn run_chain_validate(config: &ChainConfig, actors: &ChainActors) -> R
 
     // // Tạo REVM cache database từ provider
     // let mut cache_db = init_cache_db(provider.clone());
-    let multi_provider = MultiProvider::new(&config.rpc_urls);
+    let multi_provider = MultiProvider::for_chain(config);
     println!("MultiProvider with {} providers", multi_provider.len());
-
-    // let base_fee = provider.get_gas_price().await?;
-    let (provider, url) = multi_provider.next();
-    let base_fee = provider.get_gas_price().await?;
-
-    let mut cache_db = init_cache_db(&multi_provider);
-
-    let base_fee = provider.get_gas_price().await?;
+    let block = match block {
+        BlockId::Number(BlockNumberOrTag::Latest) => BlockId::number(multi_provider.lagged_head(READ_QUORUM).await?),
+        block => block,
+    };
+    let pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Pinned block {}", pinned);
+
+    // Storage mà REVM đọc phải được `READ_QUORUM` endpoint đồng ý, cùng mức tin cậy với eth_call để so sánh
+    let rpc_db = RpcDB::new(&multi_provider, multi_provider.block_id())
+        .ok_or_else(|| anyhow!("RpcDB requires a multi-thread tokio runtime"))?
+        .with_storage_quorum(READ_QUORUM.min(multi_provider.len()));
+    let mut cache_db = CacheDB::new(PersistentDB::for_provider(rpc_db, &multi_provider));
+
+    let base_fee = multi_provider.hedged(HEDGE_FANOUT, |provider| async move { provider.get_gas_price().await }).await?;
     let base_fee = base_fee.mul(110).div(100); // +10%
 
     // 2️⃣ Load address từ config
-    let from = config.addr("ME")?;
-    let token_in = config.addr(actors.native_token_key)?;
-    let token_out = config.addr(actors.stable_token_key)?;
-    let pool = config.addr(actors.pool_3000_key.expect("pool_3000_key required"))?;
-    let quoter = config.addr(actors.quoter_key)?;
-    let custom_quoter = config.addr(actors.custom_quoter_key.expect("custom_quoter_key required"))?;
+    let from = config.me;
+    let token_in = config.token(&actors.native_token_key)?.address;
+    let token_out = config.token(&actors.stable_token_key)?.address;
+    let pool = config.pool(token_in, token_out, actors.default_fee)?.address;
+    let quoter = config.quoter(QuoterKind::UniswapV3)?;
+    let custom_quoter = config.quoter(QuoterKind::Custom)?;
 
     // 3️⃣ Chuẩn bị volume và mock data
     let volumes = volumes(U256::ZERO, ONE_ETHER.div(U256::from(10)), 10);
@@ -70,12 +77,17 @@ pub async fn run_chain_validate(config: &ChainConfig, actors: &ChainActors) -> R
         // Call onchain
         let call_calldata = quote_calldata(token_in, token_out, volume, actors.default_fee);
         let tx = build_tx(quoter, from, call_calldata, base_fee);
-        let call_response = provider.call(&tx).block(BlockId::latest()).await?;
+        let call_response = multi_provider
+            .hedged(HEDGE_FANOUT, |provider| {
+                let tx = tx.clone();
+                async move { provider.call(&tx).block(pinned.id()).await }
+            })
+            .await?;
         let call_amount_out = decode_quote_response(call_response)?;
 
         // Call REVM
         let revm_calldata = get_amount_out_calldata(pool, token_in, token_out, volume);
-        let revm_response = revm_revert(from, custom_quoter, revm_calldata, &mut cache_db)?;
+        let revm_response = revm_revert(from, custom_quoter, revm_calldata, &BlockEnv::default(), &mut cache_db)?;
         let revm_amount_out = decode_get_amount_out_response(revm_response)?;
 
         println!(
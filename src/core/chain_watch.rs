@@ -0,0 +1,415 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Result};
+use alloy::{eips::BlockId, providers::Provider};
+use revm::{db::{CacheDB, DatabaseRef}, primitives::BlockEnv};
+
+use crate::types::{ChainConfig, PoolConfig};
+use crate::chain::actors::ChainActors;
+use crate::chain::registry::Chain;
+use crate::core::chain_snapshot::{ensure_snapshot_method, prepare_on_db, simulate_on_db};
+use crate::core::consistency::ConsistencyChecker;
+use crate::core::db::{init_cache_db, AlloyCacheDB};
+use crate::core::opportunity::fetch_base_fee;
+use crate::core::provider::{MultiProvider, PinnedBlock};
+use crate::core::state_diff::{apply_state_diff, fetch_block_diff, refresh_cached_state, InvalidationReport, ProtectedState, StateDiff};
+use crate::univ3::{PoolState, PoolStateManager};
+
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// Chu kỳ poll `eth_blockNumber` (MultiProvider chỉ có HTTP nên không dùng `eth_subscribe`)
+    pub poll_interval: Duration,
+    /// Dừng sau số block này, None = chạy mãi
+    pub max_blocks: Option<usize>,
+    /// Nhảy xa hơn số block này thì refresh cả cache thay vì trace từng block
+    pub max_trace_gap: u64,
+    /// Đối chiếu pool UniV3 với on-chain mỗi số block này
+    pub consistency_interval: u64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig { poll_interval: Duration::from_secs(1), max_blocks: None, max_trace_gap: 8, consistency_interval: 10 }
+    }
+}
+
+impl WatchConfig {
+    /// Poll 3 lần mỗi block theo `block_time` của chain (tối thiểu 100ms)
+    pub fn for_chain(config: &ChainConfig) -> Self {
+        let poll_interval = (config.block_time() / 3).max(Duration::from_millis(100));
+        WatchConfig { poll_interval, ..Default::default() }
+    }
+}
+
+/// Kết quả xử lý một head mới
+#[derive(Debug, Clone)]
+pub struct BlockReport {
+    pub block: PinnedBlock,
+    pub invalidation: InvalidationReport,
+    /// Số pool lệch on-chain đã recover ở block này
+    pub recovered_pools: usize,
+    pub simulate: Duration,
+    /// Từ lúc thấy head tới lúc scan ra quyết định
+    pub latency: Duration,
+}
+
+impl fmt::Display for BlockReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "⏱️ Block #{}: head → decision {:.2?} (invalidate {}, simulate {:.2?})",
+            self.block.number, self.latency, self.invalidation, self.simulate
+        )?;
+        if self.recovered_pools > 0 {
+            write!(f, " | recovered {} drifted pools", self.recovered_pools)?;
+        }
+        Ok(())
+    }
+}
+
+/// Giữ `PoolState` của các pool UniV3 bám theo chain bằng log `Swap` / `Mint` / `Burn`,
+/// mỗi `consistency_interval` block đối chiếu với on-chain và recover pool lệch
+pub struct PoolFollower {
+    pub manager: PoolStateManager,
+    pub checker: ConsistencyChecker,
+}
+
+impl PoolFollower {
+    /// Load slot0 + liquidity của `pools` từ `cache_db`
+    pub fn load<ExtDB>(pools: &[PoolConfig], cache_db: &mut CacheDB<ExtDB>, consistency_interval: u64) -> Result<Self>
+    where
+        ExtDB: DatabaseRef,
+        ExtDB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut manager = PoolStateManager::new();
+        for pool in pools {
+            manager.track(PoolState::load(cache_db, pool.address, pool.fee)?);
+        }
+        Ok(PoolFollower { manager, checker: ConsistencyChecker::new(consistency_interval) })
+    }
+
+    /// Áp log của các block `from + 1 ..= to` rồi chạy consistency check nếu tới hạn.
+    /// Trả về số pool đã recover.
+    pub async fn advance(
+        &mut self,
+        cache_db: &mut AlloyCacheDB,
+        multi_provider: &MultiProvider,
+        from: u64,
+        to: u64,
+    ) -> Result<usize> {
+        if self.manager.is_empty() {
+            return Ok(0);
+        }
+        let filter = self.manager.log_filter(from + 1, to);
+        let logs = multi_provider
+            .request(|provider| {
+                let filter = filter.clone();
+                async move { provider.get_logs(&filter).await }
+            })
+            .await?;
+        self.manager.apply_logs(cache_db, &logs)?;
+        self.checker.run(&mut self.manager, cache_db, multi_provider, to).await
+    }
+}
+
+/// Giữ cache fork bám theo chain: áp state diff của từng block mới, state mock không bị đụng
+pub struct StateFollower {
+    pub protected: ProtectedState,
+    pub max_trace_gap: u64,
+    /// Tắt sau lần trace lỗi đầu tiên (node không có debug namespace)
+    trace_supported: bool,
+}
+
+impl StateFollower {
+    pub fn new(protected: ProtectedState, max_trace_gap: u64) -> Self {
+        StateFollower { protected, max_trace_gap, trace_supported: true }
+    }
+
+    pub fn trace_supported(&self) -> bool {
+        self.trace_supported
+    }
+
+    /// Ghim block `to`, trỏ `AlloyDB` sang block đó rồi invalidate state đã cache của các block
+    /// `from + 1 ..= to`. Refresh cả cache nếu không trace được, nhảy quá xa hoặc gặp reorg.
+    pub async fn advance(
+        &mut self,
+        cache_db: &mut AlloyCacheDB,
+        multi_provider: &MultiProvider,
+        from: &PinnedBlock,
+        to: u64,
+    ) -> Result<(PinnedBlock, InvalidationReport)> {
+        let start = Instant::now();
+        let pinned = multi_provider.pin_block(BlockId::number(to)).await?;
+        cache_db.db.set_pinned(pinned);
+
+        let reorg = to == from.number + 1 && pinned.parent_hash != from.hash;
+        if reorg {
+            println!("⚠️ Reorg at #{}: parent {} is not {}", to, pinned.parent_hash, from.hash);
+        }
+        if self.trace_supported && !reorg && to - from.number <= self.max_trace_gap {
+            match self.trace_range(multi_provider, from.number + 1, to).await {
+                Ok(diff) => {
+                    let mut report = apply_state_diff(cache_db, &diff, &self.protected);
+                    report.elapsed = start.elapsed();
+                    return Ok((pinned, report));
+                }
+                Err(e) => {
+                    println!("⚠️ State diff unavailable, refreshing cached state from now on: {}", e);
+                    self.trace_supported = false;
+                }
+            }
+        }
+
+        let mut report = refresh_cached_state(cache_db, multi_provider, pinned.id(), &self.protected).await?;
+        report.elapsed = start.elapsed();
+        Ok((pinned, report))
+    }
+
+    async fn trace_range(&self, multi_provider: &MultiProvider, first: u64, last: u64) -> Result<StateDiff> {
+        let mut diff = StateDiff::default();
+        for number in first..=last {
+            diff.merge(fetch_block_diff(multi_provider, number).await?);
+        }
+        Ok(diff)
+    }
+}
+
+/// Chạy `scan` (một method REVM-only) liên tục: mỗi head mới đưa cache lên block đó
+/// bằng state diff rồi scan lại, in latency từ lúc thấy head tới lúc có quyết định
+pub async fn run_chain_watch(
+    config: &ChainConfig,
+    actors: &ChainActors,
+    scan: &str,
+    block: BlockId,
+    watch: &WatchConfig,
+) -> Result<Vec<BlockReport>> {
+    ensure_snapshot_method(scan)?;
+
+    // 1️⃣ Setup provider + cache db, chuẩn bị state tại block bắt đầu
+    let multi_provider = MultiProvider::for_chain(config);
+    println!("MultiProvider with {} providers", multi_provider.len());
+    let mut pinned = multi_provider.pin_block(block).await?;
+    println!("📌 Watching '{}' from block {}", scan, pinned);
+    let mut cache_db = init_cache_db(&multi_provider);
+    prepare_on_db(config, actors, scan, &mut cache_db, &multi_provider).await?;
+    let mut follower = StateFollower::new(ProtectedState::capture(&cache_db), watch.max_trace_gap);
+    let mut pools = PoolFollower::load(&config.univ3_pools(), &mut cache_db, watch.consistency_interval)?;
+
+    // 2️⃣ Scan đầu tiên để warm-up cache
+    let base_fee = fetch_base_fee(&multi_provider).await?;
+    simulate_on_db(config, actors, scan, &mut cache_db, &BlockEnv::default(), base_fee)?;
+
+    // 3️⃣ Poll head mới, invalidate rồi scan lại
+    let mut reports: Vec<BlockReport> = Vec::new();
+    let mut interval = tokio::time::interval(watch.poll_interval);
+    while watch.max_blocks.map_or(true, |max| reports.len() < max) {
+        interval.tick().await;
+        let head = match multi_provider.request(|provider| async move { provider.get_block_number().await }).await {
+            Ok(head) => head,
+            Err(e) => {
+                println!("⚠️ Head poll failed: {}", e);
+                continue;
+            }
+        };
+        if head <= pinned.number {
+            continue;
+        }
+        let arrived = Instant::now();
+
+        let (block, invalidation) = follower.advance(&mut cache_db, &multi_provider, &pinned, head).await?;
+        let recovered_pools = pools.advance(&mut cache_db, &multi_provider, pinned.number, block.number).await?;
+        let base_fee = fetch_base_fee(&multi_provider).await?;
+        let start = Instant::now();
+        simulate_on_db(config, actors, scan, &mut cache_db, &BlockEnv::default(), base_fee)?;
+        let report = BlockReport { block, invalidation, recovered_pools, simulate: start.elapsed(), latency: arrived.elapsed() };
+        println!("{}", report);
+
+        pinned = block;
+        reports.push(report);
+    }
+
+    // 4️⃣ Tổng kết
+    if let Some(max) = reports.iter().map(|report| report.latency).max() {
+        let total: Duration = reports.iter().map(|report| report.latency).sum();
+        println!(
+            "📊 {} blocks: head → decision avg {:.2?}, max {:.2?}, state diff via {}",
+            reports.len(),
+            total / reports.len() as u32,
+            max,
+            if follower.trace_supported() { "prestateTracer" } else { "refresh" }
+        );
+    } else if watch.max_blocks != Some(0) {
+        bail!("Watch stopped before any new block");
+    }
+    if pools.checker.metrics.checks_run > 0 {
+        print!("{}", pools.checker.metrics);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_rpc::{default_account_response, mock_block, MockRpc};
+    use crate::core::state_diff::DiffSource;
+    use alloy::primitives::{Address, U256};
+    use revm::db::Database;
+    use revm::primitives::AccountInfo;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    const POOL: Address = Address::repeat_byte(0xaa);
+    const TOKEN: Address = Address::repeat_byte(0xbb);
+
+    fn block_number(params: &Value) -> u64 {
+        u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+    }
+
+    /// Cache ghim block 100: token mock (được bảo vệ) + pool đã cache slot 4 lúc scan
+    async fn setup(mock: &MockRpc) -> (MultiProvider, AlloyCacheDB, StateFollower, PinnedBlock) {
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let pinned = multi_provider.pin_block(BlockId::number(100)).await.unwrap();
+        let mut cache_db = init_cache_db(&multi_provider);
+        cache_db.insert_account_info(TOKEN, AccountInfo::default());
+        cache_db.insert_account_storage(TOKEN, U256::from(1), U256::from(77)).unwrap();
+        let follower = StateFollower::new(ProtectedState::capture(&cache_db), 8);
+
+        cache_db.insert_account_info(POOL, AccountInfo::default());
+        cache_db.insert_account_storage(POOL, U256::from(4), U256::from(1)).unwrap();
+        (multi_provider, cache_db, follower, pinned)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_advance_applies_block_diff_and_forks_at_new_block() {
+        let storage_blocks = Arc::new(Mutex::new(Vec::new()));
+        let seen = storage_blocks.clone();
+        let mock = MockRpc::spawn(move |method, params: &Value| match method {
+            "eth_getBlockByNumber" => Ok(mock_block(block_number(params), Some(10))),
+            "debug_traceBlockByNumber" => Ok(json!([{ "txHash": "0x01", "result": {
+                "pre": {
+                    format!("{POOL:?}"): { "storage": { format!("0x{:064x}", 4): format!("0x{:064x}", 1) } },
+                    format!("{TOKEN:?}"): { "storage": { format!("0x{:064x}", 1): format!("0x{:064x}", 5) } },
+                },
+                "post": {
+                    format!("{POOL:?}"): { "storage": {
+                        format!("0x{:064x}", 4): format!("0x{:064x}", 9),
+                        format!("0x{:064x}", 5): format!("0x{:064x}", 3),
+                    } },
+                    format!("{TOKEN:?}"): { "storage": { format!("0x{:064x}", 1): format!("0x{:064x}", 6) } },
+                },
+            } }])),
+            "eth_getStorageAt" => {
+                seen.lock().unwrap().push(params[2].clone());
+                Ok(json!(format!("0x{:064x}", 3)))
+            }
+            _ => default_account_response(method).ok_or(format!("unexpected method {method}")),
+        })
+        .await;
+        let (multi_provider, mut cache_db, mut follower, pinned) = setup(&mock).await;
+
+        let (block, report) = follower.advance(&mut cache_db, &multi_provider, &pinned, 101).await.unwrap();
+        assert_eq!(block.number, 101);
+        assert_eq!(report.source, DiffSource::Trace);
+        assert_eq!((report.accounts_changed, report.slots_updated, report.protected_skipped), (2, 1, 1));
+        assert_eq!(cache_db.storage(POOL, U256::from(4)).unwrap(), U256::from(9));
+        assert_eq!(cache_db.storage(TOKEN, U256::from(1)).unwrap(), U256::from(77));
+
+        // Slot chưa cache load lazily ở block mới
+        assert_eq!(cache_db.storage(POOL, U256::from(5)).unwrap(), U256::from(3));
+        assert_eq!(*storage_blocks.lock().unwrap(), vec![json!("0x65")]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_advance_falls_back_to_refresh_without_debug_api() {
+        let mock = MockRpc::spawn(|method, params: &Value| match method {
+            "eth_getBlockByNumber" => Ok(mock_block(block_number(params), Some(10))),
+            "debug_traceBlockByNumber" => Err("the method debug_traceBlockByNumber does not exist".to_string()),
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", 2))),
+            _ => default_account_response(method).ok_or(format!("unexpected method {method}")),
+        })
+        .await;
+        let (multi_provider, mut cache_db, mut follower, pinned) = setup(&mock).await;
+
+        let (block, report) = follower.advance(&mut cache_db, &multi_provider, &pinned, 101).await.unwrap();
+        assert_eq!(report.source, DiffSource::Refresh);
+        assert_eq!((report.accounts_changed, report.slots_updated), (1, 1));
+        assert!(!follower.trace_supported());
+        assert_eq!(cache_db.storage(POOL, U256::from(4)).unwrap(), U256::from(2));
+        assert_eq!(cache_db.storage(TOKEN, U256::from(1)).unwrap(), U256::from(77));
+
+        // Không thử trace lại ở block sau
+        let calls = mock.calls();
+        let (_, report) = follower.advance(&mut cache_db, &multi_provider, &block, 102).await.unwrap();
+        assert_eq!((report.source, report.slots_updated), (DiffSource::Refresh, 0));
+        // getBlockByNumber + một batch (nonce, balance, code của pool + slot 4)
+        assert_eq!(mock.calls() - calls, 1 + 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pool_follower_recovers_drift_every_interval() {
+        use crate::types::Protocol;
+        use crate::univ3::pool::{LIQUIDITY_SLOT, SLOT0_SLOT};
+
+        let price = U256::from(1) << 96;
+        let slot0 = Arc::new(Mutex::new(price));
+        let (seen_slot0, log_calls) = (slot0.clone(), Arc::new(Mutex::new(0)));
+        let seen_logs = log_calls.clone();
+        let mock = MockRpc::spawn(move |method, params: &Value| match method {
+            "eth_getBlockByNumber" => Ok(mock_block(block_number(params), Some(10))),
+            "eth_getLogs" => {
+                *seen_logs.lock().unwrap() += 1;
+                Ok(json!([]))
+            }
+            "eth_getStorageAt" => {
+                let slot: U256 = serde_json::from_value(params[1].clone()).unwrap();
+                let value = match slot {
+                    SLOT0_SLOT => *seen_slot0.lock().unwrap(),
+                    LIQUIDITY_SLOT => U256::from(5),
+                    _ => U256::ZERO,
+                };
+                Ok(json!(value))
+            }
+            _ => default_account_response(method).ok_or(format!("unexpected method {method}")),
+        })
+        .await;
+        let (multi_provider, mut cache_db, _, _) = setup(&mock).await;
+        let pool = PoolConfig {
+            protocol: Protocol::UniV3, address: POOL, token0: TOKEN, token1: Address::repeat_byte(0xcc),
+            symbol0: "A".to_string(), symbol1: "B".to_string(), fee: 3000, tick_spacing: Some(60),
+        };
+        cache_db.accounts.remove(&POOL);
+        let mut pools = PoolFollower::load(&[pool], &mut cache_db, 2).unwrap();
+        assert_eq!(pools.advance(&mut cache_db, &multi_provider, 100, 101).await.unwrap(), 0);
+
+        // Pool đổi giá on-chain mà không có log: lệch tới lần check kế tiếp (block 103)
+        let moved = price + (price >> 4_usize);
+        *slot0.lock().unwrap() = moved | (U256::from(1212) << 160);
+        assert_eq!(pools.advance(&mut cache_db, &multi_provider, 101, 102).await.unwrap(), 0);
+        assert_eq!(pools.manager.get(&POOL).unwrap().sqrt_price_x96, price);
+        assert_eq!(pools.advance(&mut cache_db, &multi_provider, 102, 103).await.unwrap(), 1);
+        let state = pools.manager.get_for_simulation(&POOL).unwrap();
+        assert_eq!((state.sqrt_price_x96, state.tick), (moved, 1212));
+        assert_eq!(*log_calls.lock().unwrap(), 3);
+        assert_eq!(pools.checker.metrics.total_drifts(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires anvil + RPC_URL"]
+    async fn test_watch_follows_anvil_blocks() {
+        use alloy::node_bindings::Anvil;
+        use crate::chain::actors::ChainActors;
+        use crate::types::load_chain_config;
+
+        let anvil = Anvil::new()
+            .fork(std::env::var("RPC_URL").expect("Please set RPC_URL env"))
+            .block_time(1_u64)
+            .spawn();
+        let mut config = load_chain_config("src/config/eth.toml").unwrap();
+        config.rpc_urls = vec![anvil.endpoint()];
+        let watch = WatchConfig { poll_interval: Duration::from_millis(200), max_blocks: Some(3), ..Default::default() };
+
+        let reports = run_chain_watch(&config, &ChainActors::from(&config), "arbitrage", BlockId::latest(), &watch).await.unwrap();
+        assert_eq!(reports.len(), 3);
+        assert!(reports.windows(2).all(|pair| pair[1].block.number > pair[0].block.number));
+    }
+}
@@ -0,0 +1,281 @@
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use anyhow::Result;
+use futures::future::try_join_all;
+use revm::db::{CacheDB, DatabaseRef};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::core::provider::MultiProvider;
+use crate::univ3::pool::{decode_slot0, LIQUIDITY_SLOT, SLOT0_SLOT};
+use crate::univ3::PoolStateManager;
+
+/// slot0 + liquidity của một pool đọc trực tiếp từ chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnchainSlot0 {
+    pub address: Address,
+    pub slot0: U256,
+    pub liquidity: U256,
+}
+
+/// Thống kê lệch state của một pool
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolDrift {
+    pub checks: u64,
+    pub drifts: u64,
+    pub recoveries: u64,
+    pub last_drift_block: Option<u64>,
+    /// |tick local - tick on-chain| lớn nhất từng gặp
+    pub max_tick_drift: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyMetrics {
+    pub checks_run: u64,
+    pub pools: HashMap<Address, PoolDrift>,
+}
+
+impl ConsistencyMetrics {
+    pub fn total_drifts(&self) -> u64 {
+        self.pools.values().map(|drift| drift.drifts).sum()
+    }
+}
+
+impl fmt::Display for ConsistencyMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Consistency checks: {} | total drifts: {}", self.checks_run, self.total_drifts())?;
+        let mut pools: Vec<_> = self.pools.iter().collect();
+        pools.sort_by_key(|(address, _)| **address);
+        for (address, drift) in pools {
+            writeln!(
+                f,
+                "  {:?}: checks={} drifts={} recoveries={} max_tick_drift={} last_drift_block={:?}",
+                address, drift.checks, drift.drifts, drift.recoveries, drift.max_tick_drift, drift.last_drift_block
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Định kỳ (mỗi `interval` block) so slot0/liquidity local với on-chain,
+/// đánh dấu pool lệch là dirty và dựng lại cache storage của pool đó
+#[derive(Debug, Clone)]
+pub struct ConsistencyChecker {
+    pub interval: u64,
+    pub last_checked_block: Option<u64>,
+    pub metrics: ConsistencyMetrics,
+}
+
+async fn fetch_onchain(multi_provider: &MultiProvider, address: Address, block: u64) -> Result<OnchainSlot0> {
+    let block_id = BlockId::number(block);
+    let slot0 = multi_provider
+        .request(|provider| async move { provider.get_storage_at(address, SLOT0_SLOT).block_id(block_id).await })
+        .await?;
+    let liquidity = multi_provider
+        .request(|provider| async move { provider.get_storage_at(address, LIQUIDITY_SLOT).block_id(block_id).await })
+        .await?;
+    Ok(OnchainSlot0 { address, slot0, liquidity })
+}
+
+impl ConsistencyChecker {
+    pub fn new(interval: u64) -> Self {
+        ConsistencyChecker { interval: interval.max(1), last_checked_block: None, metrics: ConsistencyMetrics::default() }
+    }
+
+    pub fn should_check(&self, block: u64) -> bool {
+        match self.last_checked_block {
+            Some(last) => block >= last + self.interval,
+            None => true,
+        }
+    }
+
+    /// Đọc slot0/liquidity on-chain tại `block` cho mọi pool đang track (chia đều qua `MultiProvider`).
+    /// Pool lệch bị mark dirty. Trả về state on-chain của mọi pool đang dirty để `recover`.
+    pub async fn check(
+        &mut self,
+        manager: &mut PoolStateManager,
+        multi_provider: &MultiProvider,
+        block: u64,
+    ) -> Result<Vec<OnchainSlot0>> {
+        let addresses: Vec<Address> = manager.pools.keys().copied().collect();
+        let onchain = try_join_all(addresses.iter().map(|address| fetch_onchain(multi_provider, *address, block))).await?;
+
+        self.metrics.checks_run += 1;
+        self.last_checked_block = Some(block);
+
+        let mut dirty = Vec::new();
+        for state in onchain {
+            let Some(pool) = manager.get(&state.address) else { continue };
+            let (sqrt_price_x96, tick) = decode_slot0(state.slot0);
+            let diverged = pool.sqrt_price_x96 != sqrt_price_x96
+                || pool.tick != tick
+                || U256::from(pool.liquidity) != state.liquidity;
+
+            let drift = self.metrics.pools.entry(state.address).or_default();
+            drift.checks += 1;
+            if diverged {
+                drift.drifts += 1;
+                drift.last_drift_block = Some(block);
+                drift.max_tick_drift = drift.max_tick_drift.max((pool.tick - tick).unsigned_abs());
+                println!(
+                    "⚠️ Pool {:?} drifted at block {}: local tick={} L={} | onchain tick={} L={}",
+                    state.address, block, pool.tick, pool.liquidity, tick, state.liquidity
+                );
+                manager.mark_dirty(state.address);
+            }
+            if manager.is_dirty(&state.address) {
+                dirty.push(state);
+            }
+        }
+        Ok(dirty)
+    }
+
+    /// Dựng lại pool dirty từ state on-chain: xoá storage đã cache của pool trong `cache_db`,
+    /// ghi slot0/liquidity mới, reset `PoolState` (tick load lại lazily) rồi bỏ dirty.
+    pub fn recover<ExtDB>(
+        &mut self,
+        manager: &mut PoolStateManager,
+        cache_db: &mut CacheDB<ExtDB>,
+        onchain: &[OnchainSlot0],
+    ) -> Result<()>
+    where
+        ExtDB: DatabaseRef,
+        ExtDB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        for state in onchain {
+            let Some(pool) = manager.get_mut(&state.address) else { continue };
+            if let Some(account) = cache_db.accounts.get_mut(&state.address) {
+                account.storage.clear();
+            }
+            cache_db.insert_account_storage(state.address, SLOT0_SLOT, state.slot0)?;
+            cache_db.insert_account_storage(state.address, LIQUIDITY_SLOT, state.liquidity)?;
+            pool.reset(state.slot0, state.liquidity);
+
+            manager.mark_clean(&state.address);
+            self.metrics.pools.entry(state.address).or_default().recoveries += 1;
+        }
+        Ok(())
+    }
+
+    /// Gọi mỗi block: chỉ check khi tới hạn, recover ngay các pool dirty.
+    /// Trả về số pool đã recover.
+    pub async fn run<ExtDB>(
+        &mut self,
+        manager: &mut PoolStateManager,
+        cache_db: &mut CacheDB<ExtDB>,
+        multi_provider: &MultiProvider,
+        block: u64,
+    ) -> Result<usize>
+    where
+        ExtDB: DatabaseRef,
+        ExtDB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        if !self.should_check(block) {
+            return Ok(0);
+        }
+        let dirty = self.check(manager, multi_provider, block).await?;
+        self.recover(manager, cache_db, &dirty)?;
+        Ok(dirty.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::init_cache_db;
+    use crate::core::mock_rpc::{default_account_response, MockRpc};
+    use crate::univ3::PoolState;
+    use revm::db::Database;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    type Storage = Arc<Mutex<HashMap<(Address, U256), U256>>>;
+
+    fn slot0(sqrt_price_x96: U256, tick: i32) -> U256 {
+        sqrt_price_x96 | (U256::from((tick as u32) & 0xffffff) << 160)
+    }
+
+    async fn spawn_chain(storage: Storage) -> MockRpc {
+        MockRpc::spawn(move |method, params| {
+            if let Some(result) = default_account_response(method) {
+                return Ok(result);
+            }
+            match method {
+                "eth_getStorageAt" => {
+                    let address: Address = serde_json::from_value(params[0].clone()).map_err(|e| e.to_string())?;
+                    let slot: U256 = serde_json::from_value(params[1].clone()).map_err(|e| e.to_string())?;
+                    let value = storage.lock().unwrap().get(&(address, slot)).copied().unwrap_or_default();
+                    Ok(json!(value))
+                }
+                _ => Err(format!("unsupported method {method}")),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_drift_marks_dirty_and_recovers() {
+        let (pool_a, pool_b) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        let price = U256::from(1) << 96;
+        let storage: Storage = Arc::new(Mutex::new(HashMap::new()));
+        for pool in [pool_a, pool_b] {
+            let mut storage = storage.lock().unwrap();
+            storage.insert((pool, SLOT0_SLOT), slot0(price, 0));
+            storage.insert((pool, LIQUIDITY_SLOT), U256::from(1_000_000u64));
+        }
+        let mock = spawn_chain(storage.clone()).await;
+        let multi_provider = MultiProvider::new(&[mock.url.clone(), mock.url.clone()]);
+        let mut cache_db = init_cache_db(&multi_provider);
+
+        let mut manager = PoolStateManager::new();
+        for pool in [pool_a, pool_b] {
+            manager.track(PoolState::load(&mut cache_db, pool, 3000).unwrap());
+        }
+
+        let mut checker = ConsistencyChecker::new(10);
+        assert_eq!(checker.run(&mut manager, &mut cache_db, &multi_provider, 100).await.unwrap(), 0);
+        assert!(!checker.should_check(105));
+
+        // Pool A bị swap on-chain mà local không thấy log
+        let moved_price = price + (price >> 4_usize);
+        storage.lock().unwrap().insert((pool_a, SLOT0_SLOT), slot0(moved_price, 1212));
+        storage.lock().unwrap().insert((pool_a, LIQUIDITY_SLOT), U256::from(2_000_000u64));
+
+        let dirty = checker.check(&mut manager, &multi_provider, 110).await.unwrap();
+        assert_eq!(dirty.len(), 1);
+        assert!(manager.get_for_simulation(&pool_a).is_none());
+        assert!(manager.get_for_simulation(&pool_b).is_some());
+        assert_eq!(manager.clean_pools().count(), 1);
+        // cache vẫn còn slot0 cũ cho tới khi recover
+        assert_eq!(cache_db.storage(pool_a, SLOT0_SLOT).unwrap(), slot0(price, 0));
+
+        checker.recover(&mut manager, &mut cache_db, &dirty).unwrap();
+        assert!(!manager.is_dirty(&pool_a));
+        assert_eq!(cache_db.storage(pool_a, SLOT0_SLOT).unwrap(), slot0(moved_price, 1212));
+        let pool = manager.get_for_simulation(&pool_a).unwrap();
+        assert_eq!((pool.sqrt_price_x96, pool.tick, pool.liquidity), (moved_price, 1212, 2_000_000));
+
+        let drift_a = &checker.metrics.pools[&pool_a];
+        assert_eq!((drift_a.checks, drift_a.drifts, drift_a.recoveries), (2, 1, 1));
+        assert_eq!(drift_a.max_tick_drift, 1212);
+        assert_eq!(drift_a.last_drift_block, Some(110));
+        assert_eq!(checker.metrics.pools[&pool_b], PoolDrift { checks: 2, ..Default::default() });
+        assert_eq!(checker.metrics.total_drifts(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rpc_error_is_reported() {
+        let mock = MockRpc::spawn(|_, _: &Value| Err("boom".to_string())).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let mut manager = PoolStateManager::new();
+        let mut db = CacheDB::new(revm::db::EmptyDB::default());
+        manager.track(PoolState::load(&mut db, Address::repeat_byte(0xaa), 500).unwrap());
+
+        let mut checker = ConsistencyChecker::new(5);
+        assert!(checker.check(&mut manager, &multi_provider, 1).await.is_err());
+        assert!(mock.calls() > 0);
+    }
+}
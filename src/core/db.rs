@@ -1,4 +1,5 @@
 use alloy::{
+    eips::BlockId,
     network::Ethereum,
     primitives::{keccak256, Address, Bytes, U256},
     providers::{Provider, RootProvider},
@@ -8,60 +9,81 @@ use alloy::{
 use anyhow::{anyhow, Result};
 use revm::{
     db::{AlloyDB, CacheDB},
-    primitives::{AccountInfo, Bytecode, ExecutionResult, Output, TransactTo, B256},
-    Evm, EvmContext,
+    primitives::{AccountInfo, BlockEnv, Bytecode, Env, ExecutionResult, Output, TransactTo, B256},
+    Evm,
 };
 use std::sync::Arc;
 use crate::core::provider::MultiProvider;
-/// Wrapper để log các access đến storage
-/// 
-/// 
-/// 
+use crate::core::access_set::AccessSet;
+use crate::core::db_persistent::PersistentDB;
+use crate::core::db_rpc::RpcDB;
 
-use revm::db::{Database, DatabaseCommit};
+use revm::db::{Database, DatabaseRef};
 
 /// Wrapper quanh một Database để log các truy cập storage
+/// và ghi lại access set (account, code hash, slot + giá trị, block hash) của call
 pub struct LoggingDB<DB> {
     pub inner: DB,
+    pub access: AccessSet,
+    /// In từng storage access ra stdout
+    pub verbose: bool,
+}
+
+impl<DB> LoggingDB<DB> {
+    pub fn new(inner: DB) -> Self {
+        LoggingDB { inner, access: AccessSet::default(), verbose: true }
+    }
+
+    /// Chỉ ghi access set, không in
+    pub fn quiet(inner: DB) -> Self {
+        LoggingDB { inner, access: AccessSet::default(), verbose: false }
+    }
 }
 
 impl<DB: Database> Database for LoggingDB<DB> {
     type Error = DB::Error;
 
     fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
-        // Các phương thức khác chỉ cần gọi thẳng vào inner db
-        self.inner.basic(address)
+        let info = self.inner.basic(address)?;
+        self.access.record_account(address, info.as_ref().map(|info| info.code_hash));
+        Ok(info)
     }
 
     fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.access.record_code(code_hash);
         self.inner.code_by_hash(code_hash)
     }
 
     // Đây là phương thức chúng ta quan tâm
     fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
-        println!(
-            "📦 DB Access:   Contract: {:?}, Slot: {:#x}",
-            address, index
-        );
+        if self.verbose {
+            println!(
+                "📦 DB Access:   Contract: {:?}, Slot: {:#x}",
+                address, index
+            );
+        }
         // Sau khi log, gọi phương thức của inner db
-        self.inner.storage(address, index)
+        let value = self.inner.storage(address, index)?;
+        self.access.record_storage(address, index, value);
+        Ok(value)
     }
 
     fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
-        self.inner.block_hash(number)
+        let hash = self.inner.block_hash(number)?;
+        self.access.record_block_hash(number, hash);
+        Ok(hash)
     }
 }
 
 
 
-pub type AlloyCacheDB =
-    CacheDB<AlloyDB<Http<Client>, Ethereum, Arc<RootProvider<Http<Client>>>>>;
+/// Cache của mọi runner: `CacheDB` → cache đĩa → `RpcDB` (read qua `MultiProvider::request`)
+pub type AlloyCacheDB = CacheDB<PersistentDB<RpcDB>>;
 
 
 use revm::db::{ EmptyDBTyped};
 use std::convert::Infallible;
 
-use crate::core::db_empty::InMemoryDB;
 
 /// Convert CacheDB<AlloyDB> → CacheDB<EmptyDBTyped<Infallible>>
 pub fn convert_cache_to_empty_db<DB>(src: &CacheDB<DB>) -> CacheDB<EmptyDBTyped<Infallible>> {
@@ -83,8 +105,12 @@ pub fn convert_cache_to_empty_db<DB>(src: &CacheDB<DB>) -> CacheDB<EmptyDBTyped<
 
 
 
-pub fn init_cache_db_single(provider: Arc<RootProvider<Http<Client>>>) -> AlloyCacheDB {
-    CacheDB::new(AlloyDB::new(provider, Default::default()).unwrap())
+/// Cache fork qua đúng một endpoint (không failover)
+pub type SingleAlloyCacheDB =
+    CacheDB<PersistentDB<AlloyDB<Http<Client>, Ethereum, Arc<RootProvider<Http<Client>>>>>>;
+
+pub fn init_cache_db_single(provider: Arc<RootProvider<Http<Client>>>, block: BlockId) -> SingleAlloyCacheDB {
+    CacheDB::new(PersistentDB::disabled(AlloyDB::new(provider, block).unwrap()))
 }
 
 // Hàm init_cache_db của bạn
@@ -92,18 +118,18 @@ pub fn init_cache_db_single(provider: Arc<RootProvider<Http<Client>>>) -> AlloyC
 // mà ConcreteHttpProvider là một kiểu Sized và implements Provider
 // Nên AlloyDB::new có thể chấp nhận nó tùy thuộc vào signature của nó.
 pub fn init_cache_db(multi_provider: &MultiProvider) -> AlloyCacheDB {
-    let (provider, url) = multi_provider.next();
-    // Vẫn cần kiểm tra lại signature của AlloyDB::new
-    // Nếu nó cần T: Provider + Sized, thì Arc<ConcreteHttpProvider> là phù hợp.
-    // Nếu nó cần Arc<T: Provider>, thì Arc<ConcreteHttpProvider> cũng phù hợp.
-    CacheDB::new(AlloyDB::new(provider, Default::default()).unwrap())
+    // Fork tại block đã ghim (`MultiProvider::pin_block`), `latest` nếu chưa ghim.
+    // Mọi read đi qua `MultiProvider::request` nên endpoint lỗi được bỏ qua và tính vào health.
+    // Đã ghim + biết chain thì state đọc qua RPC được lưu lại cho lần chạy sau.
+    let rpc_db = RpcDB::new(multi_provider, multi_provider.block_id()).expect("RpcDB requires a multi-thread tokio runtime");
+    CacheDB::new(PersistentDB::for_provider(rpc_db, multi_provider))
 }
 
 // ... các import và định nghĩa struct/impl khác cho CacheDB, AlloyDB ...
 
-// pub async fn init_account(
+// pub async fn init_account<ExtDB: DatabaseRef>(
 //     address: Address,
-//     cache_db: &mut AlloyCacheDB,
+//     cache_db: &mut CacheDB<ExtDB>,
 //     provider: Arc<RootProvider<Http<Client>>>,
 // ) -> Result<()> {
 //     let cache_key = format!("bytecode-{:?}", address);
@@ -138,24 +164,27 @@ pub async fn init_account(
 ) -> Result<()> {
     use crate::core::logger::measure_start;
 
-    let cache_key = format!("bytecode-{:?}", address);
+    // Cùng address trên chain khác là contract khác
+    let cache_key = format!("bytecode-{}-{:?}", multi_provider.chain_id().unwrap_or_default(), address);
 
     let start = measure_start(&format!("init_account {:?}", address));
 
-    let (provider, url) = multi_provider.next();  // lấy (provider, url)
+    println!("Init account {:?} via {} RPC endpoints", address, multi_provider.len());
 
-    println!("Init account {:?} using RPC {}", address, url);
-
-    let bytecode = match cacache::read(&cache_dir(), cache_key.clone()).await {
+    let bytecode = match cacache::read(multi_provider.cache_dir(), cache_key.clone()).await {
         Ok(bytecode) => {
             let bytecode = Bytes::from(bytecode);
             Bytecode::new_raw(bytecode)
         }
         Err(_) => {
-            let bytecode = provider.get_code_at(address).await?;
+            // Endpoint lỗi / bị rate limit thì thử endpoint khác
+            let block = multi_provider.block_id();
+            let bytecode = multi_provider
+                .request(|provider| async move { provider.get_code_at(address).block_id(block).await })
+                .await?;
             let bytecode_result = Bytecode::new_raw(bytecode.clone());
             let bytecode_vec = bytecode.to_vec();
-            cacache::write(&cache_dir(), cache_key, bytecode_vec).await?;
+            cacache::write(multi_provider.cache_dir(), cache_key, bytecode_vec).await?;
             bytecode_result
         }
     };
@@ -175,11 +204,15 @@ pub async fn init_account(
 }
 
 
-pub fn init_account_with_bytecode(
+pub fn init_account_with_bytecode<ExtDB>(
     address: Address,
     bytecode: Bytecode,
-    cache_db: &mut AlloyCacheDB,
-) -> Result<()> {
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
     let code_hash = bytecode.hash_slow();
     let acc_info = AccountInfo {
         balance: U256::ZERO,
@@ -191,27 +224,78 @@ pub fn init_account_with_bytecode(
     Ok(())
 }
 
-pub fn insert_mapping_storage_slot(
+pub fn insert_mapping_storage_slot<ExtDB>(
     contract: Address,
     slot: U256,
     slot_address: Address,
     value: U256,
-    cache_db: &mut AlloyCacheDB,
-) -> Result<()> {
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<()>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
     let hashed_slot = keccak256((slot_address, slot).abi_encode());
     cache_db.insert_account_storage(contract, hashed_slot.into(), value)?;
     Ok(())
 }
 
-pub fn revm_call(
+/// Block env cho một lần gọi `revm_*` (number, timestamp, coinbase, prevrandao, gas limit),
+/// vd. block env của snapshot khi replay, `BlockEnv::default()` khi không cần block cụ thể.
+/// Giống `eth_call` với gas price 0: base fee không áp vào call, phí gas tính riêng từ base fee của block.
+pub fn call_block_env(block: &BlockEnv) -> BlockEnv {
+    BlockEnv { basefee: U256::ZERO, ..block.clone() }
+}
+
+pub(crate) fn apply_call_block_env(env: &mut Env, block: &BlockEnv) {
+    env.block = call_block_env(block);
+    // Tx không được vượt gas limit của block
+    env.tx.gas_limit = env.tx.gas_limit.min(env.block.gas_limit.saturating_to());
+}
+
+pub fn revm_call<ExtDB>(
     from: Address,
     to: Address,
     calldata: Bytes,
-    cache_db: &mut AlloyCacheDB,
-) -> Result<Bytes> {
+    block: &BlockEnv,
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<Bytes>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let (value, _access) = transact_logged(from, to, calldata, block, LoggingDB::new(cache_db))?;
+    Ok(value)
+}
+
+/// Như `revm_call` (không in) nhưng trả thêm access set của call
+pub fn revm_call_with_access<ExtDB>(
+    from: Address,
+    to: Address,
+    calldata: Bytes,
+    block: &BlockEnv,
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<(Bytes, AccessSet)>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    transact_logged(from, to, calldata, block, LoggingDB::quiet(cache_db))
+}
+
+fn transact_logged<DB>(
+    from: Address,
+    to: Address,
+    calldata: Bytes,
+    block: &BlockEnv,
+    logging_db: LoggingDB<DB>,
+) -> Result<(Bytes, AccessSet)>
+where
+    DB: Database,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
     // Khởi tạo inspector của bạn
     // let mut inspector = StorageLoggerInspector::default();
-    let logging_db = LoggingDB { inner: cache_db };
     let mut evm = Evm::builder()
         .with_db(logging_db)
         
@@ -221,11 +305,13 @@ pub fn revm_call(
             tx.data = calldata;
             tx.value = U256::ZERO;
         })
+        .modify_env(|env| apply_call_block_env(env, block))
         .build();
 
     // evm.set_inspector(inspector);
 
     let result = evm.transact()?.result;
+    let access = std::mem::take(&mut evm.db_mut().access);
 
     let value = match result {
         ExecutionResult::Success {
@@ -237,17 +323,23 @@ pub fn revm_call(
         }
     };
 
-    Ok(value)
+    Ok((value, access))
 }
 
 
 
-pub fn revm_call_db(
+/// Như `revm_call` nhưng không log storage access, dùng cho loop song song trên snapshot
+pub fn revm_call_db<DB>(
     from: Address,
     to: Address,
     calldata: Bytes,
-    cache_db: &mut InMemoryDB,
-) -> Result<Bytes> {
+    block: &BlockEnv,
+    cache_db: &mut DB,
+) -> Result<Bytes>
+where
+    DB: Database,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
     let mut evm = Evm::builder()
         .with_db(cache_db)
         .modify_tx_env(|tx| {
@@ -256,6 +348,7 @@ pub fn revm_call_db(
             tx.data = calldata;
             tx.value = U256::ZERO;
         })
+        .modify_env(|env| apply_call_block_env(env, block))
         .build();
 
     let result = evm.transact()?.result;
@@ -275,12 +368,33 @@ pub fn revm_call_db(
 
 
 
-pub fn revm_revert(
+pub fn revm_revert<ExtDB>(
     from: Address,
     to: Address,
     calldata: Bytes,
-    cache_db: &mut AlloyCacheDB,
-) -> Result<Bytes> {
+    block: &BlockEnv,
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<Bytes>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let (output, _gas_used) = revm_revert_with_gas(from, to, calldata, block, cache_db)?;
+    Ok(output)
+}
+
+/// Giống `revm_revert` nhưng trả thêm `gas_used` của lần execute (gồm cả intrinsic gas)
+pub fn revm_revert_with_gas<ExtDB>(
+    from: Address,
+    to: Address,
+    calldata: Bytes,
+    block: &BlockEnv,
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<(Bytes, u64)>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
     let mut evm = Evm::builder()
         .with_db(cache_db)
         .modify_tx_env(|tx| {
@@ -289,16 +403,49 @@ pub fn revm_revert(
             tx.data = calldata;
             tx.value = U256::ZERO;
         })
+        .modify_env(|env| apply_call_block_env(env, block))
+        .build();
+
+    let result = evm.transact()?.result;
+
+    match result {
+        ExecutionResult::Revert { output, gas_used } => Ok((output, gas_used)),
+        _ => Err(anyhow!("Expected revert result")),
+    }
+}
+
+/// Như `revm_revert_with_gas` nhưng chạy qua `LoggingDB` (không in) và trả thêm access set
+pub fn revm_revert_with_access<ExtDB>(
+    from: Address,
+    to: Address,
+    calldata: Bytes,
+    block: &BlockEnv,
+    cache_db: &mut CacheDB<ExtDB>,
+) -> Result<(Bytes, u64, AccessSet)>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut evm = Evm::builder()
+        .with_db(LoggingDB::quiet(cache_db))
+        .modify_tx_env(|tx| {
+            tx.caller = from;
+            tx.transact_to = TransactTo::Call(to);
+            tx.data = calldata;
+            tx.value = U256::ZERO;
+        })
+        .modify_env(|env| apply_call_block_env(env, block))
         .build();
 
     let result = evm.transact()?.result;
+    let access = std::mem::take(&mut evm.db_mut().access);
 
     match result {
-        ExecutionResult::Revert { output, .. } => Ok(output),
+        ExecutionResult::Revert { output, gas_used } => Ok((output, gas_used, access)),
         _ => Err(anyhow!("Expected revert result")),
     }
 }
 
-fn cache_dir() -> String {
+pub fn cache_dir() -> String {
     ".evm_cache".to_string()
 }
@@ -1,24 +1,72 @@
-use revm::db::Database;
-use revm::primitives::{AccountInfo, Address, Bytecode, U256, B256};
+use revm::db::{Database, DatabaseRef};
+use revm::primitives::{AccountInfo, Address, Bytecode, U256, B256, KECCAK_EMPTY};
 use std::collections::HashMap;
-use std::convert::Infallible;
+use std::fmt;
+
+/// Lỗi khi state cần cho execute không có trong InMemoryDB (snapshot thiếu dữ liệu)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InMemoryDBError {
+    CodeNotFound(B256),
+    BlockHashNotFound(u64),
+}
+
+impl fmt::Display for InMemoryDBError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InMemoryDBError::CodeNotFound(code_hash) => write!(f, "bytecode {} not found in InMemoryDB", code_hash),
+            InMemoryDBError::BlockHashNotFound(number) => write!(f, "block hash of block {} not found in InMemoryDB", number),
+        }
+    }
+}
+
+impl std::error::Error for InMemoryDBError {}
 
 /// InMemoryDB: full in-memory Database implement cho REVM.
+/// Bytecode nằm trong `contracts` theo code hash (giống `CacheDB`), `accounts` chỉ giữ `code_hash`
+/// nên `basic` clone rẻ và `code_by_hash` là một lần lookup.
 #[derive(Debug, Clone, Default)]
 pub struct InMemoryDB {
     pub accounts: HashMap<Address, AccountInfo>,
+    pub contracts: HashMap<B256, Bytecode>,
     pub storage: HashMap<(Address, U256), U256>,
+    pub block_hashes: HashMap<u64, B256>,
 }
 
 impl InMemoryDB {
+    /// Thêm account, tách bytecode sang `contracts` và tính `code_hash` nếu còn thiếu
+    pub fn insert_account_info(&mut self, address: Address, mut info: AccountInfo) {
+        if let Some(code) = info.code.take() {
+            if !code.is_empty() {
+                if info.code_hash == KECCAK_EMPTY || info.code_hash == B256::ZERO {
+                    info.code_hash = code.hash_slow();
+                }
+                self.contracts.entry(info.code_hash).or_insert(code);
+            }
+        }
+        if info.code_hash == B256::ZERO {
+            info.code_hash = KECCAK_EMPTY;
+        }
+        self.accounts.insert(address, info);
+    }
+
+    /// Bytecode của account (lấy từ `contracts`), `None` với EOA hoặc account chưa có
+    pub fn account_code(&self, address: &Address) -> Option<&Bytecode> {
+        self.accounts.get(address).and_then(|info| self.contracts.get(&info.code_hash))
+    }
+
     /// Convert từ CacheDB<AlloyDB> → InMemoryDB
     pub fn from_cache_db<DB>(src: &revm::db::CacheDB<DB>) -> Self {
         let mut mem_db = InMemoryDB::default();
 
-        // Clone account info
+        // Bytecode đã load theo hash (gồm cả account mà info không giữ code)
+        for (code_hash, code) in &src.contracts {
+            if *code_hash != KECCAK_EMPTY && !code.is_empty() {
+                mem_db.contracts.insert(*code_hash, code.clone());
+            }
+        }
+
         for (addr, db_account) in &src.accounts {
-            // Clone account info → AccountInfo luôn
-            mem_db.accounts.insert(*addr, db_account.info.clone());
+            mem_db.insert_account_info(*addr, db_account.info.clone());
 
             // Clone storage slot
             for (slot, value) in &db_account.storage {
@@ -26,39 +74,89 @@ impl InMemoryDB {
             }
         }
 
+        for (number, hash) in &src.block_hashes {
+            mem_db.block_hashes.insert(number.saturating_to(), *hash);
+        }
+
         mem_db
     }
 }
 
-/// Implement Database cho InMemoryDB
-impl Database for InMemoryDB {
-    type Error = Infallible;
+impl DatabaseRef for InMemoryDB {
+    type Error = InMemoryDBError;
 
-    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
         Ok(self.accounts.get(&address).cloned())
     }
 
-    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        // Lookup code từ accounts
-        for info in self.accounts.values() {
-            if let Some(code) = &info.code {
-                if code.hash_slow() == code_hash {
-                    return Ok(code.clone());
-                }
-            }
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if code_hash == KECCAK_EMPTY {
+            return Ok(Bytecode::default());
         }
-        Ok(Bytecode::new()) // Không tìm thấy → empty
+        self.contracts.get(&code_hash).cloned().ok_or(InMemoryDBError::CodeNotFound(code_hash))
     }
 
-    fn storage(
-        &mut self,
-        address: Address,
-        index: U256,
-    ) -> Result<U256, Self::Error> {
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
         Ok(*self.storage.get(&(address, index)).unwrap_or(&U256::ZERO))
     }
 
-    fn block_hash(&mut self, _number: u64) -> Result<B256, Self::Error> {
-        Ok(B256::ZERO) // Fake luôn, không cần block hash
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hashes.get(&number).copied().ok_or(InMemoryDBError::BlockHashNotFound(number))
+    }
+}
+
+/// Implement Database cho InMemoryDB, đọc thẳng qua `DatabaseRef`
+impl Database for InMemoryDB {
+    type Error = InMemoryDBError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+    use revm::db::{CacheDB, EmptyDB};
+
+    #[test]
+    fn test_code_indexed_by_hash() {
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3]));
+        let code_hash = code.hash_slow();
+        let (contract, eoa) = (Address::repeat_byte(0x01), Address::repeat_byte(0x02));
+
+        // `code_hash` mặc định (KECCAK_EMPTY) được tính lại khi snapshot
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        cache_db.insert_account_info(contract, AccountInfo { code: Some(code.clone()), ..Default::default() });
+        cache_db.insert_account_info(eoa, AccountInfo { balance: U256::from(1), ..Default::default() });
+        cache_db.block_hashes.insert(U256::from(100), B256::repeat_byte(0xbb));
+
+        let mut db = InMemoryDB::from_cache_db(&cache_db);
+        let info = db.basic(contract).unwrap().unwrap();
+        assert_eq!(info.code_hash, code_hash);
+        assert!(info.code.is_none());
+        assert_eq!(db.code_by_hash(code_hash).unwrap(), code);
+        assert_eq!(db.account_code(&contract), Some(&code));
+        assert_eq!(db.basic(eoa).unwrap().unwrap().code_hash, KECCAK_EMPTY);
+        assert!(db.account_code(&eoa).is_none());
+        assert_eq!(db.code_by_hash(KECCAK_EMPTY).unwrap(), Bytecode::default());
+
+        assert_eq!(db.block_hash(100).unwrap(), B256::repeat_byte(0xbb));
+        assert_eq!(db.block_hash(99), Err(InMemoryDBError::BlockHashNotFound(99)));
+        let unknown = B256::repeat_byte(0xee);
+        assert_eq!(db.code_by_hash(unknown), Err(InMemoryDBError::CodeNotFound(unknown)));
     }
 }
@@ -0,0 +1,328 @@
+use revm::db::{Database, DatabaseCommit, DatabaseRef};
+use revm::primitives::{Account, AccountInfo, Address, Bytecode, HashMap as EvmHashMap, B256, KECCAK_EMPTY, U256};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::core::db_empty::{InMemoryDB, InMemoryDBError};
+
+/// Thay đổi của một account trong một lớp overlay
+#[derive(Debug, Clone, Default)]
+struct OverlayAccount {
+    /// `None`: account đã selfdestruct ở lớp này
+    info: Option<AccountInfo>,
+    storage: HashMap<U256, U256>,
+    /// Storage ở các lớp dưới không còn hiệu lực (account mới tạo hoặc selfdestruct)
+    storage_cleared: bool,
+}
+
+/// Một lớp ghi đè. Lớp đã đóng băng được chia sẻ qua `Arc` giữa các fork.
+#[derive(Debug, Clone, Default)]
+struct Overlay {
+    accounts: HashMap<Address, OverlayAccount>,
+    contracts: HashMap<B256, Bytecode>,
+    parent: Option<Arc<Overlay>>,
+}
+
+impl Overlay {
+    fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.contracts.is_empty()
+    }
+}
+
+/// DB copy-on-write: snapshot `Arc<InMemoryDB>` bất biến + chuỗi overlay ghi nhận thay đổi.
+///
+/// - `new` / `fork` là O(1): không copy snapshot, fork chỉ đóng băng overlay hiện tại thành lớp cha
+/// - commit chỉ ghi vào overlay trên cùng, snapshot và các fork khác không bị ảnh hưởng
+/// - `discard` bỏ thay đổi, `merge` gộp base + overlay thành snapshot mới
+///
+/// Dùng cho kịch bản "apply trigger tx rồi simulate": commit trigger tx, `fork` cho từng lần quote.
+#[derive(Debug, Clone)]
+pub struct LayeredDB {
+    base: Arc<InMemoryDB>,
+    overlay: Overlay,
+}
+
+impl LayeredDB {
+    pub fn new(base: Arc<InMemoryDB>) -> Self {
+        LayeredDB { base, overlay: Overlay::default() }
+    }
+
+    pub fn base(&self) -> &Arc<InMemoryDB> {
+        &self.base
+    }
+
+    /// Số lớp overlay (gồm cả lớp đang ghi)
+    pub fn depth(&self) -> usize {
+        self.layers().count()
+    }
+
+    /// Số account đã bị ghi đè trong mọi lớp overlay
+    pub fn touched_accounts(&self) -> usize {
+        let mut touched: Vec<&Address> = self.layers().flat_map(|layer| layer.accounts.keys()).collect();
+        touched.sort();
+        touched.dedup();
+        touched.len()
+    }
+
+    /// Tạo nhánh con thấy mọi thay đổi hiện tại. Overlay hiện tại bị đóng băng và dùng chung,
+    /// các commit sau đó của `self` và của nhánh con tách biệt nhau.
+    pub fn fork(&mut self) -> LayeredDB {
+        if !self.overlay.is_empty() {
+            let frozen = Arc::new(std::mem::take(&mut self.overlay));
+            self.overlay.parent = Some(frozen);
+        }
+        LayeredDB {
+            base: self.base.clone(),
+            overlay: Overlay { parent: self.overlay.parent.clone(), ..Default::default() },
+        }
+    }
+
+    /// Bỏ mọi thay đổi chưa đóng băng (lớp đang ghi)
+    pub fn discard(&mut self) {
+        self.overlay.accounts.clear();
+        self.overlay.contracts.clear();
+    }
+
+    /// Gộp base + toàn bộ overlay thành snapshot mới
+    pub fn merge(&self) -> InMemoryDB {
+        let mut merged = (*self.base).clone();
+        let mut layers: Vec<&Overlay> = self.layers().collect();
+        layers.reverse();
+        for layer in layers {
+            merged.contracts.extend(layer.contracts.iter().map(|(hash, code)| (*hash, code.clone())));
+            for (address, account) in &layer.accounts {
+                if account.storage_cleared {
+                    merged.storage.retain(|(slot_address, _), _| slot_address != address);
+                }
+                match &account.info {
+                    Some(info) => merged.insert_account_info(*address, info.clone()),
+                    None => {
+                        merged.accounts.remove(address);
+                    }
+                }
+                for (slot, value) in &account.storage {
+                    merged.storage.insert((*address, *slot), *value);
+                }
+            }
+        }
+        merged
+    }
+
+    /// Lớp overlay từ trên xuống dưới
+    fn layers(&self) -> impl Iterator<Item = &Overlay> {
+        std::iter::successors(Some(&self.overlay), |layer| layer.parent.as_deref())
+    }
+}
+
+impl DatabaseRef for LayeredDB {
+    type Error = InMemoryDBError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        for layer in self.layers() {
+            if let Some(account) = layer.accounts.get(&address) {
+                return Ok(account.info.clone());
+            }
+        }
+        self.base.basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        for layer in self.layers() {
+            if let Some(code) = layer.contracts.get(&code_hash) {
+                return Ok(code.clone());
+            }
+        }
+        self.base.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        for layer in self.layers() {
+            if let Some(account) = layer.accounts.get(&address) {
+                if let Some(value) = account.storage.get(&index) {
+                    return Ok(*value);
+                }
+                if account.storage_cleared {
+                    return Ok(U256::ZERO);
+                }
+            }
+        }
+        self.base.storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.base.block_hash_ref(number)
+    }
+}
+
+impl Database for LayeredDB {
+    type Error = InMemoryDBError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+/// Giống `CacheDB::commit` nhưng chỉ ghi vào overlay trên cùng
+impl DatabaseCommit for LayeredDB {
+    fn commit(&mut self, changes: EvmHashMap<Address, Account>) {
+        for (address, account) in changes {
+            if !account.is_touched() {
+                continue;
+            }
+            let overlay_account = self.overlay.accounts.entry(address).or_default();
+            if account.is_selfdestructed() {
+                *overlay_account = OverlayAccount { info: None, storage: HashMap::new(), storage_cleared: true };
+                continue;
+            }
+            if account.is_created() {
+                overlay_account.storage.clear();
+                overlay_account.storage_cleared = true;
+            }
+
+            let mut info = account.info;
+            if let Some(code) = info.code.take() {
+                if !code.is_empty() {
+                    if info.code_hash == KECCAK_EMPTY {
+                        info.code_hash = code.hash_slow();
+                    }
+                    self.overlay.contracts.entry(info.code_hash).or_insert(code);
+                }
+            }
+            overlay_account.info = Some(info);
+            overlay_account
+                .storage
+                .extend(account.storage.into_iter().map(|(slot, value)| (slot, value.present_value())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{keccak256, Bytes};
+    use alloy::sol_types::SolValue;
+    use revm::primitives::{AccountStatus, EvmStorageSlot, ExecutionResult, TransactTo};
+    use revm::Evm;
+    use std::str::FromStr;
+
+    fn balance_slot(holder: Address) -> U256 {
+        keccak256((holder, U256::ZERO).abi_encode()).into()
+    }
+
+    /// Snapshot có một ERC20 giả, `holder` giữ 1000 token
+    fn base() -> (Arc<InMemoryDB>, Address, Address) {
+        let token = Address::repeat_byte(0x11);
+        let holder = Address::repeat_byte(0x22);
+        let mut db = InMemoryDB::default();
+        let erc20 = Bytecode::new_raw(Bytes::from_str(include_str!("../bytecode/generic_erc20.hex")).unwrap());
+        db.insert_account_info(token, AccountInfo { code: Some(erc20), ..Default::default() });
+        db.insert_account_info(holder, AccountInfo::default());
+        db.storage.insert((token, balance_slot(holder)), U256::from(1000));
+        (Arc::new(db), token, holder)
+    }
+
+    /// `transfer(to, amount)` từ `from`, commit vào `db`
+    fn transfer(db: &mut LayeredDB, token: Address, from: Address, to: Address, amount: u64) {
+        let mut calldata = vec![0xa9, 0x05, 0x9c, 0xbb];
+        calldata.extend_from_slice(&(to, U256::from(amount)).abi_encode());
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = from;
+                tx.transact_to = TransactTo::Call(token);
+                tx.data = calldata.into();
+                tx.gas_price = U256::ZERO;
+            })
+            .build();
+        let result = evm.transact_commit().unwrap();
+        assert!(matches!(result, ExecutionResult::Success { .. }), "{result:?}");
+    }
+
+    #[test]
+    fn test_forks_are_isolated_and_mergeable() {
+        let (base, token, holder) = base();
+        let (alice, bob) = (Address::repeat_byte(0xa1), Address::repeat_byte(0xb0));
+
+        let mut a = LayeredDB::new(base.clone());
+        let mut b = LayeredDB::new(base.clone());
+        transfer(&mut a, token, holder, alice, 300);
+        transfer(&mut b, token, holder, bob, 50);
+
+        assert_eq!(a.storage(token, balance_slot(holder)).unwrap(), U256::from(700));
+        assert_eq!(a.storage(token, balance_slot(bob)).unwrap(), U256::ZERO);
+        assert_eq!(b.storage(token, balance_slot(holder)).unwrap(), U256::from(950));
+        // Snapshot gốc không đổi và vẫn được chia sẻ
+        assert_eq!(base.storage_ref(token, balance_slot(holder)).unwrap(), U256::from(1000));
+        assert!(Arc::ptr_eq(a.base(), &base));
+
+        let merged = a.merge();
+        assert_eq!(merged.storage[&(token, balance_slot(alice))], U256::from(300));
+        assert_eq!(merged.account_code(&token), base.account_code(&token));
+
+        a.discard();
+        assert_eq!(a.storage(token, balance_slot(holder)).unwrap(), U256::from(1000));
+    }
+
+    #[test]
+    fn test_stacked_fork_after_trigger_tx() {
+        let (base, token, holder) = base();
+        let alice = Address::repeat_byte(0xa1);
+
+        // Trigger tx ở lớp dưới, từng simulation fork từ đó
+        let mut triggered = LayeredDB::new(base);
+        transfer(&mut triggered, token, holder, alice, 400);
+        let mut sim1 = triggered.fork();
+        let mut sim2 = triggered.fork();
+        assert_eq!(sim1.depth(), 2);
+
+        transfer(&mut sim1, token, alice, holder, 100);
+        transfer(&mut sim2, token, alice, holder, 400);
+        assert_eq!(sim1.storage(token, balance_slot(alice)).unwrap(), U256::from(300));
+        assert_eq!(sim2.storage(token, balance_slot(alice)).unwrap(), U256::ZERO);
+        assert_eq!(triggered.storage(token, balance_slot(alice)).unwrap(), U256::from(400));
+
+        // Merge cả chuỗi overlay thành snapshot mới
+        let merged = sim1.merge();
+        assert_eq!(merged.storage[&(token, balance_slot(alice))], U256::from(300));
+        assert_eq!(merged.storage[&(token, balance_slot(holder))], U256::from(700));
+        // token, holder, alice + coinbase
+        assert_eq!(sim1.touched_accounts(), 4);
+    }
+
+    #[test]
+    fn test_selfdestruct_and_create_hide_lower_storage() {
+        let (base, token, holder) = base();
+        let mut db = LayeredDB::new(base);
+
+        let mut destroyed = Account::from(AccountInfo::default());
+        destroyed.status = AccountStatus::Touched | AccountStatus::SelfDestructed;
+        db.commit(EvmHashMap::from_iter([(token, destroyed)]));
+        assert_eq!(db.basic(token).unwrap(), None);
+        assert_eq!(db.storage(token, balance_slot(holder)).unwrap(), U256::ZERO);
+
+        let mut recreated = Account::from(AccountInfo { nonce: 1, ..Default::default() });
+        recreated.status = AccountStatus::Touched | AccountStatus::Created;
+        recreated.storage.insert(U256::from(7), EvmStorageSlot::new_changed(U256::ZERO, U256::from(9)));
+        let mut child = db.fork();
+        child.commit(EvmHashMap::from_iter([(token, recreated)]));
+        assert_eq!(child.basic(token).unwrap().unwrap().nonce, 1);
+        assert_eq!(child.storage(token, U256::from(7)).unwrap(), U256::from(9));
+        assert_eq!(child.storage(token, balance_slot(holder)).unwrap(), U256::ZERO);
+
+        let merged = child.merge();
+        assert!(!merged.storage.contains_key(&(token, balance_slot(holder))));
+        assert_eq!(merged.storage[&(token, U256::from(7))], U256::from(9));
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::network::Network;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use anyhow::Result;
+use revm::db::{AlloyDB, DatabaseRef, EmptyDBTyped};
+use revm::primitives::{AccountInfo, Address, Bytecode, B256, KECCAK_EMPTY, U256};
+
+use crate::core::db_rpc::RpcDB;
+use crate::core::provider::{MultiProvider, PinnedBlock};
+
+/// Lớp DB lưu được state qua các lần chạy. Mặc định không lưu gì (AlloyDB, EmptyDB...).
+pub trait PersistentState {
+    /// State đọc tại `block` có được lưu / đọc lại từ đĩa không
+    fn persists(&self, _block: BlockId) -> bool {
+        false
+    }
+
+    /// `Some(None)`: đã lưu là account không tồn tại
+    fn load_account(&self, _address: Address) -> Option<Option<AccountInfo>> {
+        None
+    }
+
+    fn load_storage(&self, _address: Address, _slot: U256) -> Option<U256> {
+        None
+    }
+
+    fn save_account(&self, _address: Address, _info: Option<&AccountInfo>) {}
+
+    fn save_storage(&self, _address: Address, _slot: U256, _value: U256) {}
+}
+
+impl<T: Transport + Clone, N: Network, P: Provider<T, N>> PersistentState for AlloyDB<T, N, P> {}
+
+impl<E> PersistentState for EmptyDBTyped<E> {}
+
+impl PersistentState for RpcDB {}
+
+/// Cache state trên đĩa (cacache) nằm giữa `CacheDB` và `RpcDB`:
+/// - account info + storage theo (chain_id, block hash, address, slot)
+/// - code theo code hash (bất biến, dùng chung mọi block), block hash theo (chain_id, block hash, number)
+///
+/// Key theo hash của block đã ghim chứ không theo số: sau reorg cùng số block là state khác.
+/// Chỉ lưu khi biết chain và đã ghim block; ngược lại chỉ chuyển tiếp xuống `inner`.
+/// Ghi lỗi thì bỏ qua (cache best-effort), đọc lỗi coi như miss.
+#[derive(Debug)]
+pub struct PersistentDB<ExtDB> {
+    pub inner: ExtDB,
+    dir: String,
+    chain_id: Option<u64>,
+    block: Option<PinnedBlock>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<ExtDB> PersistentDB<ExtDB> {
+    pub fn new(inner: ExtDB, dir: impl Into<String>, chain_id: Option<u64>, block: Option<PinnedBlock>) -> Self {
+        PersistentDB { inner, dir: dir.into(), chain_id, block, hits: AtomicUsize::new(0), misses: AtomicUsize::new(0) }
+    }
+
+    /// Theo chain, cache dir và block đã ghim của `multi_provider`
+    pub fn for_provider(inner: ExtDB, multi_provider: &MultiProvider) -> Self {
+        Self::new(inner, multi_provider.cache_dir(), multi_provider.chain_id(), multi_provider.pinned())
+    }
+
+    /// Không lưu gì, chỉ chuyển tiếp
+    pub fn disabled(inner: ExtDB) -> Self {
+        Self::new(inner, String::new(), None, None)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.chain_id.is_some() && self.block.is_some()
+    }
+
+    /// Read được trả từ đĩa
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Read phải xuống `inner` (RPC với `RpcDB`)
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    fn state_key(&self, kind: &str, address: Address) -> Option<String> {
+        Some(format!("{}-{}-{:?}-{:?}", kind, self.chain_id?, self.block?.hash, address))
+    }
+
+    fn storage_key(&self, address: Address, slot: U256) -> Option<String> {
+        self.state_key("storage", address).map(|key| format!("{}-{:#x}", key, slot))
+    }
+
+    fn code_key(code_hash: B256) -> String {
+        format!("code-{:?}", code_hash)
+    }
+
+    fn read(&self, key: &str) -> Option<Vec<u8>> {
+        cacache::read_sync(&self.dir, key).ok()
+    }
+
+    fn write(&self, key: &str, data: &[u8]) {
+        let _ = write_entry(&self.dir, key, data);
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl PersistentDB<RpcDB> {
+    /// Trỏ `RpcDB` sang block đã ghim; state lưu sau đó theo hash của block mới
+    pub fn set_pinned(&mut self, pinned: PinnedBlock) {
+        self.inner.set_block_number(pinned.id());
+        self.block = Some(pinned);
+    }
+}
+
+/// Như `cacache::write_sync` nhưng ghi cả size vào index (`write_sync` để 0), `prune_cache` cần size
+fn write_entry(dir: &str, key: &str, data: &[u8]) -> Result<()> {
+    let mut writer = cacache::WriteOpts::new().size(data.len()).open_sync(dir, key)?;
+    writer.write_all(data)?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// balance (32) | nonce (8) | code hash (32); rỗng = account không tồn tại
+fn encode_account(info: Option<&AccountInfo>) -> Vec<u8> {
+    let Some(info) = info else { return Vec::new() };
+    let mut bytes = Vec::with_capacity(72);
+    bytes.extend_from_slice(&info.balance.to_be_bytes::<32>());
+    bytes.extend_from_slice(&info.nonce.to_be_bytes());
+    bytes.extend_from_slice(info.code_hash.as_slice());
+    bytes
+}
+
+fn decode_account(bytes: &[u8]) -> Option<Option<AccountInfo>> {
+    match bytes.len() {
+        0 => Some(None),
+        72 => Some(Some(AccountInfo {
+            balance: U256::from_be_slice(&bytes[0..32]),
+            nonce: u64::from_be_bytes(bytes[32..40].try_into().ok()?),
+            code_hash: B256::from_slice(&bytes[40..72]),
+            code: None,
+        })),
+        _ => None,
+    }
+}
+
+impl<ExtDB> PersistentState for PersistentDB<ExtDB> {
+    fn persists(&self, block: BlockId) -> bool {
+        self.chain_id.is_some() && matches!(block, BlockId::Number(BlockNumberOrTag::Number(number)) if Some(number) == self.block.map(|pinned| pinned.number))
+    }
+
+    fn load_account(&self, address: Address) -> Option<Option<AccountInfo>> {
+        let mut account = decode_account(&self.read(&self.state_key("account", address)?)?)?;
+        // Account có code mà thiếu code thì coi như miss: `AlloyDB` không trả code theo hash
+        if let Some(info) = account.as_mut().filter(|info| info.code_hash != KECCAK_EMPTY) {
+            let code = self.read(&Self::code_key(info.code_hash))?;
+            info.code = Some(Bytecode::new_raw(code.into()));
+        }
+        Some(account)
+    }
+
+    fn load_storage(&self, address: Address, slot: U256) -> Option<U256> {
+        let bytes = self.read(&self.storage_key(address, slot)?)?;
+        (bytes.len() == 32).then(|| U256::from_be_slice(&bytes))
+    }
+
+    fn save_account(&self, address: Address, info: Option<&AccountInfo>) {
+        let Some(key) = self.state_key("account", address) else { return };
+        if let Some(code) = info.filter(|info| info.code_hash != KECCAK_EMPTY).and_then(|info| info.code.as_ref()) {
+            self.write(&Self::code_key(code.hash_slow()), code.original_byte_slice());
+        }
+        self.write(&key, &encode_account(info));
+    }
+
+    fn save_storage(&self, address: Address, slot: U256, value: U256) {
+        if let Some(key) = self.storage_key(address, slot) {
+            self.write(&key, &value.to_be_bytes::<32>());
+        }
+    }
+}
+
+impl<ExtDB: DatabaseRef> DatabaseRef for PersistentDB<ExtDB> {
+    type Error = ExtDB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(account) = self.load_account(address) {
+            self.record(true);
+            return Ok(account);
+        }
+        self.record(false);
+        let account = self.inner.basic_ref(address)?;
+        self.save_account(address, account.as_ref());
+        Ok(account)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if let Some(code) = self.read(&Self::code_key(code_hash)) {
+            self.record(true);
+            return Ok(Bytecode::new_raw(code.into()));
+        }
+        self.record(false);
+        self.inner.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self.load_storage(address, index) {
+            self.record(true);
+            return Ok(value);
+        }
+        self.record(false);
+        let value = self.inner.storage_ref(address, index)?;
+        self.save_storage(address, index, value);
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        // Hash của block tổ tiên cố định theo block đã ghim (không theo số: có thể reorg)
+        let key = self.chain_id.zip(self.block).map(|(chain_id, pinned)| format!("blockhash-{}-{:?}-{}", chain_id, pinned.hash, number));
+        if let Some(bytes) = key.as_deref().and_then(|key| self.read(key)).filter(|bytes| bytes.len() == 32) {
+            self.record(true);
+            return Ok(B256::from_slice(&bytes));
+        }
+        self.record(false);
+        let hash = self.inner.block_hash_ref(number)?;
+        if let Some(key) = key {
+            self.write(&key, hash.as_slice());
+        }
+        Ok(hash)
+    }
+}
+
+/// Giới hạn khi dọn cache
+#[derive(Debug, Clone, Default)]
+pub struct PruneConfig {
+    /// Xoá entry ghi trước đây lâu hơn
+    pub max_age: Option<Duration>,
+    /// Sau khi xoá theo tuổi, xoá tiếp entry cũ nhất tới khi tổng dung lượng không vượt quá
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    pub removed: usize,
+    pub kept: usize,
+    pub bytes_removed: u64,
+    pub bytes_kept: u64,
+}
+
+impl fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "removed {} entries ({} bytes), kept {} entries ({} bytes)",
+            self.removed, self.bytes_removed, self.kept, self.bytes_kept
+        )
+    }
+}
+
+/// Dọn thư mục cacache theo tuổi rồi theo dung lượng (entry cũ nhất trước).
+/// Content chỉ bị xoá khi không còn entry nào trỏ tới (content-addressed, nhiều slot có thể chung giá trị).
+pub fn prune_cache(dir: &str, config: &PruneConfig) -> Result<PruneReport> {
+    let mut entries = cacache::list_sync(dir).collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.time);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let expired = |time: u128| config.max_age.is_some_and(|max_age| now.saturating_sub(time) > max_age.as_millis());
+    let mut bytes_kept: u64 = entries.iter().filter(|entry| !expired(entry.time)).map(|entry| entry.size as u64).sum();
+
+    let mut report = PruneReport::default();
+    let mut removed = Vec::new();
+    let mut kept = HashSet::new();
+    for entry in entries {
+        let over_size = config.max_bytes.is_some_and(|max_bytes| bytes_kept > max_bytes);
+        if expired(entry.time) || over_size {
+            if !expired(entry.time) {
+                bytes_kept -= entry.size as u64;
+            }
+            cacache::remove_sync(dir, &entry.key)?;
+            report.removed += 1;
+            report.bytes_removed += entry.size as u64;
+            removed.push(entry.integrity);
+        } else {
+            report.kept += 1;
+            kept.insert(entry.integrity.to_string());
+        }
+    }
+    report.bytes_kept = bytes_kept;
+
+    for integrity in removed {
+        if !kept.contains(&integrity.to_string()) {
+            // Có thể đã xoá qua entry khác cùng content
+            let _ = cacache::remove_hash_sync(dir, &integrity);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::actors::ChainActors;
+    use crate::core::chain_revm_cached::{prepare_chain_revm_cached, simulate_chain_revm_cached};
+    use crate::core::db::{init_cache_db, revm_call_db};
+    use crate::core::mock_rpc::{mock_block, MockRpc};
+    use crate::core::prefetch::{prefetch_pool, prefetch_storage, PrefetchConfig};
+    use crate::source::abi::{balance_of_calldata, decode_balance_of_response};
+    use crate::types::load_chain_config;
+    use revm::db::Database;
+    use revm::primitives::BlockEnv;
+    use serde_json::{json, Value};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    const TOKEN: Address = Address::repeat_byte(0xaa);
+    const HOLDER: Address = Address::repeat_byte(0x01);
+    const BLOCK: u64 = 100;
+
+    /// Chain giả: TOKEN là generic ERC20, mọi slot có giá trị 42, finalized tới `BLOCK`
+    fn chain(method: &str, params: &Value) -> Result<Value, String> {
+        match method {
+            "eth_getBlockByNumber" if params[0] == "finalized" => Ok(mock_block(BLOCK, Some(7))),
+            "eth_getBlockByNumber" => {
+                let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                Ok(mock_block(number, Some(7)))
+            }
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", 42))),
+            "eth_getBalance" | "eth_getTransactionCount" => Ok(json!("0x0")),
+            "eth_getCode" if params[0].as_str().unwrap().eq_ignore_ascii_case(&format!("{:?}", TOKEN)) => {
+                Ok(json!(include_str!("../bytecode/generic_erc20.hex").trim()))
+            }
+            "eth_getCode" => Ok(json!("0x")),
+            _ => Err(format!("unexpected method {method}")),
+        }
+    }
+
+    fn temp_cache(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("uv3-persistent-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    async fn pinned_provider(url: &str, chain_id: u64, cache: &str, block: u64) -> MultiProvider {
+        let multi_provider = MultiProvider::new(&[url.to_string()]).with_chain_id(chain_id).with_cache_dir(cache);
+        multi_provider.pin_block(BlockId::number(block)).await.unwrap();
+        multi_provider
+    }
+
+    /// balanceOf qua REVM + đọc block hash, trả (balance, block hash)
+    fn read_state(multi_provider: &MultiProvider) -> (U256, B256, usize, usize) {
+        let mut cache_db = init_cache_db(multi_provider);
+        let response = revm_call_db(Address::ZERO, TOKEN, balance_of_calldata(HOLDER), &BlockEnv::default(), &mut cache_db).unwrap();
+        let hash = cache_db.block_hash(BLOCK - 1).unwrap();
+        (decode_balance_of_response(response).unwrap(), hash, cache_db.db.hits(), cache_db.db.misses())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_warm_run_needs_no_rpc() {
+        let cache = temp_cache("warm");
+        let mock = MockRpc::spawn(chain).await;
+        let (balance, hash, hits, misses) = read_state(&pinned_provider(&mock.url, 1, &cache, BLOCK).await);
+        assert_eq!((balance, hits), (U256::from(42), 0));
+        assert!(misses > 0);
+        let cold_calls = mock.calls();
+
+        // Lần chạy sau cùng chain + block: không gọi RPC nào, kể cả resolve block
+        let warm = pinned_provider(&mock.url, 1, &cache, BLOCK).await;
+        assert_eq!(read_state(&warm), (balance, hash, misses, 0));
+        assert_eq!(mock.calls(), cold_calls);
+
+        // Chain khác hoặc block khác: không dùng lại state
+        let (.., misses_other_chain) = read_state(&pinned_provider(&mock.url, 43114, &cache, BLOCK).await);
+        let (.., misses_other_block) = read_state(&pinned_provider(&mock.url, 1, &cache, BLOCK + 1).await);
+        assert!(misses_other_chain > 0 && misses_other_block > 0);
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reorged_block_does_not_reuse_state() {
+        let cache = temp_cache("reorg");
+        let reorged = Arc::new(AtomicBool::new(false));
+        let head = BLOCK + 1;
+        let flag = reorged.clone();
+        let mock = MockRpc::spawn(move |method, params: &Value| match method {
+            "eth_getBlockByNumber" if params[0] == format!("0x{:x}", head) => {
+                let mut block = mock_block(head, Some(7));
+                if flag.load(Ordering::Relaxed) {
+                    block["hash"] = json!(format!("0x{:064x}", 0xbeef));
+                }
+                Ok(block)
+            }
+            _ => chain(method, params),
+        })
+        .await;
+
+        // Block chưa finalized: lần chạy sau vẫn resolve lại qua RPC, state theo hash thì dùng lại được
+        let first = pinned_provider(&mock.url, 1, &cache, head).await;
+        assert!(read_state(&first).3 > 0);
+        let calls = mock.calls();
+        let same = pinned_provider(&mock.url, 1, &cache, head).await;
+        assert_eq!(read_state(&same).3, 0);
+        assert!(mock.calls() > calls);
+
+        // Reorg: cùng số block, hash khác → không dùng state của block cũ
+        reorged.store(true, Ordering::Relaxed);
+        let after = pinned_provider(&mock.url, 1, &cache, head).await;
+        assert_eq!(after.pinned().unwrap().hash, B256::from(U256::from(0xbeef)));
+        assert!(read_state(&after).3 > 0);
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prefetch_reads_and_fills_disk_cache() {
+        let cache = temp_cache("prefetch");
+        let mock = MockRpc::spawn(chain).await;
+        let slots: Vec<(Address, U256)> = (0..5).map(|slot| (TOKEN, U256::from(slot))).collect();
+        let config = PrefetchConfig::default();
+
+        let multi_provider = pinned_provider(&mock.url, 1, &cache, BLOCK).await;
+        let block = BlockId::number(BLOCK);
+        let report = prefetch_storage(&mut init_cache_db(&multi_provider), &multi_provider, block, &slots, &config).await.unwrap();
+        assert_eq!((report.slots_fetched, report.from_disk), (5, 0));
+
+        let calls = mock.calls();
+        let mut cache_db = init_cache_db(&multi_provider);
+        let report = prefetch_storage(&mut cache_db, &multi_provider, block, &slots, &config).await.unwrap();
+        assert_eq!((report.slots_fetched, report.from_disk, report.calls), (0, 5, 0));
+        assert_eq!(cache_db.accounts[&TOKEN].storage[&U256::from(3)], U256::from(42));
+        assert!(cache_db.accounts[&TOKEN].info.code.is_some());
+        assert_eq!(mock.calls(), calls);
+
+        // Prefetch ở block khác block đã ghim thì không đọc / ghi đĩa
+        let report = prefetch_storage(&mut init_cache_db(&multi_provider), &multi_provider, BlockId::number(BLOCK - 1), &slots, &config).await.unwrap();
+        assert_eq!((report.slots_fetched, report.from_disk), (5, 0));
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_not_persisted_without_chain_or_pin() {
+        let cache = temp_cache("disabled");
+        let mock = MockRpc::spawn(chain).await;
+        let unpinned = MultiProvider::new(std::slice::from_ref(&mock.url)).with_chain_id(1).with_cache_dir(&cache);
+        read_state(&unpinned);
+        let unknown_chain = MultiProvider::new(std::slice::from_ref(&mock.url)).with_cache_dir(&cache);
+        unknown_chain.pin_block(BlockId::number(BLOCK)).await.unwrap();
+        assert!(!init_cache_db(&unknown_chain).db.is_enabled());
+        read_state(&unknown_chain);
+        assert!(!std::path::Path::new(&cache).exists());
+    }
+
+    #[test]
+    fn test_account_encoding_roundtrip() {
+        let info = AccountInfo { balance: U256::MAX, nonce: 7, code_hash: B256::repeat_byte(0x11), code: None };
+        assert_eq!(decode_account(&encode_account(Some(&info))), Some(Some(info)));
+        assert_eq!(decode_account(&encode_account(None)), Some(None));
+        assert_eq!(decode_account(&[0; 5]), None);
+    }
+
+    #[test]
+    fn test_prune_by_age_then_size() {
+        let cache = temp_cache("prune");
+        // a, b cùng content: xoá a không được làm mất content của b
+        write_entry(&cache, "a", &[1u8; 100]).unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        for key in ["b", "c", "d"] {
+            write_entry(&cache, key, &if key == "b" { [1u8; 100] } else { [key.as_bytes()[0]; 100] }).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        let report = prune_cache(&cache, &PruneConfig { max_age: Some(Duration::from_millis(20)), max_bytes: None }).unwrap();
+        assert_eq!((report.removed, report.kept, report.bytes_kept), (1, 3, 300));
+        assert!(cacache::read_sync(&cache, "a").is_err());
+        assert_eq!(cacache::read_sync(&cache, "b").unwrap(), vec![1u8; 100]);
+
+        let report = prune_cache(&cache, &PruneConfig { max_age: None, max_bytes: Some(150) }).unwrap();
+        assert_eq!((report.removed, report.kept, report.bytes_removed), (2, 1, 200));
+        assert_eq!(cacache::read_sync(&cache, "d").unwrap(), vec![b'd'; 100]);
+        assert!(cacache::read_sync(&cache, "b").is_err() && cacache::read_sync(&cache, "c").is_err());
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires RPC_URL"]
+    async fn test_revm_cached_warm_rerun_without_rpc() {
+        let cache = temp_cache("revm-cached");
+        let config = load_chain_config("src/config/eth.toml").unwrap();
+        let actors = ChainActors::from(&config);
+        let (weth, usdc) = (config.token("WETH").unwrap().address, config.token("USDC").unwrap().address);
+        let pool = config.pool(weth, usdc, actors.default_fee).unwrap().address;
+        let run = |url: String| {
+            let (config, actors, cache) = (&config, &actors, cache.clone());
+            async move {
+                let multi_provider = MultiProvider::new(&[url]).with_chain_id(config.chain_id).with_cache_dir(cache);
+                let pinned = multi_provider.pin_block(BlockId::number(21_000_000)).await?;
+                let mut cache_db = init_cache_db(&multi_provider);
+                prepare_chain_revm_cached(config, actors, &mut cache_db, &multi_provider).await?;
+                prefetch_pool(&mut cache_db, &multi_provider, pinned.id(), pool, actors.default_fee, 1).await?;
+                simulate_chain_revm_cached(config, actors, &mut cache_db, &BlockEnv::default())?;
+                anyhow::Ok(cache_db.db.misses())
+            }
+        };
+        run(std::env::var("RPC_URL").expect("Please set RPC_URL env")).await.unwrap();
+
+        // Lần hai qua endpoint từ chối mọi request
+        let dead = MockRpc::spawn(|method, _: &Value| Err(format!("unexpected method {method}"))).await;
+        assert_eq!(run(dead.url.clone()).await.unwrap(), 0);
+        assert_eq!(dead.calls(), 0);
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::future::Future;
+
+use alloy::{eips::BlockId, providers::Provider};
+use anyhow::anyhow;
+use revm::db::{Database, DatabaseRef};
+use revm::primitives::{AccountInfo, Address, Bytecode, B256, U256};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::core::provider::MultiProvider;
+
+/// Read RPC của `RpcDB` lỗi sau khi đã thử qua các endpoint của `MultiProvider`
+#[derive(Debug)]
+pub struct RpcDBError(pub anyhow::Error);
+
+impl fmt::Display for RpcDBError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for RpcDBError {}
+
+impl From<anyhow::Error> for RpcDBError {
+    fn from(error: anyhow::Error) -> Self {
+        RpcDBError(error)
+    }
+}
+
+/// Như `AlloyDB` nhưng mọi read của revm (account, storage, block hash) đi qua `MultiProvider::request`:
+/// chọn endpoint theo health, thử lại trên endpoint khác khi 429 / 5xx / lỗi transport.
+pub struct RpcDB {
+    multi_provider: MultiProvider,
+    block: BlockId,
+    handle: Handle,
+    /// Số endpoint phải trả cùng giá trị cho mỗi storage slot (`None` = một endpoint như `request`)
+    storage_quorum: Option<usize>,
+}
+
+impl RpcDB {
+    /// `None` nếu không ở trong runtime tokio multi-thread (giống `AlloyDB::new`)
+    pub fn new(multi_provider: &MultiProvider, block: BlockId) -> Option<Self> {
+        let handle = Handle::try_current().ok()?;
+        if handle.runtime_flavor() == RuntimeFlavor::CurrentThread {
+            return None;
+        }
+        Some(RpcDB { multi_provider: multi_provider.clone(), block, handle, storage_quorum: None })
+    }
+
+    /// Mỗi storage slot phải được `k` endpoint trả cùng giá trị (`MultiProvider::quorum`),
+    /// để một node bị trễ không làm sai state của lần validate
+    pub fn with_storage_quorum(mut self, k: usize) -> Self {
+        self.storage_quorum = Some(k);
+        self
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.block
+    }
+
+    pub fn set_block_number(&mut self, block: BlockId) {
+        self.block = block;
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        tokio::task::block_in_place(|| self.handle.block_on(future))
+    }
+}
+
+impl DatabaseRef for RpcDB {
+    type Error = RpcDBError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let (block, multi_provider) = (self.block, &self.multi_provider);
+        let (nonce, balance, code) = self.block_on(async {
+            tokio::join!(
+                multi_provider.request(|provider| async move { provider.get_transaction_count(address).block_id(block).await }),
+                multi_provider.request(|provider| async move { provider.get_balance(address).block_id(block).await }),
+                multi_provider.request(|provider| async move { provider.get_code_at(address).block_id(block).await }),
+            )
+        });
+        let code = Bytecode::new_raw(code?);
+        Ok(Some(AccountInfo::new(balance?, nonce?, code.hash_slow(), code)))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code đã được nạp cùng account trong `basic_ref`
+        Err(RpcDBError(anyhow!("Code {} was not loaded with its account", code_hash)))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let block = self.block;
+        let value = match self.storage_quorum {
+            Some(k) => {
+                let read = self.multi_provider
+                    .quorum(k, |provider| async move { provider.get_storage_at(address, index).block_id(block).await });
+                self.block_on(read)?.value
+            }
+            None => self.block_on(
+                self.multi_provider
+                    .request(|provider| async move { provider.get_storage_at(address, index).block_id(block).await }),
+            )?,
+        };
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        let block = self.block_on(
+            self.multi_provider
+                .request(|provider| async move { provider.get_block_by_number(number.into(), false).await }),
+        )?;
+        let block = block.ok_or_else(|| anyhow!("Block {} not found", number))?;
+        Ok(block.header.hash)
+    }
+}
+
+impl Database for RpcDB {
+    type Error = RpcDBError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_rpc::MockRpc;
+    use serde_json::{json, Value};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reads_fail_over_to_healthy_endpoint() {
+        let down = MockRpc::spawn(|_, _: &Value| Err("unreachable".to_string())).await;
+        down.fail_with_status(503);
+        let up = MockRpc::spawn(|method, _: &Value| match method {
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", 42))),
+            "eth_getTransactionCount" => Ok(json!("0x3")),
+            "eth_getBalance" => Ok(json!("0x64")),
+            "eth_getCode" => Ok(json!("0x6001")),
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await;
+        let multi_provider = MultiProvider::new(&[down.url.clone(), up.url.clone()]);
+        let db = RpcDB::new(&multi_provider, BlockId::number(100)).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(db.storage_ref(Address::ZERO, U256::from(1)).unwrap(), U256::from(42));
+        }
+        let info = db.basic_ref(Address::ZERO).unwrap().unwrap();
+        assert_eq!((info.nonce, info.balance), (3, U256::from(100)));
+        assert_eq!(info.code.unwrap().original_bytes().as_ref(), &[0x60, 0x01]);
+
+        // Lỗi được ghi vào health của endpoint hỏng như mọi request khác
+        let health = multi_provider.health();
+        assert!(health[0].errors > 0 && health[0].last_error.is_some());
+        assert_eq!((health[1].requests, health[1].errors), (6, 0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_storage_quorum_outvotes_lagging_endpoint() {
+        let storage_mock = |value: u64| {
+            MockRpc::spawn(move |method, _: &Value| match method {
+                "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", value))),
+                _ => Err(format!("unexpected method {method}")),
+            })
+        };
+        let (lagging, a, b) = (storage_mock(41).await, storage_mock(42).await, storage_mock(42).await);
+        a.set_delay(std::time::Duration::from_millis(20));
+        b.set_delay(std::time::Duration::from_millis(20));
+        let multi_provider = MultiProvider::new(&[lagging.url.clone(), a.url.clone(), b.url.clone()]);
+
+        // Một endpoint: giá trị của node trả lời đầu tiên (node trễ đứng đầu round-robin)
+        let db = RpcDB::new(&multi_provider, BlockId::number(100)).unwrap();
+        assert_eq!(db.storage_ref(Address::ZERO, U256::from(1)).unwrap(), U256::from(41));
+
+        let db = db.with_storage_quorum(2);
+        assert_eq!(db.storage_ref(Address::ZERO, U256::from(1)).unwrap(), U256::from(42));
+        let db = db.with_storage_quorum(3);
+        assert!(db.storage_ref(Address::ZERO, U256::from(1)).unwrap_err().to_string().contains("Quorum of 3 not reached"));
+    }
+}
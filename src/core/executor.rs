@@ -0,0 +1,336 @@
+use alloy::primitives::{Address, Bytes, U256};
+use revm::primitives::{BlockEnv, ExecutionResult, Output, TransactTo};
+use revm::Evm;
+use std::fmt;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::core::db::{apply_call_block_env, revm_call_db};
+use crate::core::db_empty::InMemoryDB;
+use crate::core::db_layered::LayeredDB;
+
+/// Kết quả mong đợi của call: quoter Uniswap trả qua return, custom UniV3Quoter trả qua revert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteKind {
+    Call,
+    Revert,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuoteRequest {
+    pub from: Address,
+    pub to: Address,
+    pub calldata: Bytes,
+    pub kind: QuoteKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteResult {
+    /// Output của call (hoặc revert data với `QuoteKind::Revert`), lỗi dạng chuỗi để clone được
+    pub output: Result<Bytes, String>,
+    pub gas_used: u64,
+    /// Worker đã chạy request này
+    pub worker: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutorConfig {
+    pub threads: usize,
+    /// Block env cho mọi request (như `revm_*`), vd. block env của snapshot
+    pub block: BlockEnv,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        ExecutorConfig { threads, block: BlockEnv::default() }
+    }
+}
+
+/// Thống kê một lần `quote_batch`
+#[derive(Debug, Clone, Default)]
+pub struct BatchStats {
+    pub requests: usize,
+    pub failed: usize,
+    pub elapsed: Duration,
+    /// Số request mỗi worker đã chạy
+    pub per_worker: Vec<usize>,
+}
+
+impl BatchStats {
+    /// Quote / giây
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.requests as f64 / secs
+    }
+}
+
+impl fmt::Display for BatchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} quotes ({} failed) in {:.2?} on {} threads = {:.0} quotes/s, per worker {:?}",
+            self.requests,
+            self.failed,
+            self.elapsed,
+            self.per_worker.len(),
+            self.throughput(),
+            self.per_worker
+        )
+    }
+}
+
+struct Job {
+    index: usize,
+    request: QuoteRequest,
+    reply: mpsc::Sender<(usize, QuoteResult)>,
+}
+
+/// Pool OS thread cố định cho execute REVM (CPU-bound), tách khỏi runtime tokio.
+/// Mỗi worker giữ một `Evm` dùng lại qua các request, đọc snapshot chung qua `LayeredDB`
+/// (không commit nên overlay luôn rỗng).
+pub struct SimulationExecutor {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    last_stats: Mutex<Option<BatchStats>>,
+}
+
+impl SimulationExecutor {
+    pub fn new(snapshot: Arc<InMemoryDB>, config: ExecutorConfig) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..config.threads.max(1))
+            .map(|worker| {
+                let receiver = receiver.clone();
+                let snapshot = snapshot.clone();
+                let block = config.block.clone();
+                std::thread::Builder::new()
+                    .name(format!("sim-worker-{worker}"))
+                    .spawn(move || {
+                        let mut evm = Evm::builder()
+                            .with_db(LayeredDB::new(snapshot))
+                            .modify_env(|env| apply_call_block_env(env, &block))
+                            .build();
+                        loop {
+                            // Giữ lock chỉ trong lúc lấy job
+                            let job = receiver.lock().unwrap().recv();
+                            let Ok(job) = job else { break };
+                            let result = execute(&mut evm, &job.request, worker);
+                            // Bên gọi đã bỏ batch thì bỏ qua kết quả
+                            let _ = job.reply.send((job.index, result));
+                        }
+                    })
+                    .expect("failed to spawn simulation worker")
+            })
+            .collect();
+
+        SimulationExecutor { sender: Some(sender), workers, last_stats: Mutex::new(None) }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Chạy cả batch trên pool, block tới khi xong. Kết quả đúng thứ tự `requests`.
+    pub fn quote_batch(&self, requests: Vec<QuoteRequest>) -> Vec<QuoteResult> {
+        let start = Instant::now();
+        let total = requests.len();
+        let (reply, results) = mpsc::channel();
+        let sender = self.sender.as_ref().expect("executor is shut down");
+        for (index, request) in requests.into_iter().enumerate() {
+            sender
+                .send(Job { index, request, reply: reply.clone() })
+                .expect("simulation workers stopped");
+        }
+        drop(reply);
+
+        let mut ordered: Vec<Option<QuoteResult>> = vec![None; total];
+        let mut stats = BatchStats { requests: total, per_worker: vec![0; self.threads()], ..Default::default() };
+        for (index, result) in results {
+            stats.per_worker[result.worker] += 1;
+            if result.output.is_err() {
+                stats.failed += 1;
+            }
+            ordered[index] = Some(result);
+        }
+        stats.elapsed = start.elapsed();
+        *self.last_stats.lock().unwrap() = Some(stats);
+
+        ordered.into_iter().map(|result| result.expect("worker dropped a request")).collect()
+    }
+
+    /// Thống kê của lần `quote_batch` gần nhất
+    pub fn last_stats(&self) -> Option<BatchStats> {
+        self.last_stats.lock().unwrap().clone()
+    }
+}
+
+impl Drop for SimulationExecutor {
+    fn drop(&mut self) {
+        // Đóng channel để worker thoát khỏi vòng lặp
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Chạy song song từng `calldata` trên snapshot dùng chung, trả kết quả đúng thứ tự input.
+/// Mỗi task có một `LayeredDB` riêng (overlay rỗng trên snapshot) thay vì clone cả snapshot.
+pub async fn call_snapshot_parallel(
+    snapshot_db: Arc<InMemoryDB>,
+    from: Address,
+    to: Address,
+    calldatas: Vec<Bytes>,
+    block: &BlockEnv,
+) -> Result<Vec<Bytes>> {
+    let mut futs = FuturesUnordered::new();
+    for (index, calldata) in calldatas.into_iter().enumerate() {
+        let (db_template, block) = (snapshot_db.clone(), block.clone());
+        futs.push(tokio::spawn(async move {
+            let mut db = LayeredDB::new(db_template);
+            let response = revm_call_db(from, to, calldata, &block, &mut db)?;
+            Ok::<_, anyhow::Error>((index, response))
+        }));
+    }
+
+    let mut responses = vec![Bytes::new(); futs.len()];
+    while let Some(res) = futs.next().await {
+        let (index, response) = res??;
+        responses[index] = response;
+    }
+    Ok(responses)
+}
+
+fn execute(evm: &mut Evm<'static, (), LayeredDB>, request: &QuoteRequest, worker: usize) -> QuoteResult {
+    let tx = evm.tx_mut();
+    tx.caller = request.from;
+    tx.transact_to = TransactTo::Call(request.to);
+    tx.data = request.calldata.clone();
+    tx.value = U256::ZERO;
+
+    let (output, gas_used) = match evm.transact() {
+        Ok(result) => match (result.result, request.kind) {
+            (ExecutionResult::Success { output: Output::Call(value), gas_used, .. }, QuoteKind::Call) => (Ok(value), gas_used),
+            (ExecutionResult::Revert { output, gas_used }, QuoteKind::Revert) => (Ok(output), gas_used),
+            (result, _) => (Err(format!("unexpected result: {result:?}")), result.gas_used()),
+        },
+        Err(e) => (Err(e.to_string()), 0),
+    };
+    QuoteResult { output, gas_used, worker }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::keccak256;
+    use alloy::sol_types::SolValue;
+    use revm::primitives::{AccountInfo, Bytecode};
+    use std::str::FromStr;
+
+    fn holder(index: usize) -> Address {
+        Address::left_padding_from(&(0x1000 + index as u64).to_be_bytes())
+    }
+
+    fn balance_of(token: Address, index: usize) -> QuoteRequest {
+        let mut calldata = vec![0x70, 0xa0, 0x82, 0x31];
+        calldata.extend_from_slice(&holder(index).abi_encode());
+        QuoteRequest { from: holder(index), to: token, calldata: calldata.into(), kind: QuoteKind::Call }
+    }
+
+    /// ERC20 giả, `holder(i)` có balance `i * 10`
+    fn snapshot(token: Address, holders: usize) -> Arc<InMemoryDB> {
+        let mut db = InMemoryDB::default();
+        let erc20 = Bytecode::new_raw(Bytes::from_str(include_str!("../bytecode/generic_erc20.hex")).unwrap());
+        db.insert_account_info(token, AccountInfo { code: Some(erc20), ..Default::default() });
+        for index in 0..holders {
+            let slot: U256 = keccak256((holder(index), U256::ZERO).abi_encode()).into();
+            db.storage.insert((token, slot), U256::from(index * 10));
+        }
+        Arc::new(db)
+    }
+
+    #[test]
+    fn test_quote_batch_preserves_order() {
+        let token = Address::repeat_byte(0x11);
+        let executor = SimulationExecutor::new(snapshot(token, 200), ExecutorConfig { threads: 4, block: BlockEnv::default() });
+        assert_eq!(executor.threads(), 4);
+
+        let requests: Vec<QuoteRequest> = (0..200).map(|index| balance_of(token, index)).collect();
+        let results = executor.quote_batch(requests);
+        assert_eq!(results.len(), 200);
+        for (index, result) in results.iter().enumerate() {
+            let output = result.output.as_ref().unwrap();
+            assert_eq!(U256::abi_decode(output, true).unwrap(), U256::from(index * 10));
+            assert!(result.gas_used > 21_000);
+        }
+
+        let stats = executor.last_stats().unwrap();
+        assert_eq!((stats.requests, stats.failed), (200, 0));
+        assert_eq!(stats.per_worker.iter().sum::<usize>(), 200);
+        assert!(stats.throughput() > 0.0);
+
+        // Executor dùng lại được cho batch sau
+        let again = executor.quote_batch(vec![balance_of(token, 7)]);
+        assert_eq!(again[0].output, results[7].output);
+        assert_eq!(executor.last_stats().unwrap().requests, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_call_snapshot_parallel_matches_quote_batch() {
+        let token = Address::repeat_byte(0x11);
+        let snapshot = snapshot(token, 50);
+        let requests: Vec<QuoteRequest> = (0..50).map(|index| balance_of(token, index)).collect();
+        let calldatas = requests.iter().map(|request| request.calldata.clone()).collect();
+
+        let responses = call_snapshot_parallel(snapshot.clone(), holder(0), token, calldatas, &BlockEnv::default()).await.unwrap();
+        let executor = SimulationExecutor::new(snapshot, ExecutorConfig { threads: 2, block: BlockEnv::default() });
+        let results = tokio::task::block_in_place(|| executor.quote_batch(requests));
+        assert!(results.into_iter().map(|result| result.output.unwrap()).eq(responses));
+    }
+
+    #[test]
+    fn test_failures_are_reported_per_request() {
+        let token = Address::repeat_byte(0x11);
+        let executor = SimulationExecutor::new(snapshot(token, 2), ExecutorConfig { threads: 2, block: BlockEnv::default() });
+        let mut expect_revert = balance_of(token, 1);
+        expect_revert.kind = QuoteKind::Revert;
+
+        let results = executor.quote_batch(vec![balance_of(token, 0), expect_revert, balance_of(token, 1)]);
+        assert!(results[0].output.is_ok());
+        assert!(results[1].output.as_ref().unwrap_err().contains("unexpected result"));
+        assert!(results[2].output.is_ok());
+        assert_eq!(executor.last_stats().unwrap().failed, 1);
+        assert!(executor.quote_batch(Vec::new()).is_empty());
+    }
+
+    /// Block env phải tới được cả worker thread của executor lẫn task tokio của `call_snapshot_parallel`
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_env_reaches_workers_and_tasks() {
+        // return abi.encode(block.number, block.timestamp)
+        let block_info = Address::repeat_byte(0x33);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x43, 0x60, 0x00, 0x52, 0x42, 0x60, 0x20, 0x52, 0x60, 0x40, 0x60, 0x00, 0xf3]));
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(block_info, AccountInfo { code: Some(code), ..Default::default() });
+        let snapshot = Arc::new(db);
+        let block = BlockEnv { number: U256::from(21_000_000), timestamp: U256::from(1_700_000_000), ..Default::default() };
+        let expected = (block.number, block.timestamp);
+
+        let responses = call_snapshot_parallel(snapshot.clone(), holder(0), block_info, vec![Bytes::new(); 8], &block).await.unwrap();
+        assert!(responses.iter().all(|response| <(U256, U256)>::abi_decode(response, true).unwrap() == expected));
+
+        let executor = SimulationExecutor::new(snapshot, ExecutorConfig { threads: 4, block });
+        let requests = (0..8).map(|_| QuoteRequest { from: holder(0), to: block_info, calldata: Bytes::new(), kind: QuoteKind::Call }).collect();
+        let results = tokio::task::block_in_place(|| executor.quote_batch(requests));
+        assert!(results.iter().all(|result| <(U256, U256)>::abi_decode(result.output.as_ref().unwrap(), true).unwrap() == expected));
+    }
+}
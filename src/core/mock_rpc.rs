@@ -0,0 +1,206 @@
+//! Mock JSON-RPC server chạy local cho test (không cần RPC_URL thật).
+//! Mỗi connection xử lý một request HTTP rồi đóng; hỗ trợ cả batch request.
+//! Có thể bắt server trả HTTP lỗi (429 / 5xx) hoặc trả lời chậm theo yêu cầu để test failover.
+
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU16, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Handler nhận `(method, params)` và trả về `result` hoặc message lỗi JSON-RPC
+pub type Handler = Arc<dyn Fn(&str, &Value) -> Result<Value, String> + Send + Sync>;
+
+pub struct MockRpc {
+    pub url: String,
+    /// Số JSON-RPC call đã nhận (batch tính từng phần tử)
+    pub calls: Arc<AtomicUsize>,
+    /// Số HTTP request đã nhận (một batch = một request)
+    pub http_requests: Arc<AtomicUsize>,
+    /// HTTP status trả về thay cho response (0 = bình thường)
+    fail_status: Arc<AtomicU16>,
+    /// Độ trễ trước khi trả lời (ms)
+    delay_ms: Arc<AtomicU64>,
+    handle: JoinHandle<()>,
+}
+
+impl MockRpc {
+    pub async fn spawn<F>(handler: F) -> Self
+    where
+        F: Fn(&str, &Value) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock rpc");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handler: Handler = Arc::new(handler);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let http_requests = Arc::new(AtomicUsize::new(0));
+
+        let fail_status = Arc::new(AtomicU16::new(0));
+        let delay_ms = Arc::new(AtomicU64::new(0));
+
+        let behavior = Behavior { fail_status: fail_status.clone(), delay_ms: delay_ms.clone() };
+        let (task_calls, task_requests) = (calls.clone(), http_requests.clone());
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (handler, calls, requests) = (handler.clone(), task_calls.clone(), task_requests.clone());
+                let behavior = behavior.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, handler, calls, requests, behavior).await;
+                });
+            }
+        });
+
+        MockRpc { url, calls, http_requests, fail_status, delay_ms, handle }
+    }
+
+    /// Mọi HTTP request sau đó nhận `status` (vd. 429, 503) thay vì JSON-RPC response
+    pub fn fail_with_status(&self, status: u16) {
+        self.fail_status.store(status, Ordering::Relaxed);
+    }
+
+    /// Trả lời bình thường trở lại
+    pub fn recover(&self) {
+        self.fail_status.store(0, Ordering::Relaxed);
+    }
+
+    /// Trễ `delay` trước mỗi response
+    pub fn set_delay(&self, delay: Duration) {
+        self.delay_ms.store(delay.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub fn http_requests(&self) -> usize {
+        self.http_requests.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MockRpc {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Trả lời mặc định cho các call mà `AlloyDB::basic` cần (account rỗng)
+pub fn default_account_response(method: &str) -> Option<Value> {
+    match method {
+        "eth_getBalance" | "eth_getTransactionCount" => Some(json!("0x0")),
+        "eth_getCode" => Some(json!("0x")),
+        "eth_chainId" => Some(json!("0x1")),
+        _ => None,
+    }
+}
+
+/// Block tối thiểu cho `eth_getBlockByNumber` / `eth_getBlockByHash` (hash = `number` lặp lại)
+pub fn mock_block(number: u64, base_fee: Option<u64>) -> Value {
+    let hash = format!("0x{:064x}", number);
+    let mut block = json!({
+        "hash": hash,
+        "parentHash": format!("0x{:064x}", number.saturating_sub(1)),
+        "sha3Uncles": format!("0x{:064x}", 0),
+        "miner": format!("0x{:040x}", 0),
+        "stateRoot": hash,
+        "transactionsRoot": hash,
+        "receiptsRoot": hash,
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "difficulty": "0x0",
+        "number": format!("0x{:x}", number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": format!("0x{:x}", 1_700_000_000 + number * 12),
+        "extraData": "0x",
+        "uncles": [],
+        "transactions": [],
+    });
+    if let Some(base_fee) = base_fee {
+        block["baseFeePerGas"] = json!(format!("0x{:x}", base_fee));
+    }
+    block
+}
+
+#[derive(Clone)]
+struct Behavior {
+    fail_status: Arc<AtomicU16>,
+    delay_ms: Arc<AtomicU64>,
+}
+
+fn respond(handler: &Handler, calls: &AtomicUsize, request: &Value) -> Value {
+    calls.fetch_add(1, Ordering::Relaxed);
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    match handler(method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(message) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32000, "message": message } }),
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    handler: Handler,
+    calls: Arc<AtomicUsize>,
+    requests: Arc<AtomicUsize>,
+    behavior: Behavior,
+) -> std::io::Result<()> {
+    // Đọc header tới "\r\n\r\n", sau đó đọc đủ Content-Length byte body
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+    let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+    let content_length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    requests.fetch_add(1, Ordering::Relaxed);
+
+    let delay = behavior.delay_ms.load(Ordering::Relaxed);
+    if delay > 0 {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+    let status = behavior.fail_status.load(Ordering::Relaxed);
+    if status != 0 {
+        let payload = format!("mock failure {status}");
+        let head = format!(
+            "HTTP/1.1 {status} Mock Failure\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            payload.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(payload.as_bytes()).await?;
+        return stream.shutdown().await;
+    }
+
+    let body: Value = serde_json::from_slice(&buf[header_end..]).unwrap_or(Value::Null);
+    let response = match &body {
+        Value::Array(batch) => Value::Array(batch.iter().map(|r| respond(&handler, &calls, r)).collect()),
+        single => respond(&handler, &calls, single),
+    };
+    let payload = response.to_string();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        payload.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(payload.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -13,8 +13,37 @@ pub mod chain_revm_cached; // chứa run_eth_revm_cached, v.v.
 pub mod chain_revm_quoter; // chứa run_eth_revm_quoter, v.v.
 pub mod chain_validate;
 pub mod chain_arbitrage;
+pub mod chain_native;     // quote bằng mô hình UniV3 thuần Rust
+pub mod chain_cycles;     // tìm + xếp hạng cycle arbitrage N hop
+pub mod chain_snapshot;   // record / replay offline từ snapshot
+pub mod chain_access;     // record access set ra manifest + prewarm từ manifest
+pub mod chain_watch;      // theo dõi head mới, invalidate cache, scan lại mỗi block
+pub mod chain_backrun;    // áp pending tx lên state, tìm cycle backrun cho từng victim
+pub mod chain_execute;    // swap thật các cycle đứng đầu qua ArbExecutor
+pub mod chain_discover;   // tìm pool trên factory, ghi registry cho chain config
 
 pub mod db;        // chứa init_cache_db, init_account, v.v.
 pub mod logger;    // chứa measure_start, structured log, ...
 pub mod db_empty;
+pub mod db_layered; // snapshot Arc + overlay copy-on-write cho fork rẻ
+pub mod db_persistent; // cache state trên đĩa theo (chain, block, address, slot) giữa CacheDB và AlloyDB, prune
+pub mod db_rpc;     // revm Database đọc qua MultiProvider::request (health, retry, failover)
+pub mod executor;   // pool OS thread chạy REVM song song trên snapshot
 pub mod provider; // chứa ProviderBuilder, v.v.
+pub mod path_search; // đồ thị pool + liệt kê cycle
+pub mod optimizer;   // tìm volume tối ưu (golden-section / bisection)
+pub mod opportunity; // lợi nhuận ròng sau gas + quyết định go / no-go
+pub mod consistency; // check định kỳ PoolState với on-chain + recover
+pub mod snapshot;    // lưu / load InMemoryDB + block env (JSON, binary)
+pub mod prefetch;    // nạp trước storage bằng JSON-RPC batch / eth_getProof
+pub mod access_set;  // access set của call (LoggingDB), manifest JSON, EIP-2930 access list
+pub mod state_diff;  // diff state theo block (prestateTracer) + invalidate cache
+pub mod backrun;     // TxEnv từ pending tx, transact_commit, decode Swap của victim
+pub mod bundle;      // bundle nhiều tx chạy nguyên tử, rollback khi revert, balance delta của ME
+pub mod arb_executor; // contract flash-swap cycle: nạp bytecode, calldata, swap thật qua REVM
+pub mod submit;      // signer (env / keystore), nonce, fee theo chain, ký + eth_sendRawTransaction
+pub mod balance_slot; // dò slot mapping balances của token ERC20 thật (Solidity / Vyper / proxy), cache theo token
+pub mod pool_discovery; // getPool / PoolCreated trên UniswapV3Factory, lọc liquidity, registry TOML
+
+#[cfg(test)]
+pub mod mock_rpc; // JSON-RPC server giả cho test
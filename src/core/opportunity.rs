@@ -0,0 +1,206 @@
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{I256, U256},
+    providers::Provider,
+};
+use anyhow::Result;
+use std::fmt;
+
+use crate::core::arb_executor::ExecutionReport;
+use crate::core::provider::MultiProvider;
+use crate::source::builder::FeePolicy;
+use crate::types::ChainConfig;
+
+/// Base fee của block đã ghim (block mới nhất nếu chưa ghim), fallback `eth_gasPrice` nếu chain không có EIP-1559
+pub async fn fetch_base_fee(multi_provider: &MultiProvider) -> Result<u128> {
+    match multi_provider.pinned() {
+        Some(pinned) => match pinned.base_fee {
+            Some(base_fee) => Ok(base_fee),
+            None => multi_provider.request(|provider| async move { provider.get_gas_price().await }).await,
+        },
+        None => fetch_latest_base_fee(multi_provider).await,
+    }
+}
+
+/// Base fee của block mới nhất, bỏ qua block đã ghim (dùng khi ký transaction thật)
+pub async fn fetch_latest_base_fee(multi_provider: &MultiProvider) -> Result<u128> {
+    let base_fee = multi_provider
+        .request(|provider| async move { provider.get_block_by_number(BlockNumberOrTag::Latest, false).await })
+        .await?
+        .and_then(|block| block.header.base_fee_per_gas.map(u128::from));
+    match base_fee {
+        Some(base_fee) => Ok(base_fee),
+        None => multi_provider.request(|provider| async move { provider.get_gas_price().await }).await,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Go,
+    NoGo(String),
+}
+
+/// Kết quả đánh giá một cơ hội arbitrage, mọi amount tính theo native token (wei)
+#[derive(Debug, Clone)]
+pub struct Opportunity {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub gross_profit: I256,
+    pub gas_used: u64,
+    /// `gas_used * gas_multiplier`, dùng làm gas limit khi gửi tx
+    pub gas_limit: u64,
+    pub gas_price: u128,
+    pub gas_cost: U256,
+    pub net_profit: I256,
+    pub decision: Decision,
+}
+
+impl fmt::Display for Opportunity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let decision = match &self.decision {
+            Decision::Go => "✅ GO".to_string(),
+            Decision::NoGo(reason) => format!("❌ NO-GO ({})", reason),
+        };
+        write!(
+            f,
+            "{} | in={} out={} gross={} gas={} (limit {}) @ {} wei = {} | net={}",
+            decision,
+            self.amount_in,
+            self.amount_out,
+            self.gross_profit,
+            self.gas_used,
+            self.gas_limit,
+            self.gas_price,
+            self.gas_cost,
+            self.net_profit
+        )
+    }
+}
+
+/// Tính lợi nhuận ròng sau gas. Token đầu/cuối của cycle phải là wrapped native token
+/// (WETH, WAVAX, WRON) nên phí gas quy đổi 1:1.
+#[derive(Debug, Clone)]
+pub struct OpportunityEvaluator {
+    pub fee_policy: FeePolicy,
+    pub base_fee: u128,
+    pub gas_multiplier: f64,
+    /// Lợi nhuận ròng tối thiểu để quyết định gửi tx
+    pub min_profit: U256,
+}
+
+impl OpportunityEvaluator {
+    pub fn new(config: &ChainConfig, base_fee: u128) -> Self {
+        OpportunityEvaluator {
+            fee_policy: config.defaults.fee_policy,
+            base_fee,
+            gas_multiplier: config.gas_multiplier,
+            min_profit: U256::ZERO,
+        }
+    }
+
+    pub fn gas_price(&self) -> u128 {
+        self.fee_policy.effective_gas_price(self.base_fee)
+    }
+
+    pub fn evaluate(&self, amount_in: U256, amount_out: U256, gas_used: u64) -> Opportunity {
+        let gross_profit = I256::from_raw(amount_out) - I256::from_raw(amount_in);
+        let gas_price = self.gas_price();
+        let gas_cost = U256::from(gas_used) * U256::from(gas_price);
+        let net_profit = gross_profit - I256::from_raw(gas_cost);
+        // Nhân theo phần nghìn để tránh sai số float (1.1 * 200000 = 220000.00000000003)
+        let multiplier_permille = (self.gas_multiplier.max(1.0) * 1000.0).round() as u64;
+        let gas_limit = (gas_used * multiplier_permille).div_ceil(1000);
+
+        let decision = if !gross_profit.is_positive() {
+            Decision::NoGo("no gross profit".to_string())
+        } else if net_profit <= I256::from_raw(self.min_profit) {
+            Decision::NoGo(format!("gas cost {} exceeds profit", gas_cost))
+        } else {
+            Decision::Go
+        };
+
+        Opportunity {
+            amount_in,
+            amount_out,
+            gross_profit,
+            gas_used,
+            gas_limit,
+            gas_price,
+            gas_cost,
+            net_profit,
+            decision,
+        }
+    }
+
+    /// Như `evaluate` nhưng theo lần chạy cycle qua ArbExecutor tại `report.amount_in`:
+    /// volume 0, `execute` revert hoặc bundle lỗi đều là NO-GO, gas của tx đó không được tính là cơ hội
+    pub fn evaluate_execution(&self, amount_out: U256, report: &ExecutionReport) -> Opportunity {
+        let mut opportunity = self.evaluate(report.amount_in, amount_out, report.bundle.gas_used());
+        if report.amount_in.is_zero() {
+            opportunity.decision = Decision::NoGo("no profitable volume".to_string());
+        } else if report.profit.is_none() || !report.bundle.is_success() {
+            opportunity.decision = Decision::NoGo("execution reverted".to_string());
+        }
+        opportunity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn evaluator(policy: FeePolicy, base_fee: u128) -> OpportunityEvaluator {
+        OpportunityEvaluator { fee_policy: policy, base_fee, gas_multiplier: 1.1, min_profit: U256::ZERO }
+    }
+
+    #[test]
+    fn test_go_when_profit_covers_gas() {
+        let opportunity = evaluator(FeePolicy::Eth, 10 * GWEI).evaluate(U256::from(10u128.pow(18)), U256::from(10u128.pow(18) + 10u128.pow(16)), 200_000);
+        // 200k gas * 11 gwei = 0.0022 ETH < 0.01 ETH
+        assert_eq!(opportunity.gas_price, 11 * GWEI);
+        assert_eq!(opportunity.gas_cost, U256::from(200_000 * 11 * GWEI));
+        assert_eq!(opportunity.decision, Decision::Go);
+        assert_eq!(opportunity.gas_limit, 220_000);
+        assert_eq!(opportunity.net_profit, I256::try_from(10u128.pow(16) - 200_000 * 11 * GWEI).unwrap());
+    }
+
+    #[test]
+    fn test_no_go_when_gas_eats_profit() {
+        let opportunity = evaluator(FeePolicy::Avalanche, 25 * GWEI).evaluate(U256::from(10u128.pow(18)), U256::from(10u128.pow(18) + 10u128.pow(15)), 300_000);
+        // 300k gas * 75 gwei = 0.0225 AVAX > 0.001 AVAX
+        assert!(matches!(opportunity.decision, Decision::NoGo(_)));
+        assert!(opportunity.net_profit.is_negative());
+
+        let loss = evaluator(FeePolicy::Ronin, GWEI).evaluate(U256::from(1000), U256::from(999), 1);
+        assert_eq!(loss.decision, Decision::NoGo("no gross profit".to_string()));
+    }
+
+    #[test]
+    fn test_no_go_when_execution_fails() {
+        use crate::core::bundle::BundleResult;
+        use crate::core::path_search::Cycle;
+
+        let (amount_in, amount_out) = (U256::from(10u128.pow(18)), U256::from(10u128.pow(18) + 10u128.pow(16)));
+        let report = |amount_in, profit, reverted| ExecutionReport {
+            cycle: Cycle { hops: vec![] },
+            amount_in,
+            profit,
+            bundle: BundleResult { outcomes: vec![], reverted, balance_deltas: vec![] },
+        };
+        let evaluator = evaluator(FeePolicy::Eth, 10 * GWEI);
+
+        let ok = evaluator.evaluate_execution(amount_out, &report(amount_in, Some(U256::from(10u128.pow(16))), None));
+        assert_eq!(ok.decision, Decision::Go);
+
+        // Quote có lãi nhưng `execute` revert (vd. bị front-run, minProfit không đạt)
+        let reverted = evaluator.evaluate_execution(amount_out, &report(amount_in, None, Some(0)));
+        assert_eq!(reverted.decision, Decision::NoGo("execution reverted".to_string()));
+        let failed = evaluator.evaluate_execution(amount_out, &report(amount_in, Some(U256::ZERO), Some(1)));
+        assert_eq!(failed.decision, Decision::NoGo("execution reverted".to_string()));
+
+        let idle = evaluator.evaluate_execution(U256::from(5), &report(U256::ZERO, Some(U256::from(5)), None));
+        assert_eq!(idle.decision, Decision::NoGo("no profitable volume".to_string()));
+    }
+}
@@ -0,0 +1,392 @@
+use alloy::{
+    primitives::{aliases::U24, Address, I256, U256},
+    providers::Provider,
+};
+use anyhow::{anyhow, bail, Result};
+use revm::db::{CacheDB, Database, DatabaseRef};
+use revm::primitives::BlockEnv;
+use std::fmt;
+
+use crate::core::db::revm_revert;
+use crate::core::path_search::Cycle;
+use crate::core::provider::MultiProvider;
+use crate::source::abi::{
+    decode_get_amount_out_response, decode_quote_exact_input_response, get_amount_out_calldata,
+    quote_exact_input_calldata,
+};
+use crate::source::builder::build_tx;
+use crate::univ3::PoolStateManager;
+
+/// Quote lượng token nhận về cho `amount_in`. Với cycle arbitrage: output là lượng token đầu quay về.
+pub trait Quoter {
+    fn quote(&mut self, amount_in: U256) -> Result<U256>;
+}
+
+impl<F> Quoter for F
+where
+    F: FnMut(U256) -> Result<U256>,
+{
+    fn quote(&mut self, amount_in: U256) -> Result<U256> {
+        self(amount_in)
+    }
+}
+
+/// Quote cycle qua custom UniV3Quoter bằng REVM (`getAmountOut` từng hop)
+pub struct RevmCycleQuoter<'a, ExtDB> {
+    pub cache_db: &'a mut CacheDB<ExtDB>,
+    pub block: &'a BlockEnv,
+    pub from: Address,
+    pub quoter: Address,
+    pub cycle: &'a Cycle,
+}
+
+impl<ExtDB> Quoter for RevmCycleQuoter<'_, ExtDB>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn quote(&mut self, amount_in: U256) -> Result<U256> {
+        self.cycle.simulate(amount_in, |hop, amount| {
+            let calldata = get_amount_out_calldata(hop.pool, hop.token_in, hop.token_out, amount);
+            let response = revm_revert(self.from, self.quoter, calldata, self.block, self.cache_db)?;
+            Ok(U256::from(decode_get_amount_out_response(response)?))
+        })
+    }
+}
+
+/// Quote cả path bằng `quoteExactInput` qua `eth_call` tại block đã ghim của `multi_provider`
+/// (`latest` nếu chưa ghim), có failover giữa các endpoint.
+/// Block trong runtime tokio hiện tại (giống `AlloyDB`), cần runtime multi-thread.
+pub struct EthCallPathQuoter<'a> {
+    pub multi_provider: &'a MultiProvider,
+    pub from: Address,
+    pub quoter: Address,
+    pub tokens: Vec<Address>,
+    pub fees: Vec<U24>,
+    pub base_fee: u128,
+}
+
+impl Quoter for EthCallPathQuoter<'_> {
+    fn quote(&mut self, amount_in: U256) -> Result<U256> {
+        let calldata = quote_exact_input_calldata(&self.tokens, &self.fees, amount_in);
+        let tx = build_tx(self.quoter, self.from, calldata, self.base_fee);
+        let block = self.multi_provider.block_id();
+        let handle = tokio::runtime::Handle::current();
+        let response = tokio::task::block_in_place(|| {
+            handle.block_on(self.multi_provider.request(|provider| {
+                let tx = tx.clone();
+                async move { provider.call(&tx).block(block).await }
+            }))
+        })?;
+        decode_quote_exact_input_response(response)
+    }
+}
+
+/// Quote cycle bằng mô hình UniV3 thuần Rust, pool lấy từ `PoolStateManager`.
+/// Pool đang dirty (lệch on-chain, chờ recover) làm cả cycle lỗi thay vì quote trên state sai.
+pub struct NativeCycleQuoter<'a, DB> {
+    pub manager: &'a mut PoolStateManager,
+    pub db: &'a mut DB,
+    pub cycle: &'a Cycle,
+}
+
+impl<DB> Quoter for NativeCycleQuoter<'_, DB>
+where
+    DB: Database,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn quote(&mut self, amount_in: U256) -> Result<U256> {
+        self.cycle.simulate(amount_in, |hop, amount| {
+            if self.manager.is_dirty(&hop.pool) {
+                bail!("Pool {:?} is dirty, waiting for consistency recovery", hop.pool);
+            }
+            let pool = self
+                .manager
+                .get_mut_for_simulation(&hop.pool)
+                .ok_or_else(|| anyhow!("Pool {:?} is not tracked", hop.pool))?;
+            pool.quote_exact_input(self.db, hop.token_in < hop.token_out, amount)
+        })
+    }
+}
+
+/// Khoảng tìm kiếm và điều kiện dừng
+#[derive(Debug, Clone)]
+pub struct OptimizerConfig {
+    pub lower: U256,
+    pub upper: U256,
+    /// Dừng khi khoảng còn nhỏ hơn giá trị này (đơn vị token)
+    pub tolerance: U256,
+    pub max_iterations: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMethod {
+    GoldenSection,
+    Bisection,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizationResult {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// `amount_out - amount_in`, có thể âm
+    pub profit: I256,
+    pub iterations: usize,
+    pub quotes: usize,
+    pub method: SearchMethod,
+}
+
+impl fmt::Display for OptimizationResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "in={} out={} profit={} ({:?}, {} iterations, {} quotes)",
+            self.amount_in, self.amount_out, self.profit, self.method, self.iterations, self.quotes
+        )
+    }
+}
+
+/// Đếm số quote và giữ điểm lời nhất đã thấy
+struct Evaluator<'a, Q: ?Sized> {
+    quoter: &'a mut Q,
+    quotes: usize,
+    best: Option<(U256, U256, I256)>,
+}
+
+impl<Q: Quoter + ?Sized> Evaluator<'_, Q> {
+    fn profit(&mut self, amount_in: U256) -> Result<I256> {
+        self.quotes += 1;
+        let amount_out = self.quoter.quote(amount_in)?;
+        let profit = I256::from_raw(amount_out) - I256::from_raw(amount_in);
+        if self.best.map_or(true, |(_, _, best)| profit > best) {
+            self.best = Some((amount_in, amount_out, profit));
+        }
+        Ok(profit)
+    }
+
+    fn result(&self, iterations: usize, method: SearchMethod) -> Result<OptimizationResult> {
+        let (amount_in, amount_out, profit) = self.best.ok_or_else(|| anyhow!("No successful quote in search range"))?;
+        Ok(OptimizationResult { amount_in, amount_out, profit, iterations, quotes: self.quotes, method })
+    }
+}
+
+/// (b - a) * 0.618034
+fn golden_step(width: U256) -> U256 {
+    width * U256::from(618_034) / U256::from(1_000_000)
+}
+
+fn golden_section<Q: Quoter + ?Sized>(eval: &mut Evaluator<Q>, config: &OptimizerConfig) -> Result<OptimizationResult> {
+    let (mut a, mut b) = (config.lower, config.upper);
+    let mut c = b - golden_step(b - a);
+    let mut d = a + golden_step(b - a);
+    let mut fc = eval.profit(c)?;
+    let mut fd = eval.profit(d)?;
+
+    let mut iterations = 0;
+    while b - a > config.tolerance && iterations < config.max_iterations {
+        iterations += 1;
+        if fc >= fd {
+            // Đỉnh nằm trong [a, d]
+            b = d;
+            (d, fd) = (c, fc);
+            c = b - golden_step(b - a);
+            fc = eval.profit(c)?;
+        } else {
+            // Đỉnh nằm trong [c, b]
+            a = c;
+            (c, fc) = (d, fd);
+            d = a + golden_step(b - a);
+            fd = eval.profit(d)?;
+        }
+        if c > d {
+            (c, fc, d, fd) = (d, fd, c, fc);
+        }
+    }
+    eval.result(iterations, SearchMethod::GoldenSection)
+}
+
+/// Bisection theo dấu độ dốc: so `profit(mid)` với `profit(mid + tolerance)`.
+/// Quote lỗi (revert vì thiếu liquidity, ...) được coi như đã vượt quá điểm tối ưu.
+fn bisection<Q: Quoter + ?Sized>(eval: &mut Evaluator<Q>, config: &OptimizerConfig) -> Result<OptimizationResult> {
+    let (mut lo, mut hi) = (config.lower, config.upper);
+    let step = config.tolerance.max(U256::from(1));
+
+    let mut iterations = 0;
+    while hi - lo > config.tolerance && iterations < config.max_iterations {
+        iterations += 1;
+        let mid = lo + (hi - lo) / U256::from(2);
+        let rising = match (eval.profit(mid), eval.profit(mid + step)) {
+            (Ok(here), Ok(next)) => next > here,
+            _ => false,
+        };
+        if rising {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    eval.result(iterations, SearchMethod::Bisection)
+}
+
+/// Tìm `amount_in` làm `amount_out - amount_in` lớn nhất.
+/// Golden-section trên đường lợi nhuận lõm; nếu quote lỗi giữa chừng thì fallback bisection.
+pub fn optimize<Q: Quoter + ?Sized>(quoter: &mut Q, config: &OptimizerConfig) -> Result<OptimizationResult> {
+    let mut eval = Evaluator { quoter, quotes: 0, best: None };
+    match golden_section(&mut eval, config) {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            println!("⚠️ Golden-section failed ({}), fallback to bisection", e);
+            bisection(&mut eval, config)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::eips::BlockId;
+    use anyhow::bail;
+
+    const SCALE: u64 = 100_000_000_000_000_000; // 0.1 ETH
+
+    fn config() -> OptimizerConfig {
+        OptimizerConfig {
+            lower: U256::ZERO,
+            upper: U256::from(SCALE),
+            tolerance: U256::from(SCALE / 100_000),
+            max_iterations: 64,
+        }
+    }
+
+    /// profit(x) = x * (SCALE - x) / SCALE, đỉnh tại SCALE / 2
+    fn concave(amount_in: U256) -> Result<U256> {
+        let scale = U256::from(SCALE);
+        Ok(amount_in + amount_in * (scale - amount_in.min(scale)) / scale)
+    }
+
+    fn close_to(actual: U256, expected: U256, tolerance: U256) -> bool {
+        actual.max(expected) - actual.min(expected) <= tolerance
+    }
+
+    #[test]
+    fn test_golden_section_finds_peak() {
+        let config = config();
+        let result = optimize(&mut concave, &config).unwrap();
+        assert_eq!(result.method, SearchMethod::GoldenSection);
+        assert!(close_to(result.amount_in, U256::from(SCALE / 2), config.tolerance));
+        assert!(result.profit.is_positive());
+        // Nhỏ hơn nhiều so với sweep 100-1000 điểm
+        assert!(result.quotes <= 40, "quotes = {}", result.quotes);
+        assert_eq!(result.quotes, result.iterations + 2);
+    }
+
+    #[test]
+    fn test_bisection_fallback_on_revert() {
+        let config = config();
+        let limit = U256::from(SCALE * 3 / 10);
+        // Quote revert khi vượt liquidity → đỉnh thực tế bị chặn ở `limit`
+        let mut quoter = |amount_in: U256| {
+            if amount_in > limit {
+                bail!("execution reverted");
+            }
+            concave(amount_in)
+        };
+        let result = optimize(&mut quoter, &config).unwrap();
+        assert_eq!(result.method, SearchMethod::Bisection);
+        assert!(result.amount_in <= limit);
+        assert!(close_to(result.amount_in, limit, config.tolerance * U256::from(2)));
+    }
+
+    #[test]
+    fn test_unprofitable_curve_reports_negative_profit() {
+        // Mất 1% mỗi lần: tối ưu là khối lượng nhỏ nhất
+        let mut quoter = |amount_in: U256| Ok(amount_in * U256::from(99) / U256::from(100));
+        let result = optimize(&mut quoter, &config()).unwrap();
+        assert!(!result.profit.is_positive());
+        assert!(result.amount_in < U256::from(SCALE / 1000));
+    }
+
+    #[test]
+    fn test_all_quotes_failing_is_error() {
+        let mut quoter = |_: U256| -> Result<U256> { bail!("boom") };
+        assert!(optimize(&mut quoter, &config()).is_err());
+    }
+
+    #[test]
+    fn test_native_quoter_skips_dirty_pool() {
+        use crate::core::path_search::PoolEdge;
+        use crate::univ3::pool::SLOT0_SLOT;
+        use crate::univ3::PoolState;
+        use revm::db::EmptyDB;
+
+        let (pool, token0, token1) = (Address::repeat_byte(0xaa), Address::repeat_byte(0x01), Address::repeat_byte(0x02));
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(pool, SLOT0_SLOT, U256::from(1) << 96).unwrap();
+        let mut manager = PoolStateManager::new();
+        manager.track(PoolState::load(&mut db, pool, 3000).unwrap());
+
+        let hop = |token_in, token_out| PoolEdge {
+            pool, token_in, token_out, symbol_in: String::new(), symbol_out: String::new(), fee: 3000,
+        };
+        let cycle = Cycle { hops: vec![hop(token0, token1), hop(token1, token0)] };
+
+        manager.mark_dirty(pool);
+        let mut quoter = NativeCycleQuoter { manager: &mut manager, db: &mut db, cycle: &cycle };
+        let err = quoter.quote(U256::from(1000)).unwrap_err();
+        assert!(err.to_string().contains("dirty"), "{}", err);
+
+        // Sau recover quote lại bình thường (không có liquidity nên hop đầu không ra token nào)
+        manager.mark_clean(&pool);
+        let first_hop = Cycle { hops: cycle.hops[..1].to_vec() };
+        let mut quoter = NativeCycleQuoter { manager: &mut manager, db: &mut db, cycle: &first_hop };
+        assert_eq!(quoter.quote(U256::from(1000)).unwrap(), U256::ZERO);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_eth_call_quoter_uses_pinned_block_with_failover() {
+        use crate::core::mock_rpc::{mock_block, MockRpc};
+        use crate::source::abi::quoteExactInputCall;
+        use alloy::primitives::{aliases::U160, Bytes};
+        use alloy::sol_types::{SolCall, SolValue};
+        use serde_json::{json, Value};
+        use std::str::FromStr;
+        use std::sync::{Arc, Mutex};
+
+        let blocks_seen = Arc::new(Mutex::new(Vec::new()));
+        let seen = blocks_seen.clone();
+        let handler = move |method: &str, params: &Value| match method {
+            "eth_getBlockByNumber" => Ok(mock_block(100, Some(7))),
+            "eth_call" => {
+                seen.lock().unwrap().push(params[1].clone());
+                let input = params[0].get("input").or_else(|| params[0].get("data")).and_then(Value::as_str).unwrap_or_default();
+                let call = quoteExactInputCall::abi_decode(&Bytes::from_str(input).unwrap(), true).unwrap();
+                let amount_out = call.amountIn * U256::from(997) / U256::from(1000);
+                let response = (amount_out, Vec::<U160>::new(), Vec::<u32>::new(), U256::ZERO).abi_encode_params();
+                Ok(json!(Bytes::from(response)))
+            }
+            _ => Err(format!("unexpected method {method}")),
+        };
+        let (down, up) = (MockRpc::spawn(handler.clone()).await, MockRpc::spawn(handler).await);
+        let multi_provider = MultiProvider::new(&[down.url.clone(), up.url.clone()]);
+        multi_provider.pin_block(BlockId::number(100)).await.unwrap();
+        // Endpoint đầu trả 503: quote vẫn ra nhờ failover, và vẫn ở block đã ghim
+        down.fail_with_status(503);
+
+        let mut quoter = EthCallPathQuoter {
+            multi_provider: &multi_provider,
+            from: Address::repeat_byte(0x01),
+            quoter: Address::repeat_byte(0x02),
+            tokens: vec![Address::repeat_byte(0x03), Address::repeat_byte(0x04), Address::repeat_byte(0x03)],
+            fees: vec![U24::from(500), U24::from(3000)],
+            base_fee: 7,
+        };
+        for amount_in in [1_000u64, 2_000_000] {
+            let expected = U256::from(amount_in) * U256::from(997) / U256::from(1000);
+            assert_eq!(quoter.quote(U256::from(amount_in)).unwrap(), expected);
+        }
+
+        let seen = blocks_seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen.iter().all(|block| *block == json!("0x64")), "{:?}", seen);
+    }
+}
@@ -0,0 +1,251 @@
+use alloy::primitives::{aliases::U24, Address, I256, U256};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::types::PoolConfig;
+
+/// Một chiều swap qua pool: `token_in` → `token_out`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolEdge {
+    pub pool: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub symbol_in: String,
+    pub symbol_out: String,
+    pub fee: u32,
+}
+
+/// Chuỗi swap bắt đầu và kết thúc ở cùng một token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub hops: Vec<PoolEdge>,
+}
+
+impl Cycle {
+    pub fn len(&self) -> usize {
+        self.hops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hops.is_empty()
+    }
+
+    /// Token theo thứ tự đi qua, gồm cả token đầu ở cuối (dùng cho `encode_path`)
+    pub fn tokens(&self) -> Vec<Address> {
+        let mut tokens: Vec<Address> = self.hops.iter().map(|hop| hop.token_in).collect();
+        tokens.extend(self.hops.last().map(|hop| hop.token_out));
+        tokens
+    }
+
+    pub fn fees(&self) -> Vec<U24> {
+        self.hops.iter().map(|hop| U24::from(hop.fee)).collect()
+    }
+
+    /// Quote cả cycle bằng hàm quote từng hop; output hop trước là input hop sau
+    pub fn simulate<F>(&self, amount_in: U256, mut quote_hop: F) -> Result<U256>
+    where
+        F: FnMut(&PoolEdge, U256) -> Result<U256>,
+    {
+        let mut amount = amount_in;
+        for hop in &self.hops {
+            amount = quote_hop(hop, amount)?;
+        }
+        Ok(amount)
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(first) = self.hops.first() {
+            write!(f, "{}", first.symbol_in)?;
+        }
+        for hop in &self.hops {
+            write!(f, " -({})-> {}", hop.fee, hop.symbol_out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Đồ thị token / pool dựng từ `[[pools]]` của chain config, mỗi pool là hai cạnh ngược chiều
+#[derive(Debug, Clone, Default)]
+pub struct PoolGraph {
+    edges: HashMap<Address, Vec<PoolEdge>>,
+}
+
+impl PoolGraph {
+    pub fn from_pools(pools: &[PoolConfig]) -> Self {
+        let mut edges: HashMap<Address, Vec<PoolEdge>> = HashMap::new();
+        for pool in pools {
+            for (token_in, token_out, symbol_in, symbol_out) in [
+                (pool.token0, pool.token1, &pool.symbol0, &pool.symbol1),
+                (pool.token1, pool.token0, &pool.symbol1, &pool.symbol0),
+            ] {
+                edges.entry(token_in).or_default().push(PoolEdge {
+                    pool: pool.address,
+                    token_in,
+                    token_out,
+                    symbol_in: symbol_in.clone(),
+                    symbol_out: symbol_out.clone(),
+                    fee: pool.fee,
+                });
+            }
+        }
+        PoolGraph { edges }
+    }
+
+    /// Mọi cycle từ `start` quay về `start` với số hop trong `[min_hops, max_hops]`.
+    /// Không đi lại token trung gian và không dùng một pool hai lần.
+    pub fn cycles(&self, start: Address, min_hops: usize, max_hops: usize) -> Vec<Cycle> {
+        let mut found = Vec::new();
+        let mut path = Vec::new();
+        self.walk(start, start, min_hops, max_hops, &mut path, &mut found);
+        found
+    }
+
+    fn walk(
+        &self,
+        start: Address,
+        current: Address,
+        min_hops: usize,
+        max_hops: usize,
+        path: &mut Vec<PoolEdge>,
+        found: &mut Vec<Cycle>,
+    ) {
+        if path.len() == max_hops {
+            return;
+        }
+        for edge in self.edges.get(&current).into_iter().flatten() {
+            if path.iter().any(|hop| hop.pool == edge.pool) {
+                continue;
+            }
+            if edge.token_out == start {
+                if path.len() + 1 >= min_hops {
+                    let mut hops = path.clone();
+                    hops.push(edge.clone());
+                    found.push(Cycle { hops });
+                }
+                continue;
+            }
+            if path.iter().any(|hop| hop.token_in == edge.token_out) {
+                continue;
+            }
+            path.push(edge.clone());
+            self.walk(start, edge.token_out, min_hops, max_hops, path, found);
+            path.pop();
+        }
+    }
+}
+
+/// Kết quả tốt nhất của một cycle
+#[derive(Debug, Clone)]
+pub struct CycleResult {
+    pub cycle: Cycle,
+    pub best_amount_in: U256,
+    pub amount_out: U256,
+    /// `amount_out - amount_in` (chưa trừ gas, xem `OpportunityEvaluator`), có thể âm
+    pub gross_profit: I256,
+}
+
+impl CycleResult {
+    pub fn new(cycle: Cycle, amount_in: U256, amount_out: U256) -> Self {
+        let gross_profit = I256::from_raw(amount_out) - I256::from_raw(amount_in);
+        CycleResult { cycle, best_amount_in: amount_in, amount_out, gross_profit }
+    }
+}
+
+/// Sắp xếp cycle theo lợi nhuận gộp (chưa trừ gas) giảm dần
+pub fn rank_cycles(mut results: Vec<CycleResult>) -> Vec<CycleResult> {
+    results.sort_by_key(|result| std::cmp::Reverse(result.gross_profit));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Protocol;
+
+    fn token(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn pool(byte: u8, a: u8, b: u8, fee: u32) -> PoolConfig {
+        PoolConfig {
+            protocol: Protocol::UniV3,
+            address: Address::repeat_byte(byte),
+            token0: token(a),
+            token1: token(b),
+            symbol0: format!("T{a}"),
+            symbol1: format!("T{b}"),
+            fee,
+            tick_spacing: None,
+        }
+    }
+
+    /// WETH(1) / USDC(2) / USDT(3): giống bộ pool trong `eth.toml`
+    fn eth_like_graph() -> PoolGraph {
+        PoolGraph::from_pools(&[
+            pool(0xa0, 2, 1, 500),
+            pool(0xa1, 2, 1, 3000),
+            pool(0xa2, 1, 3, 500),
+            pool(0xa3, 1, 3, 3000),
+            pool(0xa4, 2, 3, 100),
+        ])
+    }
+
+    #[test]
+    fn test_two_hop_cycles_use_distinct_pools() {
+        let cycles = eth_like_graph().cycles(token(1), 2, 2);
+        // (500, 3000) và (3000, 500) cho mỗi stable
+        assert_eq!(cycles.len(), 4);
+        for cycle in &cycles {
+            assert_ne!(cycle.hops[0].pool, cycle.hops[1].pool);
+            assert_eq!(cycle.tokens().first(), cycle.tokens().last());
+        }
+    }
+
+    #[test]
+    fn test_three_and_four_hop_cycles() {
+        let graph = eth_like_graph();
+        let three = graph.cycles(token(1), 3, 3);
+        // WETH → USDC → USDT → WETH và chiều ngược lại, 2 pool WETH mỗi đầu
+        assert_eq!(three.len(), 8);
+        assert!(three.iter().all(|cycle| cycle.len() == 3));
+        // Chỉ có 3 token nên không có cycle 4 hop đơn
+        assert!(graph.cycles(token(1), 4, 4).is_empty());
+        assert_eq!(graph.cycles(token(1), 2, 4).len(), 12);
+
+        // Vòng vuông 1 → 2 → 3 → 4 → 1: đúng một cycle 4 hop mỗi chiều
+        let square = PoolGraph::from_pools(&[
+            pool(0xb0, 1, 2, 500),
+            pool(0xb1, 2, 3, 500),
+            pool(0xb2, 3, 4, 500),
+            pool(0xb3, 4, 1, 500),
+        ]);
+        let four = square.cycles(token(1), 2, 4);
+        assert_eq!(four.len(), 2);
+        assert!(four.iter().all(|cycle| cycle.len() == 4));
+        assert_eq!(four[0].tokens().len(), 5);
+    }
+
+    #[test]
+    fn test_cycle_simulation_and_ranking() {
+        let cycles = eth_like_graph().cycles(token(1), 2, 2);
+        let results: Vec<CycleResult> = cycles
+            .into_iter()
+            .map(|cycle| {
+                // Hop qua pool fee 500 được lợi 1%, fee 3000 lỗ 1%
+                let out = cycle
+                    .simulate(U256::from(1000), |hop, amount| {
+                        Ok(if hop.fee == 500 { amount * U256::from(101) / U256::from(100) } else { amount * U256::from(99) / U256::from(100) })
+                    })
+                    .unwrap();
+                CycleResult::new(cycle, U256::from(1000), out)
+            })
+            .collect();
+        let ranked = rank_cycles(results);
+        assert!(ranked.windows(2).all(|pair| pair[0].gross_profit >= pair[1].gross_profit));
+        assert_eq!(ranked[0].gross_profit, I256::try_from(-1).unwrap());
+        assert_eq!(ranked[0].cycle.to_string().matches("->").count(), 2);
+    }
+}
@@ -0,0 +1,434 @@
+//! Tìm pool UniV3 on-chain cho allowlist token trong `[[tokens]]`: `getPool` trên factory với mọi fee tier
+//! và/hoặc quét event `PoolCreated` theo khoảng block, lọc theo `liquidity()`, rồi ghi registry TOML
+//! mà `load_chain_config` đọc qua key `pool_registry`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, Bytes, B256},
+    providers::Provider,
+    rpc::types::{Filter, TransactionRequest},
+    sol_types::SolEvent,
+};
+use anyhow::{anyhow, bail, Result};
+
+use crate::core::provider::{MultiProvider, PinnedBlock};
+use crate::source::abi::{
+    decode_fee_tick_spacing_response, decode_get_pool_response, decode_liquidity_response, fee_tick_spacing_calldata,
+    get_pool_calldata, liquidity_calldata, IUniswapV3Factory::PoolCreated,
+};
+use crate::types::{ChainConfig, PoolConfigRaw, PoolRegistry, Protocol, TokenConfig};
+
+/// Fee tier mặc định của `UniswapV3Factory`
+pub const FEE_TIERS: [u32; 4] = [100, 500, 3000, 10000];
+
+/// Số block tối đa mỗi `eth_getLogs` (nhiều RPC public giới hạn khoảng block)
+pub const LOG_CHUNK: u64 = 10_000;
+
+/// Pool tìm được trên factory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPool {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub liquidity: u128,
+}
+
+impl fmt::Display for DiscoveredPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {:?}/{:?} fee={} spacing={} liquidity={}",
+            self.address, self.token0, self.token1, self.fee, self.tick_spacing, self.liquidity
+        )
+    }
+}
+
+/// Tham số tìm pool
+#[derive(Debug, Clone)]
+pub struct DiscoverConfig {
+    /// Factory thay cho `factory` trong chain config
+    pub factory: Option<Address>,
+    /// Fee tier thử `getPool`
+    pub fees: Vec<u32>,
+    /// Block bắt đầu quét `PoolCreated` (tới block ghim); `None` thì chỉ dùng `getPool`
+    pub from_block: Option<u64>,
+    pub log_chunk: u64,
+    /// Bỏ pool có `liquidity()` nhỏ hơn ngưỡng này
+    pub min_liquidity: u128,
+    /// File registry thay cho `pool_registry` trong chain config
+    pub registry: Option<PathBuf>,
+}
+
+impl Default for DiscoverConfig {
+    fn default() -> Self {
+        DiscoverConfig {
+            factory: None,
+            fees: FEE_TIERS.to_vec(),
+            from_block: None,
+            log_chunk: LOG_CHUNK,
+            min_liquidity: 1,
+            registry: None,
+        }
+    }
+}
+
+async fn call(multi_provider: &MultiProvider, to: Address, calldata: Bytes, block: BlockId) -> Result<Bytes> {
+    let tx = TransactionRequest::default().to(to).input(calldata.into());
+    multi_provider
+        .request(|provider| {
+            let tx = tx.clone();
+            async move { provider.call(&tx).block(block).await }
+        })
+        .await
+}
+
+/// `getPool` cho mọi cặp token × fee tier; fee tier factory chưa bật (`feeAmountTickSpacing` = 0) bị bỏ qua
+pub async fn discover_by_get_pool(
+    multi_provider: &MultiProvider,
+    factory: Address,
+    tokens: &[Address],
+    fees: &[u32],
+    block: BlockId,
+) -> Result<Vec<DiscoveredPool>> {
+    let mut tiers = Vec::new();
+    for fee in fees {
+        let response = call(multi_provider, factory, fee_tick_spacing_calldata(*fee), block).await?;
+        match decode_fee_tick_spacing_response(response)? {
+            0 => println!("⚠️ Fee tier {} not enabled on factory {:?}", fee, factory),
+            tick_spacing => tiers.push((*fee, tick_spacing)),
+        }
+    }
+
+    let mut pools = Vec::new();
+    for (index, token_a) in tokens.iter().enumerate() {
+        for token_b in &tokens[index + 1..] {
+            let (token0, token1) = if token_a < token_b { (*token_a, *token_b) } else { (*token_b, *token_a) };
+            for (fee, tick_spacing) in &tiers {
+                let response = call(multi_provider, factory, get_pool_calldata(token0, token1, *fee), block).await?;
+                let address = decode_get_pool_response(response)?;
+                if !address.is_zero() {
+                    pools.push(DiscoveredPool { address, token0, token1, fee: *fee, tick_spacing: *tick_spacing, liquidity: 0 });
+                }
+            }
+        }
+    }
+    Ok(pools)
+}
+
+/// Quét `PoolCreated` của factory trong `[from_block, to_block]` theo từng đoạn `chunk` block,
+/// chỉ giữ pool có cả hai token trong allowlist
+pub async fn discover_by_logs(
+    multi_provider: &MultiProvider,
+    factory: Address,
+    tokens: &[Address],
+    from_block: u64,
+    to_block: u64,
+    chunk: u64,
+) -> Result<Vec<DiscoveredPool>> {
+    // Lọc token0 / token1 (topic 1, 2) ngay trên node
+    let token_topics: Vec<B256> = tokens.iter().map(|token| token.into_word()).collect();
+    let mut pools = Vec::new();
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start.saturating_add(chunk.max(1) - 1));
+        let filter = Filter::new()
+            .address(factory)
+            .event_signature(PoolCreated::SIGNATURE_HASH)
+            .topic1(token_topics.clone())
+            .topic2(token_topics.clone())
+            .from_block(start)
+            .to_block(end);
+        let logs = multi_provider
+            .request(|provider| {
+                let filter = filter.clone();
+                async move { provider.get_logs(&filter).await }
+            })
+            .await?;
+        for log in logs.iter().filter(|log| !log.removed && log.address() == factory) {
+            let event = PoolCreated::decode_log_data(&log.inner.data, true)?;
+            if tokens.contains(&event.token0) && tokens.contains(&event.token1) {
+                pools.push(DiscoveredPool {
+                    address: event.pool,
+                    token0: event.token0,
+                    token1: event.token1,
+                    fee: event.fee.to::<u32>(),
+                    tick_spacing: event.tickSpacing.as_i32(),
+                    liquidity: 0,
+                });
+            }
+        }
+        start = end + 1;
+    }
+    Ok(pools)
+}
+
+/// Đọc `liquidity()` (liquidity đang active ở tick hiện tại) của từng pool tại `block`
+pub async fn fill_liquidity(multi_provider: &MultiProvider, pools: &mut [DiscoveredPool], block: BlockId) -> Result<()> {
+    for pool in pools.iter_mut() {
+        pool.liquidity = decode_liquidity_response(call(multi_provider, pool.address, liquidity_calldata(), block).await?)?;
+    }
+    Ok(())
+}
+
+/// Bỏ trùng theo address, sắp theo (token0, token1, fee) để registry ổn định giữa các lần chạy
+pub fn merge_pools(pools: impl IntoIterator<Item = DiscoveredPool>) -> Vec<DiscoveredPool> {
+    let unique: BTreeMap<Address, DiscoveredPool> = pools.into_iter().map(|pool| (pool.address, pool)).collect();
+    let mut pools: Vec<_> = unique.into_values().collect();
+    pools.sort_by_key(|pool| (pool.token0, pool.token1, pool.fee));
+    pools
+}
+
+/// Registry TOML với token ghi bằng symbol trong `[[tokens]]`
+pub fn to_registry(chain_id: u64, factory: Address, block: u64, pools: &[DiscoveredPool], tokens: &[TokenConfig]) -> Result<PoolRegistry> {
+    let symbol = |address: Address| {
+        tokens.iter().find(|token| token.address == address).map(|token| token.symbol.clone())
+            .ok_or_else(|| anyhow!("Token {:?} is not in [[tokens]]", address))
+    };
+    let pools = pools
+        .iter()
+        .map(|pool| {
+            Ok(PoolConfigRaw {
+                protocol: Protocol::UniV3,
+                address: pool.address.to_string(),
+                token0: symbol(pool.token0)?,
+                token1: symbol(pool.token1)?,
+                fee: pool.fee,
+                tick_spacing: Some(pool.tick_spacing),
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(PoolRegistry { chain_id, factory: factory.to_string(), block, pools })
+}
+
+/// Tìm pool của mọi cặp token trong `[[tokens]]` tại block đã ghim, lọc theo liquidity rồi ghi registry
+pub async fn discover_pools(
+    config: &ChainConfig,
+    multi_provider: &MultiProvider,
+    pinned: &PinnedBlock,
+    discover: &DiscoverConfig,
+) -> Result<Vec<DiscoveredPool>> {
+    let factory = discover.factory.or(config.factory)
+        .ok_or_else(|| anyhow!("No factory in chain config, pass --factory"))?;
+    let registry = discover.registry.clone().or_else(|| config.pool_registry.clone())
+        .ok_or_else(|| anyhow!("No pool_registry in chain config, pass --registry"))?;
+    if config.tokens.len() < 2 {
+        bail!("Need at least two [[tokens]] to discover pools");
+    }
+    let tokens: Vec<Address> = config.tokens.iter().map(|token| token.address).collect();
+
+    let mut found = discover_by_get_pool(multi_provider, factory, &tokens, &discover.fees, pinned.id()).await?;
+    println!("🔎 getPool: {} pools for {} tokens × {} fee tiers", found.len(), tokens.len(), discover.fees.len());
+    if let Some(from_block) = discover.from_block {
+        let created = discover_by_logs(multi_provider, factory, &tokens, from_block, pinned.number, discover.log_chunk).await?;
+        println!("🔎 PoolCreated {}..={}: {} pools", from_block, pinned.number, created.len());
+        found.extend(created);
+    }
+
+    let mut pools = merge_pools(found);
+    fill_liquidity(multi_provider, &mut pools, pinned.id()).await?;
+    let total = pools.len();
+    pools.retain(|pool| pool.liquidity >= discover.min_liquidity);
+    for pool in &pools {
+        println!("{}", pool);
+    }
+    println!("{} / {} pools with liquidity >= {}", pools.len(), total, discover.min_liquidity);
+
+    to_registry(config.chain_id, factory, pinned.number, &pools, &config.tokens)?.save(&registry)?;
+    println!("📝 Wrote pool registry {}", registry.display());
+    Ok(pools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_rpc::{mock_block, MockRpc};
+    use crate::source::abi::{liquidityCall, IUniswapV3Factory};
+    use crate::types::load_chain_config;
+    use alloy::primitives::{aliases::{I24, U24}, LogData};
+    use alloy::sol_types::{SolCall, SolValue};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    const FACTORY: Address = Address::repeat_byte(0xfa);
+    const TOKEN_A: Address = Address::repeat_byte(0x0a);
+    const TOKEN_B: Address = Address::repeat_byte(0x0b);
+    const TOKEN_C: Address = Address::repeat_byte(0x0c);
+    const OUTSIDER: Address = Address::repeat_byte(0x0d);
+    const POOL_AB_500: Address = Address::repeat_byte(0xa5);
+    const POOL_AB_3000: Address = Address::repeat_byte(0xa3);
+    const POOL_BC_100: Address = Address::repeat_byte(0xb1);
+    const POOL_CD_500: Address = Address::repeat_byte(0xc5);
+
+    fn encoded(value: impl SolValue) -> Value {
+        json!(Bytes::from(value.abi_encode()))
+    }
+
+    fn pool_created(token0: Address, token1: Address, fee: u32, tick_spacing: i32, pool: Address, block: u64) -> Value {
+        let event = PoolCreated { token0, token1, fee: U24::from(fee), tickSpacing: I24::try_from(tick_spacing).unwrap(), pool };
+        let data: LogData = event.encode_log_data();
+        json!({
+            "address": FACTORY,
+            "topics": data.topics(),
+            "data": data.data,
+            "blockHash": B256::repeat_byte(block as u8),
+            "blockNumber": format!("{:#x}", block),
+            "transactionHash": B256::repeat_byte(0xee),
+            "transactionIndex": "0x0",
+            "logIndex": "0x0",
+            "removed": false,
+        })
+    }
+
+    /// Factory có pool A/B (500, 3000 — pool 3000 không có liquidity) và B/C (100); fee 10000 chưa bật.
+    /// `PoolCreated` ở block 10, 25, 40 và pool C/D với token ngoài allowlist ở block 30.
+    /// Ghi lại khoảng block của từng `eth_getLogs`.
+    async fn mock_factory(ranges: Arc<Mutex<Vec<(u64, u64)>>>) -> MockRpc {
+        MockRpc::spawn(move |method, params: &Value| match method {
+            "eth_getBlockByNumber" => Ok(mock_block(100, Some(1))),
+            "eth_call" => {
+                let tx = &params[0];
+                let to: Address = serde_json::from_value(tx["to"].clone()).unwrap();
+                let input: Bytes = serde_json::from_value(tx.get("input").or(tx.get("data")).unwrap().clone()).unwrap();
+                if to != FACTORY {
+                    let liquidity: u128 = if to == POOL_AB_3000 { 0 } else { 1_000_000 };
+                    assert_eq!(input.as_ref(), liquidityCall {}.abi_encode().as_slice());
+                    return Ok(encoded(liquidity));
+                }
+                if let Ok(call) = IUniswapV3Factory::feeAmountTickSpacingCall::abi_decode(&input, true) {
+                    let spacing = match call.fee.to::<u32>() { 100 => 1, 500 => 10, 3000 => 60, _ => 0 };
+                    return Ok(encoded(I24::try_from(spacing).unwrap()));
+                }
+                let call = IUniswapV3Factory::getPoolCall::abi_decode(&input, true).map_err(|e| e.to_string())?;
+                assert!(call.tokenA < call.tokenB);
+                let pool = match (call.tokenA, call.tokenB, call.fee.to::<u32>()) {
+                    (TOKEN_A, TOKEN_B, 500) => POOL_AB_500,
+                    (TOKEN_A, TOKEN_B, 3000) => POOL_AB_3000,
+                    (TOKEN_B, TOKEN_C, 100) => POOL_BC_100,
+                    _ => Address::ZERO,
+                };
+                Ok(encoded(pool))
+            }
+            "eth_getLogs" => {
+                let block = |key: &str| u64::from_str_radix(params[0][key].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                let (from, to) = (block("fromBlock"), block("toBlock"));
+                ranges.lock().unwrap().push((from, to));
+                let logs: Vec<Value> = [
+                    pool_created(TOKEN_A, TOKEN_B, 500, 10, POOL_AB_500, 10),
+                    pool_created(TOKEN_B, TOKEN_C, 100, 1, POOL_BC_100, 25),
+                    pool_created(TOKEN_C, OUTSIDER, 500, 10, POOL_CD_500, 30),
+                    pool_created(TOKEN_A, TOKEN_B, 3000, 60, POOL_AB_3000, 40),
+                ]
+                .into_iter()
+                .filter(|log| (from..=to).contains(&u64::from_str_radix(log["blockNumber"].as_str().unwrap().trim_start_matches("0x"), 16).unwrap()))
+                .collect();
+                Ok(json!(logs))
+            }
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("uv3-pool-discovery-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_get_pool_skips_missing_pairs_and_disabled_tiers() {
+        let rpc = mock_factory(Arc::default()).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&rpc.url));
+        // Token truyền lộn thứ tự: getPool luôn nhận token0 < token1
+        let pools = discover_by_get_pool(&multi_provider, FACTORY, &[TOKEN_C, TOKEN_B, TOKEN_A], &FEE_TIERS, BlockId::latest()).await.unwrap();
+        let found: Vec<_> = merge_pools(pools).into_iter().map(|pool| (pool.address, pool.fee, pool.tick_spacing)).collect();
+        assert_eq!(found, vec![(POOL_AB_500, 500, 10), (POOL_AB_3000, 3000, 60), (POOL_BC_100, 100, 1)]);
+        // 4 feeAmountTickSpacing + 3 cặp × 3 fee tier đã bật
+        assert_eq!(rpc.calls(), 4 + 3 * 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logs_are_chunked_and_filtered_by_allowlist() {
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let rpc = mock_factory(ranges.clone()).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&rpc.url));
+        let pools = discover_by_logs(&multi_provider, FACTORY, &[TOKEN_A, TOKEN_B, TOKEN_C], 5, 34, 10).await.unwrap();
+        assert_eq!(*ranges.lock().unwrap(), vec![(5, 14), (15, 24), (25, 34)]);
+        // Pool C/D có token ngoài allowlist, pool A/B 3000 tạo sau block 34
+        let found: Vec<_> = pools.iter().map(|pool| (pool.address, pool.token0, pool.token1, pool.fee)).collect();
+        assert_eq!(found, vec![(POOL_AB_500, TOKEN_A, TOKEN_B, 500), (POOL_BC_100, TOKEN_B, TOKEN_C, 100)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_discovered_registry_is_loaded_by_chain_config() {
+        let dir = temp_dir("registry");
+        let rpc = mock_factory(Arc::default()).await;
+        let config_path = dir.join("test.toml");
+        std::fs::write(&config_path, format!(
+            r#"
+chain_id = 31337
+rpc_url = "{url}"
+gas_multiplier = 1.0
+me = "0x0000000000000000000000000000000000000001"
+factory = "{FACTORY}"
+pool_registry = "test.pools.toml"
+
+[defaults]
+native_token = "A"
+stable_token = "B"
+
+[[tokens]]
+symbol = "A"
+address = "{TOKEN_A}"
+decimals = 18
+
+[[tokens]]
+symbol = "B"
+address = "{TOKEN_B}"
+decimals = 6
+
+[[tokens]]
+symbol = "C"
+address = "{TOKEN_C}"
+decimals = 6
+
+# Khai báo tay: registry không ghi đè
+[[pools]]
+protocol = "univ3"
+address = "{POOL_BC_100}"
+token0 = "B"
+token1 = "C"
+fee = 100
+"#,
+            url = rpc.url,
+        )).unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        let config = load_chain_config(config_path).unwrap();
+        assert_eq!(config.pools.len(), 1);
+        assert_eq!(config.pool_registry, Some(dir.join("test.pools.toml")));
+
+        let multi_provider = MultiProvider::new(&config.rpc_urls);
+        let pinned = multi_provider.pin_block(BlockId::latest()).await.unwrap();
+        let discover = DiscoverConfig { from_block: Some(0), log_chunk: 1_000, ..Default::default() };
+        let pools = discover_pools(&config, &multi_provider, &pinned, &discover).await.unwrap();
+        // Pool A/B 3000 không có liquidity bị lọc
+        let found: Vec<_> = pools.iter().map(|pool| pool.address).collect();
+        assert_eq!(found, vec![POOL_AB_500, POOL_BC_100]);
+
+        let registry = PoolRegistry::load(&dir.join("test.pools.toml")).unwrap();
+        assert_eq!((registry.chain_id, registry.block, registry.pools.len()), (31337, 100, 2));
+
+        let config = load_chain_config(config_path).unwrap();
+        let pool = config.pool(TOKEN_A, TOKEN_B, 500).unwrap();
+        assert_eq!((pool.address, pool.symbol0.as_str(), pool.symbol1.as_str(), pool.tick_spacing), (POOL_AB_500, "A", "B", Some(10)));
+        assert_eq!(config.pools.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
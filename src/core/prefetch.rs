@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, Bytes, B256, U256, U64},
+    providers::{Provider, RootProvider},
+    rpc::client::{BatchRequest, Waiter},
+    rpc::types::EIP1186AccountProofResponse,
+    transports::http::{Client, Http},
+    transports::TransportResult,
+};
+use anyhow::Result;
+use revm::db::{CacheDB, DatabaseRef};
+use revm::primitives::{AccountInfo, Bytecode};
+
+use crate::core::db_persistent::PersistentState;
+use crate::core::logger::{measure_end, measure_start};
+use crate::core::provider::MultiProvider;
+use crate::univ3::pool::{decode_slot0, mapping_slot, tick_spacing_for_fee, LIQUIDITY_SLOT, SLOT0_SLOT, TICK_BITMAP_SLOT};
+use crate::univ3::tick_bitmap::word_of;
+
+/// Cách lấy storage khi prefetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrefetchMode {
+    /// `eth_getStorageAt` (+ balance / nonce / code cho account chưa có) gom trong JSON-RPC batch
+    #[default]
+    Batch,
+    /// Một `eth_getProof` cho mỗi account (mọi slot cùng lúc), cũng gom batch. Proof không được verify.
+    Proof,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrefetchConfig {
+    pub mode: PrefetchMode,
+    /// Số JSON-RPC call tối đa trong một HTTP batch
+    pub batch_size: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        PrefetchConfig { mode: PrefetchMode::Batch, batch_size: 100 }
+    }
+}
+
+/// Thống kê một lần prefetch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrefetchReport {
+    /// Cặp (address, slot) được yêu cầu (đã bỏ trùng)
+    pub requested: usize,
+    /// Slot đã có sẵn trong cache, không fetch
+    pub already_cached: usize,
+    /// Slot nạp từ cache trên đĩa (`PersistentDB`) của lần chạy trước, không fetch
+    pub from_disk: usize,
+    pub slots_fetched: usize,
+    /// Account chưa có trong cache, được nạp info (balance, nonce, code)
+    pub accounts_fetched: usize,
+    /// JSON-RPC call đã gửi
+    pub calls: usize,
+    /// HTTP request (batch) đã gửi
+    pub round_trips: usize,
+    pub elapsed: Duration,
+}
+
+impl PrefetchReport {
+    /// Round-trip `AlloyDB` sẽ tốn nếu load lazily: một cho mỗi slot, một cho mỗi `basic` account
+    pub fn lazy_round_trips(&self) -> usize {
+        self.slots_fetched + self.accounts_fetched
+    }
+
+    pub fn round_trips_saved(&self) -> usize {
+        self.lazy_round_trips().saturating_sub(self.round_trips)
+    }
+
+    pub fn merge(&mut self, other: &PrefetchReport) {
+        self.requested += other.requested;
+        self.already_cached += other.already_cached;
+        self.from_disk += other.from_disk;
+        self.slots_fetched += other.slots_fetched;
+        self.accounts_fetched += other.accounts_fetched;
+        self.calls += other.calls;
+        self.round_trips += other.round_trips;
+        self.elapsed += other.elapsed;
+    }
+}
+
+impl fmt::Display for PrefetchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} slots + {} accounts in {} calls / {} round-trips ({} already cached, {} from disk), saved {} of {} lazy round-trips in {:.2?}",
+            self.slots_fetched,
+            self.accounts_fetched,
+            self.calls,
+            self.round_trips,
+            self.already_cached,
+            self.from_disk,
+            self.round_trips_saved(),
+            self.lazy_round_trips(),
+            self.elapsed
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Call {
+    Nonce(Address),
+    Balance(Address),
+    Code(Address),
+    Storage(Address, U256),
+    Proof(Address, Vec<U256>),
+}
+
+enum Pending {
+    Nonce(Address, Waiter<U64>),
+    Balance(Address, Waiter<U256>),
+    Code(Address, Waiter<Bytes>),
+    Storage(Address, U256, Waiter<U256>),
+    Proof(Address, Vec<U256>, Waiter<EIP1186AccountProofResponse>),
+}
+
+enum Answer {
+    Nonce(Address, u64),
+    Balance(Address, U256),
+    Code(Address, Bytes),
+    Storage(Address, U256, U256),
+    Proof(Address, Vec<U256>, EIP1186AccountProofResponse),
+}
+
+/// Gửi `calls` trong một HTTP batch
+async fn send_batch(
+    provider: Arc<RootProvider<Http<Client>>>,
+    block: BlockId,
+    calls: &[Call],
+) -> TransportResult<Vec<Answer>> {
+    let client = provider.client();
+    let mut batch = BatchRequest::new(client);
+    let mut pending = Vec::with_capacity(calls.len());
+    for call in calls {
+        pending.push(match call {
+            Call::Nonce(address) => Pending::Nonce(*address, batch.add_call("eth_getTransactionCount", &(address, block))?),
+            Call::Balance(address) => Pending::Balance(*address, batch.add_call("eth_getBalance", &(address, block))?),
+            Call::Code(address) => Pending::Code(*address, batch.add_call("eth_getCode", &(address, block))?),
+            Call::Storage(address, slot) => {
+                Pending::Storage(*address, *slot, batch.add_call("eth_getStorageAt", &(address, slot, block))?)
+            }
+            Call::Proof(address, slots) => {
+                let keys: Vec<B256> = slots.iter().map(|slot| B256::from(*slot)).collect();
+                Pending::Proof(*address, slots.clone(), batch.add_call("eth_getProof", &(address, keys, block))?)
+            }
+        });
+    }
+    batch.send().await?;
+
+    let mut answers = Vec::with_capacity(pending.len());
+    for waiter in pending {
+        answers.push(match waiter {
+            Pending::Nonce(address, waiter) => Answer::Nonce(address, waiter.await?.to::<u64>()),
+            Pending::Balance(address, waiter) => Answer::Balance(address, waiter.await?),
+            Pending::Code(address, waiter) => Answer::Code(address, waiter.await?),
+            Pending::Storage(address, slot, waiter) => Answer::Storage(address, slot, waiter.await?),
+            Pending::Proof(address, slots, waiter) => Answer::Proof(address, slots, waiter.await?),
+        });
+    }
+    Ok(answers)
+}
+
+/// Nạp trước các cặp (address, slot) vào `cache_db` bằng JSON-RPC batch (hoặc `eth_getProof`)
+/// tại `block`, thay cho từng round-trip lẻ mà `AlloyDB` sẽ gọi khi REVM chạm slot lần đầu.
+/// Account chưa có trong cache được nạp info luôn (nếu không `insert_account_storage` sẽ gọi `basic`).
+/// State đã lưu trên đĩa ở lần chạy trước (cùng chain + block) được nạp thẳng, kết quả fetch được lưu lại.
+pub async fn prefetch_storage<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    multi_provider: &MultiProvider,
+    block: BlockId,
+    slots: &[(Address, U256)],
+    config: &PrefetchConfig,
+) -> Result<PrefetchReport>
+where
+    ExtDB: DatabaseRef + PersistentState,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    prefetch_state(cache_db, multi_provider, block, &[], slots, config).await
+}
+
+/// Như `prefetch_storage`, thêm các account chỉ cần info (EOA, contract không đọc storage)
+pub async fn prefetch_state<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    multi_provider: &MultiProvider,
+    block: BlockId,
+    accounts: &[Address],
+    slots: &[(Address, U256)],
+    config: &PrefetchConfig,
+) -> Result<PrefetchReport>
+where
+    ExtDB: DatabaseRef + PersistentState,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let start = Instant::now();
+    let requested: BTreeSet<(Address, U256)> = slots.iter().copied().collect();
+    let mut report = PrefetchReport { requested: requested.len(), ..Default::default() };
+
+    // 1️⃣ Nạp account đã lưu trên đĩa, bỏ slot đã cache (trong bộ nhớ hoặc trên đĩa), gom theo account
+    let persistent = cache_db.db.persists(block);
+    if persistent {
+        let addresses: BTreeSet<Address> = requested.iter().map(|(address, _)| *address).chain(accounts.iter().copied()).collect();
+        for address in addresses {
+            if cache_db.accounts.contains_key(&address) {
+                continue;
+            }
+            if let Some(info) = cache_db.db.load_account(address) {
+                cache_db.insert_account_info(address, info.unwrap_or_default());
+            }
+        }
+    }
+    let mut missing: BTreeMap<Address, Vec<U256>> = BTreeMap::new();
+    for (address, slot) in requested {
+        let cached = cache_db
+            .accounts
+            .get(&address)
+            .is_some_and(|account| account.storage.contains_key(&slot));
+        if cached {
+            report.already_cached += 1;
+            continue;
+        }
+        let stored = match cache_db.accounts.contains_key(&address) && persistent {
+            true => cache_db.db.load_storage(address, slot),
+            false => None,
+        };
+        match stored {
+            Some(value) => {
+                cache_db.insert_account_storage(address, slot, value)?;
+                report.from_disk += 1;
+            }
+            None => missing.entry(address).or_default().push(slot),
+        }
+    }
+    let new_accounts: Vec<Address> = missing
+        .keys()
+        .chain(accounts)
+        .filter(|address| !cache_db.accounts.contains_key(*address))
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    report.accounts_fetched = new_accounts.len();
+    report.slots_fetched = missing.values().map(Vec::len).sum();
+
+    // 2️⃣ Dựng danh sách call theo mode
+    let mut calls = Vec::new();
+    match config.mode {
+        PrefetchMode::Batch => {
+            for address in &new_accounts {
+                calls.extend([Call::Nonce(*address), Call::Balance(*address), Call::Code(*address)]);
+            }
+            for (address, slots) in &missing {
+                calls.extend(slots.iter().map(|slot| Call::Storage(*address, *slot)));
+            }
+        }
+        PrefetchMode::Proof => {
+            // Proof có balance / nonce / code hash nhưng không có code.
+            // Account mới không thiếu slot nào vẫn cần `eth_getProof(address, [])` để lấy balance / nonce
+            for address in &new_accounts {
+                calls.push(Call::Code(*address));
+                if !missing.contains_key(address) {
+                    calls.push(Call::Proof(*address, Vec::new()));
+                }
+            }
+            for (address, slots) in &missing {
+                calls.push(Call::Proof(*address, slots.clone()));
+            }
+        }
+    }
+    report.calls = calls.len();
+
+    // 3️⃣ Gửi từng batch (qua failover của MultiProvider)
+    let mut infos: BTreeMap<Address, AccountInfo> = new_accounts.iter().map(|address| (*address, AccountInfo::default())).collect();
+    let mut values = Vec::new();
+    for chunk in calls.chunks(config.batch_size.max(1)) {
+        let answers = multi_provider.request(|provider| send_batch(provider, block, chunk)).await?;
+        report.round_trips += 1;
+        for answer in answers {
+            match answer {
+                Answer::Nonce(address, nonce) => infos.entry(address).or_default().nonce = nonce,
+                Answer::Balance(address, balance) => infos.entry(address).or_default().balance = balance,
+                Answer::Code(address, code) => {
+                    let code = Bytecode::new_raw(code);
+                    let info = infos.entry(address).or_default();
+                    info.code_hash = code.hash_slow();
+                    info.code = Some(code);
+                }
+                Answer::Storage(address, slot, value) => values.push((address, slot, value)),
+                Answer::Proof(address, slots, proof) => {
+                    if let Some(info) = infos.get_mut(&address) {
+                        info.balance = proof.balance;
+                        info.nonce = proof.nonce;
+                    }
+                    // storageProof trả đúng thứ tự key đã gửi
+                    values.extend(slots.into_iter().zip(proof.storage_proof).map(|(slot, entry)| (address, slot, entry.value)));
+                }
+            }
+        }
+    }
+
+    // 4️⃣ Ghi vào cache: account trước để `insert_account_storage` không gọi RPC
+    for (address, info) in infos {
+        if persistent {
+            cache_db.db.save_account(address, Some(&info));
+        }
+        cache_db.insert_account_info(address, info);
+    }
+    for (address, slot, value) in values {
+        if persistent {
+            cache_db.db.save_storage(address, slot, value);
+        }
+        cache_db.insert_account_storage(address, slot, value)?;
+    }
+
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+/// Slot mà swap trên pool UniswapV3 đọc tới trước khi đi qua tick: slot0, feeGrowthGlobal0/1,
+/// protocolFees, liquidity và `words_around` word tickBitmap mỗi bên word hiện tại
+pub fn pool_prefetch_slots(pool: Address, tick: i32, tick_spacing: i32, words_around: i16) -> Vec<(Address, U256)> {
+    let mut slots: Vec<(Address, U256)> = (0..=LIQUIDITY_SLOT.to::<u64>()).map(|slot| (pool, U256::from(slot))).collect();
+    let word = word_of(tick, tick_spacing);
+    for word in word.saturating_sub(words_around)..=word.saturating_add(words_around) {
+        slots.push((pool, mapping_slot(word as i32, TICK_BITMAP_SLOT)));
+    }
+    slots
+}
+
+/// Prefetch layout của pool trong hai lượt: slot0 (để biết tick hiện tại) rồi các slot quanh tick
+pub async fn prefetch_pool<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    multi_provider: &MultiProvider,
+    block: BlockId,
+    pool: Address,
+    fee: u32,
+    words_around: i16,
+) -> Result<PrefetchReport>
+where
+    ExtDB: DatabaseRef + PersistentState,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let start = measure_start(&format!("prefetch_pool {:?}", pool));
+    let config = PrefetchConfig::default();
+    let mut report = prefetch_storage(cache_db, multi_provider, block, &[(pool, SLOT0_SLOT)], &config).await?;
+
+    let slot0 = cache_db.accounts[&pool].storage[&SLOT0_SLOT];
+    let (_, tick) = decode_slot0(slot0);
+    let slots = pool_prefetch_slots(pool, tick, tick_spacing_for_fee(fee)?, words_around);
+    report.merge(&prefetch_storage(cache_db, multi_provider, block, &slots, &config).await?);
+    measure_end(start);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::init_cache_db;
+    use crate::core::mock_rpc::MockRpc;
+    use revm::db::Database;
+    use serde_json::{json, Value};
+
+    /// Chain giả: mọi slot có giá trị `slot + 1`, account có balance 5, nonce 2, code `0x6000`
+    fn chain(method: &str, params: &Value) -> Result<Value, String> {
+        let slot_value = |slot: &Value| U256::from_str_radix(slot.as_str().unwrap().trim_start_matches("0x"), 16).unwrap() + U256::from(1);
+        match method {
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", slot_value(&params[1])))),
+            "eth_getBalance" => Ok(json!("0x5")),
+            "eth_getTransactionCount" => Ok(json!("0x2")),
+            "eth_getCode" => Ok(json!("0x6000")),
+            "eth_getProof" => {
+                let storage: Vec<Value> = params[1]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|key| json!({ "key": key, "value": format!("0x{:x}", slot_value(key)), "proof": [] }))
+                    .collect();
+                Ok(json!({
+                    "address": params[0],
+                    "balance": "0x5",
+                    "codeHash": format!("{}", alloy::primitives::keccak256([0x60, 0x00])),
+                    "nonce": "0x2",
+                    "storageHash": format!("{}", B256::ZERO),
+                    "accountProof": [],
+                    "storageProof": storage,
+                }))
+            }
+            _ => Err(format!("unexpected method {method}")),
+        }
+    }
+
+    fn pairs(accounts: usize, slots: usize) -> Vec<(Address, U256)> {
+        (0..accounts)
+            .flat_map(|account| (0..slots).map(move |slot| (Address::repeat_byte(0x10 + account as u8), U256::from(slot * 7))))
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_prefetch_fills_cache() {
+        let mock = MockRpc::spawn(chain).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let mut cache_db = init_cache_db(&multi_provider);
+
+        let config = PrefetchConfig { batch_size: 10, ..Default::default() };
+        let report = prefetch_storage(&mut cache_db, &multi_provider, BlockId::latest(), &pairs(2, 12), &config).await.unwrap();
+        // 2 account × 3 call + 24 slot = 30 call → 3 batch
+        assert_eq!((report.requested, report.slots_fetched, report.accounts_fetched), (24, 24, 2));
+        assert_eq!((report.calls, report.round_trips), (30, 3));
+        assert_eq!(report.round_trips_saved(), 26 - 3);
+        assert_eq!((mock.calls(), mock.http_requests()), (30, 3));
+
+        // Đọc qua REVM không còn chạm RPC
+        let account = Address::repeat_byte(0x11);
+        assert_eq!(cache_db.storage(account, U256::from(77)).unwrap(), U256::from(78));
+        let info = cache_db.basic(account).unwrap().unwrap();
+        assert_eq!((info.balance, info.nonce), (U256::from(5), 2));
+        assert_eq!(info.code.unwrap().original_bytes(), Bytes::from_static(&[0x60, 0x00]));
+        assert_eq!(mock.calls(), 30);
+
+        // Lần hai: mọi slot đã cache, chỉ fetch slot mới (account đã có)
+        let mut more = pairs(2, 12);
+        more.push((account, U256::from(1000)));
+        let report = prefetch_storage(&mut cache_db, &multi_provider, BlockId::latest(), &more, &config).await.unwrap();
+        assert_eq!((report.already_cached, report.slots_fetched, report.accounts_fetched), (24, 1, 0));
+        assert_eq!((report.calls, report.round_trips), (1, 1));
+        assert_eq!(cache_db.storage(account, U256::from(1000)).unwrap(), U256::from(1001));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_proof_prefetch_uses_one_call_per_account() {
+        let mock = MockRpc::spawn(chain).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let mut cache_db = init_cache_db(&multi_provider);
+
+        let config = PrefetchConfig { mode: PrefetchMode::Proof, ..Default::default() };
+        // Thêm một account không đọc slot nào (vd. EOA `from`)
+        let eoa = Address::repeat_byte(0xee);
+        let report = prefetch_state(&mut cache_db, &multi_provider, BlockId::number(100), &[eoa], &pairs(3, 5), &config)
+            .await
+            .unwrap();
+        // 4 eth_getCode + 4 eth_getProof (proof của `eoa` không có key) trong một batch
+        assert_eq!((report.calls, report.round_trips, report.slots_fetched, report.accounts_fetched), (8, 1, 15, 4));
+        assert_eq!(mock.http_requests(), 1);
+
+        let account = Address::repeat_byte(0x12);
+        assert_eq!(cache_db.storage(account, U256::from(28)).unwrap(), U256::from(29));
+        assert_eq!(cache_db.basic(account).unwrap().unwrap().nonce, 2);
+        let info = cache_db.basic(eoa).unwrap().unwrap();
+        assert_eq!((info.balance, info.nonce), (U256::from(5), 2));
+        assert_eq!(mock.calls(), 8);
+    }
+
+    #[test]
+    fn test_pool_prefetch_slots_cover_current_word() {
+        let pool = Address::repeat_byte(0xaa);
+        let slots = pool_prefetch_slots(pool, -887_220, 60, 2);
+        assert_eq!(slots.len(), 5 + 5);
+        assert_eq!(slots[0], (pool, SLOT0_SLOT));
+        assert_eq!(slots[4], (pool, LIQUIDITY_SLOT));
+        let word = word_of(-887_220, 60);
+        assert!(slots.contains(&(pool, mapping_slot(word as i32, TICK_BITMAP_SLOT))));
+        assert!(slots.contains(&(pool, mapping_slot(word as i32 - 2, TICK_BITMAP_SLOT))));
+    }
+}
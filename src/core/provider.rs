@@ -1,28 +1,206 @@
+use std::fmt;
+use std::fmt::Debug;
+use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::primitives::B256;
 use alloy::providers::{Provider, ProviderBuilder, RootProvider}; // Import RootProvider
+use alloy::rpc::types::BlockTransactionsKind;
 use alloy::transports::http::{Http, Client};
-use alloy::network::Ethereum; // Vẫn cần Ethereum nếu Provider yêu cầu Network generic
+use alloy::transports::{RpcError, TransportErrorKind, TransportResult};
+use anyhow::{anyhow, bail, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::core::db::cache_dir;
+use crate::types::ChainConfig;
 
 // Định nghĩa kiểu cố định mà `on_http` trả về
 // Đây là kiểu cụ thể, Sized, và implement Provider
 type ConcreteHttpProvider = RootProvider<Http<Client>>;
 
+/// Số endpoint nhận cùng một read khi hedging
+pub const HEDGE_FANOUT: usize = 2;
+/// Số endpoint phải đồng ý cho read cần quorum: head (`lagged_head`) và storage (`RpcDB::with_storage_quorum`)
+pub const READ_QUORUM: usize = 2;
+
+/// Cách chọn endpoint cho request tiếp theo (endpoint đang cooldown luôn bị bỏ qua)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectionStrategy {
+    /// Lần lượt từng endpoint như trước
+    #[default]
+    RoundRobin,
+    /// Endpoint có EWMA latency thấp nhất, endpoint chưa đo được thử trước
+    LowestLatency,
+    /// Smooth weighted round-robin, trọng số tỉ lệ nghịch latency và giảm theo tỉ lệ lỗi
+    Weighted,
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiProviderConfig {
+    pub strategy: SelectionStrategy,
+    /// Số endpoint khác nhau tối đa được thử cho một request
+    pub max_attempts: usize,
+    /// Thời gian bỏ qua endpoint sau 429 / 5xx hoặc quá nhiều lỗi liên tiếp
+    pub cooldown: Duration,
+    /// Số lỗi transport liên tiếp (timeout, connection refused...) trước khi cooldown
+    pub max_consecutive_errors: u32,
+    /// Hệ số EWMA của latency, càng lớn càng nhạy với mẫu mới
+    pub ewma_alpha: f64,
+}
+
+impl Default for MultiProviderConfig {
+    fn default() -> Self {
+        MultiProviderConfig {
+            strategy: SelectionStrategy::RoundRobin,
+            max_attempts: 3,
+            cooldown: Duration::from_secs(30),
+            max_consecutive_errors: 3,
+            ewma_alpha: 0.3,
+        }
+    }
+}
+
+/// Sức khỏe một endpoint, cập nhật sau mỗi lần gọi qua `MultiProvider::request`
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub consecutive_errors: u32,
+    pub ewma_latency: Option<Duration>,
+    pub cooldown_until: Option<Instant>,
+    pub last_error: Option<String>,
+    /// Trạng thái của smooth weighted round-robin
+    current_weight: f64,
+}
+
+impl EndpointHealth {
+    pub fn in_cooldown(&self, now: Instant) -> bool {
+        self.cooldown_until.is_some_and(|until| now < until)
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.requests as f64
+    }
+
+    /// Trọng số cho `SelectionStrategy::Weighted`: 1 / latency (ms), nhân tỉ lệ thành công
+    fn weight(&self) -> f64 {
+        let latency_ms = self.ewma_latency.map(|latency| latency.as_secs_f64() * 1000.0).unwrap_or(1.0);
+        (1.0 - self.error_rate()).max(0.05) / latency_ms.max(1.0)
+    }
+}
+
+impl fmt::Display for EndpointHealth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} requests, {} errors", self.url, self.requests, self.errors)?;
+        if let Some(latency) = self.ewma_latency {
+            write!(f, ", ewma {:.1?}", latency)?;
+        }
+        if let Some(remaining) = self.cooldown_until.and_then(|until| until.checked_duration_since(Instant::now())) {
+            write!(f, ", cooldown {:.1?}", remaining)?;
+        }
+        Ok(())
+    }
+}
+
+/// Phân loại lỗi RPC để quyết định retry / cooldown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcErrorClass {
+    /// HTTP 429 / 5xx hoặc JSON-RPC error báo rate limit: cooldown ngay, thử endpoint khác
+    Throttled,
+    /// Lỗi kết nối, response hỏng...: tính lỗi, thử endpoint khác
+    Transport,
+    /// Node trả JSON-RPC error bình thường (revert, params sai): endpoint vẫn khỏe, không retry
+    Rejected,
+}
+
+pub fn classify_error(error: &RpcError<TransportErrorKind>) -> RpcErrorClass {
+    match error {
+        RpcError::Transport(TransportErrorKind::HttpError(http)) if http.status == 429 || http.status >= 500 => {
+            RpcErrorClass::Throttled
+        }
+        RpcError::ErrorResp(payload) if payload.is_retry_err() => RpcErrorClass::Throttled,
+        RpcError::ErrorResp(_)
+        | RpcError::SerError(_)
+        | RpcError::UnsupportedFeature(_)
+        | RpcError::LocalUsageError(_) => RpcErrorClass::Rejected,
+        _ => RpcErrorClass::Transport,
+    }
+}
+
+/// Kết quả đọc k-of-n qua `MultiProvider::quorum`
+#[derive(Debug, Clone)]
+pub struct QuorumRead<T> {
+    pub value: T,
+    /// Endpoint trả về đúng `value`
+    pub agreeing: Vec<String>,
+    /// Endpoint trả giá trị khác (vd. node bị trễ block)
+    pub disagreeing: Vec<(String, T)>,
+    /// Endpoint lỗi
+    pub failed: Vec<(String, String)>,
+}
+
+/// Clone dùng chung endpoint, health, bộ đếm round-robin và block đã ghim với bản gốc
+#[derive(Clone)]
 pub struct MultiProvider {
     // Lưu trữ các Arc của kiểu cụ thể này
     providers: Vec<Arc<ConcreteHttpProvider>>,
     urls: Vec<String>,  // NEW: lưu song song URL string
-    counter: AtomicUsize,
+    counter: Arc<AtomicUsize>,
+    config: MultiProviderConfig,
+    health: Arc<Mutex<Vec<EndpointHealth>>>,
+    pinned: Arc<Mutex<Option<PinnedBlock>>>,
+    /// Chain của các endpoint, cần để lưu state qua các lần chạy (`PersistentDB`)
+    chain_id: Option<u64>,
+    /// Thư mục cacache cho bytecode, state đã ghim block, header block đã resolve
+    cache_dir: String,
+}
+
+/// Block đã resolve một lần từ `--block` (số, hash hoặc tag); mọi read của lần chạy dùng block này
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedBlock {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    pub timestamp: u64,
+    pub base_fee: Option<u128>,
+}
+
+impl PinnedBlock {
+    /// Ghim theo số block: mọi endpoint đều hỗ trợ, kể cả node không có EIP-1898 (block hash)
+    pub fn id(&self) -> BlockId {
+        BlockId::number(self.number)
+    }
+}
+
+impl fmt::Display for PinnedBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} ({})", self.number, self.hash)
+    }
 }
 
 impl MultiProvider {
-    // define len 
+    // define len
     pub fn len(&self) -> usize {
         self.providers.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
 
     pub fn new(urls: &[String]) -> Self {
+        Self::with_config(urls, MultiProviderConfig::default())
+    }
+
+    pub fn with_config(urls: &[String], config: MultiProviderConfig) -> Self {
         let providers = urls.iter()
             .map(|url| {
                 let provider = ProviderBuilder::new()
@@ -31,17 +209,697 @@ impl MultiProvider {
                 Arc::new(provider)
             })
             .collect::<Vec<_>>();
+        let health = urls.iter()
+            .map(|url| EndpointHealth { url: url.clone(), ..Default::default() })
+            .collect();
 
         MultiProvider {
             providers,
-            urls: urls.to_vec(), 
-            counter: AtomicUsize::new(0),
+            urls: urls.to_vec(),
+            counter: Arc::new(AtomicUsize::new(0)),
+            config,
+            health: Arc::new(Mutex::new(health)),
+            pinned: Arc::new(Mutex::new(None)),
+            chain_id: None,
+            cache_dir: cache_dir(),
+        }
+    }
+
+    /// Endpoint `rpc_urls` của chain, bật cache state theo `chain_id`
+    pub fn for_chain(config: &ChainConfig) -> Self {
+        Self::new(&config.rpc_urls).with_chain_id(config.chain_id)
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<String>) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    pub fn cache_dir(&self) -> &str {
+        &self.cache_dir
+    }
+
+    pub fn strategy(&self) -> SelectionStrategy {
+        self.config.strategy
+    }
+
+    /// Bản sao trạng thái sức khỏe của mọi endpoint (cùng thứ tự `rpc_urls`)
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Resolve `block` (số, hash hoặc tag như `latest` / `finalized`) đúng một lần rồi ghim:
+    /// từ đó `block_id()` trả về số block cụ thể cho mọi `AlloyDB` và `eth_call`
+    /// Header của block đã finalized được cache theo số (biết `chain_id`) nên chạy lại cùng block
+    /// không cần RPC; block chưa finalized thì luôn hỏi lại (có thể đã reorg).
+    pub async fn pin_block(&self, block: BlockId) -> Result<PinnedBlock> {
+        let cache_key = |number: u64| self.chain_id.map(|chain_id| format!("block-{}-{}", chain_id, number));
+        let cached = match block {
+            BlockId::Number(BlockNumberOrTag::Number(number)) => match cache_key(number) {
+                Some(key) => cacache::read(&self.cache_dir, key)
+                    .await
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<PinnedBlock>(&bytes).ok()),
+                None => None,
+            },
+            _ => None,
+        };
+
+        let pinned = match cached {
+            Some(pinned) => pinned,
+            None => {
+                let found = self
+                    .request(|provider| async move { provider.get_block(block, BlockTransactionsKind::Hashes).await })
+                    .await?
+                    .ok_or_else(|| anyhow!("Block {} not found", block))?;
+                let pinned = PinnedBlock {
+                    number: found.header.number,
+                    hash: found.header.hash,
+                    parent_hash: found.header.parent_hash,
+                    timestamp: found.header.timestamp,
+                    base_fee: found.header.base_fee_per_gas.map(u128::from),
+                };
+                // Block chưa finalized có thể bị reorg: số block không còn trỏ tới đúng block này
+                if let Some(key) = cache_key(pinned.number) {
+                    if self.is_finalized(block, pinned.number).await {
+                        cacache::write(&self.cache_dir, key, serde_json::to_vec(&pinned)?).await?;
+                    }
+                }
+                pinned
+            }
+        };
+        *self.pinned.lock().unwrap() = Some(pinned);
+        Ok(pinned)
+    }
+
+    /// `number` (resolve từ `block`) đã finalized chưa. Endpoint không hỗ trợ tag `finalized`
+    /// thì coi như chưa.
+    async fn is_finalized(&self, block: BlockId, number: u64) -> bool {
+        if block == BlockId::Number(BlockNumberOrTag::Finalized) {
+            return true;
         }
+        let finalized = self
+            .request(|provider| async move {
+                provider.get_block(BlockId::Number(BlockNumberOrTag::Finalized), BlockTransactionsKind::Hashes).await
+            })
+            .await;
+        matches!(finalized, Ok(Some(finalized)) if number <= finalized.header.number)
     }
 
+    pub fn pinned(&self) -> Option<PinnedBlock> {
+        *self.pinned.lock().unwrap()
+    }
+
+    /// Block cho các read theo state: block đã ghim, `latest` nếu chưa ghim
+    pub fn block_id(&self) -> BlockId {
+        self.pinned().map(|pinned| pinned.id()).unwrap_or_else(BlockId::latest)
+    }
+
+    /// Endpoint theo `SelectionStrategy`, bỏ qua endpoint đang cooldown.
+    /// Request đi thẳng qua provider này (vd. trong `AlloyDB`) không được tính vào health.
     pub fn next(&self) -> (Arc<ConcreteHttpProvider>, String) {  // CHANGED: trả về tuple
-        let index = self.counter.fetch_add(1, Ordering::Relaxed) % self.providers.len();
+        let index = self.select(&[]).expect("MultiProvider has no RPC URL");
         (self.providers[index].clone(), self.urls[index].clone())  // trả về luôn URL
     }
+
+    /// Gọi `op` trên endpoint khỏe nhất, lỗi 429 / 5xx / transport thì thử lại trên endpoint khác
+    /// (tối đa `max_attempts` endpoint). Latency và lỗi của từng lần thử được ghi vào health.
+    pub async fn request<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut(Arc<ConcreteHttpProvider>) -> Fut,
+        Fut: Future<Output = TransportResult<T>>,
+    {
+        let mut tried = Vec::new();
+        let mut failures = Vec::new();
+        while tried.len() < self.config.max_attempts.max(1) {
+            let Some(index) = self.select(&tried) else { break };
+            tried.push(index);
+
+            let start = Instant::now();
+            match op(self.providers[index].clone()).await {
+                Ok(value) => {
+                    self.record_success(index, start.elapsed());
+                    return Ok(value);
+                }
+                Err(error) => match classify_error(&error) {
+                    // Node vẫn trả lời bình thường, endpoint khác cũng sẽ trả y hệt
+                    RpcErrorClass::Rejected => {
+                        self.record_success(index, start.elapsed());
+                        return Err(error.into());
+                    }
+                    class => {
+                        println!("⚠️ RPC {} failed ({:?}): {}", self.urls[index], class, error);
+                        self.record_failure(index, class, error.to_string());
+                        failures.push(format!("{}: {}", self.urls[index], error));
+                    }
+                },
+            }
+        }
+        Err(anyhow!("All {} RPC attempts failed: {}", failures.len(), failures.join("; ")))
+    }
+
+    /// Hedging: gửi cùng request tới `fanout` endpoint khác nhau cùng lúc, lấy câu trả lời đầu tiên.
+    /// Request còn lại bị hủy. Dùng cho read cần latency thấp (block number, gas price, eth_call).
+    pub async fn hedged<T, F, Fut>(&self, fanout: usize, mut op: F) -> Result<T>
+    where
+        F: FnMut(Arc<ConcreteHttpProvider>) -> Fut,
+        Fut: Future<Output = TransportResult<T>>,
+    {
+        let mut pending: FuturesUnordered<_> = self
+            .select_many(fanout.max(1))
+            .into_iter()
+            .map(|index| {
+                let request = op(self.providers[index].clone());
+                async move {
+                    let start = Instant::now();
+                    let result = request.await;
+                    (index, result, start.elapsed())
+                }
+            })
+            .collect();
+
+        let mut failures = Vec::new();
+        while let Some((index, result, latency)) = pending.next().await {
+            match result {
+                Ok(value) => {
+                    self.record_success(index, latency);
+                    return Ok(value);
+                }
+                Err(error) => match classify_error(&error) {
+                    RpcErrorClass::Rejected => {
+                        self.record_success(index, latency);
+                        return Err(error.into());
+                    }
+                    class => {
+                        println!("⚠️ Hedged RPC {} failed ({:?}): {}", self.urls[index], class, error);
+                        self.record_failure(index, class, error.to_string());
+                        failures.push(format!("{}: {}", self.urls[index], error));
+                    }
+                },
+            }
+        }
+        Err(anyhow!("All {} hedged RPC requests failed: {}", failures.len(), failures.join("; ")))
+    }
+
+    /// Quorum: gửi request tới mọi endpoint, trả về ngay khi có `k` endpoint trả cùng một giá trị.
+    /// Endpoint trả giá trị khác được log kèm URL. Lỗi nếu không giá trị nào đạt `k`.
+    pub async fn quorum<T, F, Fut>(&self, k: usize, mut op: F) -> Result<QuorumRead<T>>
+    where
+        T: PartialEq + Clone + Debug,
+        F: FnMut(Arc<ConcreteHttpProvider>) -> Fut,
+        Fut: Future<Output = TransportResult<T>>,
+    {
+        let k = k.max(1);
+        if k > self.len() {
+            bail!("Quorum of {} needs at least {} RPC endpoints, only {} configured", k, k, self.len());
+        }
+
+        // Endpoint khỏe trước, endpoint đang cooldown sau cùng
+        let mut pending: FuturesUnordered<_> = self
+            .select_many(self.len())
+            .into_iter()
+            .map(|index| {
+                let request = op(self.providers[index].clone());
+                async move {
+                    let start = Instant::now();
+                    let result = request.await;
+                    (index, result, start.elapsed())
+                }
+            })
+            .collect();
+
+        // Mỗi giá trị khác nhau kèm danh sách endpoint đã trả nó
+        let mut votes: Vec<(T, Vec<usize>)> = Vec::new();
+        let mut failed = Vec::new();
+        while let Some((index, result, latency)) = pending.next().await {
+            let value = match result {
+                Ok(value) => value,
+                Err(error) => {
+                    match classify_error(&error) {
+                        RpcErrorClass::Rejected => self.record_success(index, latency),
+                        class => self.record_failure(index, class, error.to_string()),
+                    }
+                    failed.push((self.urls[index].clone(), error.to_string()));
+                    continue;
+                }
+            };
+            self.record_success(index, latency);
+
+            let position = match votes.iter().position(|(voted, _)| *voted == value) {
+                Some(position) => position,
+                None => {
+                    votes.push((value, Vec::new()));
+                    votes.len() - 1
+                }
+            };
+            votes[position].1.push(index);
+            if votes[position].1.len() >= k {
+                let (value, agreeing) = votes.swap_remove(position);
+                let read = QuorumRead {
+                    value,
+                    agreeing: agreeing.iter().map(|&index| self.urls[index].clone()).collect(),
+                    disagreeing: votes
+                        .into_iter()
+                        .flat_map(|(value, indexes)| indexes.into_iter().map(move |index| (index, value.clone())))
+                        .map(|(index, value)| (self.urls[index].clone(), value))
+                        .collect(),
+                    failed,
+                };
+                if !read.disagreeing.is_empty() {
+                    println!(
+                        "⚠️ Quorum mismatch: {:?} from [{}], disagreeing: {}",
+                        read.value,
+                        read.agreeing.join(", "),
+                        read.disagreeing.iter().map(|(url, value)| format!("{} = {:?}", url, value)).collect::<Vec<_>>().join(", ")
+                    );
+                }
+                return Ok(read);
+            }
+        }
+
+        let answers = votes
+            .iter()
+            .map(|(value, indexes)| {
+                let urls: Vec<&str> = indexes.iter().map(|&index| self.urls[index].as_str()).collect();
+                format!("{:?} from [{}]", value, urls.join(", "))
+            })
+            .chain(failed.iter().map(|(url, error)| format!("{}: {}", url, error)))
+            .collect::<Vec<_>>();
+        println!("⚠️ Quorum of {} not reached: {}", k, answers.join("; "));
+        Err(anyhow!("Quorum of {} not reached: {}", k, answers.join("; ")))
+    }
+
+    /// Head mà ít nhất `k` endpoint đã tới (head cao thứ `k` trong các head trả về), nên node lệch nhau
+    /// vài block vẫn cho kết quả thay vì đòi `k` endpoint trả đúng cùng một số block.
+    /// Ít hơn `k` endpoint trả lời thì lùi về head thấp nhất đã nhận.
+    pub async fn lagged_head(&self, k: usize) -> Result<u64> {
+        let mut pending: FuturesUnordered<_> = self
+            .select_many(self.len())
+            .into_iter()
+            .map(|index| {
+                let provider = self.providers[index].clone();
+                async move {
+                    let start = Instant::now();
+                    let result = provider.get_block_number().await;
+                    (index, result, start.elapsed())
+                }
+            })
+            .collect();
+
+        let mut heads = Vec::new();
+        let mut failures = Vec::new();
+        while let Some((index, result, latency)) = pending.next().await {
+            match result {
+                Ok(head) => {
+                    self.record_success(index, latency);
+                    heads.push((head, index));
+                }
+                Err(error) => {
+                    match classify_error(&error) {
+                        RpcErrorClass::Rejected => self.record_success(index, latency),
+                        class => self.record_failure(index, class, error.to_string()),
+                    }
+                    failures.push(format!("{}: {}", self.urls[index], error));
+                }
+            }
+        }
+        if heads.is_empty() {
+            bail!("No RPC endpoint returned a block number: {}", failures.join("; "));
+        }
+
+        heads.sort_unstable_by(|a, b| b.cmp(a));
+        let (head, _) = heads[k.clamp(1, heads.len()) - 1];
+        let lagging: Vec<String> = heads
+            .iter()
+            .filter(|(reported, _)| *reported != head)
+            .map(|(reported, index)| format!("{} = {}", self.urls[*index], reported))
+            .collect();
+        if !lagging.is_empty() {
+            println!("⚠️ Heads differ, using #{} (reached by {} endpoints): {}", head, k.min(heads.len()), lagging.join(", "));
+        }
+        Ok(head)
+    }
+
+    /// Tối đa `count` endpoint khác nhau theo thứ tự `select`
+    fn select_many(&self, count: usize) -> Vec<usize> {
+        let mut chosen = Vec::new();
+        while chosen.len() < count {
+            let Some(index) = self.select(&chosen) else { break };
+            chosen.push(index);
+        }
+        chosen
+    }
+
+    fn select(&self, exclude: &[usize]) -> Option<usize> {
+        let now = Instant::now();
+        let mut health = self.health.lock().unwrap();
+        let available: Vec<usize> = (0..self.providers.len()).filter(|index| !exclude.contains(index)).collect();
+        let healthy: Vec<usize> = available.iter().copied().filter(|&index| !health[index].in_cooldown(now)).collect();
+        if healthy.is_empty() {
+            // Mọi endpoint đều cooldown: dùng endpoint sắp hết cooldown nhất thay vì fail luôn
+            return available.into_iter().min_by_key(|&index| health[index].cooldown_until);
+        }
+
+        let index = match self.config.strategy {
+            SelectionStrategy::RoundRobin => {
+                let len = self.providers.len();
+                let start = self.counter.fetch_add(1, Ordering::Relaxed);
+                (0..len).map(|offset| (start + offset) % len).find(|index| healthy.contains(index))?
+            }
+            SelectionStrategy::LowestLatency => {
+                healthy.into_iter().min_by_key(|&index| health[index].ewma_latency.unwrap_or_default())?
+            }
+            SelectionStrategy::Weighted => {
+                let total: f64 = healthy.iter().map(|&index| health[index].weight()).sum();
+                for &index in &healthy {
+                    health[index].current_weight += health[index].weight();
+                }
+                let best = healthy
+                    .into_iter()
+                    .max_by(|&a, &b| health[a].current_weight.total_cmp(&health[b].current_weight))?;
+                health[best].current_weight -= total;
+                best
+            }
+        };
+        Some(index)
+    }
+
+    fn record_success(&self, index: usize, latency: Duration) {
+        let alpha = self.config.ewma_alpha;
+        let mut health = self.health.lock().unwrap();
+        let endpoint = &mut health[index];
+        endpoint.requests += 1;
+        endpoint.consecutive_errors = 0;
+        endpoint.cooldown_until = None;
+        endpoint.ewma_latency = Some(match endpoint.ewma_latency {
+            Some(ewma) => ewma.mul_f64(1.0 - alpha) + latency.mul_f64(alpha),
+            None => latency,
+        });
+    }
+
+    fn record_failure(&self, index: usize, class: RpcErrorClass, message: String) {
+        let mut health = self.health.lock().unwrap();
+        let endpoint = &mut health[index];
+        endpoint.requests += 1;
+        endpoint.errors += 1;
+        endpoint.consecutive_errors += 1;
+        endpoint.last_error = Some(message);
+        if class == RpcErrorClass::Throttled || endpoint.consecutive_errors >= self.config.max_consecutive_errors {
+            endpoint.cooldown_until = Some(Instant::now() + self.config.cooldown);
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::init_cache_db;
+    use crate::core::mock_rpc::{default_account_response, mock_block, MockRpc};
+    use crate::core::opportunity::fetch_base_fee;
+    use revm::Database;
+    use serde_json::{json, Value};
+
+    async fn gas_price_mock(price: u64) -> MockRpc {
+        MockRpc::spawn(move |method, _: &Value| match method {
+            "eth_gasPrice" => Ok(json!(format!("0x{:x}", price))),
+            "eth_call" => Err("execution reverted".to_string()),
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await
+    }
+
+    async fn gas_price(multi_provider: &MultiProvider) -> Result<u128> {
+        multi_provider.request(|provider| async move { provider.get_gas_price().await }).await
+    }
+
+    fn urls(mocks: &[&MockRpc]) -> Vec<String> {
+        mocks.iter().map(|mock| mock.url.clone()).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failover_and_cooldown_after_5xx() {
+        let (a, b) = (gas_price_mock(7).await, gas_price_mock(7).await);
+        let config = MultiProviderConfig { cooldown: Duration::from_millis(300), ..Default::default() };
+        let multi_provider = MultiProvider::with_config(&urls(&[&a, &b]), config);
+
+        a.fail_with_status(503);
+        for _ in 0..4 {
+            assert_eq!(gas_price(&multi_provider).await.unwrap(), 7);
+        }
+        // A chỉ bị thử một lần rồi vào cooldown, mọi request còn lại đi qua B
+        assert_eq!((a.http_requests(), b.http_requests()), (1, 4));
+        let health = multi_provider.health();
+        assert_eq!((health[0].requests, health[0].errors), (1, 1));
+        assert!(health[0].in_cooldown(Instant::now()));
+        assert!(health[0].last_error.as_ref().unwrap().contains("503"));
+        assert_eq!((health[1].requests, health[1].errors), (4, 0));
+        assert!(health[1].ewma_latency.is_some());
+        assert_eq!(multi_provider.next().1, b.url);
+
+        // Hết cooldown thì A được dùng lại
+        a.recover();
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        for _ in 0..4 {
+            gas_price(&multi_provider).await.unwrap();
+        }
+        assert!(a.http_requests() > 1);
+        assert_eq!(multi_provider.health()[0].consecutive_errors, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dead_endpoint_cools_down_after_consecutive_errors() {
+        let good = gas_price_mock(9).await;
+        let dead = "http://127.0.0.1:1".to_string();
+        let multi_provider = MultiProvider::new(&[dead, good.url.clone()]);
+
+        for _ in 0..8 {
+            assert_eq!(gas_price(&multi_provider).await.unwrap(), 9);
+        }
+        let health = multi_provider.health();
+        assert_eq!(health[0].errors, 3);
+        assert_eq!(health[0].consecutive_errors, 3);
+        assert!(health[0].in_cooldown(Instant::now()));
+        assert_eq!(good.http_requests(), 8);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rejected_call_is_not_retried() {
+        let (a, b) = (gas_price_mock(1).await, gas_price_mock(1).await);
+        let multi_provider = MultiProvider::new(&urls(&[&a, &b]));
+        let tx = alloy::rpc::types::TransactionRequest::default();
+
+        let err = multi_provider
+            .request(|provider| {
+                let tx = tx.clone();
+                async move { provider.call(&tx).await }
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("execution reverted"));
+        assert_eq!(a.calls() + b.calls(), 1);
+        assert!(multi_provider.health().iter().all(|endpoint| endpoint.errors == 0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_all_endpoints_rate_limited() {
+        let (a, b) = (gas_price_mock(1).await, gas_price_mock(1).await);
+        let multi_provider = MultiProvider::new(&urls(&[&a, &b]));
+        a.fail_with_status(429);
+        b.fail_with_status(500);
+
+        let err = gas_price(&multi_provider).await.unwrap_err().to_string();
+        assert!(err.contains("All 2 RPC attempts failed"));
+        assert!(err.contains(&a.url) && err.contains(&b.url));
+        let now = Instant::now();
+        assert!(multi_provider.health().iter().all(|endpoint| endpoint.in_cooldown(now)));
+
+        // Vẫn trả endpoint (sắp hết cooldown nhất) thay vì panic
+        assert_eq!(multi_provider.next().1, a.url);
+    }
+
+    async fn block_number_mock(block: u64) -> MockRpc {
+        MockRpc::spawn(move |method, _: &Value| match method {
+            "eth_blockNumber" => Ok(json!(format!("0x{:x}", block))),
+            "eth_getStorageAt" => Ok(json!(format!("0x{:064x}", block))),
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await
+    }
+
+    async fn block_number_quorum(multi_provider: &MultiProvider, k: usize) -> Result<QuorumRead<u64>> {
+        multi_provider.quorum(k, |provider| async move { provider.get_block_number().await }).await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_hedged_takes_first_answer() {
+        let (slow, fast) = (gas_price_mock(1).await, gas_price_mock(2).await);
+        slow.set_delay(Duration::from_millis(500));
+        let multi_provider = MultiProvider::new(&urls(&[&slow, &fast]));
+
+        let start = Instant::now();
+        let price = multi_provider.hedged(2, |provider| async move { provider.get_gas_price().await }).await.unwrap();
+        assert_eq!(price, 2);
+        assert!(start.elapsed() < Duration::from_millis(400));
+        let health = multi_provider.health();
+        assert_eq!((health[0].requests, health[1].requests), (0, 1));
+
+        // Endpoint lỗi trong nhóm hedge không làm hỏng request; `fast` trả lời sau để lỗi luôn được ghi nhận
+        slow.set_delay(Duration::ZERO);
+        slow.fail_with_status(503);
+        fast.set_delay(Duration::from_millis(50));
+        for _ in 0..2 {
+            let price = multi_provider.hedged(2, |provider| async move { provider.get_gas_price().await }).await.unwrap();
+            assert_eq!(price, 2);
+        }
+        assert_eq!(multi_provider.health()[0].errors, 2);
+        fast.set_delay(Duration::ZERO);
+
+        fast.fail_with_status(502);
+        let err = multi_provider.hedged(3, |provider| async move { provider.get_gas_price().await }).await.unwrap_err();
+        assert!(err.to_string().contains("All 2 hedged RPC requests failed"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_quorum_reports_lagging_endpoint() {
+        let (a, b, lagging) = (block_number_mock(100).await, block_number_mock(100).await, block_number_mock(99).await);
+        // Node bị trễ trả lời trước nhưng không đủ phiếu
+        a.set_delay(Duration::from_millis(30));
+        b.set_delay(Duration::from_millis(30));
+        let multi_provider = MultiProvider::new(&urls(&[&a, &b, &lagging]));
+
+        let read = block_number_quorum(&multi_provider, 2).await.unwrap();
+        assert_eq!(read.value, 100);
+        assert_eq!(read.agreeing.len(), 2);
+        assert!(read.agreeing.contains(&a.url) && read.agreeing.contains(&b.url));
+        assert_eq!(read.disagreeing, vec![(lagging.url.clone(), 99)]);
+        assert!(read.failed.is_empty());
+
+        // 3-of-3 không đạt: lỗi liệt kê cả hai giá trị và URL
+        let err = block_number_quorum(&multi_provider, 3).await.unwrap_err().to_string();
+        assert!(err.contains("100 from") && err.contains(&format!("99 from [{}]", lagging.url)));
+        assert!(block_number_quorum(&multi_provider, 4).await.unwrap_err().to_string().contains("only 3 configured"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_lagged_head_tolerates_skewed_endpoints() {
+        let (ahead, head, lagging) = (block_number_mock(101).await, block_number_mock(100).await, block_number_mock(99).await);
+        let multi_provider = MultiProvider::new(&urls(&[&ahead, &head, &lagging]));
+        // Không có 2 endpoint cùng số block nhưng 2 endpoint đã tới #100
+        assert!(block_number_quorum(&multi_provider, 2).await.is_err());
+        assert_eq!(multi_provider.lagged_head(2).await.unwrap(), 100);
+        assert_eq!(multi_provider.lagged_head(1).await.unwrap(), 101);
+
+        // Chỉ còn một endpoint trả lời: lùi về head thấp nhất đã nhận
+        ahead.fail_with_status(503);
+        head.fail_with_status(503);
+        assert_eq!(multi_provider.lagged_head(2).await.unwrap(), 99);
+        lagging.fail_with_status(503);
+        assert!(multi_provider.lagged_head(2).await.unwrap_err().to_string().contains("No RPC endpoint returned"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_storage_quorum_tolerates_failed_endpoint() {
+        let (a, b, broken) = (block_number_mock(7).await, block_number_mock(7).await, block_number_mock(7).await);
+        broken.fail_with_status(500);
+        let multi_provider = MultiProvider::new(&urls(&[&broken, &a, &b]));
+
+        let (address, slot) = (alloy::primitives::Address::repeat_byte(0xaa), alloy::primitives::U256::from(4));
+        let read = multi_provider
+            .quorum(2, |provider| async move { provider.get_storage_at(address, slot).await })
+            .await
+            .unwrap();
+        assert_eq!(read.value, alloy::primitives::U256::from(7));
+        assert!(read.disagreeing.is_empty());
+        // `broken` có thể chưa trả lời khi đủ quorum; nếu đã trả lời thì nằm trong `failed`
+        assert!(read.failed.iter().all(|(url, error)| *url == broken.url && error.contains("500")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_latency_aware_strategies_prefer_fast_endpoint() {
+        let (slow, fast) = (gas_price_mock(1).await, gas_price_mock(1).await);
+        slow.set_delay(Duration::from_millis(150));
+
+        for strategy in [SelectionStrategy::LowestLatency, SelectionStrategy::Weighted] {
+            let config = MultiProviderConfig { strategy, ..Default::default() };
+            let multi_provider = MultiProvider::with_config(&urls(&[&slow, &fast]), config);
+            assert_eq!(multi_provider.strategy(), strategy);
+            let (slow_before, fast_before) = (slow.http_requests(), fast.http_requests());
+
+            for _ in 0..12 {
+                gas_price(&multi_provider).await.unwrap();
+            }
+            let slow_hits = slow.http_requests() - slow_before;
+            let fast_hits = fast.http_requests() - fast_before;
+            assert_eq!(slow_hits + fast_hits, 12);
+            // LowestLatency gần như chỉ dùng endpoint nhanh; Weighted chia theo 1/latency nên chỉ cần nghiêng rõ
+            // về endpoint nhanh (latency đo được dao động khi cả test suite chạy song song)
+            let min_fast_hits = match strategy {
+                SelectionStrategy::LowestLatency => 10,
+                _ => 8,
+            };
+            assert!(fast_hits >= min_fast_hits, "{strategy:?}: slow {slow_hits}, fast {fast_hits}");
+
+            let health = multi_provider.health();
+            assert!(health[0].ewma_latency.unwrap() > health[1].ewma_latency.unwrap());
+            println!("{strategy:?}: {} | {}", health[0], health[1]);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pin_block_forks_every_read_at_resolved_number() {
+        let blocks_seen = Arc::new(Mutex::new(Vec::new()));
+        let seen = blocks_seen.clone();
+        let mock = MockRpc::spawn(move |method, params: &Value| match method {
+            "eth_getBlockByNumber" if params[0] == "finalized" => Ok(mock_block(100, Some(7))),
+            "eth_getStorageAt" | "eth_getBalance" | "eth_getTransactionCount" | "eth_getCode" => {
+                let block = params.as_array().and_then(|params| params.last()).cloned().unwrap_or_default();
+                seen.lock().unwrap().push(block);
+                Ok(default_account_response(method).unwrap_or_else(|| json!(format!("0x{:064x}", 1))))
+            }
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        assert_eq!(multi_provider.block_id(), BlockId::latest());
+
+        let pinned = multi_provider.pin_block("finalized".parse().unwrap()).await.unwrap();
+        assert_eq!((pinned.number, pinned.base_fee), (100, Some(7)));
+        assert_eq!(pinned.hash, B256::from(alloy::primitives::U256::from(100)));
+        assert_eq!(multi_provider.block_id(), BlockId::number(100));
+
+        // Base fee lấy từ block đã ghim, không gọi thêm RPC
+        let calls = mock.calls();
+        assert_eq!(fetch_base_fee(&multi_provider).await.unwrap(), 7);
+        assert_eq!(mock.calls(), calls);
+
+        let mut cache_db = init_cache_db(&multi_provider);
+        let address = alloy::primitives::Address::repeat_byte(0x11);
+        cache_db.storage(address, alloy::primitives::U256::ZERO).unwrap();
+        let seen = blocks_seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        assert!(seen.iter().all(|block| *block == json!("0x64")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pin_unknown_block_fails() {
+        let mock = MockRpc::spawn(|method, _: &Value| match method {
+            "eth_getBlockByHash" => Ok(Value::Null),
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let hash = format!("0x{}", "ab".repeat(32));
+        let err = multi_provider.pin_block(hash.parse().unwrap()).await.unwrap_err();
+        assert!(err.to_string().contains("not found"));
+        assert!(multi_provider.pinned().is_none());
+    }
+}
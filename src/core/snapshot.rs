@@ -0,0 +1,424 @@
+use alloy::primitives::{keccak256, Address, Bytes, B256, U256};
+use anyhow::{anyhow, bail, Context, Result};
+use revm::primitives::{AccountInfo, BlockEnv, Bytecode, KECCAK_EMPTY};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::core::db_empty::InMemoryDB;
+
+/// Tăng khi đổi layout JSON / binary, file cũ sẽ bị từ chối thay vì đọc sai
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// 4 byte đầu của file binary, dùng để phân biệt với JSON khi load
+const BINARY_MAGIC: &[u8; 4] = b"UV3S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Json,
+    Binary,
+}
+
+impl SnapshotFormat {
+    /// `*.json` → JSON, còn lại → binary
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => SnapshotFormat::Json,
+            _ => SnapshotFormat::Binary,
+        }
+    }
+}
+
+/// Block context tại thời điểm chụp snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotBlock {
+    pub number: u64,
+    pub timestamp: u64,
+    /// Base fee (hoặc gas price nếu chain không có EIP-1559), dùng để tính phí gas khi replay
+    pub base_fee: u128,
+    pub gas_limit: u64,
+    pub coinbase: Address,
+    pub prevrandao: B256,
+}
+
+impl SnapshotBlock {
+    pub fn to_block_env(&self) -> BlockEnv {
+        BlockEnv {
+            number: U256::from(self.number),
+            coinbase: self.coinbase,
+            timestamp: U256::from(self.timestamp),
+            gas_limit: U256::from(self.gas_limit),
+            basefee: U256::from(self.base_fee),
+            prevrandao: Some(self.prevrandao),
+            ..Default::default()
+        }
+    }
+}
+
+/// Một account trong snapshot: info + bytecode + toàn bộ storage đã cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub address: Address,
+    pub balance: U256,
+    pub nonce: u64,
+    pub code_hash: B256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// State đã warm-up của một lần mô phỏng (accounts, bytecode, storage, block env),
+/// đủ để chạy lại quote / arbitrage hoàn toàn offline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub chain_id: u64,
+    pub block: SnapshotBlock,
+    /// Sắp theo address để file sinh ra ổn định giữa các lần chụp
+    pub accounts: Vec<AccountSnapshot>,
+    /// Block hash đã đọc qua `BLOCKHASH` khi warm-up
+    #[serde(default)]
+    pub block_hashes: BTreeMap<u64, B256>,
+}
+
+impl Snapshot {
+    pub fn from_db(chain_id: u64, block: SnapshotBlock, db: &InMemoryDB) -> Self {
+        let mut addresses: BTreeSet<Address> = db.accounts.keys().copied().collect();
+        addresses.extend(db.storage.keys().map(|(address, _)| *address));
+
+        let mut accounts: BTreeMap<Address, AccountSnapshot> = addresses
+            .into_iter()
+            .map(|address| {
+                let info = db.accounts.get(&address).cloned().unwrap_or_default();
+                let code = db.account_code(&address).map(|code| code.original_bytes());
+                let account = AccountSnapshot {
+                    address,
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code_hash: info.code_hash,
+                    code,
+                    storage: BTreeMap::new(),
+                };
+                (address, account)
+            })
+            .collect();
+        for ((address, slot), value) in &db.storage {
+            if let Some(account) = accounts.get_mut(address) {
+                account.storage.insert(*slot, *value);
+            }
+        }
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            chain_id,
+            block,
+            accounts: accounts.into_values().collect(),
+            block_hashes: db.block_hashes.iter().map(|(number, hash)| (*number, *hash)).collect(),
+        }
+    }
+
+    /// Dựng lại InMemoryDB, kiểm tra bytecode khớp `code_hash`
+    pub fn to_db(&self) -> Result<InMemoryDB> {
+        let mut db = InMemoryDB::default();
+        for account in &self.accounts {
+            let code = match &account.code {
+                Some(bytes) => {
+                    if keccak256(bytes) != account.code_hash {
+                        bail!("Snapshot bytecode of {:?} does not match code hash {}", account.address, account.code_hash);
+                    }
+                    Some(Bytecode::new_raw(bytes.clone()))
+                }
+                None if account.code_hash == KECCAK_EMPTY || account.code_hash == B256::ZERO => None,
+                None => bail!("Snapshot account {:?} has code hash {} but no bytecode", account.address, account.code_hash),
+            };
+            let info = AccountInfo { balance: account.balance, nonce: account.nonce, code_hash: account.code_hash, code };
+            db.insert_account_info(account.address, info);
+            for (slot, value) in &account.storage {
+                db.storage.insert((account.address, *slot), *value);
+            }
+        }
+        db.block_hashes.extend(self.block_hashes.iter().map(|(number, hash)| (*number, *hash)));
+        Ok(db)
+    }
+
+    pub fn storage_slots(&self) -> usize {
+        self.accounts.iter().map(|account| account.storage.len()).sum()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let snapshot: Snapshot = serde_json::from_str(json)?;
+        check_version(snapshot.version)?;
+        Ok(snapshot)
+    }
+
+    /// Layout binary (số nguyên little-endian, U256/B256 big-endian 32 byte):
+    /// `magic | version u32 | chain_id u64 | block | n_accounts u32 | account* | n_hashes u32 | (number u64, hash)*`
+    /// với account = `address | balance | nonce u64 | code_hash | code_len u32 | code | n_slots u32 | (slot, value)*`
+    /// (`code_len = u32::MAX` nghĩa là không có bytecode)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.accounts.len() * 128 + self.storage_slots() * 64);
+        out.extend_from_slice(BINARY_MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&self.chain_id.to_le_bytes());
+
+        out.extend_from_slice(&self.block.number.to_le_bytes());
+        out.extend_from_slice(&self.block.timestamp.to_le_bytes());
+        out.extend_from_slice(&self.block.base_fee.to_le_bytes());
+        out.extend_from_slice(&self.block.gas_limit.to_le_bytes());
+        out.extend_from_slice(self.block.coinbase.as_slice());
+        out.extend_from_slice(self.block.prevrandao.as_slice());
+
+        out.extend_from_slice(&(self.accounts.len() as u32).to_le_bytes());
+        for account in &self.accounts {
+            out.extend_from_slice(account.address.as_slice());
+            out.extend_from_slice(&account.balance.to_be_bytes::<32>());
+            out.extend_from_slice(&account.nonce.to_le_bytes());
+            out.extend_from_slice(account.code_hash.as_slice());
+            match &account.code {
+                Some(code) => {
+                    out.extend_from_slice(&(code.len() as u32).to_le_bytes());
+                    out.extend_from_slice(code);
+                }
+                None => out.extend_from_slice(&u32::MAX.to_le_bytes()),
+            }
+            out.extend_from_slice(&(account.storage.len() as u32).to_le_bytes());
+            for (slot, value) in &account.storage {
+                out.extend_from_slice(&slot.to_be_bytes::<32>());
+                out.extend_from_slice(&value.to_be_bytes::<32>());
+            }
+        }
+        out.extend_from_slice(&(self.block_hashes.len() as u32).to_le_bytes());
+        for (number, hash) in &self.block_hashes {
+            out.extend_from_slice(&number.to_le_bytes());
+            out.extend_from_slice(hash.as_slice());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != BINARY_MAGIC {
+            bail!("Not a binary snapshot (bad magic)");
+        }
+        let version = reader.u32()?;
+        check_version(version)?;
+        let chain_id = reader.u64()?;
+
+        let block = SnapshotBlock {
+            number: reader.u64()?,
+            timestamp: reader.u64()?,
+            base_fee: u128::from_le_bytes(reader.array()?),
+            gas_limit: reader.u64()?,
+            coinbase: Address::from_slice(reader.take(20)?),
+            prevrandao: B256::from_slice(reader.take(32)?),
+        };
+
+        let n_accounts = reader.u32()?;
+        let mut accounts = Vec::with_capacity(n_accounts.min(1 << 16) as usize);
+        for _ in 0..n_accounts {
+            let address = Address::from_slice(reader.take(20)?);
+            let balance = U256::from_be_bytes::<32>(reader.array()?);
+            let nonce = reader.u64()?;
+            let code_hash = B256::from_slice(reader.take(32)?);
+            let code = match reader.u32()? {
+                u32::MAX => None,
+                len => Some(Bytes::copy_from_slice(reader.take(len as usize)?)),
+            };
+            let n_slots = reader.u32()?;
+            let mut storage = BTreeMap::new();
+            for _ in 0..n_slots {
+                let slot = U256::from_be_bytes::<32>(reader.array()?);
+                let value = U256::from_be_bytes::<32>(reader.array()?);
+                storage.insert(slot, value);
+            }
+            accounts.push(AccountSnapshot { address, balance, nonce, code_hash, code, storage });
+        }
+        let mut block_hashes = BTreeMap::new();
+        for _ in 0..reader.u32()? {
+            let number = reader.u64()?;
+            block_hashes.insert(number, B256::from_slice(reader.take(32)?));
+        }
+        if reader.offset != bytes.len() {
+            bail!("Trailing {} bytes after snapshot", bytes.len() - reader.offset);
+        }
+
+        Ok(Snapshot { version, chain_id, block, accounts, block_hashes })
+    }
+
+    /// Ghi ra file, format chọn theo đuôi file (`.json` hoặc binary)
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = match SnapshotFormat::from_path(path) {
+            SnapshotFormat::Json => self.to_json()?.into_bytes(),
+            SnapshotFormat::Binary => self.to_bytes(),
+        };
+        std::fs::write(path, bytes).with_context(|| format!("Failed to write snapshot {}", path.display()))
+    }
+
+    /// Đọc snapshot, tự nhận format theo magic bytes
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+        let snapshot = if bytes.starts_with(BINARY_MAGIC) {
+            Self::from_bytes(&bytes)
+        } else {
+            Self::from_json(std::str::from_utf8(&bytes)?)
+        };
+        snapshot.with_context(|| format!("Invalid snapshot {}", path.display()))
+    }
+}
+
+fn check_version(version: u32) -> Result<()> {
+    if version != SNAPSHOT_VERSION {
+        bail!("Unsupported snapshot version {} (expected {})", version, SNAPSHOT_VERSION);
+    }
+    Ok(())
+}
+
+/// Đọc tuần tự từ buffer, lỗi khi file bị cắt cụt
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.offset.checked_add(len).filter(|end| *end <= self.bytes.len());
+        let end = end.ok_or_else(|| anyhow!("Truncated snapshot at byte {}", self.offset))?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into()?)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::{init_account_with_bytecode, insert_mapping_storage_slot, revm_call};
+    use alloy::sol_types::SolValue;
+    use revm::db::{CacheDB, EmptyDB};
+    use std::str::FromStr;
+
+    fn sample_snapshot() -> (Snapshot, Address, Address, U256) {
+        let token = Address::repeat_byte(0x11);
+        let holder = Address::repeat_byte(0x22);
+        let balance = U256::from(123_456_789u64);
+
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        let erc20 = Bytecode::new_raw(Bytes::from_str(include_str!("../bytecode/generic_erc20.hex")).unwrap());
+        init_account_with_bytecode(token, erc20, &mut cache_db).unwrap();
+        insert_mapping_storage_slot(token, U256::ZERO, holder, balance, &mut cache_db).unwrap();
+        cache_db.insert_account_info(holder, AccountInfo { balance: U256::from(7), nonce: 3, ..Default::default() });
+        cache_db.block_hashes.insert(U256::from(20_999_999), B256::repeat_byte(0xbb));
+
+        let block = SnapshotBlock {
+            number: 21_000_000,
+            timestamp: 1_730_000_000,
+            base_fee: 12_345_678_901,
+            gas_limit: 30_000_000,
+            coinbase: Address::repeat_byte(0xcb),
+            prevrandao: B256::repeat_byte(0x42),
+        };
+        let snapshot = Snapshot::from_db(1, block, &InMemoryDB::from_cache_db(&cache_db));
+        (snapshot, token, holder, balance)
+    }
+
+    fn balance_of(db: InMemoryDB, token: Address, holder: Address) -> U256 {
+        // balanceOf(address)
+        let mut calldata = vec![0x70, 0xa0, 0x82, 0x31];
+        calldata.extend_from_slice(&holder.abi_encode());
+        let mut cache_db = CacheDB::new(db);
+        let response = revm_call(holder, token, calldata.into(), &BlockEnv::default(), &mut cache_db).unwrap();
+        U256::abi_decode(&response, true).unwrap()
+    }
+
+    #[test]
+    fn test_json_and_binary_round_trip() {
+        let (snapshot, ..) = sample_snapshot();
+        assert_eq!(snapshot.accounts.len(), 2);
+        assert_eq!(snapshot.storage_slots(), 1);
+        assert_eq!(snapshot.block_hashes[&20_999_999], B256::repeat_byte(0xbb));
+
+        let from_json = Snapshot::from_json(&snapshot.to_json().unwrap()).unwrap();
+        assert_eq!(from_json, snapshot);
+
+        let bytes = snapshot.to_bytes();
+        assert!(bytes.len() < snapshot.to_json().unwrap().len());
+        assert_eq!(Snapshot::from_bytes(&bytes).unwrap(), snapshot);
+
+        assert!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 99;
+        assert!(Snapshot::from_bytes(&wrong_version).is_err());
+    }
+
+    #[test]
+    fn test_offline_call_on_loaded_snapshot() {
+        let (snapshot, token, holder, balance) = sample_snapshot();
+        let dir = std::env::temp_dir().join(format!("uv3-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for name in ["state.json", "state.bin"] {
+            let path = dir.join(name);
+            snapshot.save(&path).unwrap();
+            let loaded = Snapshot::load(&path).unwrap();
+            assert_eq!(loaded, snapshot);
+
+            let db = loaded.to_db().unwrap();
+            assert_eq!(db.accounts[&holder].nonce, 3);
+            assert_eq!(balance_of(db, token, holder), balance);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let env = snapshot.block.to_block_env();
+        assert_eq!(env.number, U256::from(21_000_000));
+        assert_eq!(env.basefee, U256::from(12_345_678_901u64));
+    }
+
+    #[test]
+    fn test_replay_runs_evm_with_snapshot_block_env() {
+        let (snapshot, _, holder, _) = sample_snapshot();
+        // return abi.encode(block.number, block.timestamp, block.coinbase)
+        let block_info = Address::repeat_byte(0x33);
+        let code = Bytecode::new_raw(Bytes::from_static(&[
+            0x43, 0x60, 0x00, 0x52, 0x42, 0x60, 0x20, 0x52, 0x41, 0x60, 0x40, 0x52, 0x60, 0x60, 0x60, 0x00, 0xf3,
+        ]));
+        let mut cache_db = CacheDB::new(snapshot.to_db().unwrap());
+        init_account_with_bytecode(block_info, code, &mut cache_db).unwrap();
+
+        let response = revm_call(holder, block_info, Bytes::new(), &snapshot.block.to_block_env(), &mut cache_db).unwrap();
+        let (number, timestamp, coinbase) = <(U256, U256, Address)>::abi_decode(&response, true).unwrap();
+        assert_eq!(number, U256::from(snapshot.block.number));
+        assert_eq!(timestamp, U256::from(snapshot.block.timestamp));
+        assert_eq!(coinbase, snapshot.block.coinbase);
+
+        // Block env mặc định của revm
+        let response = revm_call(holder, block_info, Bytes::new(), &BlockEnv::default(), &mut cache_db).unwrap();
+        assert_eq!(<(U256, U256, Address)>::abi_decode(&response, true).unwrap().0, U256::ZERO);
+    }
+
+    #[test]
+    fn test_corrupted_code_is_rejected() {
+        let (mut snapshot, ..) = sample_snapshot();
+        let account = snapshot.accounts.iter_mut().find(|account| account.code.is_some()).unwrap();
+        account.code = Some(Bytes::from_static(&[0x00]));
+        assert!(snapshot.to_db().is_err());
+    }
+}
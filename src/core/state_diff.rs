@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
+    primitives::{Address, Bytes, B256, U256},
+    providers::Provider,
+};
+use anyhow::{anyhow, bail, Result};
+use revm::db::{AccountState, CacheDB, DatabaseRef};
+use revm::primitives::AccountInfo;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::core::db_persistent::PersistentState;
+use crate::core::prefetch::{prefetch_state, PrefetchConfig};
+use crate::core::provider::MultiProvider;
+
+/// Giá trị mới của một account sau block, chỉ các field bị ghi
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    pub code: Option<Bytes>,
+    /// Slot → giá trị mới (slot bị xóa về 0 cũng có mặt)
+    pub storage: BTreeMap<U256, U256>,
+    /// Account bị selfdestruct trong block
+    pub destroyed: bool,
+}
+
+/// Những gì một hoặc nhiều block đã ghi vào state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts: BTreeMap<Address, AccountDiff>,
+}
+
+/// Account trong output `prestateTracer` (diffMode)
+#[derive(Debug, Default, Deserialize)]
+struct TracedAccount {
+    balance: Option<U256>,
+    /// Geth trả số JSON, vài client trả hex string
+    nonce: Option<Value>,
+    code: Option<Bytes>,
+    #[serde(default)]
+    storage: BTreeMap<B256, B256>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TracedDiff {
+    #[serde(default)]
+    pre: BTreeMap<Address, TracedAccount>,
+    #[serde(default)]
+    post: BTreeMap<Address, TracedAccount>,
+}
+
+fn parse_nonce(nonce: &Value) -> Result<u64> {
+    match nonce {
+        Value::Number(number) => number.as_u64().ok_or_else(|| anyhow!("Invalid nonce {}", number)),
+        Value::String(hex) => Ok(u64::from_str_radix(hex.trim_start_matches("0x"), 16)?),
+        other => bail!("Invalid nonce {}", other),
+    }
+}
+
+impl StateDiff {
+    /// Gộp diff của một transaction (`pre` / `post` của prestateTracer diffMode), tx sau đè tx trước.
+    /// Geth bỏ slot về 0 khỏi `post`, account bị xóa chỉ còn trong `pre`.
+    fn apply_tx(&mut self, tx: TracedDiff) -> Result<()> {
+        for (address, pre) in &tx.pre {
+            let Some(post) = tx.post.get(address) else {
+                let account = self.accounts.entry(*address).or_default();
+                *account = AccountDiff { destroyed: true, ..Default::default() };
+                continue;
+            };
+            let account = self.accounts.entry(*address).or_default();
+            for slot in pre.storage.keys() {
+                if !post.storage.contains_key(slot) {
+                    account.storage.insert((*slot).into(), U256::ZERO);
+                }
+            }
+        }
+        for (address, post) in tx.post {
+            let account = self.accounts.entry(address).or_default();
+            account.destroyed = false;
+            if let Some(balance) = post.balance {
+                account.balance = Some(balance);
+            }
+            if let Some(nonce) = &post.nonce {
+                account.nonce = Some(parse_nonce(nonce)?);
+            }
+            if let Some(code) = post.code {
+                account.code = Some(code);
+            }
+            for (slot, value) in post.storage {
+                account.storage.insert(slot.into(), value.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Gộp diff của block sau
+    pub fn merge(&mut self, other: StateDiff) {
+        for (address, diff) in other.accounts {
+            let account = self.accounts.entry(address).or_default();
+            if diff.destroyed {
+                *account = diff;
+                continue;
+            }
+            account.destroyed = false;
+            account.balance = diff.balance.or(account.balance);
+            account.nonce = diff.nonce.or(account.nonce);
+            account.code = diff.code.or(account.code.take());
+            account.storage.extend(diff.storage);
+        }
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.accounts.values().map(|account| account.storage.len()).sum()
+    }
+}
+
+/// Parse kết quả `debug_traceBlockByNumber` với `prestateTracer` diffMode (một phần tử mỗi tx)
+pub fn parse_prestate_diff(traces: &[Value]) -> Result<StateDiff> {
+    let mut diff = StateDiff::default();
+    for trace in traces {
+        if let Some(error) = trace.get("error") {
+            bail!("Trace of tx {} failed: {}", trace.get("txHash").unwrap_or(&Value::Null), error);
+        }
+        let result = trace.get("result").unwrap_or(trace);
+        diff.apply_tx(serde_json::from_value(result.clone())?)?;
+    }
+    Ok(diff)
+}
+
+/// State diff của block `number` qua `debug_traceBlockByNumber` (cần debug namespace trên node)
+pub async fn fetch_block_diff(multi_provider: &MultiProvider, number: u64) -> Result<StateDiff> {
+    let tracer = json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } });
+    let traces: Vec<Value> = multi_provider
+        .request(|provider| {
+            let tracer = tracer.clone();
+            async move { provider.client().request("debug_traceBlockByNumber", (BlockNumberOrTag::Number(number), tracer)).await }
+        })
+        .await?;
+    parse_prestate_diff(&traces)
+}
+
+/// State đã mock lúc chuẩn bị (bytecode / balance giả): không bao giờ bị diff on-chain ghi đè
+#[derive(Debug, Clone, Default)]
+pub struct ProtectedState {
+    /// Account có trong cache sau bước chuẩn bị: giữ nguyên info (code, balance)
+    pub accounts: BTreeSet<Address>,
+    pub slots: BTreeSet<(Address, U256)>,
+}
+
+impl ProtectedState {
+    /// Chụp mọi account / slot đang có trong `cache_db`, gọi ngay sau `prepare_chain_*`
+    pub fn capture<ExtDB>(cache_db: &CacheDB<ExtDB>) -> Self {
+        let mut protected = ProtectedState::default();
+        for (address, account) in &cache_db.accounts {
+            protected.accounts.insert(*address);
+            protected.slots.extend(account.storage.keys().map(|slot| (*address, *slot)));
+        }
+        protected
+    }
+}
+
+/// Nguồn của lần invalidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffSource {
+    /// Diff chính xác từ `prestateTracer`
+    #[default]
+    Trace,
+    /// Node không trace được (hoặc reorg): đọc lại mọi state đã cache trong một lượt batch
+    Refresh,
+}
+
+/// Thống kê một lần đưa cache lên block mới
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvalidationReport {
+    pub source: DiffSource,
+    /// Account bị ghi trong block (theo diff), kể cả account chưa cache
+    pub accounts_changed: usize,
+    pub accounts_updated: usize,
+    pub slots_updated: usize,
+    /// Account bị gỡ khỏi cache để load lại lazily (selfdestruct, đổi code...)
+    pub accounts_evicted: usize,
+    /// Account / slot mock bị block ghi nhưng được giữ nguyên
+    pub protected_skipped: usize,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for InvalidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} accounts changed, {} accounts + {} slots updated, {} evicted, {} protected kept in {:.2?}",
+            self.source,
+            self.accounts_changed,
+            self.accounts_updated,
+            self.slots_updated,
+            self.accounts_evicted,
+            self.protected_skipped,
+            self.elapsed
+        )
+    }
+}
+
+/// Áp `diff` lên `cache_db`: chỉ cập nhật account / slot đã cache (phần còn lại sẽ load lazily
+/// ở block mới), bỏ qua state trong `protected`
+pub fn apply_state_diff<ExtDB>(cache_db: &mut CacheDB<ExtDB>, diff: &StateDiff, protected: &ProtectedState) -> InvalidationReport {
+    let start = Instant::now();
+    let mut report = InvalidationReport { accounts_changed: diff.accounts.len(), ..Default::default() };
+    for (address, change) in &diff.accounts {
+        let Some(account) = cache_db.accounts.get_mut(address) else { continue };
+        let is_protected = protected.accounts.contains(address);
+
+        // Account mới xuất hiện / đổi code / bị xóa: gỡ hẳn, REVM load lại ở block mới
+        let replaced = change.destroyed || change.code.is_some() || account.account_state == AccountState::NotExisting;
+        if replaced {
+            if is_protected {
+                report.protected_skipped += 1;
+            } else {
+                cache_db.accounts.remove(address);
+                report.accounts_evicted += 1;
+            }
+            continue;
+        }
+
+        if change.balance.is_some() || change.nonce.is_some() {
+            if is_protected {
+                report.protected_skipped += 1;
+            } else {
+                account.info.balance = change.balance.unwrap_or(account.info.balance);
+                account.info.nonce = change.nonce.unwrap_or(account.info.nonce);
+                report.accounts_updated += 1;
+            }
+        }
+        for (slot, value) in &change.storage {
+            let Some(cached) = account.storage.get_mut(slot) else { continue };
+            if protected.slots.contains(&(*address, *slot)) {
+                report.protected_skipped += 1;
+            } else if cached != value {
+                *cached = *value;
+                report.slots_updated += 1;
+            }
+        }
+    }
+    report.elapsed = start.elapsed();
+    report
+}
+
+/// Fallback khi không có diff: gỡ mọi account / slot không được bảo vệ rồi nạp lại tại `block`
+/// trong một lượt batch, đếm những gì thực sự đổi
+pub async fn refresh_cached_state<ExtDB>(
+    cache_db: &mut CacheDB<ExtDB>,
+    multi_provider: &MultiProvider,
+    block: BlockId,
+    protected: &ProtectedState,
+) -> Result<InvalidationReport>
+where
+    ExtDB: DatabaseRef + PersistentState,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let start = Instant::now();
+
+    // 1️⃣ Gỡ state cũ, nhớ giá trị để so sánh
+    let mut old_infos: BTreeMap<Address, AccountInfo> = BTreeMap::new();
+    let mut old_slots: BTreeMap<(Address, U256), U256> = BTreeMap::new();
+    let addresses: Vec<Address> = cache_db.accounts.keys().copied().collect();
+    for address in addresses {
+        if protected.accounts.contains(&address) {
+            let account = cache_db.accounts.get_mut(&address).expect("address listed from cache");
+            account.storage.retain(|slot, value| {
+                let keep = protected.slots.contains(&(address, *slot));
+                if !keep {
+                    old_slots.insert((address, *slot), *value);
+                }
+                keep
+            });
+        } else {
+            let account = cache_db.accounts.remove(&address).expect("address listed from cache");
+            old_slots.extend(account.storage.iter().map(|(slot, value)| ((address, *slot), *value)));
+            old_infos.insert(address, account.info);
+        }
+    }
+
+    // 2️⃣ Nạp lại một lượt
+    let accounts: Vec<Address> = old_infos.keys().copied().collect();
+    let slots: Vec<(Address, U256)> = old_slots.keys().copied().collect();
+    prefetch_state(cache_db, multi_provider, block, &accounts, &slots, &PrefetchConfig::default()).await?;
+
+    // 3️⃣ Đếm thay đổi
+    let mut report = InvalidationReport { source: DiffSource::Refresh, ..Default::default() };
+    let mut changed = BTreeSet::new();
+    for (address, old) in &old_infos {
+        let Some(info) = cache_db.accounts.get(address).map(|account| &account.info) else { continue };
+        if info.code_hash != old.code_hash {
+            report.accounts_evicted += 1;
+            changed.insert(*address);
+        } else if info.balance != old.balance || info.nonce != old.nonce {
+            report.accounts_updated += 1;
+            changed.insert(*address);
+        }
+    }
+    for ((address, slot), old) in &old_slots {
+        let value = cache_db.accounts.get(address).and_then(|account| account.storage.get(slot));
+        if value != Some(old) {
+            report.slots_updated += 1;
+            changed.insert(*address);
+        }
+    }
+    report.accounts_changed = changed.len();
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::db::EmptyDB;
+
+    fn word(value: u64) -> String {
+        format!("0x{:064x}", value)
+    }
+
+    #[test]
+    fn test_parse_prestate_diff_zeroed_slots_and_selfdestruct() {
+        let (pool, victim, sender) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xcc), Address::repeat_byte(0x01));
+        let traces = vec![
+            json!({ "txHash": "0x01", "result": {
+                "pre": {
+                    format!("{pool:?}"): { "storage": { word(1): word(5), word(2): word(6) } },
+                    format!("{victim:?}"): { "balance": "0x1", "code": "0x00" },
+                    format!("{sender:?}"): { "balance": "0x10", "nonce": 3 },
+                },
+                "post": {
+                    format!("{pool:?}"): { "storage": { word(1): word(8) } },
+                    format!("{sender:?}"): { "balance": "0x0f", "nonce": 4 },
+                },
+            } }),
+            json!({ "txHash": "0x02", "result": {
+                "pre": { format!("{sender:?}"): { "balance": "0x0f", "nonce": 4 } },
+                "post": { format!("{sender:?}"): { "nonce": "0x5" } },
+            } }),
+        ];
+        let diff = parse_prestate_diff(&traces).unwrap();
+
+        let pool_diff = &diff.accounts[&pool];
+        assert_eq!(pool_diff.storage[&U256::from(1)], U256::from(8));
+        assert_eq!(pool_diff.storage[&U256::from(2)], U256::ZERO);
+        assert!(diff.accounts[&victim].destroyed);
+        assert_eq!((diff.accounts[&sender].balance, diff.accounts[&sender].nonce), (Some(U256::from(15)), Some(5)));
+        assert_eq!(diff.slot_count(), 2);
+
+        assert!(parse_prestate_diff(&[json!({ "txHash": "0x03", "error": "execution timeout" })]).is_err());
+    }
+
+    #[test]
+    fn test_apply_state_diff_only_touches_cached_unprotected_state() {
+        let (pool, token, victim) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb), Address::repeat_byte(0xcc));
+        let mut cache_db = CacheDB::new(EmptyDB::default());
+        cache_db.insert_account_info(token, AccountInfo::default());
+        cache_db.insert_account_storage(token, U256::from(1), U256::from(77)).unwrap();
+        let protected = ProtectedState::capture(&cache_db);
+        cache_db.insert_account_info(pool, AccountInfo::default());
+        cache_db.insert_account_storage(pool, U256::from(1), U256::from(5)).unwrap();
+        cache_db.insert_account_info(victim, AccountInfo::default());
+
+        let mut diff = StateDiff::default();
+        let pool_diff = diff.accounts.entry(pool).or_default();
+        pool_diff.storage.insert(U256::from(1), U256::from(8));
+        pool_diff.storage.insert(U256::from(9), U256::from(1));
+        pool_diff.balance = Some(U256::from(3));
+        diff.accounts.entry(token).or_default().storage.insert(U256::from(1), U256::ZERO);
+        diff.accounts.entry(victim).or_default().destroyed = true;
+        diff.accounts.entry(Address::repeat_byte(0xdd)).or_default().nonce = Some(1);
+
+        let report = apply_state_diff(&mut cache_db, &diff, &protected);
+        assert_eq!(report.accounts_changed, 4);
+        assert_eq!((report.accounts_updated, report.slots_updated), (1, 1));
+        assert_eq!((report.accounts_evicted, report.protected_skipped), (1, 1));
+
+        let pool_account = &cache_db.accounts[&pool];
+        assert_eq!(pool_account.storage[&U256::from(1)], U256::from(8));
+        assert!(!pool_account.storage.contains_key(&U256::from(9)));
+        assert_eq!(pool_account.info.balance, U256::from(3));
+        assert_eq!(cache_db.accounts[&token].storage[&U256::from(1)], U256::from(77));
+        assert!(!cache_db.accounts.contains_key(&victim));
+    }
+}
@@ -0,0 +1,468 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{Address, Bytes, B256, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::core::opportunity::fetch_latest_base_fee;
+use crate::core::provider::MultiProvider;
+use crate::source::builder::{FeePolicy, TxType};
+use crate::types::ChainConfig;
+
+/// Biến môi trường chứa private key (hex) khi không dùng keystore
+pub const PRIVATE_KEY_ENV: &str = "ARB_PRIVATE_KEY";
+/// Biến môi trường chứa password của keystore
+pub const KEYSTORE_PASSWORD_ENV: &str = "ARB_KEYSTORE_PASSWORD";
+
+/// Nguồn private key để ký transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerSource {
+    /// Private key hex trong biến môi trường
+    Env(String),
+    /// Keystore JSON (EIP-2335 / geth), password trong biến môi trường
+    Keystore { path: PathBuf, password_env: String },
+}
+
+impl SignerSource {
+    /// Keystore nếu có `path`, ngược lại đọc `ARB_PRIVATE_KEY`
+    pub fn from_args(keystore: Option<PathBuf>) -> Self {
+        match keystore {
+            Some(path) => SignerSource::Keystore { path, password_env: KEYSTORE_PASSWORD_ENV.to_string() },
+            None => SignerSource::Env(PRIVATE_KEY_ENV.to_string()),
+        }
+    }
+
+    pub fn load(&self) -> Result<PrivateKeySigner> {
+        match self {
+            SignerSource::Env(var) => {
+                let key = std::env::var(var).with_context(|| format!("Set {} to the signer private key", var))?;
+                key.trim().parse().map_err(|e| anyhow!("Invalid private key in {}: {}", var, e))
+            }
+            SignerSource::Keystore { path, password_env } => {
+                let password = std::env::var(password_env)
+                    .with_context(|| format!("Set {} to the password of keystore {}", password_env, path.display()))?;
+                PrivateKeySigner::decrypt_keystore(path, password)
+                    .map_err(|e| anyhow!("Cannot decrypt keystore {}: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+/// Nonce cấp phát local: lấy `pending` nonce từ RPC một lần, sau đó tự tăng.
+/// `reset` khi gửi lỗi để lần sau đọc lại từ chain.
+#[derive(Debug, Default)]
+pub struct NonceTracker {
+    next: Mutex<Option<u64>>,
+}
+
+impl NonceTracker {
+    /// Nonce sẽ dùng cho transaction kế tiếp (chưa cấp phát)
+    pub async fn peek(&self, multi_provider: &MultiProvider, address: Address) -> Result<u64> {
+        if let Some(nonce) = *self.next.lock().unwrap() {
+            return Ok(nonce);
+        }
+        let nonce = multi_provider
+            .request(|provider| async move { provider.get_transaction_count(address).pending().await })
+            .await?;
+        Ok(*self.next.lock().unwrap().get_or_insert(nonce))
+    }
+
+    /// Đánh dấu `nonce` đã dùng bởi transaction vừa gửi thành công
+    pub fn commit(&self, nonce: u64) {
+        let mut next = self.next.lock().unwrap();
+        *next = Some(next.map_or(nonce + 1, |current| current.max(nonce + 1)));
+    }
+
+    pub fn reset(&self) {
+        *self.next.lock().unwrap() = None;
+    }
+}
+
+/// Transaction đã ký
+#[derive(Debug, Clone)]
+pub struct SignedTx {
+    pub hash: B256,
+    pub nonce: u64,
+    pub raw: Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub enum Submission {
+    /// `--dry-run`: chỉ ký, không gửi, nonce không bị tiêu
+    DryRun(SignedTx),
+    Sent(SignedTx),
+}
+
+impl Submission {
+    pub fn signed(&self) -> &SignedTx {
+        match self {
+            Submission::DryRun(signed) | Submission::Sent(signed) => signed,
+        }
+    }
+}
+
+impl fmt::Display for Submission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Submission::DryRun(signed) => write!(f, "🧪 Dry run, not sent: {} (nonce {})\n    raw: {}", signed.hash, signed.nonce, signed.raw),
+            Submission::Sent(signed) => write!(f, "🚀 Sent {} (nonce {})", signed.hash, signed.nonce),
+        }
+    }
+}
+
+/// Chỉ cho ký / gửi khi block đã ghim là head hiện tại: cơ hội mô phỏng trên block cũ không còn trên chain
+pub async fn ensure_pinned_head(multi_provider: &MultiProvider) -> Result<()> {
+    let Some(pinned) = multi_provider.pinned() else { return Ok(()) };
+    let head = multi_provider.request(|provider| async move { provider.get_block_number().await }).await?;
+    if pinned.number < head {
+        bail!(
+            "Refusing to submit: simulated at block #{} but head is #{} (drop --block to submit against the head)",
+            pinned.number, head
+        );
+    }
+    Ok(())
+}
+
+/// Ký + gửi transaction bằng `eth_sendRawTransaction`, fee theo `FeePolicy` của chain.
+/// Mặc định `dry_run`: chỉ in raw transaction đã ký.
+pub struct Submitter {
+    signer: PrivateKeySigner,
+    wallet: EthereumWallet,
+    pub chain_id: u64,
+    pub fee_policy: FeePolicy,
+    pub tx_type: TxType,
+    pub gas_multiplier: f64,
+    pub dry_run: bool,
+    pub nonces: NonceTracker,
+}
+
+impl Submitter {
+    pub fn new(signer: PrivateKeySigner, config: &ChainConfig, dry_run: bool) -> Self {
+        Submitter {
+            wallet: EthereumWallet::from(signer.clone()),
+            signer,
+            chain_id: config.chain_id,
+            fee_policy: config.defaults.fee_policy,
+            tx_type: config.defaults.tx_type,
+            gas_multiplier: config.gas_multiplier,
+            dry_run,
+            nonces: NonceTracker::default(),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Transaction chưa ký: EIP-1559 hoặc legacy tuỳ chain
+    pub fn request(&self, to: Address, calldata: Bytes, value: U256, nonce: u64, gas_limit: u64, base_fee: u128) -> TransactionRequest {
+        let tx = TransactionRequest::default()
+            .with_from(self.address())
+            .with_to(to)
+            .with_input(calldata)
+            .with_value(value)
+            .with_nonce(nonce)
+            .with_gas_limit(gas_limit)
+            .with_chain_id(self.chain_id);
+        if self.tx_type == TxType::Legacy {
+            tx.with_gas_price(self.fee_policy.max_fee_per_gas(base_fee))
+        } else {
+            tx.with_max_fee_per_gas(self.fee_policy.max_fee_per_gas(base_fee))
+                .with_max_priority_fee_per_gas(self.fee_policy.max_priority_fee_per_gas(base_fee))
+        }
+    }
+
+    /// Ký transaction gọi `to`. `gas_limit = None` thì ước lượng bằng `eth_estimateGas * gas_multiplier`.
+    /// Fee theo base fee của block mới nhất, kể cả khi `multi_provider` đang ghim block cũ.
+    pub async fn sign(
+        &self,
+        multi_provider: &MultiProvider,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        gas_limit: Option<u64>,
+    ) -> Result<SignedTx> {
+        let nonce = self.nonces.peek(multi_provider, self.address()).await?;
+        let base_fee = fetch_latest_base_fee(multi_provider).await?;
+        let gas_limit = match gas_limit {
+            Some(gas_limit) => gas_limit,
+            None => {
+                let tx = TransactionRequest { gas: None, ..self.request(to, calldata.clone(), value, nonce, 0, base_fee) };
+                let estimate = multi_provider.request(|provider| {
+                    let tx = tx.clone();
+                    async move { provider.estimate_gas(&tx).await }
+                }).await?;
+                (estimate as f64 * self.gas_multiplier.max(1.0)).ceil() as u64
+            }
+        };
+
+        let envelope = self
+            .request(to, calldata, value, nonce, gas_limit, base_fee)
+            .build(&self.wallet)
+            .await
+            .map_err(|e| anyhow!("Cannot sign transaction: {}", e))?;
+        Ok(SignedTx { hash: *envelope.tx_hash(), nonce, raw: envelope.encoded_2718().into() })
+    }
+
+    /// Ký rồi gửi (hoặc chỉ trả raw tx nếu `dry_run`). Gửi lỗi thì đọc lại nonce từ chain ở lần sau.
+    pub async fn submit(
+        &self,
+        multi_provider: &MultiProvider,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        gas_limit: Option<u64>,
+    ) -> Result<Submission> {
+        let signed = self.sign(multi_provider, to, calldata, value, gas_limit).await?;
+        if self.dry_run {
+            return Ok(Submission::DryRun(signed));
+        }
+
+        let sent = multi_provider
+            .request(|provider| {
+                let raw = signed.raw.clone();
+                async move { provider.send_raw_transaction(&raw).await.map(|pending| *pending.tx_hash()) }
+            })
+            .await;
+        match sent {
+            Ok(hash) if hash == signed.hash => {
+                self.nonces.commit(signed.nonce);
+                Ok(Submission::Sent(signed))
+            }
+            Ok(hash) => {
+                self.nonces.reset();
+                Err(anyhow!("Node returned hash {} for signed transaction {}", hash, signed.hash))
+            }
+            Err(e) => {
+                self.nonces.reset();
+                Err(e.context(format!("eth_sendRawTransaction failed for {}", signed.hash)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_rpc::{mock_block, MockRpc};
+    use alloy::{
+        consensus::{Transaction as _, TxEnvelope},
+        eips::eip2718::Decodable2718,
+        node_bindings::Anvil,
+        primitives::{keccak256, TxKind},
+        providers::ProviderBuilder,
+    };
+    use serde_json::{json, Value};
+    use crate::types::ChainDefaults;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Key #0 của anvil / hardhat
+    const TEST_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const TO: Address = Address::repeat_byte(0x42);
+
+    fn test_config(chain_id: u64, rpc_url: &str) -> ChainConfig {
+        ChainConfig {
+            name: "test".to_string(),
+            chain_id,
+            rpc_url: rpc_url.to_string(),
+            rpc_urls: vec![rpc_url.to_string()],
+            gas_multiplier: 1.5,
+            me: Address::ZERO,
+            arb_executor: None,
+            factory: None,
+            pool_registry: None,
+            defaults: ChainDefaults {
+                native_token: "WETH".to_string(),
+                stable_token: "USDC".to_string(),
+                default_fee: 3000,
+                low_fee: 500,
+                fee_policy: FeePolicy::for_chain(chain_id),
+                tx_type: FeePolicy::for_chain(chain_id).default_tx_type(),
+                block_time: Duration::from_secs(12),
+            },
+            tokens: Vec::new(),
+            quoters: Vec::new(),
+            pools: Vec::new(),
+        }
+    }
+
+    /// Mock node: nonce 7, base fee 10 gwei, estimateGas 40000; `send_error` thì từ chối raw tx
+    async fn mock_node(methods: Arc<Mutex<Vec<String>>>, send_error: bool) -> MockRpc {
+        MockRpc::spawn(move |method, params: &Value| {
+            methods.lock().unwrap().push(method.to_string());
+            match method {
+                "eth_getTransactionCount" => Ok(json!("0x7")),
+                "eth_getBlockByNumber" => Ok(mock_block(100, Some(10_000_000_000))),
+                "eth_estimateGas" => Ok(json!("0x9c40")),
+                "eth_sendRawTransaction" if send_error => Err("nonce too low".to_string()),
+                "eth_sendRawTransaction" => {
+                    let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                    Ok(json!(keccak256(&raw)))
+                }
+                _ => Err(format!("unexpected method {method}")),
+            }
+        })
+        .await
+    }
+
+    fn decode(signed: &SignedTx) -> TxEnvelope {
+        TxEnvelope::decode_2718(&mut signed.raw.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_signer_from_env() {
+        std::env::set_var("ARB_TEST_SUBMIT_KEY", format!("0x{}\n", TEST_KEY));
+        let signer = SignerSource::Env("ARB_TEST_SUBMIT_KEY".to_string()).load().unwrap();
+        assert_eq!(signer.address(), alloy::primitives::address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+
+        assert!(SignerSource::Env("ARB_TEST_SUBMIT_KEY_MISSING".to_string()).load().is_err());
+        let keystore = SignerSource::from_args(Some(PathBuf::from("/nonexistent/keystore.json")));
+        assert_eq!(keystore, SignerSource::Keystore {
+            path: PathBuf::from("/nonexistent/keystore.json"),
+            password_env: KEYSTORE_PASSWORD_ENV.to_string(),
+        });
+    }
+
+    #[test]
+    fn test_fee_policy_picks_tx_type() {
+        let signer: PrivateKeySigner = TEST_KEY.parse().unwrap();
+        let base_fee = 10_000_000_000;
+
+        let eth = Submitter::new(signer.clone(), &test_config(1, "http://localhost"), true);
+        let tx = eth.request(TO, Bytes::new(), U256::ZERO, 0, 21_000, base_fee);
+        assert_eq!(tx.gas_price, None);
+        assert_eq!(tx.max_fee_per_gas, Some(eth.fee_policy.max_fee_per_gas(base_fee)));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(eth.fee_policy.max_priority_fee_per_gas(base_fee)));
+
+        let ronin = Submitter::new(signer, &test_config(2020, "http://localhost"), true);
+        let tx = ronin.request(TO, Bytes::new(), U256::ZERO, 0, 21_000, base_fee);
+        assert_eq!((tx.gas_price, tx.max_fee_per_gas), (Some(base_fee), None));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dry_run_signs_without_sending() {
+        let methods = Arc::new(Mutex::new(Vec::new()));
+        let mock = mock_node(methods.clone(), false).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let submitter = Submitter::new(TEST_KEY.parse().unwrap(), &test_config(1, &mock.url), true);
+
+        let calldata = Bytes::from_static(&[0xde, 0xad]);
+        let submission = submitter.submit(&multi_provider, TO, calldata.clone(), U256::from(5), None).await.unwrap();
+        let Submission::DryRun(signed) = &submission else { panic!("dry run must not send: {}", submission) };
+
+        let envelope = decode(signed);
+        assert_eq!(*envelope.tx_hash(), signed.hash);
+        assert_eq!(envelope.recover_signer().unwrap(), submitter.address());
+        assert!(matches!(envelope, TxEnvelope::Eip1559(_)));
+        assert_eq!((envelope.nonce(), envelope.chain_id()), (7, Some(1)));
+        assert_eq!((envelope.kind(), envelope.value()), (TxKind::Call(TO), U256::from(5)));
+        assert_eq!(envelope.input(), &calldata);
+        // estimateGas 40000 * gas_multiplier 1.5
+        assert_eq!(envelope.gas_limit(), 60_000);
+
+        // Dry run không tiêu nonce
+        let again = submitter.submit(&multi_provider, TO, calldata, U256::from(5), Some(21_000)).await.unwrap();
+        assert_eq!(again.signed().nonce, 7);
+        let methods = methods.lock().unwrap();
+        assert!(!methods.iter().any(|method| method == "eth_sendRawTransaction"));
+        assert_eq!(methods.iter().filter(|method| *method == "eth_getTransactionCount").count(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_tracks_nonce_and_resets_on_error() {
+        let methods = Arc::new(Mutex::new(Vec::new()));
+        let mock = mock_node(methods.clone(), false).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        let submitter = Submitter::new(TEST_KEY.parse().unwrap(), &test_config(1, &mock.url), false);
+
+        for nonce in 7..10 {
+            let submission = submitter.submit(&multi_provider, TO, Bytes::new(), U256::ZERO, Some(21_000)).await.unwrap();
+            assert!(matches!(submission, Submission::Sent(_)));
+            assert_eq!(submission.signed().nonce, nonce);
+        }
+        assert_eq!(methods.lock().unwrap().iter().filter(|method| *method == "eth_getTransactionCount").count(), 1);
+
+        // Node từ chối: nonce đọc lại từ chain ở lần sau
+        let failing = mock_node(methods.clone(), true).await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&failing.url));
+        assert!(submitter.submit(&multi_provider, TO, Bytes::new(), U256::ZERO, Some(21_000)).await.is_err());
+        let signed = submitter.sign(&multi_provider, TO, Bytes::new(), U256::ZERO, Some(21_000)).await.unwrap();
+        assert_eq!(signed.nonce, 7);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sign_uses_latest_base_fee_when_pinned() {
+        // Block 100 base fee 10 gwei, head 30 gwei
+        let mock = MockRpc::spawn(|method, params: &Value| match method {
+            "eth_getBlockByNumber" if params[0] == json!("latest") => Ok(mock_block(120, Some(30_000_000_000))),
+            "eth_getBlockByNumber" => Ok(mock_block(100, Some(10_000_000_000))),
+            "eth_getTransactionCount" => Ok(json!("0x7")),
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        multi_provider.pin_block(alloy::eips::BlockId::number(100)).await.unwrap();
+        let submitter = Submitter::new(TEST_KEY.parse().unwrap(), &test_config(1, &mock.url), true);
+
+        let signed = submitter.sign(&multi_provider, TO, Bytes::new(), U256::ZERO, Some(21_000)).await.unwrap();
+        let envelope = decode(&signed);
+        assert_eq!(envelope.max_fee_per_gas(), submitter.fee_policy.max_fee_per_gas(30_000_000_000));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_refuses_submit_on_old_block() {
+        let mock = MockRpc::spawn(|method, params: &Value| match method {
+            "eth_getBlockByNumber" => {
+                let number = u64::from_str_radix(params[0].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+                Ok(mock_block(number, Some(10)))
+            }
+            "eth_blockNumber" => Ok(json!("0x78")),
+            _ => Err(format!("unexpected method {method}")),
+        })
+        .await;
+        let multi_provider = MultiProvider::new(std::slice::from_ref(&mock.url));
+        multi_provider.pin_block(alloy::eips::BlockId::number(100)).await.unwrap();
+        let err = ensure_pinned_head(&multi_provider).await.unwrap_err();
+        assert!(err.to_string().contains("head is #120"), "{}", err);
+
+        multi_provider.pin_block(alloy::eips::BlockId::number(120)).await.unwrap();
+        ensure_pinned_head(&multi_provider).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires anvil"]
+    async fn test_submit_to_anvil() {
+        let anvil = Anvil::new().spawn();
+        let signer = PrivateKeySigner::from(anvil.keys()[0].clone());
+        let config = test_config(anvil.chain_id(), anvil.endpoint().as_str());
+        let multi_provider = MultiProvider::new(&config.rpc_urls);
+        let provider = ProviderBuilder::new().on_http(anvil.endpoint_url());
+
+        // Dry run: không có gì lên chain
+        let dry = Submitter::new(signer.clone(), &config, true);
+        let submission = dry.submit(&multi_provider, TO, Bytes::new(), U256::from(1), None).await.unwrap();
+        assert!(matches!(submission, Submission::DryRun(_)));
+        assert_eq!(provider.get_transaction_count(dry.address()).await.unwrap(), 0);
+        assert_eq!(provider.get_balance(TO).await.unwrap(), U256::ZERO);
+
+        // Gửi thật hai lần liên tiếp, nonce tự tăng local
+        let submitter = Submitter::new(signer, &config, false);
+        for nonce in 0..2 {
+            let submission = submitter.submit(&multi_provider, TO, Bytes::new(), U256::from(1), None).await.unwrap();
+            assert_eq!(submission.signed().nonce, nonce);
+            let receipt = provider.get_transaction_receipt(submission.signed().hash).await.unwrap().expect("anvil automines");
+            assert!(receipt.status());
+        }
+        assert_eq!(provider.get_transaction_count(submitter.address()).await.unwrap(), 2);
+        assert_eq!(provider.get_balance(TO).await.unwrap(), U256::from(2));
+    }
+}
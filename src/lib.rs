@@ -1,4 +1,5 @@
 pub mod source;     // Giữ nguyên: abi encode/decode, builder
 pub mod core;       // Tất cả logic call/revm/arb/validate
 pub mod types;      // Struct ChainConfig, load từ toml
-pub mod chain;
\ No newline at end of file
+pub mod chain;
+pub mod univ3;      // Swap math UniswapV3 thuần Rust (native quote)
@@ -1,3 +1,4 @@
+use crate::core::bundle::{transact_bundle, Bundle, BundleResult};
 use crate::simulation::{state_db::StateDB, SimulationStrategyTrait};
 use alloy_eips::{BlockId, BlockNumberOrTag};
 use alloy_primitives::{address, aliases::U112, Address, Bytes, TxKind, B256, U256};
@@ -233,6 +234,18 @@ impl SimulationStrategyTrait for Revm {
         Ok(result)
     }
 
+    fn transact_bundle(&mut self, bundle: &Bundle, me: Address, tokens: &[Address]) -> Result<BundleResult, Error> {
+        let db = self.db.as_mut().ok_or_else(|| Error::msg("DB is not initialized"))?;
+        // Cùng chain id với `RTransaction`
+        let result = transact_bundle(db, bundle, &self.block, 1, me, tokens)?;
+        if result.is_success() {
+            for outcome in &result.outcomes {
+                self.handle_logs(&outcome.logs().to_vec())?;
+            }
+        }
+        Ok(result)
+    }
+
     fn get_state(&self) -> State {
         self.state.clone()
     }
@@ -1,3 +1,5 @@
+use crate::core::bundle::{Bundle, BundleResult};
+use alloy_primitives::Address;
 use anyhow::Error;
 use e_primitives::structs::{State, Transaction};
 use revm::primitives::{ExecutionResult, ResultAndState};
@@ -23,6 +25,18 @@ pub trait SimulationStrategyTrait {
     ///
     /// * `Result<ExecutionResult, Error>` - Returns the result of the execution if successful, otherwise returns an `Error`.
     fn transact_commit(&mut self, tx: &Transaction) -> Result<ExecutionResult, Error>;
+    /// Executes an ordered bundle atomically and commits it only if every transaction succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `bundle` - The ordered transactions (victim, arb, approve...) to be executed.
+    /// * `me` - The account whose balances of `tokens` (and native token) are reported.
+    /// * `tokens` - The ERC20 tokens to report balance deltas for.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BundleResult, Error>` - Returns per-transaction outcomes, the index of the first reverted transaction if any, and the balance deltas of `me`.
+    fn transact_bundle(&mut self, bundle: &Bundle, me: Address, tokens: &[Address]) -> Result<BundleResult, Error>;
 
     fn get_state(&self) -> State;
 }
@@ -35,11 +35,51 @@ sol! {
     ) external;
 }
 
+sol! {
+    /// Event của `UniswapV3Pool` dùng để cập nhật state pool incremental
+    interface IUniswapV3PoolEvents {
+        event Swap(
+            address indexed sender,
+            address indexed recipient,
+            int256 amount0,
+            int256 amount1,
+            uint160 sqrtPriceX96,
+            uint128 liquidity,
+            int24 tick
+        );
+
+        event Mint(
+            address sender,
+            address indexed owner,
+            int24 indexed tickLower,
+            int24 indexed tickUpper,
+            uint128 amount,
+            uint256 amount0,
+            uint256 amount1
+        );
+
+        event Burn(
+            address indexed owner,
+            int24 indexed tickLower,
+            int24 indexed tickUpper,
+            uint128 amount,
+            uint256 amount0,
+            uint256 amount1
+        );
+    }
+}
+
 pub fn decode_quote_response(response: Bytes) -> Result<u128> {
     let (amount_out, _, _, _) = <(u128, u128, u32, u128)>::abi_decode(&response, false)?;
     Ok(amount_out)
 }
 
+/// Decode `amountOut` từ response của `quoteExactInput` (multi-hop path)
+pub fn decode_quote_exact_input_response(response: Bytes) -> Result<U256> {
+    let decoded = quoteExactInputCall::abi_decode_returns(&response, false)?;
+    Ok(decoded.amountOut)
+}
+
 pub fn decode_get_amount_out_response(response: Bytes) -> Result<u128> {
     let value = response.to_vec();
     let last_64_bytes = &value[value.len() - 64..];
@@ -157,3 +197,98 @@ pub fn quote_exact_input_single_calldata(
     println!("encoded: {:?}", encoded.clone());
     Bytes::from(encoded)
 }
+
+sol! {
+    /// ERC20 tối thiểu: đọc balance + approve trong bundle
+    function balanceOf(address account) external view returns (uint256);
+    function approve(address spender, uint256 amount) external returns (bool);
+}
+
+pub fn balance_of_calldata(account: Address) -> Bytes {
+    Bytes::from(balanceOfCall { account }.abi_encode())
+}
+
+pub fn decode_balance_of_response(response: Bytes) -> Result<U256> {
+    Ok(balanceOfCall::abi_decode_returns(&response, false)?._0)
+}
+
+pub fn approve_calldata(spender: Address, amount: U256) -> Bytes {
+    Bytes::from(approveCall { spender, amount }.abi_encode())
+}
+
+sol! {
+    /// Flash-swap cycle của `src/contracts/arb_executor.sol`
+    function execute(bytes path, uint256 amountIn, uint256 minProfit) external returns (uint256 profit);
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes data) external;
+    function owner() external view returns (address);
+}
+
+/// Path của executor: mỗi hop 41 byte `pool | tokenIn | zeroForOne`
+pub fn encode_executor_path(hops: &[(Address, Address, bool)]) -> Bytes {
+    let mut path = Vec::with_capacity(hops.len() * 41);
+    for (pool, token_in, zero_for_one) in hops {
+        path.extend_from_slice(pool.as_slice());
+        path.extend_from_slice(token_in.as_slice());
+        path.push(*zero_for_one as u8);
+    }
+    Bytes::from(path)
+}
+
+pub fn execute_calldata(path: Bytes, amount_in: U256, min_profit: U256) -> Bytes {
+    Bytes::from(executeCall { path, amountIn: amount_in, minProfit: min_profit }.abi_encode())
+}
+
+pub fn decode_execute_response(response: Bytes) -> Result<U256> {
+    Ok(executeCall::abi_decode_returns(&response, false)?.profit)
+}
+
+pub fn owner_calldata() -> Bytes {
+    Bytes::from(ownerCall {}.abi_encode())
+}
+
+pub fn decode_owner_response(response: Bytes) -> Result<Address> {
+    Ok(ownerCall::abi_decode_returns(&response, false)?._0)
+}
+
+sol! {
+    /// `UniswapV3Factory`: tra pool theo cặp token + fee, event khi tạo pool mới
+    interface IUniswapV3Factory {
+        event PoolCreated(
+            address indexed token0,
+            address indexed token1,
+            uint24 indexed fee,
+            int24 tickSpacing,
+            address pool
+        );
+
+        function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
+        function feeAmountTickSpacing(uint24 fee) external view returns (int24);
+        function createPool(address tokenA, address tokenB, uint24 fee) external returns (address pool);
+    }
+
+    function liquidity() external view returns (uint128);
+}
+
+pub fn get_pool_calldata(token_a: Address, token_b: Address, fee: u32) -> Bytes {
+    Bytes::from(IUniswapV3Factory::getPoolCall { tokenA: token_a, tokenB: token_b, fee: U24::from(fee) }.abi_encode())
+}
+
+pub fn decode_get_pool_response(response: Bytes) -> Result<Address> {
+    Ok(IUniswapV3Factory::getPoolCall::abi_decode_returns(&response, false)?.pool)
+}
+
+pub fn fee_tick_spacing_calldata(fee: u32) -> Bytes {
+    Bytes::from(IUniswapV3Factory::feeAmountTickSpacingCall { fee: U24::from(fee) }.abi_encode())
+}
+
+pub fn decode_fee_tick_spacing_response(response: Bytes) -> Result<i32> {
+    Ok(IUniswapV3Factory::feeAmountTickSpacingCall::abi_decode_returns(&response, false)?._0.as_i32())
+}
+
+pub fn liquidity_calldata() -> Bytes {
+    Bytes::from(liquidityCall {}.abi_encode())
+}
+
+pub fn decode_liquidity_response(response: Bytes) -> Result<u128> {
+    Ok(liquidityCall::abi_decode_returns(&response, false)?._0)
+}
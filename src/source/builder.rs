@@ -3,6 +3,7 @@ use alloy::{
     primitives::{Address, Bytes, U256},
     rpc::types::TransactionRequest,
 };
+use serde::Deserialize;
 use std::time::Duration;
 use tokio::time::Instant;
 
@@ -74,7 +75,7 @@ pub fn build_tx_ronin(
     from: Address, 
     calldata: Bytes, 
     base_fee: u128, 
-    chain_id: Option<u64> // Use Option for default handling
+    _chain_id: Option<u64> // Use Option for default handling
 ) -> TransactionRequest {
     // Use chain_id or fallback to Avalanche's default chain ID
     // let chain_id = chain_id.unwrap_or(2020); // Ronin
@@ -85,10 +86,137 @@ pub fn build_tx_ronin(
         .with_input(calldata)
         .nonce(0)
         .gas_limit(1_000_000)
-        .max_fee_per_gas(1*base_fee)
+        .max_fee_per_gas(base_fee)
         .max_priority_fee_per_gas(0)
         // .with_chain_id(chain_id) // Ronin
         .build_unsigned()
         .unwrap()
         .into()
 }
+
+/// Chính sách fee theo chain, cùng công thức với `build_tx` / `build_tx_avalanche` / `build_tx_ronin`.
+/// Khai báo trong `[defaults]` của chain TOML: `fee_policy = "eth"` hoặc
+/// `fee_policy = { custom = { max_fee_bps = 12500, priority_fee_bps = 500 } }` (bps của base fee)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeePolicy {
+    /// max = 1.2 * base, priority = 0.1 * base
+    Eth,
+    /// max = 10 * base, priority = 2 * base
+    Avalanche,
+    /// max = base, priority = 0
+    Ronin,
+    /// max = base * max_fee_bps / 10000, priority = base * priority_fee_bps / 10000
+    Custom { max_fee_bps: u32, priority_fee_bps: u32 },
+}
+
+/// Loại transaction khi ký: EIP-1559 hoặc legacy `gasPrice`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxType {
+    Eip1559,
+    Legacy,
+}
+
+impl FeePolicy {
+    pub fn for_chain(chain_id: u64) -> Self {
+        match chain_id {
+            43114 => FeePolicy::Avalanche,
+            2020 | 2021 => FeePolicy::Ronin,
+            _ => FeePolicy::Eth,
+        }
+    }
+
+    pub fn max_fee_per_gas(&self, base_fee: u128) -> u128 {
+        match self {
+            FeePolicy::Eth => base_fee * 12 / 10,
+            FeePolicy::Avalanche => 10 * base_fee,
+            FeePolicy::Ronin => base_fee,
+            FeePolicy::Custom { max_fee_bps, .. } => base_fee * *max_fee_bps as u128 / 10_000,
+        }
+    }
+
+    pub fn max_priority_fee_per_gas(&self, base_fee: u128) -> u128 {
+        match self {
+            FeePolicy::Eth => base_fee / 10,
+            FeePolicy::Avalanche => base_fee * 2,
+            FeePolicy::Ronin => 0,
+            FeePolicy::Custom { priority_fee_bps, .. } => base_fee * *priority_fee_bps as u128 / 10_000,
+        }
+    }
+
+    /// Ronin: max = base, priority = 0 nên ký transaction legacy với `gasPrice = base`
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, FeePolicy::Ronin)
+    }
+
+    /// Loại tx mặc định khi chain config không khai báo `tx_type`
+    pub fn default_tx_type(&self) -> TxType {
+        if self.is_legacy() { TxType::Legacy } else { TxType::Eip1559 }
+    }
+
+    /// Giá gas thực trả theo EIP-1559: min(maxFee, base + priority)
+    pub fn effective_gas_price(&self, base_fee: u128) -> u128 {
+        self.max_fee_per_gas(base_fee).min(base_fee + self.max_priority_fee_per_gas(base_fee))
+    }
+
+    pub fn build_tx(&self, to: Address, from: Address, calldata: Bytes, base_fee: u128, chain_id: u64) -> TransactionRequest {
+        match self {
+            FeePolicy::Eth => build_tx(to, from, calldata, base_fee),
+            FeePolicy::Avalanche => build_tx_avalanche(to, from, calldata, base_fee, Some(chain_id)),
+            FeePolicy::Ronin => build_tx_ronin(to, from, calldata, base_fee, Some(chain_id)),
+            FeePolicy::Custom { .. } => TransactionRequest::default()
+                .to(to)
+                .from(from)
+                .with_input(calldata)
+                .nonce(0)
+                .gas_limit(1_000_000)
+                .max_fee_per_gas(self.max_fee_per_gas(base_fee))
+                .max_priority_fee_per_gas(self.max_priority_fee_per_gas(base_fee))
+                .with_chain_id(chain_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_policy_matches_builders() {
+        let base_fee = 20_000_000_000u128;
+        for (chain_id, policy) in [(1, FeePolicy::Eth), (43114, FeePolicy::Avalanche), (2020, FeePolicy::Ronin)] {
+            assert_eq!(FeePolicy::for_chain(chain_id), policy);
+            let tx = policy.build_tx(Address::ZERO, Address::ZERO, Bytes::new(), base_fee, chain_id);
+            assert_eq!(tx.max_fee_per_gas, Some(policy.max_fee_per_gas(base_fee)));
+            assert_eq!(tx.max_priority_fee_per_gas, Some(policy.max_priority_fee_per_gas(base_fee)));
+        }
+        assert_eq!(FeePolicy::Eth.effective_gas_price(base_fee), base_fee * 11 / 10);
+        // Avalanche: base + 2*base < 10*base
+        assert_eq!(FeePolicy::Avalanche.effective_gas_price(base_fee), base_fee * 3);
+        assert_eq!(FeePolicy::Ronin.effective_gas_price(base_fee), base_fee);
+    }
+
+    #[test]
+    fn test_custom_fee_policy_from_toml() {
+        #[derive(Deserialize)]
+        struct Defaults {
+            fee_policy: FeePolicy,
+            tx_type: TxType,
+        }
+        let defaults: Defaults = toml::from_str(
+            "fee_policy = { custom = { max_fee_bps = 12500, priority_fee_bps = 500 } }\ntx_type = \"legacy\"",
+        ).unwrap();
+        assert_eq!(defaults.tx_type, TxType::Legacy);
+        let policy = defaults.fee_policy;
+        let base_fee = 20_000_000_000u128;
+        let tx = policy.build_tx(Address::ZERO, Address::ZERO, Bytes::new(), base_fee, 42161);
+        assert_eq!((tx.max_fee_per_gas, tx.max_priority_fee_per_gas), (Some(base_fee * 5 / 4), Some(base_fee / 20)));
+        assert_eq!(tx.chain_id, Some(42161));
+        assert_eq!(policy.effective_gas_price(base_fee), base_fee * 21 / 20);
+        assert_eq!(policy.default_tx_type(), TxType::Eip1559);
+
+        let preset: Defaults = toml::from_str("fee_policy = \"ronin\"\ntx_type = \"eip1559\"").unwrap();
+        assert_eq!(preset.fee_policy, FeePolicy::Ronin);
+    }
+}
@@ -1,60 +1,441 @@
-use std::collections::HashMap;
-use anyhow::{anyhow, Result};
-use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
 
 use alloy::{
     primitives::{Address, U256},
     uint,
 };
 
+use crate::source::builder::{FeePolicy, TxType};
+use crate::univ3::pool::tick_spacing_for_fee;
+
 pub static ONE_ETHER: U256 = uint!(1_000_000_000_000_000_000_U256);
 
 /// Struct ánh xạ nội dung từ file `eth.toml`, `avax.toml`, ...
 #[derive(Debug, Deserialize)]
 pub struct ChainConfigRaw {
+    /// Mặc định là tên file config (`eth` cho `eth.toml`)
+    pub name: Option<String>,
     pub chain_id: u64,
     pub rpc_url: String,
     pub rpc_urls: Option<Vec<String>>, // NEW
     pub gas_multiplier: f64,
-    pub tokens: HashMap<String, String>,
+    pub me: String,
+    pub arb_executor: Option<String>,
+    /// `UniswapV3Factory` dùng cho `--method discover`
+    pub factory: Option<String>,
+    /// Registry pool do `--method discover` sinh ra, đường dẫn tương đối với file config
+    pub pool_registry: Option<String>,
+    pub defaults: ChainDefaultsRaw,
+    #[serde(default)]
+    pub tokens: Vec<TokenConfigRaw>,
+    #[serde(default)]
+    pub quoters: Vec<QuoterConfigRaw>,
+    #[serde(default)]
+    pub pools: Vec<PoolConfigRaw>,
+}
+
+/// `[defaults]`: vai trò token (symbol trong `[[tokens]]`), fee tier, fee policy, loại tx, block time
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainDefaultsRaw {
+    pub native_token: String,
+    pub stable_token: String,
+    pub default_fee: Option<u32>,
+    pub low_fee: Option<u32>,
+    pub fee_policy: Option<FeePolicy>,
+    pub tx_type: Option<TxType>,
+    pub block_time_ms: Option<u64>,
+}
+
+/// Mặc định của chain sau khi điền giá trị thiếu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainDefaults {
+    pub native_token: String,
+    pub stable_token: String,
+    /// Fee tier pool native / stable chính
+    pub default_fee: u32,
+    /// Fee tier pool còn lại trong arbitrage 2 pool
+    pub low_fee: u32,
+    pub fee_policy: FeePolicy,
+    pub tx_type: TxType,
+    pub block_time: Duration,
+}
+
+impl ChainDefaultsRaw {
+    /// Thiếu `fee_policy` thì theo chain id, thiếu `tx_type` thì theo fee policy
+    pub fn resolve(self, chain_id: u64) -> ChainDefaults {
+        let fee_policy = self.fee_policy.unwrap_or_else(|| FeePolicy::for_chain(chain_id));
+        ChainDefaults {
+            native_token: self.native_token,
+            stable_token: self.stable_token,
+            default_fee: self.default_fee.unwrap_or(3000),
+            low_fee: self.low_fee.unwrap_or(500),
+            fee_policy,
+            tx_type: self.tx_type.unwrap_or_else(|| fee_policy.default_tx_type()),
+            block_time: Duration::from_millis(self.block_time_ms.unwrap_or(12_000)),
+        }
+    }
+}
+
+/// Một token trong `[[tokens]]`, `balance_slot` là slot của mapping `balances` (layout Solidity) nếu đã biết
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfigRaw {
+    pub symbol: String,
+    pub address: String,
+    pub decimals: u8,
+    pub balance_slot: Option<u64>,
+}
+
+/// Token sau khi parse address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenConfig {
+    pub symbol: String,
+    pub address: Address,
+    pub decimals: u8,
+    pub balance_slot: Option<U256>,
+}
+
+/// Loại quoter: `uniswap_v3` là QuoterV2 on-chain, `custom` là `UniV3Quoter` nạp bytecode vào cache db
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoterKind {
+    UniswapV3,
+    Custom,
+}
+
+/// Một quoter trong `[[quoters]]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuoterConfigRaw {
+    pub kind: QuoterKind,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuoterConfig {
+    pub kind: QuoterKind,
+    pub address: Address,
+}
+
+/// Giao thức của pool; đồ thị cycle hiện chỉ swap được `univ3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Protocol {
+    #[serde(rename = "univ2")]
+    UniV2,
+    #[serde(rename = "univ3")]
+    UniV3,
+    #[serde(rename = "algebra")]
+    Algebra,
+    #[serde(rename = "lb")]
+    LB,
+}
+
+/// Một pool trong `[[pools]]`, `token0` / `token1` là symbol trong `[[tokens]]`.
+/// `tick_spacing` mặc định theo fee tier với `univ3`, bắt buộc với `algebra` và `lb` (bin step).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolConfigRaw {
+    pub protocol: Protocol,
+    pub address: String,
+    pub token0: String,
+    pub token1: String,
+    pub fee: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick_spacing: Option<i32>,
+}
+
+/// File registry pool (TOML) do `--method discover` ghi; `[[pools]]` cùng format với chain config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolRegistry {
+    pub chain_id: u64,
+    pub factory: String,
+    /// Block dùng để tìm pool + đọc liquidity
+    pub block: u64,
+    #[serde(default)]
+    pub pools: Vec<PoolConfigRaw>,
+}
+
+impl PoolRegistry {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Pool sau khi resolve address, giữ lại symbol token để in log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub protocol: Protocol,
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub fee: u32,
+    pub tick_spacing: Option<i32>,
 }
 
 /// Struct dùng trong toàn bộ codebase sau khi parse địa chỉ thành `Address`
 #[derive(Debug)]
 pub struct ChainConfig {
+    pub name: String,
     pub chain_id: u64,
     pub rpc_url: String,
     pub rpc_urls: Vec<String>, // NEW
     pub gas_multiplier: f64,
-    pub tokens: HashMap<String, Address>,
+    pub me: Address,
+    pub arb_executor: Option<Address>,
+    pub factory: Option<Address>,
+    pub pool_registry: Option<PathBuf>,
+    pub defaults: ChainDefaults,
+    pub tokens: Vec<TokenConfig>,
+    pub quoters: Vec<QuoterConfig>,
+    pub pools: Vec<PoolConfig>,
 }
 
 impl ChainConfig {
-    pub fn addr(&self, key: &str) -> Result<Address> {
+    pub fn token(&self, symbol: &str) -> Result<&TokenConfig> {
         self.tokens
-            .get(key)
-            .copied()
-            .ok_or_else(|| anyhow!("Missing address for token/key: {}", key))
+            .iter()
+            .find(|token| token.symbol == symbol)
+            .ok_or_else(|| anyhow!("Missing [[tokens]] entry for symbol: {}", symbol))
+    }
+
+    pub fn quoter(&self, kind: QuoterKind) -> Result<Address> {
+        self.quoters
+            .iter()
+            .find(|quoter| quoter.kind == kind)
+            .map(|quoter| quoter.address)
+            .ok_or_else(|| anyhow!("Missing [[quoters]] entry of kind {:?}", kind))
+    }
+
+    /// Pool theo cặp token (không phân biệt thứ tự) + fee tier
+    pub fn pool(&self, token_a: Address, token_b: Address, fee: u32) -> Result<&PoolConfig> {
+        self.pools
+            .iter()
+            .find(|pool| {
+                pool.fee == fee
+                    && ((pool.token0, pool.token1) == (token_a, token_b) || (pool.token0, pool.token1) == (token_b, token_a))
+            })
+            .ok_or_else(|| anyhow!("Missing [[pools]] entry for {:?}/{:?} fee {}", token_a, token_b, fee))
+    }
+
+    /// Pool đồ thị cycle swap được (UniV3)
+    pub fn univ3_pools(&self) -> Vec<PoolConfig> {
+        self.pools.iter().filter(|pool| pool.protocol == Protocol::UniV3).cloned().collect()
     }
 }
 
+fn parse_address(value: &str, what: &str) -> Result<Address> {
+    value.parse::<Address>().map_err(|e| anyhow!("Invalid address for {}: {}", what, e))
+}
+
 /// Load + parse file TOML thành `ChainConfig`
+/// Pool trong `pool_registry` (nếu file đã có) được thêm sau `[[pools]]`, pool khai báo tay được ưu tiên
 pub fn load_chain_config(path: &str) -> Result<ChainConfig> {
     let raw_content = std::fs::read_to_string(path)?;
-    let raw: ChainConfigRaw = toml::from_str(&raw_content)?;
+    let mut raw: ChainConfigRaw = toml::from_str(&raw_content).map_err(|e| anyhow!("{}: {}", path, e))?;
+    if raw.name.is_none() {
+        raw.name = Path::new(path).file_stem().map(|stem| stem.to_string_lossy().into_owned());
+    }
+
+    let registry_path = raw.pool_registry.as_ref()
+        .map(|registry| Path::new(path).parent().unwrap_or(Path::new("")).join(registry));
+    if let Some(registry_path) = registry_path.as_ref().filter(|registry_path| registry_path.exists()) {
+        let registry = PoolRegistry::load(registry_path)?;
+        if registry.chain_id != raw.chain_id {
+            bail!("{}: chain_id {} does not match {}", registry_path.display(), registry.chain_id, raw.chain_id);
+        }
+        let declared: HashSet<String> = raw.pools.iter().map(|pool| pool.address.to_lowercase()).collect();
+        raw.pools.extend(registry.pools.into_iter().filter(|pool| !declared.contains(&pool.address.to_lowercase())));
+    }
+
+    let mut config = parse_chain_config(raw).map_err(|e| anyhow!("{}: {}", path, e))?;
+    config.pool_registry = registry_path;
+    Ok(config)
+}
+
+/// Parse + kiểm tra tham chiếu chéo: symbol / address không trùng, pool trỏ tới token đã khai báo,
+/// `token0 < token1` như factory sắp xếp
+pub fn parse_chain_config(raw: ChainConfigRaw) -> Result<ChainConfig> {
+    let mut tokens = Vec::new();
+    let mut by_symbol = HashMap::new();
+    for token in raw.tokens.iter() {
+        let address = parse_address(&token.address, &token.symbol)?;
+        if by_symbol.insert(token.symbol.clone(), address).is_some() {
+            bail!("Duplicate token symbol {}", token.symbol);
+        }
+        if let Some(other) = tokens.iter().find(|other: &&TokenConfig| other.address == address) {
+            bail!("Tokens {} and {} share address {:?}", other.symbol, token.symbol, address);
+        }
+        tokens.push(TokenConfig {
+            symbol: token.symbol.clone(),
+            address,
+            decimals: token.decimals,
+            balance_slot: token.balance_slot.map(U256::from),
+        });
+    }
+
+    let mut quoters: Vec<QuoterConfig> = Vec::new();
+    for quoter in raw.quoters.iter() {
+        if quoters.iter().any(|other| other.kind == quoter.kind) {
+            bail!("Duplicate quoter of kind {:?}", quoter.kind);
+        }
+        quoters.push(QuoterConfig {
+            kind: quoter.kind,
+            address: parse_address(&quoter.address, &format!("{:?} quoter", quoter.kind))?,
+        });
+    }
 
-    let mut parsed = HashMap::new();
-    for (k, v) in raw.tokens.iter() {
-        let addr = v.parse::<Address>()
-            .map_err(|e| anyhow!("Invalid address for {}: {}", k, e))?;
-        parsed.insert(k.clone(), addr);
+    let mut pools = Vec::new();
+    let mut pool_addresses = HashSet::new();
+    for pool in raw.pools.iter() {
+        let address = parse_address(&pool.address, "pool")?;
+        if !pool_addresses.insert(address) {
+            bail!("Duplicate pool {:?}", address);
+        }
+        let token = |symbol: &str| {
+            by_symbol.get(symbol).copied()
+                .ok_or_else(|| anyhow!("Pool {:?} references unknown token {}", address, symbol))
+        };
+        let (token0, token1) = (token(&pool.token0)?, token(&pool.token1)?);
+        if token0 >= token1 {
+            bail!("Pool {:?}: token0 {} must sort below token1 {}", address, pool.token0, pool.token1);
+        }
+        let tick_spacing = match (pool.protocol, pool.tick_spacing) {
+            (Protocol::UniV2, Some(_)) => bail!("Pool {:?}: univ2 has no tick_spacing", address),
+            (Protocol::UniV2, None) => None,
+            (Protocol::UniV3, None) => Some(tick_spacing_for_fee(pool.fee).map_err(|e| anyhow!("Pool {:?}: {}", address, e))?),
+            (_, Some(spacing)) if spacing <= 0 => bail!("Pool {:?}: tick_spacing must be positive", address),
+            (_, Some(spacing)) => Some(spacing),
+            (protocol, None) => bail!("Pool {:?}: {:?} requires tick_spacing", address, protocol),
+        };
+        pools.push(PoolConfig {
+            protocol: pool.protocol,
+            address,
+            token0,
+            token1,
+            symbol0: pool.token0.clone(),
+            symbol1: pool.token1.clone(),
+            fee: pool.fee,
+            tick_spacing,
+        });
+    }
+
+    let defaults = raw.defaults.resolve(raw.chain_id);
+    for symbol in [&defaults.native_token, &defaults.stable_token] {
+        if !by_symbol.contains_key(symbol) {
+            bail!("[defaults] references unknown token {}", symbol);
+        }
     }
 
     Ok(ChainConfig {
+        name: raw.name.unwrap_or_else(|| format!("chain-{}", raw.chain_id)),
         chain_id: raw.chain_id,
         rpc_url: raw.rpc_url.clone(),
         rpc_urls: raw.rpc_urls.unwrap_or_else(|| vec![raw.rpc_url.clone()]),
         gas_multiplier: raw.gas_multiplier,
-        tokens: parsed,
+        me: parse_address(&raw.me, "me")?,
+        arb_executor: raw.arb_executor.as_deref().map(|address| parse_address(address, "arb_executor")).transpose()?,
+        factory: raw.factory.as_deref().map(|address| parse_address(address, "factory")).transpose()?,
+        pool_registry: raw.pool_registry.map(PathBuf::from),
+        defaults,
+        tokens,
+        quoters,
+        pools,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = r#"
+chain_id = 1
+rpc_url = "http://localhost:8545"
+gas_multiplier = 1.0
+me = "0x0000000000000000000000000000000000000001"
+
+[defaults]
+native_token = "AAA"
+stable_token = "BBB"
+
+[[tokens]]
+symbol = "AAA"
+address = "0x1111111111111111111111111111111111111111"
+decimals = 18
+balance_slot = 3
+
+[[tokens]]
+symbol = "BBB"
+address = "0x2222222222222222222222222222222222222222"
+decimals = 6
+
+[[quoters]]
+kind = "custom"
+address = "0x3333333333333333333333333333333333333333"
+"#;
+
+    fn parse(extra: &str) -> Result<ChainConfig> {
+        parse_chain_config(toml::from_str(&format!("{HEADER}{extra}"))?)
+    }
+
+    fn pool(protocol: &str, token0: &str, token1: &str, extra: &str) -> String {
+        format!(
+            "\n[[pools]]\nprotocol = \"{protocol}\"\naddress = \"0x4444444444444444444444444444444444444444\"\ntoken0 = \"{token0}\"\ntoken1 = \"{token1}\"\nfee = 500\n{extra}"
+        )
+    }
+
+    #[test]
+    fn test_shipped_configs_load() {
+        for chain in ["eth", "avax", "ronin"] {
+            let config = load_chain_config(&format!("src/config/{chain}.toml")).unwrap();
+            assert!(config.quoter(QuoterKind::UniswapV3).is_ok());
+            assert!(config.quoter(QuoterKind::Custom).is_ok());
+        }
+        let eth = load_chain_config("src/config/eth.toml").unwrap();
+        let (weth, usdc) = (eth.token("WETH").unwrap(), eth.token("USDC").unwrap());
+        assert_eq!((weth.decimals, usdc.decimals), (18, 6));
+        assert_eq!(eth.pool(weth.address, usdc.address, 500).unwrap().tick_spacing, Some(10));
+    }
+
+    #[test]
+    fn test_typed_lookups() {
+        let config = parse(&pool("univ3", "AAA", "BBB", "")).unwrap();
+        let (a, b) = (config.token("AAA").unwrap(), config.token("BBB").unwrap());
+        assert_eq!(a.balance_slot, Some(U256::from(3)));
+        assert_eq!(b.balance_slot, None);
+        // Tra pool không phụ thuộc thứ tự token
+        let pool = config.pool(b.address, a.address, 500).unwrap();
+        assert_eq!((pool.protocol, pool.tick_spacing), (Protocol::UniV3, Some(10)));
+        assert!(config.pool(a.address, b.address, 3000).is_err());
+        assert!(config.quoter(QuoterKind::UniswapV3).is_err());
+        assert!(config.token("CCC").is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_cross_references() {
+        let err = |extra: &str| parse(extra).unwrap_err().to_string();
+        assert!(err(&pool("univ3", "AAA", "CCC", "")).contains("unknown token CCC"));
+        assert!(err(&pool("univ3", "BBB", "AAA", "")).contains("must sort below"));
+        assert!(err(&pool("univ2", "AAA", "BBB", "tick_spacing = 1")).contains("no tick_spacing"));
+        assert!(err(&pool("algebra", "AAA", "BBB", "")).contains("requires tick_spacing"));
+        let duplicate = format!("{}{}", pool("univ3", "AAA", "BBB", ""), pool("univ2", "AAA", "BBB", ""));
+        assert!(err(&duplicate).contains("Duplicate pool"));
+        let token = "\n[[tokens]]\nsymbol = \"AAA\"\naddress = \"0x5555555555555555555555555555555555555555\"\ndecimals = 18\n";
+        assert!(err(token).contains("Duplicate token symbol"));
+    }
+
+    #[test]
+    fn test_non_univ3_pools_stay_out_of_cycle_graph() {
+        let config = parse(&pool("lb", "AAA", "BBB", "tick_spacing = 25")).unwrap();
+        assert_eq!(config.pools[0].tick_spacing, Some(25));
+        assert!(config.univ3_pools().is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+use alloy::primitives::{U256, U512};
+use anyhow::{bail, Result};
+
+/// Port của `FullMath.mulDiv`: floor(a * b / denominator), nhân 512-bit nên không tràn giữa chừng
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    if denominator.is_zero() {
+        bail!("mulDiv: denominator is zero");
+    }
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    if result > U512::from(U256::MAX) {
+        bail!("mulDiv: result overflows uint256");
+    }
+    Ok(result.to::<U256>())
+}
+
+/// Port của `FullMath.mulDivRoundingUp`: ceil(a * b / denominator)
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Result<U256> {
+    let result = mul_div(a, b, denominator)?;
+    let remainder = U512::from(a).mul_mod(U512::from(b), U512::from(denominator));
+    if remainder.is_zero() {
+        return Ok(result);
+    }
+    if result == U256::MAX {
+        bail!("mulDivRoundingUp: result overflows uint256");
+    }
+    Ok(result + U256::from(1))
+}
+
+/// Port của `UnsafeMath.divRoundingUp`: ceil(x / y), y phải khác 0
+pub fn div_rounding_up(x: U256, y: U256) -> U256 {
+    let quotient = x / y;
+    if (x % y).is_zero() {
+        quotient
+    } else {
+        quotient + U256::from(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div() {
+        let q128 = U256::from(1) << 128;
+        // (2^128 * 2^128) / 2^128 cần phép nhân 512-bit
+        assert_eq!(mul_div(q128, q128, q128).unwrap(), q128);
+        assert_eq!(mul_div(q128, U256::from(35), U256::from(8)).unwrap(), q128 * U256::from(35) / U256::from(8));
+        assert!(mul_div(q128, q128, U256::from(1)).is_err());
+        assert!(mul_div(q128, q128, U256::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_mul_div_rounding_up() {
+        assert_eq!(mul_div_rounding_up(U256::from(7), U256::from(3), U256::from(2)).unwrap(), U256::from(11));
+        assert_eq!(mul_div_rounding_up(U256::from(8), U256::from(3), U256::from(2)).unwrap(), U256::from(12));
+        assert_eq!(mul_div_rounding_up(U256::MAX, U256::from(3), U256::from(3)).unwrap(), U256::MAX);
+        assert!(mul_div_rounding_up(U256::MAX, U256::MAX, U256::MAX - U256::from(1)).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! Mô hình UniswapV3 thuần Rust: port TickMath, SqrtPriceMath, SwapMath, FullMath
+//! để quote `amountOut` không cần chạy EVM.
+
+pub mod full_math;
+pub mod pool;
+pub mod sqrt_price_math;
+pub mod swap_math;
+pub mod tick_bitmap;
+pub mod tick_math;
+pub mod tracker;
+
+pub use pool::{PoolState, SwapResult, TickInfo, TickWordNotLoaded};
+pub use tracker::PoolStateManager;
@@ -0,0 +1,483 @@
+use alloy::{
+    primitives::{keccak256, uint, Address, I256, U256},
+    sol_types::SolValue,
+};
+use anyhow::{anyhow, bail, Result};
+use revm::db::Database;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::univ3::swap_math::compute_swap_step;
+use crate::univ3::tick_bitmap::{flip_tick, next_initialized_tick_within_one_word, word_of};
+use crate::univ3::tick_math::{
+    get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK,
+};
+
+/// Storage layout của `UniswapV3Pool`
+pub const SLOT0_SLOT: U256 = uint!(0_U256);
+pub const LIQUIDITY_SLOT: U256 = uint!(4_U256);
+pub const TICKS_SLOT: U256 = uint!(5_U256);
+pub const TICK_BITMAP_SLOT: U256 = uint!(6_U256);
+
+/// Price limit giống hệt `UniV3Quoter.getAmountOut` (xem `src/contracts/uni_v3_quoter.sol`)
+pub const QUOTER_MIN_SQRT_PRICE_LIMIT: U256 = uint!(4295128749_U256);
+pub const QUOTER_MAX_SQRT_PRICE_LIMIT: U256 = uint!(1461446703485210103287273052203988822378723970341_U256);
+
+/// Tick spacing mặc định của factory theo fee tier
+pub fn tick_spacing_for_fee(fee: u32) -> Result<i32> {
+    match fee {
+        100 => Ok(1),
+        500 => Ok(10),
+        3000 => Ok(60),
+        10000 => Ok(200),
+        _ => Err(anyhow!("Unknown tick spacing for fee tier {}", fee)),
+    }
+}
+
+/// Word của `tickBitmap` chưa được load vào `PoolState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickWordNotLoaded(pub i16);
+
+impl fmt::Display for TickWordNotLoaded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tickBitmap word {} is not loaded", self.0)
+    }
+}
+
+impl std::error::Error for TickWordNotLoaded {}
+
+/// Phần của `Tick.Info` mà swap cần
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickInfo {
+    pub liquidity_gross: u128,
+    pub liquidity_net: i128,
+}
+
+/// Kết quả swap, cùng quy ước dấu với `UniswapV3Pool.swap` (dương = pool nhận, âm = pool trả)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapResult {
+    pub amount0: I256,
+    pub amount1: I256,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    pub ticks_crossed: u32,
+}
+
+/// Mô hình pool UniswapV3 thuần Rust: slot0, liquidity, tick bitmap và liquidityNet từng tick
+#[derive(Debug, Clone)]
+pub struct PoolState {
+    pub address: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    pub ticks: HashMap<i32, TickInfo>,
+    /// Chỉ chứa các word đã load; word thiếu → `TickWordNotLoaded`
+    pub tick_bitmap: HashMap<i16, U256>,
+}
+
+/// Tách `(sqrtPriceX96, tick)` từ word slot0
+pub fn decode_slot0(slot0: U256) -> (U256, i32) {
+    let sqrt_price_x96 = slot0 & ((U256::from(1) << 160) - U256::from(1));
+    // tick là int24 nằm ở bit 160..184
+    let raw_tick = ((slot0 >> 160_usize) & U256::from(0xffffff)).to::<u32>();
+    (sqrt_price_x96, ((raw_tick << 8) as i32) >> 8)
+}
+
+pub(crate) fn mapping_slot(key: i32, slot: U256) -> U256 {
+    let key = I256::try_from(key).expect("i32 fits in int256").into_raw();
+    keccak256((key, slot).abi_encode()).into()
+}
+
+fn add_delta(liquidity: u128, delta: i128) -> Result<u128> {
+    if delta < 0 {
+        liquidity.checked_sub(delta.unsigned_abs()).ok_or_else(|| anyhow!("LS: liquidity underflow"))
+    } else {
+        liquidity.checked_add(delta as u128).ok_or_else(|| anyhow!("LA: liquidity overflow"))
+    }
+}
+
+fn read_storage<DB>(db: &mut DB, address: Address, slot: U256) -> Result<U256>
+where
+    DB: Database,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    Ok(db.storage(address, slot)?)
+}
+
+impl PoolState {
+    /// Đọc slot0 + liquidity của pool từ bất kỳ revm `Database` nào (AlloyCacheDB, InMemoryDB, ...).
+    /// Tick bitmap được load lazily khi swap cần.
+    pub fn load<DB>(db: &mut DB, address: Address, fee: u32) -> Result<Self>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut pool = PoolState {
+            address,
+            fee,
+            tick_spacing: tick_spacing_for_fee(fee)?,
+            sqrt_price_x96: U256::ZERO,
+            tick: 0,
+            liquidity: 0,
+            ticks: HashMap::new(),
+            tick_bitmap: HashMap::new(),
+        };
+        pool.reload_slot0(db)?;
+        Ok(pool)
+    }
+
+    /// Đọc lại slot0 và liquidity (giữ nguyên tick đã cache)
+    pub fn reload_slot0<DB>(&mut self, db: &mut DB) -> Result<()>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let slot0 = read_storage(db, self.address, SLOT0_SLOT)?;
+        (self.sqrt_price_x96, self.tick) = decode_slot0(slot0);
+
+        let liquidity = read_storage(db, self.address, LIQUIDITY_SLOT)?;
+        self.liquidity = liquidity.to::<u128>();
+        Ok(())
+    }
+
+    /// Dựng lại pool từ slot0 + liquidity on-chain, bỏ toàn bộ tick đã cache (load lại lazily)
+    pub fn reset(&mut self, slot0: U256, liquidity: U256) {
+        (self.sqrt_price_x96, self.tick) = decode_slot0(slot0);
+        self.liquidity = liquidity.to::<u128>();
+        self.ticks.clear();
+        self.tick_bitmap.clear();
+    }
+
+    /// Load một word của `tickBitmap` và `ticks[tick]` của mọi tick đã khởi tạo trong word đó
+    pub fn load_tick_word<DB>(&mut self, db: &mut DB, word: i16) -> Result<()>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let bitmap = read_storage(db, self.address, mapping_slot(word as i32, TICK_BITMAP_SLOT))?;
+        for bit in 0..256 {
+            if bitmap.bit(bit) {
+                let tick = (((word as i32) << 8) + bit as i32) * self.tick_spacing;
+                let info = read_storage(db, self.address, mapping_slot(tick, TICKS_SLOT))?;
+                self.ticks.insert(
+                    tick,
+                    TickInfo {
+                        liquidity_gross: (info & U256::from(u128::MAX)).to::<u128>(),
+                        liquidity_net: (info >> 128_usize).to::<u128>() as i128,
+                    },
+                );
+            }
+        }
+        self.tick_bitmap.insert(word, bitmap);
+        Ok(())
+    }
+
+    /// Port của `UniswapV3Pool.swap` (bỏ phần fee growth / oracle không ảnh hưởng amount).
+    /// Không đổi state; lỗi `TickWordNotLoaded` nếu swap đi vào word chưa load.
+    pub fn swap(&self, zero_for_one: bool, amount_specified: I256, sqrt_price_limit_x96: U256) -> Result<SwapResult> {
+        if amount_specified.is_zero() {
+            bail!("AS: amount specified is zero");
+        }
+        let limit_ok = if zero_for_one {
+            sqrt_price_limit_x96 < self.sqrt_price_x96 && sqrt_price_limit_x96 > MIN_SQRT_RATIO
+        } else {
+            sqrt_price_limit_x96 > self.sqrt_price_x96 && sqrt_price_limit_x96 < MAX_SQRT_RATIO
+        };
+        if !limit_ok {
+            bail!("SPL: invalid sqrt price limit {}", sqrt_price_limit_x96);
+        }
+
+        let exact_input = amount_specified.is_positive();
+        let mut amount_specified_remaining = amount_specified;
+        let mut amount_calculated = I256::ZERO;
+        let mut sqrt_price_x96 = self.sqrt_price_x96;
+        let mut tick = self.tick;
+        let mut liquidity = self.liquidity;
+        let mut ticks_crossed = 0;
+
+        while !amount_specified_remaining.is_zero() && sqrt_price_x96 != sqrt_price_limit_x96 {
+            let sqrt_price_start_x96 = sqrt_price_x96;
+
+            let (tick_next, initialized) =
+                next_initialized_tick_within_one_word(&self.tick_bitmap, tick, self.tick_spacing, zero_for_one)
+                    .map_err(TickWordNotLoaded)?;
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next_x96 = get_sqrt_ratio_at_tick(tick_next)?;
+
+            let target = if (zero_for_one && sqrt_price_next_x96 < sqrt_price_limit_x96)
+                || (!zero_for_one && sqrt_price_next_x96 > sqrt_price_limit_x96)
+            {
+                sqrt_price_limit_x96
+            } else {
+                sqrt_price_next_x96
+            };
+            let step = compute_swap_step(sqrt_price_x96, target, liquidity, amount_specified_remaining, self.fee)?;
+            sqrt_price_x96 = step.sqrt_ratio_next_x96;
+
+            let amount_in_with_fee = I256::from_raw(step.amount_in + step.fee_amount);
+            let amount_out = I256::from_raw(step.amount_out);
+            if exact_input {
+                amount_specified_remaining -= amount_in_with_fee;
+                amount_calculated -= amount_out;
+            } else {
+                amount_specified_remaining += amount_out;
+                amount_calculated += amount_in_with_fee;
+            }
+
+            if sqrt_price_x96 == sqrt_price_next_x96 {
+                if initialized {
+                    let info = self
+                        .ticks
+                        .get(&tick_next)
+                        .ok_or_else(|| anyhow!("Tick {} is initialized but not loaded", tick_next))?;
+                    let liquidity_net = if zero_for_one { -info.liquidity_net } else { info.liquidity_net };
+                    liquidity = add_delta(liquidity, liquidity_net)?;
+                    ticks_crossed += 1;
+                }
+                tick = if zero_for_one { tick_next - 1 } else { tick_next };
+            } else if sqrt_price_x96 != sqrt_price_start_x96 {
+                tick = get_tick_at_sqrt_ratio(sqrt_price_x96)?;
+            }
+        }
+
+        let (amount0, amount1) = if zero_for_one == exact_input {
+            (amount_specified - amount_specified_remaining, amount_calculated)
+        } else {
+            (amount_calculated, amount_specified - amount_specified_remaining)
+        };
+
+        Ok(SwapResult { amount0, amount1, sqrt_price_x96, tick, liquidity, ticks_crossed })
+    }
+
+    /// Swap, tự load các word tick bitmap còn thiếu từ `db` rồi chạy lại
+    pub fn swap_with_db<DB>(
+        &mut self,
+        db: &mut DB,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<SwapResult>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        loop {
+            match self.swap(zero_for_one, amount_specified, sqrt_price_limit_x96) {
+                Err(e) => match e.downcast_ref::<TickWordNotLoaded>() {
+                    Some(TickWordNotLoaded(word)) => self.load_tick_word(db, *word)?,
+                    None => return Err(e),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Quote exact input giống `UniV3Quoter.getAmountOut`: trả về lượng token nhận được
+    pub fn quote_exact_input<DB>(&mut self, db: &mut DB, zero_for_one: bool, amount_in: U256) -> Result<U256>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let limit = if zero_for_one { QUOTER_MIN_SQRT_PRICE_LIMIT } else { QUOTER_MAX_SQRT_PRICE_LIMIT };
+        let amount_in = I256::try_from(amount_in).map_err(|_| anyhow!("amountIn {} overflows int256", amount_in))?;
+        let result = self.swap_with_db(db, zero_for_one, amount_in, limit)?;
+        let amount_out = if zero_for_one { result.amount1 } else { result.amount0 };
+        Ok(amount_out.unsigned_abs())
+    }
+
+    /// Load word chứa `tick` nếu chưa có trong cache
+    pub fn ensure_tick_word<DB>(&mut self, db: &mut DB, tick: i32) -> Result<()>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let word = word_of(tick, self.tick_spacing);
+        if !self.tick_bitmap.contains_key(&word) {
+            self.load_tick_word(db, word)?;
+        }
+        Ok(())
+    }
+
+    /// Áp dụng event `Swap` đã land: slot0 + liquidity lấy thẳng từ event.
+    /// Các word bị swap đi qua được load lazily để liquidityNet của tick vừa cross có sẵn khi quote.
+    pub fn apply_swap_event<DB>(&mut self, db: &mut DB, sqrt_price_x96: U256, tick: i32, liquidity: u128) -> Result<()>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let (from, to) = (self.tick.min(tick), self.tick.max(tick));
+        for word in word_of(from, self.tick_spacing)..=word_of(to, self.tick_spacing) {
+            if !self.tick_bitmap.contains_key(&word) {
+                self.load_tick_word(db, word)?;
+            }
+        }
+        self.sqrt_price_x96 = sqrt_price_x96;
+        self.tick = tick;
+        self.liquidity = liquidity;
+        Ok(())
+    }
+
+    /// Port của `Tick.update` + `TickBitmap.flipTick` cho một biên của vị trí LP
+    fn update_tick(&mut self, tick: i32, liquidity_delta: i128, upper: bool) -> Result<()> {
+        let info = self.ticks.get(&tick).copied().unwrap_or_default();
+        let liquidity_gross = add_delta(info.liquidity_gross, liquidity_delta)?;
+        let liquidity_net = if upper {
+            info.liquidity_net.checked_sub(liquidity_delta)
+        } else {
+            info.liquidity_net.checked_add(liquidity_delta)
+        }
+        .ok_or_else(|| anyhow!("liquidityNet overflow at tick {}", tick))?;
+
+        if (liquidity_gross == 0) != (info.liquidity_gross == 0) {
+            flip_tick(&mut self.tick_bitmap, tick, self.tick_spacing);
+        }
+        if liquidity_gross == 0 {
+            self.ticks.remove(&tick);
+        } else {
+            self.ticks.insert(tick, TickInfo { liquidity_gross, liquidity_net });
+        }
+        Ok(())
+    }
+
+    /// Áp dụng event `Mint` (delta > 0) / `Burn` (delta < 0) giống `UniswapV3Pool._modifyPosition`
+    pub fn apply_position_event<DB>(
+        &mut self,
+        db: &mut DB,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<()>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        if tick_lower >= tick_upper {
+            bail!("TLU: tickLower {} >= tickUpper {}", tick_lower, tick_upper);
+        }
+        if liquidity_delta == 0 {
+            // Burn 0 chỉ để poke fee, không đổi liquidity
+            return Ok(());
+        }
+        // Load word trước khi sửa để giữ nguyên các tick khác trong word
+        self.ensure_tick_word(db, tick_lower)?;
+        self.ensure_tick_word(db, tick_upper)?;
+
+        self.update_tick(tick_lower, liquidity_delta, false)?;
+        self.update_tick(tick_upper, liquidity_delta, true)?;
+
+        if tick_lower <= self.tick && self.tick < tick_upper {
+            self.liquidity = add_delta(self.liquidity, liquidity_delta)?;
+        }
+        Ok(())
+    }
+
+    /// Đánh dấu word chứa `tick` là đã biết (dùng khi dựng pool thủ công, không đọc từ DB)
+    pub fn insert_empty_word(&mut self, tick: i32) {
+        self.tick_bitmap.entry(word_of(tick, self.tick_spacing)).or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::db::{CacheDB, EmptyDB};
+
+    const ONE_E18: u128 = 1_000_000_000_000_000_000;
+
+    /// Pool fee 3000 với một vị trí LP duy nhất [-600, 600], giá = 1
+    fn single_range_pool() -> PoolState {
+        let mut pool = PoolState {
+            address: Address::ZERO,
+            fee: 3000,
+            tick_spacing: 60,
+            sqrt_price_x96: U256::from(1) << 96,
+            tick: 0,
+            liquidity: ONE_E18,
+            ticks: HashMap::new(),
+            tick_bitmap: HashMap::new(),
+        };
+        for (tick, net) in [(-600, ONE_E18 as i128), (600, -(ONE_E18 as i128))] {
+            pool.ticks.insert(tick, TickInfo { liquidity_gross: ONE_E18, liquidity_net: net });
+            flip_tick(&mut pool.tick_bitmap, tick, pool.tick_spacing);
+        }
+        pool
+    }
+
+    #[test]
+    fn test_swap_within_single_tick_matches_swap_step() {
+        let pool = single_range_pool();
+        let amount_in = I256::try_from(ONE_E18 / 1000).unwrap();
+        let result = pool.swap(true, amount_in, QUOTER_MIN_SQRT_PRICE_LIMIT).unwrap();
+
+        let step = compute_swap_step(
+            pool.sqrt_price_x96,
+            get_sqrt_ratio_at_tick(-600).unwrap(),
+            pool.liquidity,
+            amount_in,
+            pool.fee,
+        )
+        .unwrap();
+        assert_eq!(result.amount0, amount_in);
+        assert_eq!(result.amount1, -I256::from_raw(step.amount_out));
+        assert_eq!(result.sqrt_price_x96, step.sqrt_ratio_next_x96);
+        assert_eq!(result.tick, get_tick_at_sqrt_ratio(step.sqrt_ratio_next_x96).unwrap());
+        assert_eq!(result.ticks_crossed, 0);
+    }
+
+    #[test]
+    fn test_swap_crossing_out_of_range_stops_at_limit() {
+        let pool = single_range_pool();
+        // Input đủ lớn để vét hết liquidity của range → dừng tại price limit với liquidity = 0
+        let amount_in = I256::try_from(ONE_E18).unwrap();
+        let limit = get_sqrt_ratio_at_tick(-1200).unwrap();
+        let mut pool = pool;
+        pool.insert_empty_word(-1200);
+        let result = pool.swap(true, amount_in, limit).unwrap();
+        assert_eq!(result.liquidity, 0);
+        assert_eq!(result.ticks_crossed, 1);
+        assert_eq!(result.sqrt_price_x96, limit);
+        assert!(result.amount0 < amount_in);
+    }
+
+    #[test]
+    fn test_swap_reports_missing_word() {
+        let mut pool = single_range_pool();
+        pool.tick_bitmap.clear();
+        let err = pool.swap(false, I256::try_from(1000).unwrap(), QUOTER_MAX_SQRT_PRICE_LIMIT).unwrap_err();
+        assert_eq!(err.downcast_ref::<TickWordNotLoaded>(), Some(&TickWordNotLoaded(0)));
+    }
+
+    #[test]
+    fn test_load_from_storage() {
+        let pool_address = Address::repeat_byte(0x11);
+        let sqrt_price = U256::from(1) << 96;
+        let tick: i32 = -60;
+        let slot0 = sqrt_price | (U256::from((tick as u32) & 0xffffff) << 160);
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(pool_address, SLOT0_SLOT, slot0).unwrap();
+        db.insert_account_storage(pool_address, LIQUIDITY_SLOT, U256::from(ONE_E18)).unwrap();
+
+        let mut bitmap = HashMap::new();
+        flip_tick(&mut bitmap, -600, 60);
+        flip_tick(&mut bitmap, 600, 60);
+        for (word, value) in &bitmap {
+            db.insert_account_storage(pool_address, mapping_slot(*word as i32, TICK_BITMAP_SLOT), *value).unwrap();
+        }
+        let lower = U256::from(ONE_E18) | (U256::from(ONE_E18) << 128);
+        let upper = U256::from(ONE_E18) | (U256::from((-(ONE_E18 as i128)) as u128) << 128);
+        db.insert_account_storage(pool_address, mapping_slot(-600, TICKS_SLOT), lower).unwrap();
+        db.insert_account_storage(pool_address, mapping_slot(600, TICKS_SLOT), upper).unwrap();
+
+        let mut pool = PoolState::load(&mut db, pool_address, 3000).unwrap();
+        assert_eq!(pool.tick, -60);
+        assert_eq!(pool.liquidity, ONE_E18);
+
+        let amount_out = pool.quote_exact_input(&mut db, false, U256::from(ONE_E18 / 1000)).unwrap();
+        assert!(amount_out > U256::ZERO);
+        assert_eq!(pool.ticks.get(&600), Some(&TickInfo { liquidity_gross: ONE_E18, liquidity_net: -(ONE_E18 as i128) }));
+    }
+}
@@ -0,0 +1,242 @@
+use alloy::primitives::{U160, U256};
+use anyhow::{anyhow, bail, Result};
+
+use crate::univ3::full_math::{div_rounding_up, mul_div, mul_div_rounding_up};
+
+pub const RESOLUTION: usize = 96;
+
+fn q96() -> U256 {
+    U256::from(1) << RESOLUTION
+}
+
+fn to_u160(value: U256) -> Result<U256> {
+    if value > U256::from(U160::MAX) {
+        bail!("sqrt price {} overflows uint160", value);
+    }
+    Ok(value)
+}
+
+/// Port của `SqrtPriceMath.getNextSqrtPriceFromAmount0RoundingUp`
+pub fn get_next_sqrt_price_from_amount0_rounding_up(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> Result<U256> {
+    if amount.is_zero() {
+        return Ok(sqrt_price_x96);
+    }
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+
+    if add {
+        if let Some(product) = amount.checked_mul(sqrt_price_x96) {
+            if let Some(denominator) = numerator1.checked_add(product) {
+                return to_u160(mul_div_rounding_up(numerator1, sqrt_price_x96, denominator)?);
+            }
+        }
+        let denominator = (numerator1 / sqrt_price_x96)
+            .checked_add(amount)
+            .ok_or_else(|| anyhow!("getNextSqrtPriceFromAmount0RoundingUp: denominator overflow"))?;
+        to_u160(div_rounding_up(numerator1, denominator))
+    } else {
+        let product = amount
+            .checked_mul(sqrt_price_x96)
+            .ok_or_else(|| anyhow!("getNextSqrtPriceFromAmount0RoundingUp: product overflow"))?;
+        if numerator1 <= product {
+            bail!("getNextSqrtPriceFromAmount0RoundingUp: output exceeds virtual reserves of token0");
+        }
+        to_u160(mul_div_rounding_up(numerator1, sqrt_price_x96, numerator1 - product)?)
+    }
+}
+
+/// Port của `SqrtPriceMath.getNextSqrtPriceFromAmount1RoundingDown`
+pub fn get_next_sqrt_price_from_amount1_rounding_down(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> Result<U256> {
+    let liquidity = U256::from(liquidity);
+    let fits_u160 = amount <= U256::from(U160::MAX);
+
+    if add {
+        let quotient = if fits_u160 {
+            (amount << RESOLUTION) / liquidity
+        } else {
+            mul_div(amount, q96(), liquidity)?
+        };
+        let next = sqrt_price_x96
+            .checked_add(quotient)
+            .ok_or_else(|| anyhow!("getNextSqrtPriceFromAmount1RoundingDown: price overflow"))?;
+        to_u160(next)
+    } else {
+        let quotient = if fits_u160 {
+            div_rounding_up(amount << RESOLUTION, liquidity)
+        } else {
+            mul_div_rounding_up(amount, q96(), liquidity)?
+        };
+        if sqrt_price_x96 <= quotient {
+            bail!("getNextSqrtPriceFromAmount1RoundingDown: output exceeds virtual reserves of token1");
+        }
+        Ok(sqrt_price_x96 - quotient)
+    }
+}
+
+/// Port của `SqrtPriceMath.getNextSqrtPriceFromInput`
+pub fn get_next_sqrt_price_from_input(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<U256> {
+    if sqrt_price_x96.is_zero() {
+        bail!("getNextSqrtPriceFromInput: sqrt price is zero");
+    }
+    if liquidity == 0 {
+        bail!("getNextSqrtPriceFromInput: liquidity is zero");
+    }
+    if zero_for_one {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_price_x96, liquidity, amount_in, true)
+    } else {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_price_x96, liquidity, amount_in, true)
+    }
+}
+
+/// Port của `SqrtPriceMath.getNextSqrtPriceFromOutput`
+pub fn get_next_sqrt_price_from_output(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool,
+) -> Result<U256> {
+    if sqrt_price_x96.is_zero() {
+        bail!("getNextSqrtPriceFromOutput: sqrt price is zero");
+    }
+    if liquidity == 0 {
+        bail!("getNextSqrtPriceFromOutput: liquidity is zero");
+    }
+    if zero_for_one {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_price_x96, liquidity, amount_out, false)
+    } else {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_price_x96, liquidity, amount_out, false)
+    }
+}
+
+/// Port của `SqrtPriceMath.getAmount0Delta` (bản unsigned)
+pub fn get_amount0_delta(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256> {
+    let (sqrt_ratio_a_x96, sqrt_ratio_b_x96) = if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        (sqrt_ratio_b_x96, sqrt_ratio_a_x96)
+    } else {
+        (sqrt_ratio_a_x96, sqrt_ratio_b_x96)
+    };
+    if sqrt_ratio_a_x96.is_zero() {
+        bail!("getAmount0Delta: sqrt ratio is zero");
+    }
+
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    let numerator2 = sqrt_ratio_b_x96 - sqrt_ratio_a_x96;
+
+    if round_up {
+        Ok(div_rounding_up(
+            mul_div_rounding_up(numerator1, numerator2, sqrt_ratio_b_x96)?,
+            sqrt_ratio_a_x96,
+        ))
+    } else {
+        Ok(mul_div(numerator1, numerator2, sqrt_ratio_b_x96)? / sqrt_ratio_a_x96)
+    }
+}
+
+/// Port của `SqrtPriceMath.getAmount1Delta` (bản unsigned)
+pub fn get_amount1_delta(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256> {
+    let diff = if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        sqrt_ratio_a_x96 - sqrt_ratio_b_x96
+    } else {
+        sqrt_ratio_b_x96 - sqrt_ratio_a_x96
+    };
+
+    if round_up {
+        mul_div_rounding_up(U256::from(liquidity), diff, q96())
+    } else {
+        mul_div(U256::from(liquidity), diff, q96())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::uint;
+
+    const PRICE_1: U256 = uint!(79228162514264337593543950336_U256);
+    const PRICE_121_100: U256 = uint!(87150978765690771352898345369_U256);
+    const ONE_E18: u128 = 1_000_000_000_000_000_000;
+
+    #[test]
+    fn test_get_next_sqrt_price_from_input() {
+        let amount = U256::from(ONE_E18 / 10);
+        assert_eq!(get_next_sqrt_price_from_input(PRICE_1, ONE_E18, amount, false).unwrap(), PRICE_121_100);
+        assert_eq!(
+            get_next_sqrt_price_from_input(PRICE_1, ONE_E18, amount, true).unwrap(),
+            uint!(72025602285694852357767227579_U256)
+        );
+        // amountIn > type(uint96).max với zeroForOne
+        assert_eq!(
+            get_next_sqrt_price_from_input(PRICE_1, 10 * ONE_E18, uint!(1267650600228229401496703205376_U256), true)
+                .unwrap(),
+            uint!(624999999995069620_U256)
+        );
+        assert_eq!(get_next_sqrt_price_from_input(PRICE_1, 1, U256::MAX / U256::from(2), true).unwrap(), U256::from(1));
+        assert_eq!(get_next_sqrt_price_from_input(PRICE_1, ONE_E18, U256::ZERO, true).unwrap(), PRICE_1);
+
+        assert!(get_next_sqrt_price_from_input(U256::ZERO, 1, amount, false).is_err());
+        assert!(get_next_sqrt_price_from_input(PRICE_1, 0, amount, false).is_err());
+        assert!(get_next_sqrt_price_from_input(U256::from(U160::MAX), 1024, U256::from(1024), false).is_err());
+    }
+
+    #[test]
+    fn test_get_next_sqrt_price_from_output() {
+        let amount = U256::from(ONE_E18 / 10);
+        assert_eq!(
+            get_next_sqrt_price_from_output(PRICE_1, ONE_E18, amount, false).unwrap(),
+            uint!(88031291682515930659493278152_U256)
+        );
+        assert_eq!(
+            get_next_sqrt_price_from_output(PRICE_1, ONE_E18, amount, true).unwrap(),
+            uint!(71305346262837903834189555302_U256)
+        );
+        // Output đúng bằng virtual reserve token0 thì phải lỗi
+        assert!(get_next_sqrt_price_from_output(uint!(20282409603651670423947251286016_U256), 1024, U256::from(262144), false).is_err());
+    }
+
+    #[test]
+    fn test_get_amount_deltas() {
+        let amount0 = get_amount0_delta(PRICE_1, PRICE_121_100, ONE_E18, true).unwrap();
+        assert_eq!(amount0, uint!(90909090909090910_U256));
+        assert_eq!(get_amount0_delta(PRICE_1, PRICE_121_100, ONE_E18, false).unwrap(), amount0 - U256::from(1));
+
+        let amount1 = get_amount1_delta(PRICE_1, PRICE_121_100, ONE_E18, true).unwrap();
+        assert_eq!(amount1, U256::from(ONE_E18 / 10));
+        assert_eq!(get_amount1_delta(PRICE_1, PRICE_121_100, ONE_E18, false).unwrap(), amount1 - U256::from(1));
+
+        assert_eq!(get_amount0_delta(PRICE_1, PRICE_121_100, 0, true).unwrap(), U256::ZERO);
+        assert_eq!(get_amount1_delta(PRICE_1, PRICE_1, ONE_E18, true).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_swap_computation() {
+        let sqrt_price = uint!(1025574284609383690408304870162715216695788925244_U256);
+        let liquidity = 50015962439936049619261659728067971248_u128;
+        let sqrt_q = get_next_sqrt_price_from_input(sqrt_price, liquidity, U256::from(406), true).unwrap();
+        assert_eq!(sqrt_q, uint!(1025574284609383582644711336373707553698163132913_U256));
+        assert_eq!(get_amount0_delta(sqrt_q, sqrt_price, liquidity, true).unwrap(), U256::from(406));
+    }
+}
@@ -0,0 +1,178 @@
+use alloy::primitives::{I256, U256};
+use anyhow::Result;
+
+use crate::univ3::full_math::{mul_div, mul_div_rounding_up};
+use crate::univ3::sqrt_price_math::{
+    get_amount0_delta, get_amount1_delta, get_next_sqrt_price_from_input, get_next_sqrt_price_from_output,
+};
+
+/// Kết quả một bước swap trong phạm vi một tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_ratio_next_x96: U256,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+/// Port của `SwapMath.computeSwapStep`.
+/// `amount_remaining` > 0 là exact input, < 0 là exact output; `fee_pips` tính theo 1e6.
+pub fn compute_swap_step(
+    sqrt_ratio_current_x96: U256,
+    sqrt_ratio_target_x96: U256,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: u32,
+) -> Result<SwapStep> {
+    let zero_for_one = sqrt_ratio_current_x96 >= sqrt_ratio_target_x96;
+    let exact_in = !amount_remaining.is_negative();
+    let fee = U256::from(fee_pips);
+    let fee_complement = U256::from(1_000_000 - fee_pips);
+
+    let mut amount_in = U256::ZERO;
+    let mut amount_out = U256::ZERO;
+
+    let sqrt_ratio_next_x96 = if exact_in {
+        let amount_remaining_less_fee =
+            mul_div(amount_remaining.into_raw(), fee_complement, U256::from(1_000_000))?;
+        amount_in = if zero_for_one {
+            get_amount0_delta(sqrt_ratio_target_x96, sqrt_ratio_current_x96, liquidity, true)?
+        } else {
+            get_amount1_delta(sqrt_ratio_current_x96, sqrt_ratio_target_x96, liquidity, true)?
+        };
+        if amount_remaining_less_fee >= amount_in {
+            sqrt_ratio_target_x96
+        } else {
+            get_next_sqrt_price_from_input(sqrt_ratio_current_x96, liquidity, amount_remaining_less_fee, zero_for_one)?
+        }
+    } else {
+        amount_out = if zero_for_one {
+            get_amount1_delta(sqrt_ratio_target_x96, sqrt_ratio_current_x96, liquidity, false)?
+        } else {
+            get_amount0_delta(sqrt_ratio_current_x96, sqrt_ratio_target_x96, liquidity, false)?
+        };
+        if amount_remaining.unsigned_abs() >= amount_out {
+            sqrt_ratio_target_x96
+        } else {
+            get_next_sqrt_price_from_output(
+                sqrt_ratio_current_x96,
+                liquidity,
+                amount_remaining.unsigned_abs(),
+                zero_for_one,
+            )?
+        }
+    };
+
+    let max = sqrt_ratio_target_x96 == sqrt_ratio_next_x96;
+
+    if zero_for_one {
+        if !max || !exact_in {
+            amount_in = get_amount0_delta(sqrt_ratio_next_x96, sqrt_ratio_current_x96, liquidity, true)?;
+        }
+        if !max || exact_in {
+            amount_out = get_amount1_delta(sqrt_ratio_next_x96, sqrt_ratio_current_x96, liquidity, false)?;
+        }
+    } else {
+        if !max || !exact_in {
+            amount_in = get_amount1_delta(sqrt_ratio_current_x96, sqrt_ratio_next_x96, liquidity, true)?;
+        }
+        if !max || exact_in {
+            amount_out = get_amount0_delta(sqrt_ratio_current_x96, sqrt_ratio_next_x96, liquidity, false)?;
+        }
+    }
+
+    // Không trả ra nhiều hơn lượng output yêu cầu
+    if !exact_in && amount_out > amount_remaining.unsigned_abs() {
+        amount_out = amount_remaining.unsigned_abs();
+    }
+
+    let fee_amount = if exact_in && sqrt_ratio_next_x96 != sqrt_ratio_target_x96 {
+        // Không chạm target → phần còn lại của input là phí
+        amount_remaining.into_raw() - amount_in
+    } else {
+        mul_div_rounding_up(amount_in, fee, fee_complement)?
+    };
+
+    Ok(SwapStep { sqrt_ratio_next_x96, amount_in, amount_out, fee_amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::uint;
+
+    const PRICE_1: U256 = uint!(79228162514264337593543950336_U256);
+    const TWO_E18: u128 = 2_000_000_000_000_000_000;
+
+    fn amount(value: &str) -> I256 {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_exact_in_capped_at_price_target() {
+        let target = uint!(79623317895830914510639640423_U256);
+        let step = compute_swap_step(PRICE_1, target, TWO_E18, amount("1000000000000000000"), 600).unwrap();
+        assert_eq!(step.sqrt_ratio_next_x96, target);
+        assert_eq!(step.amount_in, uint!(9975124224178055_U256));
+        assert_eq!(step.fee_amount, uint!(5988667735148_U256));
+        assert_eq!(step.amount_out, uint!(9925619580021728_U256));
+    }
+
+    #[test]
+    fn test_exact_out_capped_at_price_target() {
+        let target = uint!(79623317895830914510639640423_U256);
+        let step = compute_swap_step(PRICE_1, target, TWO_E18, amount("-1000000000000000000"), 600).unwrap();
+        assert_eq!(step.sqrt_ratio_next_x96, target);
+        assert_eq!(step.amount_in, uint!(9975124224178055_U256));
+        assert_eq!(step.fee_amount, uint!(5988667735148_U256));
+        assert_eq!(step.amount_out, uint!(9925619580021728_U256));
+    }
+
+    #[test]
+    fn test_exact_in_fully_spent() {
+        let target = uint!(0xe6666666666666666666666666_U256);
+        let step = compute_swap_step(PRICE_1, target, TWO_E18, amount("1000000000000000000"), 600).unwrap();
+        assert!(step.sqrt_ratio_next_x96 < target);
+        assert_eq!(step.amount_in, uint!(999400000000000000_U256));
+        assert_eq!(step.fee_amount, uint!(600000000000000_U256));
+        assert_eq!(step.amount_out, uint!(666399946655997866_U256));
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // Output bị chặn ở đúng lượng yêu cầu
+        let step = compute_swap_step(
+            uint!(417332158212080721273783715441582_U256),
+            uint!(1452870262520218020823638996_U256),
+            159344665391607089467575320103,
+            amount("-1"),
+            1,
+        )
+        .unwrap();
+        assert_eq!(step.amount_in, U256::from(1));
+        assert_eq!(step.fee_amount, U256::from(1));
+        assert_eq!(step.amount_out, U256::from(1));
+        assert_eq!(step.sqrt_ratio_next_x96, uint!(417332158212080721273783715441581_U256));
+
+        // Toàn bộ input bị tính là phí
+        let step = compute_swap_step(
+            U256::from(2413),
+            uint!(79887613182836312_U256),
+            1985041575832132834610021537970,
+            amount("10"),
+            1872,
+        )
+        .unwrap();
+        assert_eq!(step.amount_in, U256::ZERO);
+        assert_eq!(step.fee_amount, U256::from(10));
+        assert_eq!(step.amount_out, U256::ZERO);
+        assert_eq!(step.sqrt_ratio_next_x96, U256::from(2413));
+
+        // Thiếu liquidity trung gian, zeroForOne exact output
+        let price = uint!(20282409603651670423947251286016_U256);
+        let step = compute_swap_step(price, price * U256::from(11) / U256::from(10), 1024, amount("-4"), 3000).unwrap();
+        assert_eq!(step.amount_out, U256::ZERO);
+        assert_eq!(step.amount_in, U256::from(26215));
+        assert_eq!(step.fee_amount, U256::from(79));
+    }
+}
@@ -0,0 +1,137 @@
+use alloy::primitives::U256;
+use std::collections::HashMap;
+
+use crate::univ3::tick_math::{MAX_TICK, MIN_TICK};
+
+/// Vị trí (word, bit) của một tick đã nén trong `tickBitmap`, giống `TickBitmap.position`
+pub fn position(compressed: i32) -> (i16, u8) {
+    ((compressed >> 8) as i16, (compressed & 0xff) as u8)
+}
+
+/// Tick đã chia cho tick spacing, làm tròn về âm vô cùng
+pub fn compress(tick: i32, tick_spacing: i32) -> i32 {
+    let mut compressed = tick / tick_spacing;
+    if tick < 0 && tick % tick_spacing != 0 {
+        compressed -= 1;
+    }
+    compressed
+}
+
+/// Word chứa `tick` trong bitmap
+pub fn word_of(tick: i32, tick_spacing: i32) -> i16 {
+    position(compress(tick, tick_spacing)).0
+}
+
+/// Khoảng word hợp lệ của bitmap với tick spacing cho trước
+pub fn word_range(tick_spacing: i32) -> (i16, i16) {
+    (word_of(MIN_TICK, tick_spacing), word_of(MAX_TICK, tick_spacing))
+}
+
+fn most_significant_bit(x: U256) -> u8 {
+    (255 - x.leading_zeros()) as u8
+}
+
+fn least_significant_bit(x: U256) -> u8 {
+    x.trailing_zeros() as u8
+}
+
+/// Port của `TickBitmap.nextInitializedTickWithinOneWord`.
+///
+/// Trả về `Err(word)` nếu word cần đọc chưa có trong `bitmap` để caller load lazily.
+pub fn next_initialized_tick_within_one_word(
+    bitmap: &HashMap<i16, U256>,
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+) -> Result<(i32, bool), i16> {
+    let compressed = compress(tick, tick_spacing);
+
+    if lte {
+        let (word_pos, bit_pos) = position(compressed);
+        let word = *bitmap.get(&word_pos).ok_or(word_pos)?;
+        let mask = (U256::from(1) << bit_pos) - U256::from(1) + (U256::from(1) << bit_pos);
+        let masked = word & mask;
+
+        let initialized = !masked.is_zero();
+        let next = if initialized {
+            (compressed - (bit_pos - most_significant_bit(masked)) as i32) * tick_spacing
+        } else {
+            (compressed - bit_pos as i32) * tick_spacing
+        };
+        Ok((next, initialized))
+    } else {
+        let (word_pos, bit_pos) = position(compressed + 1);
+        let word = *bitmap.get(&word_pos).ok_or(word_pos)?;
+        let mask = !((U256::from(1) << bit_pos) - U256::from(1));
+        let masked = word & mask;
+
+        let initialized = !masked.is_zero();
+        let next = if initialized {
+            (compressed + 1 + (least_significant_bit(masked) - bit_pos) as i32) * tick_spacing
+        } else {
+            (compressed + 1 + (u8::MAX - bit_pos) as i32) * tick_spacing
+        };
+        Ok((next, initialized))
+    }
+}
+
+/// Bật/tắt bit của tick trong bitmap (`TickBitmap.flipTick`)
+pub fn flip_tick(bitmap: &mut HashMap<i16, U256>, tick: i32, tick_spacing: i32) {
+    let (word_pos, bit_pos) = position(compress(tick, tick_spacing));
+    let word = bitmap.entry(word_pos).or_default();
+    *word ^= U256::from(1) << bit_pos;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap_with(ticks: &[i32], tick_spacing: i32) -> HashMap<i16, U256> {
+        let mut bitmap = HashMap::new();
+        for word in -2..=2 {
+            bitmap.insert(word, U256::ZERO);
+        }
+        for &tick in ticks {
+            flip_tick(&mut bitmap, tick, tick_spacing);
+        }
+        bitmap
+    }
+
+    #[test]
+    fn test_position_and_compress() {
+        assert_eq!(position(-1), (-1, 255));
+        assert_eq!(position(256), (1, 0));
+        assert_eq!(compress(-5, 10), -1);
+        assert_eq!(compress(-10, 10), -1);
+        assert_eq!(compress(15, 10), 1);
+    }
+
+    #[test]
+    fn test_next_initialized_tick_lte() {
+        // Cùng bộ tick với test của TickBitmap.spec.ts (tick spacing = 1)
+        let bitmap = bitmap_with(&[-200, -55, -4, 70, 78, 84, 139, 240, 535], 1);
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 78, 1, true), Ok((78, true)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 79, 1, true), Ok((78, true)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 258, 1, true), Ok((256, false)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 256, 1, true), Ok((256, false)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 72, 1, true), Ok((70, true)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, -257, 1, true), Ok((-512, false)));
+    }
+
+    #[test]
+    fn test_next_initialized_tick_gt() {
+        let bitmap = bitmap_with(&[-200, -55, -4, 70, 78, 84, 139, 240, 535], 1);
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 78, 1, false), Ok((84, true)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 77, 1, false), Ok((78, true)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, -56, 1, false), Ok((-55, true)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 255, 1, false), Ok((511, false)));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 383, 1, false), Ok((511, false)));
+    }
+
+    #[test]
+    fn test_missing_word_is_reported() {
+        let bitmap = HashMap::new();
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, 600, 60, true), Err(0));
+        assert_eq!(next_initialized_tick_within_one_word(&bitmap, -61, 60, false), Err(-1));
+    }
+}
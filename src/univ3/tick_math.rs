@@ -0,0 +1,118 @@
+use alloy::primitives::{uint, U256};
+use anyhow::{bail, Result};
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// `getSqrtRatioAtTick(MIN_TICK)`
+pub const MIN_SQRT_RATIO: U256 = uint!(4295128739_U256);
+/// `getSqrtRatioAtTick(MAX_TICK)`
+pub const MAX_SQRT_RATIO: U256 = uint!(1461446703485210103287273052203988822378723970342_U256);
+
+/// Hệ số Q128 của sqrt(1.0001)^-(2^i), lấy nguyên từ `TickMath.sol`
+const RATIO_FACTORS: [U256; 19] = [
+    uint!(0xfff97272373d413259a46990580e213a_U256),
+    uint!(0xfff2e50f5f656932ef12357cf3c7fdcc_U256),
+    uint!(0xffe5caca7e10e4e61c3624eaa0941cd0_U256),
+    uint!(0xffcb9843d60f6159c9db58835c926644_U256),
+    uint!(0xff973b41fa98c081472e6896dfb254c0_U256),
+    uint!(0xff2ea16466c96a3843ec78b326b52861_U256),
+    uint!(0xfe5dee046a99a2a811c461f1969c3053_U256),
+    uint!(0xfcbe86c7900a88aedcffc83b479aa3a4_U256),
+    uint!(0xf987a7253ac413176f2b074cf7815e54_U256),
+    uint!(0xf3392b0822b70005940c7a398e4b70f3_U256),
+    uint!(0xe7159475a2c29b7443b29c7fa6e889d9_U256),
+    uint!(0xd097f3bdfd2022b8845ad8f792aa5825_U256),
+    uint!(0xa9f746462d870fdf8a65dc1f90e061e5_U256),
+    uint!(0x70d869a156d2a1b890bb3df62baf32f7_U256),
+    uint!(0x31be135f97d08fd981231505542fcfa6_U256),
+    uint!(0x9aa508b5b7a84e1c677de54f3e99bc9_U256),
+    uint!(0x5d6af8dedb81196699c329225ee604_U256),
+    uint!(0x2216e584f5fa1ea926041bedfe98_U256),
+    uint!(0x48a170391f7dc42444e8fa2_U256),
+];
+
+/// Port của `TickMath.getSqrtRatioAtTick`: sqrt(1.0001^tick) * 2^96
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        bail!("getSqrtRatioAtTick: tick {} out of range", tick);
+    }
+
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        uint!(0xfffcb933bd6fad37aa2d162d1a594001_U256)
+    } else {
+        U256::from(1) << 128
+    };
+    for (i, factor) in RATIO_FACTORS.iter().enumerate() {
+        if abs_tick & (0x2 << i) != 0 {
+            ratio = (ratio * *factor) >> 128;
+        }
+    }
+
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Chia cho 2^32, làm tròn lên để getTickAtSqrtRatio nhất quán
+    let round_up = !(ratio & U256::from(u32::MAX)).is_zero();
+    Ok((ratio >> 32) + U256::from(round_up as u8))
+}
+
+/// Tương đương `TickMath.getTickAtSqrtRatio`: tick lớn nhất thoả getSqrtRatioAtTick(tick) <= sqrt_price_x96.
+///
+/// Tìm nhị phân trên `get_sqrt_ratio_at_tick` (đơn điệu) thay vì port log2 bit-by-bit, kết quả giống hệt.
+pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U256) -> Result<i32> {
+    if sqrt_price_x96 < MIN_SQRT_RATIO || sqrt_price_x96 >= MAX_SQRT_RATIO {
+        bail!("getTickAtSqrtRatio: sqrt price {} out of range", sqrt_price_x96);
+    }
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if get_sqrt_ratio_at_tick(mid)? <= sqrt_price_x96 {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_sqrt_ratio_at_tick() {
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK).unwrap(), MAX_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(0).unwrap(), U256::from(1) << 96);
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK + 1).unwrap(), U256::from(4295343490_u64));
+        assert_eq!(get_sqrt_ratio_at_tick(50).unwrap(), U256::from(79426470787362580746886972461_u128));
+        assert_eq!(get_sqrt_ratio_at_tick(3000).unwrap(), U256::from(92049301871182272007977902845_u128));
+        assert_eq!(get_sqrt_ratio_at_tick(150000).unwrap(), U256::from(143194173941309278083010301478497_u128));
+        assert_eq!(
+            get_sqrt_ratio_at_tick(MAX_TICK - 1).unwrap(),
+            uint!(1461373636630004318706518188784493106690254656249_U256)
+        );
+        assert!(get_sqrt_ratio_at_tick(MIN_TICK - 1).is_err());
+        assert!(get_sqrt_ratio_at_tick(MAX_TICK + 1).is_err());
+    }
+
+    #[test]
+    fn test_get_tick_at_sqrt_ratio() {
+        assert_eq!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO).unwrap(), MIN_TICK);
+        assert_eq!(get_tick_at_sqrt_ratio(U256::from(4295343490_u64)).unwrap(), MIN_TICK + 1);
+        assert_eq!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO - U256::from(1)).unwrap(), MAX_TICK - 1);
+        assert_eq!(get_tick_at_sqrt_ratio(U256::from(1) << 96).unwrap(), 0);
+
+        // Ngay dưới biên của tick 50 vẫn thuộc tick 49
+        let at_50 = get_sqrt_ratio_at_tick(50).unwrap();
+        assert_eq!(get_tick_at_sqrt_ratio(at_50).unwrap(), 50);
+        assert_eq!(get_tick_at_sqrt_ratio(at_50 - U256::from(1)).unwrap(), 49);
+
+        assert!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO - U256::from(1)).is_err());
+        assert!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO).is_err());
+    }
+}
@@ -0,0 +1,437 @@
+use alloy::{
+    primitives::{Address, Log, U256},
+    rpc::types::{Filter, Log as RpcLog},
+    sol_types::SolEvent,
+};
+use anyhow::Result;
+use revm::db::Database;
+use std::collections::{HashMap, HashSet};
+
+use crate::source::abi::IUniswapV3PoolEvents::{Burn, Mint, Swap};
+use crate::univ3::PoolState;
+
+/// Giữ `PoolState` của các pool đang track và cập nhật incremental từ log `Swap` / `Mint` / `Burn`
+/// (cùng cách `Revm::handle_logs` xử lý topic `Sync` của V2)
+#[derive(Debug, Clone, Default)]
+pub struct PoolStateManager {
+    pub pools: HashMap<Address, PoolState>,
+    /// Block của log cuối cùng đã áp dụng
+    pub last_block: Option<u64>,
+    /// Pool lệch so với on-chain, bị loại khỏi simulate cho tới khi recover
+    pub dirty: HashSet<Address>,
+}
+
+impl PoolStateManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Thêm pool vào danh sách track (ghi đè state cũ nếu đã có)
+    pub fn track(&mut self, pool: PoolState) {
+        self.pools.insert(pool.address, pool);
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&PoolState> {
+        self.pools.get(address)
+    }
+
+    pub fn get_mut(&mut self, address: &Address) -> Option<&mut PoolState> {
+        self.pools.get_mut(address)
+    }
+
+    pub fn mark_dirty(&mut self, address: Address) {
+        self.dirty.insert(address);
+    }
+
+    pub fn mark_clean(&mut self, address: &Address) {
+        self.dirty.remove(address);
+    }
+
+    pub fn is_dirty(&self, address: &Address) -> bool {
+        self.dirty.contains(address)
+    }
+
+    /// Pool dùng được cho simulate (`None` nếu không track hoặc đang dirty)
+    pub fn get_for_simulation(&self, address: &Address) -> Option<&PoolState> {
+        if self.is_dirty(address) {
+            return None;
+        }
+        self.pools.get(address)
+    }
+
+    /// Như `get_for_simulation` nhưng cho phép load tick lazily khi quote
+    pub fn get_mut_for_simulation(&mut self, address: &Address) -> Option<&mut PoolState> {
+        if self.is_dirty(address) {
+            return None;
+        }
+        self.pools.get_mut(address)
+    }
+
+    /// Các pool không dirty
+    pub fn clean_pools(&self) -> impl Iterator<Item = &PoolState> {
+        self.pools.values().filter(|pool| !self.dirty.contains(&pool.address))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Filter `eth_getLogs` cho các event cần thiết của mọi pool đang track
+    pub fn log_filter(&self, from_block: u64, to_block: u64) -> Filter {
+        Filter::new()
+            .address(self.pools.keys().copied().collect::<Vec<_>>())
+            .event_signature(vec![Swap::SIGNATURE_HASH, Mint::SIGNATURE_HASH, Burn::SIGNATURE_HASH])
+            .from_block(from_block)
+            .to_block(to_block)
+    }
+
+    /// Áp dụng một log. Trả về `true` nếu log thuộc pool đang track và đã được áp dụng.
+    /// `db` chỉ dùng để load lazily các tick word chưa có trong cache.
+    pub fn apply_log<DB>(&mut self, db: &mut DB, log: &Log) -> Result<bool>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let Some(pool) = self.pools.get_mut(&log.address) else {
+            return Ok(false);
+        };
+        let Some(topic) = log.data.topics().first() else {
+            return Ok(false);
+        };
+
+        match *topic {
+            Swap::SIGNATURE_HASH => {
+                let event = Swap::decode_log_data(&log.data, true)?;
+                pool.apply_swap_event(db, U256::from(event.sqrtPriceX96), event.tick.as_i32(), event.liquidity)?;
+            }
+            Mint::SIGNATURE_HASH => {
+                let event = Mint::decode_log_data(&log.data, true)?;
+                let delta = i128::try_from(event.amount)?;
+                pool.apply_position_event(db, event.tickLower.as_i32(), event.tickUpper.as_i32(), delta)?;
+            }
+            Burn::SIGNATURE_HASH => {
+                let event = Burn::decode_log_data(&log.data, true)?;
+                let delta = -i128::try_from(event.amount)?;
+                pool.apply_position_event(db, event.tickLower.as_i32(), event.tickUpper.as_i32(), delta)?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Áp dụng log trả về từ `eth_getLogs` theo thứ tự; bỏ qua log bị reorg (`removed`).
+    /// Trả về số log đã áp dụng.
+    pub fn apply_logs<DB>(&mut self, db: &mut DB, logs: &[RpcLog]) -> Result<usize>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut applied = 0;
+        for log in logs.iter().filter(|log| !log.removed) {
+            if self.apply_log(db, &log.inner)? {
+                applied += 1;
+            }
+            if let Some(block) = log.block_number {
+                self.last_block = Some(self.last_block.map_or(block, |last| last.max(block)));
+            }
+        }
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univ3::pool::{mapping_slot, tick_spacing_for_fee, LIQUIDITY_SLOT, SLOT0_SLOT, TICKS_SLOT, TICK_BITMAP_SLOT};
+    use crate::univ3::tick_bitmap::{compress, flip_tick, position};
+    use crate::univ3::TickInfo;
+    use crate::core::db_rpc::RpcDB;
+    use crate::core::provider::MultiProvider;
+    use crate::types::load_chain_config;
+    use alloy::{eips::BlockId, providers::Provider};
+    use revm::db::{CacheDB, DatabaseRef, EmptyDB};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeSet;
+
+    /// Fixture TỔNG HỢP dựng tay tại địa chỉ pool WETH/USDC 0.3% (không phải log mainnet): Mint, Swap, Burn,
+    /// một Swap của pool khác và một log `Transfer`. `record_log_replay_fixture` ghi đè bằng log thật khi có RPC.
+    const FIXTURE_PATH: &str = "src/fixtures/univ3_log_replay.json";
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FixtureTick {
+        tick: i32,
+        liquidity_gross: String,
+        liquidity_net: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FixturePool {
+        address: Address,
+        fee: u32,
+        sqrt_price_x96: String,
+        tick: i32,
+        liquidity: String,
+        ticks: Vec<FixtureTick>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FixturePost {
+        block: u64,
+        sqrt_price_x96: String,
+        tick: i32,
+        liquidity: String,
+        ticks: Vec<FixtureTick>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Fixture {
+        pool: FixturePool,
+        logs: Vec<RpcLog>,
+        post: FixturePost,
+    }
+
+    fn tick_infos(ticks: &[FixtureTick]) -> HashMap<i32, TickInfo> {
+        ticks
+            .iter()
+            .map(|t| {
+                let info = TickInfo {
+                    liquidity_gross: t.liquidity_gross.parse().unwrap(),
+                    liquidity_net: t.liquidity_net.parse().unwrap(),
+                };
+                (t.tick, info)
+            })
+            .collect()
+    }
+
+    /// Ghi pre-state của fixture vào storage đúng layout `UniswapV3Pool`
+    fn pre_state_db(pool: &FixturePool, tick_spacing: i32) -> CacheDB<EmptyDB> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        let sqrt_price: U256 = pool.sqrt_price_x96.parse().unwrap();
+        let slot0 = sqrt_price | (U256::from((pool.tick as u32) & 0xffffff) << 160);
+        let liquidity: u128 = pool.liquidity.parse().unwrap();
+        db.insert_account_storage(pool.address, SLOT0_SLOT, slot0).unwrap();
+        db.insert_account_storage(pool.address, LIQUIDITY_SLOT, U256::from(liquidity)).unwrap();
+
+        let mut bitmap = HashMap::new();
+        for (tick, info) in tick_infos(&pool.ticks) {
+            flip_tick(&mut bitmap, tick, tick_spacing);
+            let packed = U256::from(info.liquidity_gross) | (U256::from(info.liquidity_net as u128) << 128);
+            db.insert_account_storage(pool.address, mapping_slot(tick, TICKS_SLOT), packed).unwrap();
+        }
+        for (word, value) in bitmap {
+            db.insert_account_storage(pool.address, mapping_slot(word as i32, TICK_BITMAP_SLOT), value).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_replay_logs_matches_post_state() {
+        let fixture: Fixture = serde_json::from_str(&std::fs::read_to_string(FIXTURE_PATH).unwrap()).unwrap();
+
+        let mut db = pre_state_db(&fixture.pool, tick_spacing_for_fee(fixture.pool.fee).unwrap());
+        let pool = PoolState::load(&mut db, fixture.pool.address, fixture.pool.fee).unwrap();
+        assert!(pool.tick_bitmap.is_empty());
+
+        let mut manager = PoolStateManager::new();
+        manager.track(pool);
+        let applied = manager.apply_logs(&mut db, &fixture.logs).unwrap();
+        // Bỏ qua log bị reorg, log của pool không track và topic khác Swap / Mint / Burn
+        let events = [Swap::SIGNATURE_HASH, Mint::SIGNATURE_HASH, Burn::SIGNATURE_HASH];
+        let expected = fixture
+            .logs
+            .iter()
+            .filter(|log| !log.removed && log.address() == fixture.pool.address)
+            .filter(|log| log.topics().first().is_some_and(|topic| events.contains(topic)))
+            .count();
+        assert!(expected > 0);
+        assert_eq!(applied, expected);
+        assert_eq!(manager.last_block, Some(fixture.post.block));
+
+        // Kỳ vọng tính độc lập từ nội dung log, không qua `PoolState`: Swap ghi đè slot0 + liquidity,
+        // Mint / Burn cộng trừ tick theo đúng `UniswapV3Pool._updatePosition`
+        let mut sqrt_price_x96: U256 = fixture.pool.sqrt_price_x96.parse().unwrap();
+        let mut tick = fixture.pool.tick;
+        let mut liquidity: u128 = fixture.pool.liquidity.parse().unwrap();
+        let mut ticks = tick_infos(&fixture.pool.ticks);
+        let mut update_position = |tick: i32, liquidity: &mut u128, lower: i32, upper: i32, delta: i128| {
+            for (t, net) in [(lower, delta), (upper, -delta)] {
+                let info = ticks.entry(t).or_default();
+                info.liquidity_gross = info.liquidity_gross.checked_add_signed(delta).unwrap();
+                info.liquidity_net += net;
+                if info.liquidity_gross == 0 {
+                    ticks.remove(&t);
+                }
+            }
+            if lower <= tick && tick < upper {
+                *liquidity = liquidity.checked_add_signed(delta).unwrap();
+            }
+        };
+        for log in fixture.logs.iter().filter(|log| !log.removed && log.address() == fixture.pool.address) {
+            match log.topics().first() {
+                Some(&Swap::SIGNATURE_HASH) => {
+                    let event = Swap::decode_log_data(&log.inner.data, true).unwrap();
+                    sqrt_price_x96 = U256::from(event.sqrtPriceX96);
+                    tick = event.tick.as_i32();
+                    liquidity = event.liquidity;
+                }
+                Some(&Mint::SIGNATURE_HASH) => {
+                    let event = Mint::decode_log_data(&log.inner.data, true).unwrap();
+                    let delta = event.amount as i128;
+                    update_position(tick, &mut liquidity, event.tickLower.as_i32(), event.tickUpper.as_i32(), delta);
+                }
+                Some(&Burn::SIGNATURE_HASH) => {
+                    let event = Burn::decode_log_data(&log.inner.data, true).unwrap();
+                    let delta = -(event.amount as i128);
+                    update_position(tick, &mut liquidity, event.tickLower.as_i32(), event.tickUpper.as_i32(), delta);
+                }
+                _ => {}
+            }
+        }
+        // Tick của Swap cuối phải khớp giá theo TickMath độc lập
+        let reference_price = uniswap_v3_math_primitives::U256::from_limbs(sqrt_price_x96.into_limbs());
+        assert_eq!(uniswap_v3_math::tick_math::get_tick_at_sqrt_ratio(reference_price).unwrap(), tick);
+
+        let pool = manager.get(&fixture.pool.address).unwrap();
+        assert_eq!(pool.sqrt_price_x96, sqrt_price_x96);
+        assert_eq!(pool.tick, tick);
+        assert_eq!(pool.liquidity, liquidity);
+        assert_eq!(pool.ticks, ticks);
+        // Khi fixture được ghi từ RPC, `post` là state on-chain, nguồn đối chiếu thứ ba
+        assert_eq!(sqrt_price_x96, fixture.post.sqrt_price_x96.parse::<U256>().unwrap());
+        assert_eq!(tick, fixture.post.tick);
+        assert_eq!(liquidity, fixture.post.liquidity.parse::<u128>().unwrap());
+        assert_eq!(ticks, tick_infos(&fixture.post.ticks));
+
+        // Bitmap phải khớp đúng tập tick còn liquidity
+        for (word, value) in &pool.tick_bitmap {
+            for bit in 0..256 {
+                let tick = (((*word as i32) << 8) + bit as i32) * pool.tick_spacing;
+                assert_eq!(value.bit(bit), pool.ticks.contains_key(&tick), "tick {}", tick);
+            }
+        }
+        for tick in pool.ticks.keys() {
+            let (word, bit) = position(compress(*tick, pool.tick_spacing));
+            assert!(pool.tick_bitmap[&word].bit(bit as usize));
+        }
+    }
+
+    #[test]
+    fn test_swap_event_loads_crossed_words() {
+        // Pool dựng tay: liquidity 1e18 trong khoảng tick [-600, 600], giá ở tick 0
+        let liquidity = 1_000_000_000_000_000_000i128;
+        let fixture_pool = FixturePool {
+            address: Address::repeat_byte(0x88),
+            fee: 3000,
+            sqrt_price_x96: (U256::from(1) << 96_usize).to_string(),
+            tick: 0,
+            liquidity: liquidity.to_string(),
+            ticks: [(-600, liquidity), (600, -liquidity)]
+                .into_iter()
+                .map(|(tick, net)| FixtureTick { tick, liquidity_gross: liquidity.to_string(), liquidity_net: net.to_string() })
+                .collect(),
+        };
+        let mut db = pre_state_db(&fixture_pool, tick_spacing_for_fee(fixture_pool.fee).unwrap());
+        let mut pool = PoolState::load(&mut db, fixture_pool.address, fixture_pool.fee).unwrap();
+
+        // Swap từ tick 0 xuống tick -15400 đi qua word -1 (chứa tick -600) và word -2
+        pool.apply_swap_event(&mut db, U256::from(1) << 95, -15400, 0).unwrap();
+        assert!(pool.tick_bitmap.contains_key(&-1));
+        assert!(pool.tick_bitmap.contains_key(&-2));
+        assert!(pool.tick_bitmap.contains_key(&0));
+        assert_eq!(pool.ticks.get(&-600).map(|info| info.liquidity_net), Some(1_000_000_000_000_000_000));
+    }
+
+    /// Tick đã khởi tạo (liquidity gross > 0) trong `ticks` tại block của `db`
+    fn recorded_ticks(db: &RpcDB, pool: Address, ticks: &BTreeSet<i32>) -> Vec<FixtureTick> {
+        ticks
+            .iter()
+            .filter_map(|&tick| {
+                let info = db.storage_ref(pool, mapping_slot(tick, TICKS_SLOT)).unwrap();
+                let liquidity_gross = (info & U256::from(u128::MAX)).to::<u128>();
+                let liquidity_net = (info >> 128_usize).to::<u128>() as i128;
+                (liquidity_gross > 0).then(|| FixtureTick {
+                    tick,
+                    liquidity_gross: liquidity_gross.to_string(),
+                    liquidity_net: liquidity_net.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Ghi lại `FIXTURE_PATH` từ log thật của pool WETH / USDC 0.3%: pre-state ở block ngay trước log đầu,
+    /// post-state ở block của log cuối, tick là các tick mà `Mint` / `Burn` trong khoảng block chạm tới
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires RPC_URL"]
+    async fn record_log_replay_fixture() {
+        let config = load_chain_config("src/config/eth.toml").unwrap();
+        let (weth, usdc) = (config.token("WETH").unwrap().address, config.token("USDC").unwrap().address);
+        let pool = config.pool(weth, usdc, 3000).unwrap();
+        let multi_provider = MultiProvider::new(&[std::env::var("RPC_URL").expect("Please set RPC_URL env")]);
+
+        // Lùi khỏi head để tránh log bị reorg
+        let head = multi_provider.request(|provider| async move { provider.get_block_number().await }).await.unwrap();
+        let (from_block, to_block) = (head - 264, head - 64);
+        let filter = Filter::new()
+            .address(pool.address)
+            .event_signature(vec![Swap::SIGNATURE_HASH, Mint::SIGNATURE_HASH, Burn::SIGNATURE_HASH])
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = multi_provider
+            .request(|provider| {
+                let filter = filter.clone();
+                async move { provider.get_logs(&filter).await }
+            })
+            .await
+            .unwrap();
+        let pre_block = logs.first().and_then(|log| log.block_number).expect("no logs in range") - 1;
+        let post_block = logs.last().and_then(|log| log.block_number).unwrap();
+
+        let mut ticks = BTreeSet::new();
+        for log in &logs {
+            match log.topics().first() {
+                Some(&Mint::SIGNATURE_HASH) => {
+                    let event = Mint::decode_log_data(&log.inner.data, true).unwrap();
+                    ticks.extend([event.tickLower.as_i32(), event.tickUpper.as_i32()]);
+                }
+                Some(&Burn::SIGNATURE_HASH) => {
+                    let event = Burn::decode_log_data(&log.inner.data, true).unwrap();
+                    ticks.extend([event.tickLower.as_i32(), event.tickUpper.as_i32()]);
+                }
+                _ => {}
+            }
+        }
+
+        let pre_db = RpcDB::new(&multi_provider, BlockId::number(pre_block)).unwrap();
+        let pre = PoolState::load(&mut CacheDB::new(&pre_db), pool.address, pool.fee).unwrap();
+        let post_db = RpcDB::new(&multi_provider, BlockId::number(post_block)).unwrap();
+        let post = PoolState::load(&mut CacheDB::new(&post_db), pool.address, pool.fee).unwrap();
+
+        let fixture = Fixture {
+            pool: FixturePool {
+                address: pool.address,
+                fee: pool.fee,
+                sqrt_price_x96: pre.sqrt_price_x96.to_string(),
+                tick: pre.tick,
+                liquidity: pre.liquidity.to_string(),
+                ticks: recorded_ticks(&pre_db, pool.address, &ticks),
+            },
+            post: FixturePost {
+                block: post_block,
+                sqrt_price_x96: post.sqrt_price_x96.to_string(),
+                tick: post.tick,
+                liquidity: post.liquidity.to_string(),
+                ticks: recorded_ticks(&post_db, pool.address, &ticks),
+            },
+            logs,
+        };
+        std::fs::write(FIXTURE_PATH, serde_json::to_string_pretty(&fixture).unwrap() + "\n").unwrap();
+        println!("Recorded {} logs of {:?} (blocks {}..={})", fixture.logs.len(), pool.address, pre_block + 1, post_block);
+    }
+}